@@ -1,4 +1,8 @@
-use std::{fs, path::PathBuf, sync::Arc};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use clap::Parser;
 use luxide::{
@@ -12,13 +16,13 @@ use luxide::{
         volumes,
     },
     shading::{
-        ColorRgb, ColorSpectrum, Medium, Texture,
+        ColorRgb, ColorSpectrum, Medium, Texture, ToneMappingOverride,
         materials::{Dielectric, Lambertian, Material, Specular},
         textures::{Checker, ImageLinearF64, Noise, SolidColor},
     },
     tracing::{
-        FileStorage, InMemoryStorage, RenderManager, RenderState, RenderStorage, ResourceManager,
-        Scene, SceneWorld, User,
+        Aov, CheckpointImageFormat, FileStorage, InMemoryStorage, RenderManager, RenderState,
+        RenderStorage, ResourceManager, Scene, SceneWorld, User,
     },
     utils::{Angle, Around},
 };
@@ -43,6 +47,9 @@ struct Args {
     /// Output directory for render files
     #[arg(short, long, default_value = OUTPUT_DIR)]
     output_dir: String,
+    /// Also export the final checkpoint as an HDR image (.exr or .hdr, chosen by extension)
+    #[arg(long)]
+    hdr_output: Option<PathBuf>,
 }
 
 #[tokio::main]
@@ -51,6 +58,25 @@ async fn main() -> Result<(), String> {
 
     let args = Args::parse();
 
+    // validate the hdr output format up front, rather than after the render has finished
+    let hdr_output = match args.hdr_output {
+        Some(path) => {
+            let format = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(CheckpointImageFormat::from_extension)
+                .filter(|format| format.is_hdr())
+                .ok_or_else(|| {
+                    format!(
+                        "HDR output file must end in .exr or .hdr: {}",
+                        path.display()
+                    )
+                })?;
+            Some((path, format))
+        }
+        None => None,
+    };
+
     // Create storage backend
     let storage: Arc<dyn RenderStorage> =
         Arc::new(FileStorage::new(PathBuf::from(&args.output_dir))?);
@@ -75,7 +101,12 @@ async fn main() -> Result<(), String> {
     // start render manager
     let (_, res2) = tokio::join!(
         render_manager.start(),
-        create_render_and_poll_completion(Arc::clone(&render_manager), render_config)
+        create_render_and_poll_completion(
+            Arc::clone(&render_manager),
+            render_config,
+            &args.output_dir,
+            hdr_output
+        )
     );
 
     res2.map_err(|e| format!("Failed to create render: {}", e))
@@ -84,6 +115,8 @@ async fn main() -> Result<(), String> {
 async fn create_render_and_poll_completion(
     render_manager: Arc<RenderManager>,
     render_config: RenderConfig,
+    output_dir: &str,
+    hdr_output: Option<(PathBuf, CheckpointImageFormat)>,
 ) -> Result<(), String> {
     let render = render_manager
         .create_render(
@@ -94,7 +127,7 @@ async fn create_render_and_poll_completion(
         .map_err(|e| format!("Failed to create render: {}", e))?;
 
     // Poll until render is complete
-    loop {
+    let final_iteration = loop {
         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
 
         let current_render = render_manager
@@ -105,20 +138,121 @@ async fn create_render_and_poll_completion(
         match current_render {
             Some(r) => match r.state {
                 RenderState::FinishedCheckpointIteration(n)
-                    if n == r.config.parameters.total_checkpoints =>
+                    if n == r.config.parameters.total_iterations() =>
                 {
-                    break;
+                    break n;
                 }
                 _ => continue,
             },
             None => return Err("Render disappeared".to_string()),
         }
+    };
+
+    // an animation is written out as an image sequence, one file per frame
+    if let Some(animation) = render.config.parameters.animation {
+        let frames_dir =
+            PathBuf::from(output_dir).join(format!("{}_{}_frames", render.id, render.config.name));
+        fs::create_dir_all(&frames_dir).map_err(|e| e.to_string())?;
+
+        for frame in 0..animation.frame_count {
+            let iteration = render_manager
+                .get_latest_render_frame_checkpoint_iteration(render.id, frame, 1)
+                .await
+                .map_err(|e| format!("Failed to find checkpoint of frame {}: {}", frame, e))?
+                .ok_or_else(|| format!("Frame {} has no checkpoint", frame))?;
+
+            let image = render_manager
+                .get_render_checkpoint_as_image(
+                    render.id,
+                    iteration,
+                    1,
+                    ToneMappingOverride::default(),
+                    false,
+                )
+                .await
+                .map_err(|e| format!("Failed to get frame {}: {}", frame, e))?
+                .ok_or_else(|| format!("Frame {} has no pixel data", frame))?;
+            image
+                .save(frames_dir.join(format!("frame_{:04}.png", frame)))
+                .map_err(|e| e.to_string())?;
+
+            if let Some((path, format)) = &hdr_output {
+                let image = render_manager
+                    .get_render_checkpoint_as_hdr_image(render.id, iteration, 1, false)
+                    .await
+                    .map_err(|e| format!("Failed to get frame {}: {}", frame, e))?
+                    .ok_or_else(|| format!("Frame {} has no pixel data", frame))?;
+                let frame_path = frame_file_path(path, frame);
+                fs::write(&frame_path, image.encode(*format)?).map_err(|e| e.to_string())?;
+            }
+        }
+        println!(
+            "Wrote {} frames to {}",
+            animation.frame_count,
+            frames_dir.display()
+        );
+    } else if let Some((path, format)) = &hdr_output {
+        let image = render_manager
+            .get_render_checkpoint_as_hdr_image(render.id, final_iteration, 1, false)
+            .await
+            .map_err(|e| format!("Failed to get final checkpoint: {}", e))?
+            .ok_or("Final checkpoint has no pixel data")?;
+        fs::write(path, image.encode(*format)?).map_err(|e| e.to_string())?;
+        println!("Wrote HDR image to {}", path.display());
+    }
+
+    if render.config.parameters.store_aovs && render.config.parameters.animation.is_none() {
+        let aovs_dir =
+            PathBuf::from(output_dir).join(format!("{}_{}_aovs", render.id, render.config.name));
+        fs::create_dir_all(&aovs_dir).map_err(|e| e.to_string())?;
+
+        for aov in Aov::ALL {
+            let image = render_manager
+                .get_render_checkpoint_aov_as_image(
+                    render.id,
+                    final_iteration,
+                    aov,
+                    1,
+                    ToneMappingOverride::default(),
+                )
+                .await
+                .map_err(|e| format!("Failed to get {} AOV: {}", aov.name(), e))?
+                .ok_or_else(|| format!("Final checkpoint has no {} AOV", aov.name()))?;
+            image
+                .save(aovs_dir.join(format!("{}.png", aov.name())))
+                .map_err(|e| e.to_string())?;
+
+            if let Some((path, format)) = &hdr_output {
+                let image = render_manager
+                    .get_render_checkpoint_aov_as_hdr_image(render.id, final_iteration, aov, 1)
+                    .await
+                    .map_err(|e| format!("Failed to get {} AOV: {}", aov.name(), e))?
+                    .ok_or_else(|| format!("Final checkpoint has no {} AOV", aov.name()))?;
+                fs::write(aov_file_path(path, aov), image.encode(*format)?)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        println!("Wrote AOVs to {}", aovs_dir.display());
     }
 
     println!("Render completed successfully!");
     Ok(())
 }
 
+/// `path` with the frame number before its extension, e.g. `out.exr` -> `out_0003.exr`.
+fn frame_file_path(path: &Path, frame: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path.extension().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}_{:04}.{}", stem, frame, ext))
+}
+
+/// `path` with the AOV's name before its extension, e.g. `out.exr` -> `out_albedo.exr`.
+fn aov_file_path(path: &Path, aov: Aov) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path.extension().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}_{}.{}", stem, aov.name(), ext))
+}
+
 #[allow(dead_code)]
 fn final_scene() -> Scene {
     let mut rng = rand::rng();
@@ -1,9 +1,9 @@
 use std::{f64::consts::PI, sync::Arc};
 
-use rand::RngExt;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    geometry::{Point, Ray, Vector, Vector3},
+    geometry::{Onb, Point, Ray, RayHit, Vector, Vector3},
     shading::{
         ColorRgb, ColorSpectrum, HeroWavelengths,
         color_spectrum::SPECTRAL_SAMPLE_COUNT,
@@ -12,27 +12,183 @@ use crate::{
         medium::Medium,
         pdf::Pdf,
     },
-    tracing::{BouncesConfig, ImportanceSamplingConfig, RenderParameters, Scene, SceneWorld},
-    utils::{Angle, Interval},
+    tracing::{
+        AovSample, BouncesConfig, FirstHit, ImportanceSamplingConfig, RenderParameters, Sampler,
+        Scene, SceneWorld,
+    },
+    utils::{Angle, Interval, Keyframes},
 };
 
+mod bidirectional;
+mod debug;
+mod photon_mapping;
+
+type Spectrum = Vector<HERO_WAVELENGTH_COUNT>;
+
+/// How a camera maps film positions to ray directions.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type", deny_unknown_fields)]
+pub enum Projection {
+    /// A thin-lens perspective camera with the camera's vertical field of view.
+    #[default]
+    Perspective,
+    /// Parallel rays leaving a film plane through the eye. Defocus tilts them
+    /// towards the focus distance, as a thin lens would.
+    Orthographic {
+        /// Height of the film plane in world units. Defaults to the height a
+        /// perspective camera would frame at the focus distance.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        view_height: Option<f64>,
+    },
+    /// The full sphere of directions around the eye, longitude across and
+    /// latitude down the image, with the target in the center.
+    Equirectangular,
+    /// A circular fisheye image, inscribed in the shorter side of the image.
+    /// Pixels outside the circle stay black.
+    Fisheye {
+        #[serde(default)]
+        mapping: FisheyeMapping,
+        /// The angle covered across the image circle's diameter.
+        #[serde(default = "Projection::default_fisheye_field_of_view_degrees")]
+        field_of_view_degrees: f64,
+    },
+}
+
+impl Projection {
+    fn default_fisheye_field_of_view_degrees() -> f64 {
+        180.0
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            Projection::Orthographic {
+                view_height: Some(view_height),
+            } if view_height.is_nan() || view_height <= 0.0 => {
+                Err("Orthographic view height must be positive".to_string())
+            }
+            Projection::Fisheye {
+                field_of_view_degrees,
+                ..
+            } if field_of_view_degrees <= 0.0 || !(..=360.0).contains(&field_of_view_degrees) => {
+                Err("Fisheye field of view must be in (0, 360] degrees".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How the distance from a fisheye image's center relates to the angle from
+/// the view direction.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FisheyeMapping {
+    /// Distance proportional to the angle.
+    #[default]
+    Equidistant,
+    /// Equal areas of the image cover equal solid angles.
+    Equisolid,
+}
+
+impl FisheyeMapping {
+    /// The angle from the view direction at `radius`, where 1 is the edge of
+    /// the image circle at `max_angle`.
+    fn angle(&self, radius: f64, max_angle: f64) -> f64 {
+        match self {
+            FisheyeMapping::Equidistant => radius * max_angle,
+            FisheyeMapping::Equisolid => {
+                2.0 * (radius * (max_angle / 2.0).sin()).clamp(-1.0, 1.0).asin()
+            }
+        }
+    }
+
+    /// The inverse of [`FisheyeMapping::angle`].
+    fn radius(&self, angle: f64, max_angle: f64) -> f64 {
+        match self {
+            FisheyeMapping::Equidistant => angle / max_angle,
+            FisheyeMapping::Equisolid => (angle / 2.0).sin() / (max_angle / 2.0).sin(),
+        }
+    }
+
+    /// The area of the image circle, in squared radii, per steradian around
+    /// `angle`.
+    fn solid_angle_density(&self, angle: f64, max_angle: f64) -> f64 {
+        match self {
+            FisheyeMapping::Equidistant => {
+                // θ / sin θ tends to 1 at the center
+                let angle_over_sine = if angle > 1e-6 {
+                    angle / angle.sin()
+                } else {
+                    1.0
+                };
+                angle_over_sine / (max_angle * max_angle)
+            }
+            FisheyeMapping::Equisolid => 1.0 / (4.0 * (max_angle / 2.0).sin().powi(2)),
+        }
+    }
+}
+
+/// When the shutter is open, in the same units as keyframe times. Each ray's
+/// time is drawn uniformly from this window. In animations, the window is
+/// relative to the start of each frame.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Shutter {
+    pub open: f64,
+    pub close: f64,
+}
+
+impl Default for Shutter {
+    fn default() -> Self {
+        Self {
+            open: 0.0,
+            close: 1.0,
+        }
+    }
+}
+
+impl Shutter {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.open.is_finite() || !self.close.is_finite() || self.open > self.close {
+            return Err("Shutter must open before it closes".to_string());
+        }
+        Ok(())
+    }
+
+    /// The time `u` of the way through the window.
+    fn time_at(&self, u: f64) -> f64 {
+        self.open + (self.close - self.open) * u
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct Camera {
     // "public" fields
-    eye_location: Point,
-    target_location: Point,
+    eye_location: Keyframes<Point>,
+    target_location: Keyframes<Point>,
     view_up: Vector3,
     vertical_field_of_view_degrees: f64,
+    projection: Projection,
+    shutter: Shutter,
 
     defocus_angle_degrees: f64,
     focus_distance: f64,
 
     // "private" fields
     background_color: ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+    image_width: u32,
     image_height: u32,
-    center: Point,
     importance_sampling: ImportanceSamplingConfig,
     bounces: BouncesConfig,
+    // the shutter opens this long after time 0, which moves with each frame of an animation
+    frame_start_time: f64,
+    // the view at the first keyframes, which is the only one for a still camera
+    frame: CameraFrame,
+}
+
+/// Where the camera is and what it sees, as of one moment.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct CameraFrame {
+    center: Point,
     pixel_00_location: Point,
     pixel_delta_u: Vector3,
     pixel_delta_v: Vector3,
@@ -43,6 +199,24 @@ pub struct Camera {
     defocus_disk_v: Vector3,
 }
 
+impl CameraFrame {
+    /// The point on the viewport at `film_position`.
+    fn film_point(&self, film_position: (f64, f64)) -> Point {
+        // pixel_00_location is the center of the first pixel
+        self.pixel_00_location
+            + self.pixel_delta_u * (film_position.0 - 0.5)
+            + self.pixel_delta_v * (film_position.1 - 0.5)
+    }
+
+    fn defocus_disk_sample(&self, u: (f64, f64)) -> Point {
+        let disk_unit_vector = &Vector3::concentric_disk_from(u);
+
+        self.center
+            + self.defocus_disk_u * disk_unit_vector.x
+            + self.defocus_disk_v * disk_unit_vector.y
+    }
+}
+
 impl Camera {
     pub fn new(
         vertical_field_of_view_degrees: f64,
@@ -54,8 +228,8 @@ impl Camera {
     ) -> Self {
         Self {
             vertical_field_of_view_degrees,
-            eye_location,
-            target_location,
+            eye_location: Keyframes::constant(eye_location),
+            target_location: Keyframes::constant(target_location),
             view_up,
             defocus_angle_degrees,
             focus_distance,
@@ -63,109 +237,266 @@ impl Camera {
         }
     }
 
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    /// Moves the eye and target along keyframes, instead of the fixed
+    /// locations the camera was created with.
+    pub fn with_motion(
+        mut self,
+        eye_location: Keyframes<Point>,
+        target_location: Keyframes<Point>,
+    ) -> Self {
+        self.eye_location = eye_location;
+        self.target_location = target_location;
+        self
+    }
+
+    pub fn with_shutter(mut self, shutter: Shutter) -> Self {
+        self.shutter = shutter;
+        self
+    }
+
     pub fn initialize(&mut self, parameters: &RenderParameters, scene: &Scene) {
-        self.center = self.eye_location;
         self.background_color = scene.background_color;
         self.importance_sampling = parameters.importance_sampling;
         self.bounces = parameters.bounces;
 
         let (width, height) = parameters.image_dimensions;
+        self.image_width = width;
+        self.image_height = height;
+
+        self.frame = self.frame_looking(self.eye_location.first(), self.target_location.first());
+    }
+
+    /// Opens the shutter relative to `time` instead of 0, to render one frame
+    /// of an animation.
+    pub fn set_frame_start_time(&mut self, time: f64) {
+        self.frame_start_time = time;
+    }
+
+    /// The view from `eye_location` towards `target_location`.
+    fn frame_looking(&self, eye_location: Point, target_location: Point) -> CameraFrame {
+        let (width, height) = (self.image_width, self.image_height);
+        let center = eye_location;
 
         // camera configuration
         let theta = self.vertical_field_of_view_degrees * PI / 180.0;
         let half_height = (theta / 2.0).tan();
-        let viewport_height = 2.0 * half_height * self.focus_distance;
+        let viewport_height = match self.projection {
+            Projection::Orthographic {
+                view_height: Some(view_height),
+            } => view_height,
+            _ => 2.0 * half_height * self.focus_distance,
+        };
         let viewport_width = viewport_height * (width as f64 / height as f64);
 
         // calculate basis vectors
-        self.w = self.target_location.to(self.eye_location).unit_vector();
-        self.u = self.view_up.cross(self.w).unit_vector();
-        self.v = self.w.cross(self.u);
+        let w = target_location.to(eye_location).unit_vector();
+        let u = self.view_up.cross(w).unit_vector();
+        let v = w.cross(u);
 
         // viewport / pixel vectors
-        let viewport_u = viewport_width * self.u;
-        let viewport_v = viewport_height * -self.v;
+        let viewport_u = viewport_width * u;
+        let viewport_v = viewport_height * -v;
 
-        self.pixel_delta_u = viewport_u / width as f64;
-        self.pixel_delta_v = viewport_v / height as f64;
+        let pixel_delta_u = viewport_u / width as f64;
+        let pixel_delta_v = viewport_v / height as f64;
 
-        let viewport_upper_left =
-            self.center - (self.focus_distance * self.w) - viewport_u / 2.0 - viewport_v / 2.0;
+        // the orthographic film plane passes through the eye itself
+        let viewport_center = match self.projection {
+            Projection::Orthographic { .. } => center,
+            _ => center - (self.focus_distance * w),
+        };
+        let viewport_upper_left = viewport_center - viewport_u / 2.0 - viewport_v / 2.0;
 
-        self.pixel_00_location =
-            viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
+        let pixel_00_location = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
 
         // defocus disk
         let defocus_disk_radius = self.focus_distance
             * Angle::Degrees(self.defocus_angle_degrees / 2.0)
                 .as_radians()
                 .tan();
-        self.defocus_disk_u = defocus_disk_radius * self.u;
-        self.defocus_disk_v = defocus_disk_radius * self.v;
-    }
-
-    pub fn get_ray(&self, x: u32, y: u32) -> Ray {
-        let pixel_center = self.pixel_00_location
-            + (self.pixel_delta_u * x as f64)
-            + (self.pixel_delta_v * y as f64);
-        let pixel_sample = pixel_center + self.pixel_sample_square();
 
-        let origin = if self.defocus_angle_degrees > 0.0 {
-            self.defocus_disk_sample()
-        } else {
-            self.center
-        };
-        let direction = origin.to(pixel_sample);
-        let time = rand::random();
-
-        Ray::new(origin, direction, time)
+        CameraFrame {
+            center,
+            pixel_00_location,
+            pixel_delta_u,
+            pixel_delta_v,
+            u,
+            v,
+            w,
+            defocus_disk_u: defocus_disk_radius * u,
+            defocus_disk_v: defocus_disk_radius * v,
+        }
     }
 
-    pub fn get_ray_stratified(&self, x: u32, y: u32, sample_index: u32, total_samples: u32) -> Ray {
-        let pixel_center = self.pixel_00_location
-            + (self.pixel_delta_u * x as f64)
-            + (self.pixel_delta_v * y as f64);
-
-        let grid_size = (total_samples as f64).sqrt().floor() as u32;
-        let cell_width = 1.0 / grid_size as f64;
-        let cell_height = 1.0 / grid_size as f64;
-
-        let row = sample_index / grid_size;
-        let col = sample_index % grid_size;
-
-        let mut rng = rand::rng();
+    fn frame_at(&self, time: f64) -> CameraFrame {
+        if self.eye_location.is_constant() && self.target_location.is_constant() {
+            return self.frame;
+        }
 
-        let u = (col as f64 + rng.random_range(0.0..1.0)) * cell_width - 0.5;
-        let v = (row as f64 + rng.random_range(0.0..1.0)) * cell_height - 0.5;
+        self.frame_looking(self.eye_location.at(time), self.target_location.at(time))
+    }
 
-        let pixel_sample = pixel_center + self.pixel_delta_u * u + self.pixel_delta_v * v;
+    /// A ray through `film_position`, in continuous pixel coordinates (pixel
+    /// `(x, y)` covers `[x, x + 1) × [y, y + 1)`), positioned on the lens and in
+    /// time by the samples `sampler` provides.
+    ///
+    /// Returns `None` for film positions the projection doesn't cover.
+    pub fn get_ray(&self, film_position: (f64, f64), sampler: &mut dyn Sampler) -> Option<Ray> {
+        // drawn even when unused, so later decisions keep the same dimensions
+        let lens_sample = sampler.get_2d();
+        let time = self.frame_start_time + self.shutter.time_at(sampler.get_1d());
+
+        let frame = self.frame_at(time);
+
+        let (origin, direction) = match self.projection {
+            Projection::Perspective => {
+                let origin = if self.defocus_angle_degrees > 0.0 {
+                    frame.defocus_disk_sample(lens_sample)
+                } else {
+                    frame.center
+                };
+                (origin, origin.to(frame.film_point(film_position)))
+            }
+            Projection::Orthographic { .. } => {
+                let film_point = frame.film_point(film_position);
+                let focus_point = film_point - self.focus_distance * frame.w;
+                let origin = if self.defocus_angle_degrees > 0.0 {
+                    film_point + frame.center.to(frame.defocus_disk_sample(lens_sample))
+                } else {
+                    film_point
+                };
+                (origin, origin.to(focus_point))
+            }
+            Projection::Equirectangular => {
+                let longitude = (film_position.0 / self.image_width as f64 - 0.5) * 2.0 * PI;
+                let latitude = (0.5 - film_position.1 / self.image_height as f64) * PI;
+                let direction = latitude.cos() * longitude.sin() * frame.u
+                    + latitude.sin() * frame.v
+                    - latitude.cos() * longitude.cos() * frame.w;
+                (frame.center, direction)
+            }
+            Projection::Fisheye {
+                mapping,
+                field_of_view_degrees,
+            } => {
+                let circle_radius = self.image_width.min(self.image_height) as f64 / 2.0;
+                let x = (film_position.0 - self.image_width as f64 / 2.0) / circle_radius;
+                let y = (self.image_height as f64 / 2.0 - film_position.1) / circle_radius;
+                let radius = x.hypot(y);
+                if radius > 1.0 {
+                    return None;
+                }
 
-        let origin = if self.defocus_angle_degrees > 0.0 {
-            self.defocus_disk_sample()
-        } else {
-            self.center
+                let max_angle = Angle::Degrees(field_of_view_degrees / 2.0).as_radians();
+                let theta = mapping.angle(radius, max_angle);
+                let phi = y.atan2(x);
+                let direction = theta.sin() * phi.cos() * frame.u
+                    + theta.sin() * phi.sin() * frame.v
+                    - theta.cos() * frame.w;
+                (frame.center, direction)
+            }
         };
-        let direction = origin.to(pixel_sample);
-        let time = rand::random();
 
-        Ray::new(origin, direction, time)
+        Some(Ray::new(origin, direction, time))
     }
 
-    fn defocus_disk_sample(&self) -> Point {
-        let disk_unit_vector = &Vector3::random_in_unit_disk();
-
-        self.center
-            + self.defocus_disk_u * disk_unit_vector.x
-            + self.defocus_disk_v * disk_unit_vector.y
+    /// A point on the lens for light from the scene to reach, drawn from the
+    /// same distribution camera rays leave the lens by. `None` for
+    /// orthographic cameras, whose parallel rays a point can't send light
+    /// along by chance.
+    fn lens_point(&self, frame: &CameraFrame, u: (f64, f64)) -> Option<Point> {
+        match self.projection {
+            Projection::Perspective if self.defocus_angle_degrees > 0.0 => {
+                Some(frame.defocus_disk_sample(u))
+            }
+            Projection::Orthographic { .. } => None,
+            _ => Some(frame.center),
+        }
     }
 
-    fn pixel_sample_square(&self) -> Vector3 {
-        let mut rng = rand::rng();
-
-        let x_offset = self.pixel_delta_u * rng.random_range(-0.5..0.5);
-        let y_offset = self.pixel_delta_v * rng.random_range(-0.5..0.5);
+    /// The inverse of [`Camera::get_ray`]: the film position whose ray leaves
+    /// `lens_point` in `direction`, and how many pixels of film cover a
+    /// steradian of directions there. `None` when the direction is outside
+    /// the image.
+    fn film_position_of(
+        &self,
+        frame: &CameraFrame,
+        lens_point: Point,
+        direction: Vector3,
+    ) -> Option<((f64, f64), f64)> {
+        let direction = direction.unit_vector();
+        let (width, height) = (self.image_width as f64, self.image_height as f64);
+        // along the camera's right, up and view directions
+        let (x, y, z) = (
+            direction.dot(frame.u),
+            direction.dot(frame.v),
+            -direction.dot(frame.w),
+        );
+
+        let (film_position, density) = match self.projection {
+            Projection::Perspective => {
+                if z <= 0.0 {
+                    return None;
+                }
+                let film_point = lens_point + direction * (self.focus_distance / z);
+                let offset = frame.pixel_00_location.to(film_point);
+                let (delta_u, delta_v) = (frame.pixel_delta_u, frame.pixel_delta_v);
+                let film_position = (
+                    offset.dot(delta_u) / delta_u.squared_length() + 0.5,
+                    offset.dot(delta_v) / delta_v.squared_length() + 0.5,
+                );
+                let pixel_area = delta_u.length() * delta_v.length();
+                (
+                    film_position,
+                    self.focus_distance * self.focus_distance / (z * z * z * pixel_area),
+                )
+            }
+            Projection::Orthographic { .. } => return None,
+            Projection::Equirectangular => {
+                let longitude = x.atan2(z);
+                let latitude = y.clamp(-1.0, 1.0).asin();
+                let cos_latitude = latitude.cos();
+                if cos_latitude <= 0.0 {
+                    return None;
+                }
+                (
+                    (
+                        (longitude / (2.0 * PI) + 0.5) * width,
+                        (0.5 - latitude / PI) * height,
+                    ),
+                    width * height / (2.0 * PI * PI * cos_latitude),
+                )
+            }
+            Projection::Fisheye {
+                mapping,
+                field_of_view_degrees,
+            } => {
+                let max_angle = Angle::Degrees(field_of_view_degrees / 2.0).as_radians();
+                let theta = z.clamp(-1.0, 1.0).acos();
+                if theta > max_angle {
+                    return None;
+                }
+                let circle_radius = self.image_width.min(self.image_height) as f64 / 2.0;
+                let radius = mapping.radius(theta, max_angle) * circle_radius;
+                let phi = y.atan2(x);
+                (
+                    (
+                        width / 2.0 + radius * phi.cos(),
+                        height / 2.0 - radius * phi.sin(),
+                    ),
+                    mapping.solid_angle_density(theta, max_angle) * circle_radius * circle_radius,
+                )
+            }
+        };
 
-        x_offset + y_offset
+        let inside =
+            (0.0..width).contains(&film_position.0) && (0.0..height).contains(&film_position.1);
+        inside.then_some((film_position, density))
     }
 
     /// Trace a ray through the scene, accumulating spectral radiance at
@@ -179,34 +510,52 @@ impl Camera {
         scene_world: &SceneWorld,
         mut bounces: u32,
         mut medium_stack: Vec<Medium>,
-    ) -> Vector<N> {
-        let mut rng = rand::rng();
-
+        sampler: &mut dyn Sampler,
+    ) -> PathRadiance<N> {
         // per-wavelength accumulated radiance
-        let mut accumulated = Vector::<N>::ZERO;
+        let mut accumulated = PathRadiance::<N>::ZERO;
         // per-wavelength attenuation throughput
         let mut attenuation = Vector::<N>::ONE;
+        // with next-event estimation, where the ray was last scattered and the
+        // density of the direction it was scattered in, so a light it hits can
+        // be weighted against the light samples taken there. `None` when light
+        // sampling couldn't have found the hit, e.g. after a specular bounce
+        let mut light_sampled_from: Option<(Point, f64)> = None;
 
         while let Some(ray_hit) = scene_world
             .world
             .intersect(ray, Interval::new(0.001, f64::INFINITY))
         {
+            accumulated.surfaces_hit = bounces + 1;
+
             // volume emission: accumulate glow from the medium segment
             // before attenuating throughput. Uses coupled Kirchhoff model:
             // emitted = L_e * (1 - Tr).
             let distance = ray_hit.t;
             let emission = ray.current_medium.emission(hw, distance);
-            accumulated += attenuation * emission;
+            accumulated.add(bounces, attenuation * emission);
 
             // apply Beer-Lambert attenuation for the distance traveled
             // through the current medium (vacuum returns Vector::ONE = no-op)
             attenuation *= ray.current_medium.transmittance(hw, distance);
 
             // emissive contribution at each hero wavelength
-            let emittance = ray_hit
-                .material
-                .emittance(ray_hit.u, ray_hit.v, ray_hit.point);
-            accumulated += attenuation * emittance.sample(hw);
+            let emittance =
+                ray_hit
+                    .material
+                    .emittance(ray_hit.u, ray_hit.v, ray_hit.point, ray.time);
+            if !emittance.is_black() {
+                let light_weight = match light_sampled_from {
+                    Some((origin, bounce_density)) => power_heuristic(
+                        bounce_density,
+                        scene_world
+                            .emissive_list
+                            .direction_pdf(origin, ray.direction),
+                    ),
+                    None => 1.0,
+                };
+                accumulated.add(bounces, attenuation * emittance.sample(hw) * light_weight);
+            }
 
             if bounces >= self.bounces.max {
                 return accumulated;
@@ -230,7 +579,10 @@ impl Camera {
                 }
                 HeroWavelengths::new(data)
             };
-            let Some(srec) = ray_hit.material.scatter(ray, &ray_hit, &scatter_hw) else {
+            let Some(srec) = ray_hit
+                .material
+                .scatter(ray, &ray_hit, &scatter_hw, sampler)
+            else {
                 return accumulated;
             };
 
@@ -238,14 +590,6 @@ impl Camera {
 
             let outgoing_direction = (-ray.direction).unit_vector();
 
-            // flip the geometric normal so shading calculations use the hemisphere
-            // the ray is actually incident from (back-face hit → normal points toward ray)
-            let shading_normal = if ray.direction.dot(ray_hit.normal) > 0.0 {
-                -ray_hit.normal
-            } else {
-                ray_hit.normal
-            };
-
             match srec {
                 ScatterRecord::Spectral(ss) => {
                     let SpectralScatter { rays, reflectance } = *ss;
@@ -253,8 +597,11 @@ impl Camera {
                     // managing the medium stack for entering/exiting transitions.
                     for i in 0..N {
                         if let Some(mut refracted_or_reflected_ray) = rays[i] {
-                            let refracted =
-                                ray.direction.dot(refracted_or_reflected_ray.direction) > 0.0;
+                            // on the far side of the surface, which a reflection
+                            // off of rough glass can still head forward from
+                            let refracted = ray.direction.dot(ray_hit.normal)
+                                * refracted_or_reflected_ray.direction.dot(ray_hit.normal)
+                                > 0.0;
 
                             // if we're transitioning between materials, we are experiencing a change in medium.
                             if refracted {
@@ -277,8 +624,13 @@ impl Camera {
                                 scene_world,
                                 bounces + 1,
                                 medium_stack.clone(),
+                                sampler,
+                            );
+                            accumulated.add_wavelength(
+                                i,
+                                attenuation[i] * reflectance[i],
+                                &contrib,
                             );
-                            accumulated[i] += attenuation[i] * reflectance[i] * contrib[0];
                         }
                     }
                     return accumulated;
@@ -291,6 +643,7 @@ impl Camera {
                             .reflectance(ray_hit.u, ray_hit.v, ray_hit.point);
                     attenuation *= reflectance.sample(hw);
                     ray = scattered;
+                    light_sampled_from = None;
                 }
                 ScatterRecord::Pdf(scatter_pdf) => {
                     let pdf = build_mixture_pdf(
@@ -300,20 +653,45 @@ impl Camera {
                         ray_hit.point,
                     );
 
-                    let (incident_direction, index_of_strategy) = pdf.sample();
-
-                    let cos_theta = shading_normal.dot(incident_direction);
-                    if cos_theta <= 0.0 {
-                        return accumulated;
+                    if self.importance_sampling.use_next_event_estimation {
+                        let direct = self.sample_light(
+                            ray,
+                            &ray_hit,
+                            outgoing_direction,
+                            Some(&pdf),
+                            hw,
+                            scene_world,
+                            sampler,
+                        );
+                        accumulated.add(bounces + 1, attenuation * direct);
                     }
+                    // rays can't hit the scene's lights, so they're always sampled
+                    let direct = self.sample_scene_lights(
+                        ray,
+                        &ray_hit,
+                        outgoing_direction,
+                        hw,
+                        scene_world,
+                        sampler,
+                    );
+                    accumulated.add(bounces + 1, attenuation * direct);
+
+                    let (incident_direction, index_of_strategy) = pdf.sample(sampler);
+
+                    // light reflects off of the side the ray came from, and
+                    // passes through materials that transmit it
                     let brdf_val = ray_hit.material.brdf(
                         outgoing_direction,
                         incident_direction,
-                        shading_normal,
+                        ray_hit.normal,
                         ray_hit.u,
                         ray_hit.v,
                         ray_hit.point,
                     );
+                    if brdf_val.is_black() {
+                        return accumulated;
+                    }
+                    let cos_theta = ray_hit.normal.dot(incident_direction).abs();
 
                     if self.importance_sampling.use_multiple_importance_sampling {
                         let pdf_val = pdf.strategy_density(incident_direction, index_of_strategy);
@@ -334,11 +712,15 @@ impl Camera {
                         attenuation *= brdf_val.sample(hw) * (cos_theta / pdf_val);
                     }
 
+                    if self.importance_sampling.use_next_event_estimation {
+                        light_sampled_from = Some((ray_hit.point, pdf.density(incident_direction)));
+                    }
+
                     ray = Ray::new_with_medium(
                         ray_hit.point,
                         incident_direction,
                         ray.time,
-                        ray.current_medium,
+                        medium_after_scatter(&ray, &ray_hit, incident_direction, &mut medium_stack),
                     );
                 }
             }
@@ -350,7 +732,7 @@ impl Camera {
                 && bounces > after
             {
                 let p = attenuation.iter().fold(0.0_f64, |a, &b| a.max(b)).min(1.0);
-                if rng.random::<f64>() > p {
+                if sampler.get_1d() > p {
                     return accumulated;
                 }
                 attenuation /= p;
@@ -358,21 +740,420 @@ impl Camera {
         }
 
         // ray missed — add background contribution
-        accumulated += attenuation * self.background_color.sample(hw);
+        accumulated.add(
+            bounces,
+            attenuation * self.background(ray.direction, hw, scene_world),
+        );
 
         accumulated
     }
 
-    pub fn ray_color(&self, ray: Ray, scene_world: &SceneWorld) -> ColorRgb {
-        let hw = HeroWavelengths::<HERO_WAVELENGTH_COUNT>::new_distributed();
-        let accumulated =
-            self.trace_spectral::<HERO_WAVELENGTH_COUNT>(ray, &hw, scene_world, 0, Vec::new());
-        hw.to_color_rgb(accumulated)
+    /// Next-event estimation: the radiance reflected at `ray_hit` of light
+    /// arriving straight from a direction sampled toward the emitters, checked
+    /// with a shadow ray. It is weighted against `bounce_pdf` finding the same
+    /// light, with the power heuristic, if there is one to weigh it against.
+    #[allow(clippy::too_many_arguments)]
+    fn sample_light<const N: usize>(
+        &self,
+        ray: Ray,
+        ray_hit: &RayHit,
+        outgoing_direction: Vector3,
+        bounce_pdf: Option<&Pdf>,
+        hw: &HeroWavelengths<N>,
+        scene_world: &SceneWorld,
+        sampler: &mut dyn Sampler,
+    ) -> Vector<N> {
+        let lights = &scene_world.emissive_list;
+        if lights.is_empty() {
+            return Vector::ZERO;
+        }
+
+        let direction = lights.sample_direction_from(ray_hit.point, sampler.get_2d());
+        let light_density = lights.direction_pdf(ray_hit.point, direction);
+        if light_density <= 0.0 {
+            return Vector::ZERO;
+        }
+        let brdf = ray_hit.material.brdf(
+            outgoing_direction,
+            direction,
+            ray_hit.normal,
+            ray_hit.u,
+            ray_hit.v,
+            ray_hit.point,
+        );
+        if brdf.is_black() {
+            return Vector::ZERO;
+        }
+
+        // the first thing the shadow ray hits is the light it sees, if any
+        let shadow_ray =
+            Ray::new_with_medium(ray_hit.point, direction, ray.time, ray.current_medium);
+        let Some(light_hit) = scene_world
+            .world
+            .intersect(shadow_ray, Interval::new(0.001, f64::INFINITY))
+        else {
+            return Vector::ZERO;
+        };
+        let emittance =
+            light_hit
+                .material
+                .emittance(light_hit.u, light_hit.v, light_hit.point, ray.time);
+        if emittance.is_black() {
+            return Vector::ZERO;
+        }
+
+        let cos_theta = ray_hit.normal.dot(direction).abs();
+        let weight = bounce_pdf.map_or(1.0, |pdf| {
+            power_heuristic(light_density, pdf.density(direction))
+        });
+
+        brdf.sample(hw)
+            * emittance.sample(hw)
+            * ray.current_medium.transmittance(hw, light_hit.t)
+            * (cos_theta * weight / light_density)
+    }
+
+    /// The radiance reflected at `ray_hit` of light arriving straight from one
+    /// of the scene's `lights`, checked with a shadow ray.
+    #[allow(clippy::too_many_arguments)]
+    fn sample_scene_lights<const N: usize>(
+        &self,
+        ray: Ray,
+        ray_hit: &RayHit,
+        outgoing_direction: Vector3,
+        hw: &HeroWavelengths<N>,
+        scene_world: &SceneWorld,
+        sampler: &mut dyn Sampler,
+    ) -> Vector<N> {
+        let Some((direction, irradiance)) = sample_scene_light(
+            scene_world,
+            ray_hit.point,
+            ray.time,
+            ray.current_medium,
+            hw,
+            sampler,
+        ) else {
+            return Vector::ZERO;
+        };
+        let brdf = ray_hit.material.brdf(
+            outgoing_direction,
+            direction,
+            ray_hit.normal,
+            ray_hit.u,
+            ray_hit.v,
+            ray_hit.point,
+        );
+        brdf.sample(hw) * irradiance * ray_hit.normal.dot(direction).abs()
+    }
+
+    pub fn ray_color(
+        &self,
+        ray: Ray,
+        scene_world: &SceneWorld,
+        sampler: &mut dyn Sampler,
+    ) -> ColorRgb {
+        let hw = HeroWavelengths::<HERO_WAVELENGTH_COUNT>::from_sample(sampler.get_1d());
+        let accumulated = self.trace_spectral::<HERO_WAVELENGTH_COUNT>(
+            ray,
+            &hw,
+            scene_world,
+            0,
+            Vec::new(),
+            sampler,
+        );
+        hw.to_color_rgb(accumulated.total())
+    }
+
+    /// Like `ray_color`, but also reports what the ray saw for the AOVs. The
+    /// returned color is the sum of the sample's emission, direct and indirect
+    /// light.
+    pub fn ray_color_with_aovs(
+        &self,
+        ray: Ray,
+        scene_world: &SceneWorld,
+        sampler: &mut dyn Sampler,
+    ) -> (ColorRgb, AovSample) {
+        let first_hit = scene_world
+            .world
+            .intersect(ray, Interval::new(0.001, f64::INFINITY))
+            .map(|ray_hit| first_hit(&ray, &ray_hit, scene_world));
+
+        let hw = HeroWavelengths::<HERO_WAVELENGTH_COUNT>::from_sample(sampler.get_1d());
+        let accumulated = self.trace_spectral::<HERO_WAVELENGTH_COUNT>(
+            ray,
+            &hw,
+            scene_world,
+            0,
+            Vec::new(),
+            sampler,
+        );
+
+        let sample = AovSample {
+            first_hit,
+            emission: hw.to_color_rgb(accumulated.emission),
+            direct: hw.to_color_rgb(accumulated.direct),
+            indirect: hw.to_color_rgb(accumulated.indirect),
+        };
+        (sample.emission + sample.direct + sample.indirect, sample)
+    }
+}
+
+impl Camera {
+    /// The light arriving along `-direction` from beyond the whole scene.
+    fn background<const N: usize>(
+        &self,
+        direction: Vector3,
+        hw: &HeroWavelengths<N>,
+        scene_world: &SceneWorld,
+    ) -> Vector<N> {
+        match &scene_world.environment {
+            Some(environment) => environment.radiance(direction, hw),
+            None => self.background_color.sample(hw),
+        }
+    }
+}
+
+/// What the AOVs record of the first surface `ray` hits.
+fn first_hit(ray: &Ray, ray_hit: &RayHit, scene_world: &SceneWorld) -> FirstHit {
+    FirstHit {
+        albedo: ColorRgb::from(
+            ray_hit
+                .material
+                .reflectance(ray_hit.u, ray_hit.v, ray_hit.point),
+        ),
+        normal: if ray.direction.dot(ray_hit.normal) > 0.0 {
+            -ray_hit.normal
+        } else {
+            ray_hit.normal
+        },
+        depth: ray_hit.t * ray.direction.length(),
+        position: ray_hit.point,
+        material_id: scene_world.material_ids.get(&ray_hit.material),
+    }
+}
+
+/// The BRDF at `hit`, at the hero wavelengths.
+fn brdf(
+    hit: &RayHit,
+    outgoing: Vector3,
+    incident: Vector3,
+    hw: &HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+) -> Spectrum {
+    hit.material
+        .brdf(outgoing, incident, hit.normal, hit.u, hit.v, hit.point)
+        .sample(hw)
+}
+
+/// Light arriving at `point` through `medium` straight from one of the
+/// scene's `lights`, picked by power, unless something blocks it: the unit
+/// direction toward the light, and its irradiance over the chance of picking
+/// it.
+fn sample_scene_light<const N: usize>(
+    scene_world: &SceneWorld,
+    point: Point,
+    time: f64,
+    medium: Medium,
+    hw: &HeroWavelengths<N>,
+    sampler: &mut dyn Sampler,
+) -> Option<(Vector3, Vector<N>)> {
+    let distribution = scene_world.light_distribution.as_ref()?;
+    let count = scene_world.lights.len();
+    let (x, i) = distribution.sample(sampler.get_1d());
+    let probability = distribution.density(x) / count as f64;
+    if probability <= 0.0 {
+        return None;
+    }
+    let incident = scene_world.lights[i].incident(point)?;
+
+    // stopping just short of the light lets it sit right on a surface
+    let shadow_ray = Ray::new_with_medium(point, incident.direction, time, medium);
+    let distance = incident.distance * (1.0 - 1e-6);
+    if scene_world
+        .world
+        .intersect(shadow_ray, Interval::new(0.001, distance))
+        .is_some()
+    {
+        return None;
+    }
+    // a directional light's infinite distance would make clear media NaN
+    let transmittance = medium.transmittance(hw, incident.distance.min(f64::MAX));
+    let irradiance = incident.irradiance.sample(hw) * transmittance;
+    Some((incident.direction, irradiance * (1.0 / probability)))
+}
+
+/// Where light leaves the emitters: a point on them, chosen uniformly by
+/// area, and a cosine-weighted direction out of either side of the surface.
+struct EmissionSample {
+    ray_hit: RayHit,
+    point: Point,
+    normal: Vector3,
+    emittance: ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+    direction: Vector3,
+    cos_theta: f64,
+    /// the solid-angle density of `direction`
+    density: f64,
+}
+
+/// Samples where light leaves the emitters at `time`, or `None` if the
+/// point picked doesn't emit.
+fn sample_emission(
+    scene_world: &SceneWorld,
+    time: f64,
+    sampler: &mut dyn Sampler,
+) -> Option<EmissionSample> {
+    let lights = &scene_world.emissive_list;
+    let (point, normal) = lights.sample_point(sampler.get_2d())?;
+    // the material and texture coordinates of the point
+    let probe = Ray::new(point + normal * 0.001, -normal, time);
+    let ray_hit = lights.intersect(probe, Interval::new(0.0, 0.002))?;
+    let emittance = ray_hit
+        .material
+        .emittance(ray_hit.u, ray_hit.v, ray_hit.point, time);
+    if emittance.is_black() {
+        return None;
+    }
+
+    let side = if sampler.get_1d() < 0.5 {
+        normal
+    } else {
+        -normal
+    };
+    let direction =
+        Onb::from_w(side).to_world(Vector3::cosine_weighted_direction_from(sampler.get_2d()));
+    let cos_theta = side.dot(direction);
+    let density = cos_theta / (2.0 * PI);
+    (density > 0.0).then_some(EmissionSample {
+        ray_hit,
+        point,
+        normal,
+        emittance,
+        direction,
+        cos_theta,
+        density,
+    })
+}
+
+/// Follows a spectral scatter of `ray` at `ray_hit` with a single ray for all
+/// the hero wavelengths: the one they all took, or else one wavelength's,
+/// standing in for all of them. `beta` is updated to match, and
+/// `medium_stack` as in `trace_spectral`.
+fn follow_spectral_scatter(
+    ray: &Ray,
+    ray_hit: &RayHit,
+    spectral: SpectralScatter,
+    beta: &mut Spectrum,
+    medium_stack: &mut Vec<Medium>,
+    sampler: &mut dyn Sampler,
+) -> Option<Ray> {
+    let SpectralScatter { rays, reflectance } = spectral;
+    let chosen = if rays.iter().all(|r| *r == rays[0]) {
+        // every wavelength went the same way
+        *beta *= reflectance;
+        rays[0]
+    } else {
+        let count = HERO_WAVELENGTH_COUNT;
+        let i = ((sampler.get_1d() * count as f64) as usize).min(count - 1);
+        for j in 0..count {
+            beta[j] = if j == i {
+                beta[j] * reflectance[j] * count as f64
+            } else {
+                0.0
+            };
+        }
+        rays[i]
+    };
+    let mut next_ray = chosen?;
+
+    if ray.direction.dot(ray_hit.normal) * next_ray.direction.dot(ray_hit.normal) > 0.0 {
+        if ray.direction.dot(ray_hit.normal) < 0.0 {
+            medium_stack.push(ray.current_medium);
+        } else {
+            next_ray.current_medium = medium_stack.pop().unwrap_or(Medium::Vacuum);
+        }
+    }
+    Some(next_ray)
+}
+
+/// The medium a ray scattered off of `ray_hit` into `direction` by a
+/// `ScatterRecord::Pdf` travels through, updating `medium_stack` as in
+/// `trace_spectral` if it passed through into or out of a closed object.
+fn medium_after_scatter(
+    ray: &Ray,
+    ray_hit: &RayHit,
+    direction: Vector3,
+    medium_stack: &mut Vec<Medium>,
+) -> Medium {
+    let entering = ray.direction.dot(ray_hit.normal) < 0.0;
+    let crossed = (direction.dot(ray_hit.normal) < 0.0) == entering;
+    let interior = ray_hit
+        .material
+        .interior_medium(ray_hit.u, ray_hit.v, ray_hit.point);
+    match interior {
+        Some(interior) if crossed && entering => {
+            medium_stack.push(ray.current_medium);
+            interior
+        }
+        Some(_) if crossed => medium_stack.pop().unwrap_or(Medium::Vacuum),
+        _ => ray.current_medium,
+    }
+}
+
+/// Radiance gathered along a path, split by how many bounces it took to reach
+/// the camera.
+#[derive(Clone, Copy)]
+struct PathRadiance<const N: usize> {
+    emission: Vector<N>,
+    direct: Vector<N>,
+    indirect: Vector<N>,
+    /// the most surfaces any of the wavelengths' paths hit
+    surfaces_hit: u32,
+}
+
+impl<const N: usize> PathRadiance<N> {
+    const ZERO: Self = Self {
+        emission: Vector::ZERO,
+        direct: Vector::ZERO,
+        indirect: Vector::ZERO,
+        surfaces_hit: 0,
+    };
+
+    fn add(&mut self, bounces: u32, radiance: Vector<N>) {
+        match bounces {
+            0 => self.emission += radiance,
+            1 => self.direct += radiance,
+            _ => self.indirect += radiance,
+        }
+    }
+
+    /// Adds a single-wavelength sub-path's radiance, scaled, to wavelength `i`.
+    fn add_wavelength(&mut self, i: usize, scale: f64, sub_path: &PathRadiance<1>) {
+        self.emission[i] += scale * sub_path.emission[0];
+        self.direct[i] += scale * sub_path.direct[0];
+        self.indirect[i] += scale * sub_path.indirect[0];
+        self.surfaces_hit = self.surfaces_hit.max(sub_path.surfaces_hit);
+    }
+
+    fn total(&self) -> Vector<N> {
+        &(&self.emission + &self.direct) + &self.indirect
+    }
+}
+
+/// The power heuristic weight of a strategy with density `chosen` against one
+/// with density `other`.
+fn power_heuristic(chosen: f64, other: f64) -> f64 {
+    let (chosen, other) = (chosen * chosen, other * other);
+    if chosen + other > 0.0 {
+        chosen / (chosen + other)
+    } else {
+        0.0
     }
 }
 
 /// Build a mixture PDF from the BRDF scatter PDF and any enabled importance
 /// sampling categories. Skips categories with zero weight or no objects.
+///
+/// With next-event estimation, lights are sampled separately, so the emissive
+/// category is left out.
 fn build_mixture_pdf(
     scatter_pdf: Pdf,
     config: &ImportanceSamplingConfig,
@@ -387,7 +1168,10 @@ fn build_mixture_pdf(
     entries.push((scatter_pdf, config.brdf_weight));
 
     // emissive category
-    if config.emissive_weight > 0.0 && !scene_world.emissive_list.is_empty() {
+    if config.emissive_weight > 0.0
+        && !config.use_next_event_estimation
+        && !scene_world.emissive_list.is_empty()
+    {
         entries.push((
             Pdf::Geometric {
                 geometric: Arc::clone(&scene_world.emissive_list),
@@ -431,5 +1215,209 @@ fn build_mixture_pdf(
         ));
     }
 
+    // environment category — the light of rays that escape the scene
+    if config.environment_weight > 0.0
+        && let Some(environment) = &scene_world.environment
+    {
+        entries.push((
+            Pdf::Environment(Arc::clone(environment)),
+            config.environment_weight,
+        ));
+    }
+
+    // without the emissive category, nothing may be left with any weight
+    if entries.iter().all(|(_, weight)| *weight <= 0.0) {
+        return entries.swap_remove(0).0;
+    }
+
     Pdf::mixture(entries)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        geometry::{
+            Geometric,
+            primitives::{Parallelogram, Sphere},
+        },
+        shading::{
+            lights::{Light, PointLight},
+            materials::Lambertian,
+            textures::SolidColor,
+        },
+        tracing::IndependentSampler,
+        utils::seed_thread_rng,
+    };
+
+    use super::*;
+
+    #[test]
+    fn fisheye_mappings_reach_the_edge_of_the_field_of_view() {
+        let max_angle = 85.0_f64.to_radians();
+        for mapping in [FisheyeMapping::Equidistant, FisheyeMapping::Equisolid] {
+            assert_eq!(mapping.angle(0.0, max_angle), 0.0);
+            assert!((mapping.angle(1.0, max_angle) - max_angle).abs() < 1e-12);
+            assert!(mapping.angle(0.5, max_angle) < mapping.angle(0.75, max_angle));
+        }
+
+        let projection: Projection = serde_json::from_str(r#"{ "type": "fisheye" }"#).unwrap();
+        assert_eq!(
+            projection,
+            Projection::Fisheye {
+                mapping: FisheyeMapping::Equidistant,
+                field_of_view_degrees: 180.0,
+            }
+        );
+        assert!(
+            Projection::Fisheye {
+                mapping: FisheyeMapping::Equisolid,
+                field_of_view_degrees: 400.0,
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn next_event_estimation_sees_the_light_a_glowing_sphere_sends() {
+        let floor: Arc<dyn Geometric> = Arc::new(Parallelogram::new(
+            Point::new(-5.0, 0.0, 5.0),
+            Vector3::new(10.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, -10.0),
+            Arc::new(Lambertian::new(
+                Arc::new(SolidColor::WHITE),
+                Arc::new(SolidColor::BLACK),
+            )),
+        ));
+        // a quarter of the way across the sky from 2 up, at a radiance of 1
+        let glowing = Arc::new(Lambertian::new(
+            Arc::new(SolidColor::BLACK),
+            Arc::new(SolidColor::new(ColorSpectrum::ONE)),
+        ));
+        let sphere: Arc<dyn Geometric> =
+            Arc::new(Sphere::new(Point::new(0.0, 2.0, 0.0), 0.5, glowing));
+        let scene_world = SceneWorld::from_geometrics(&vec![floor, sphere], &[], false);
+
+        let ray = Ray::new(
+            Point::new(1.0, 1.0, 0.0),
+            Vector3::new(-1.0, -1.0, 0.0),
+            0.0,
+        );
+        let ray_hit = scene_world
+            .world
+            .intersect(ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        let (outgoing, normal) = (-ray.direction.unit_vector(), Vector3::UNIT_Y);
+        let hw = HeroWavelengths::<1>::new([550.0]);
+
+        seed_thread_rng(5);
+        let n = 20_000;
+        let mut total = 0.0;
+        for _ in 0..n {
+            total += Camera::default().sample_light(
+                ray,
+                &ray_hit,
+                outgoing,
+                None,
+                &hw,
+                &scene_world,
+                &mut IndependentSampler,
+            )[0];
+        }
+        let estimate = total / n as f64;
+
+        // a disk-like source covering sin²α of the hemisphere's projected
+        // area sends π·L·sin²α onto the floor
+        let brdf = ray_hit
+            .material
+            .brdf(outgoing, Vector3::UNIT_Y, normal, 0.0, 0.0, ray_hit.point)
+            .sample(&hw)[0];
+        let expected = brdf * PI * 0.25 * 0.25;
+        assert!(
+            (estimate - expected).abs() < 0.02 * expected,
+            "{estimate} {expected}"
+        );
+
+        // the weights of the two ways of finding a light always add up to 1
+        for (a, b) in [(0.3, 2.0), (5.0, 0.0), (1.0, 1.0)] {
+            assert!((power_heuristic(a, b) + power_heuristic(b, a) - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn scene_lights_are_sampled_by_power_and_blocked_by_geometry() {
+        let point = |position: Point, power: f64| {
+            Light::Point(PointLight::new(
+                position,
+                power,
+                ColorRgb::WHITE,
+                -Vector3::UNIT_Y,
+                None,
+                None,
+            ))
+        };
+        let lights = vec![
+            point(Point::new(0.0, 2.0, 0.0), 1.0),
+            point(Point::new(2.0, 0.0, 0.0), 3.0),
+        ];
+        let hw = HeroWavelengths::<1>::new([550.0]);
+        // the irradiance of each over the chance of picking it
+        let expected = lights
+            .iter()
+            .zip([0.25, 0.75])
+            .map(|(light, probability)| {
+                light
+                    .incident(Point::ORIGIN)
+                    .unwrap()
+                    .irradiance
+                    .sample(&hw)[0]
+                    / probability
+            })
+            .collect::<Vec<_>>();
+
+        // a small sphere between the origin and the first light
+        let grey = Arc::new(Lambertian::new(
+            Arc::new(SolidColor::WHITE),
+            Arc::new(SolidColor::BLACK),
+        ));
+        let blocker: Arc<dyn Geometric> =
+            Arc::new(Sphere::new(Point::new(0.0, 1.0, 0.0), 0.1, grey));
+        for blocked in [false, true] {
+            let world = if blocked {
+                vec![blocker.clone()]
+            } else {
+                Vec::new()
+            };
+            let scene_world =
+                SceneWorld::from_geometrics(&world, &[], false).with_lights(lights.clone());
+
+            seed_thread_rng(13);
+            let n = 4000;
+            let mut picked = [0; 2];
+            for _ in 0..n {
+                let sample = sample_scene_light(
+                    &scene_world,
+                    Point::ORIGIN,
+                    0.0,
+                    Medium::Vacuum,
+                    &hw,
+                    &mut IndependentSampler,
+                );
+                let Some((direction, irradiance)) = sample else {
+                    picked[0] += 1;
+                    assert!(blocked);
+                    continue;
+                };
+                let i = usize::from(direction.x > 0.5);
+                picked[i] += 1;
+                assert!(!(blocked && i == 0));
+                assert!((irradiance[0] - expected[i]).abs() < 1e-9 * expected[i]);
+            }
+            // the brighter light is picked three times as often
+            assert!(
+                (picked[1] as f64 / n as f64 - 0.75).abs() < 0.03,
+                "{picked:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,808 @@
+use std::f64::consts::PI;
+
+use crate::{
+    geometry::{Point, Ray, RayHit, Vector3},
+    shading::{
+        ColorRgb, HeroWavelengths, hero_wavelengths::HERO_WAVELENGTH_COUNT,
+        materials::ScatterRecord, medium::Medium,
+    },
+    tracing::{AovSample, IndependentSampler, LightImage, Sampler, SceneWorld},
+    utils::Interval,
+};
+
+use super::{
+    Camera, PathRadiance, Projection, Spectrum, brdf, first_hit, follow_spectral_scatter,
+    medium_after_scatter, sample_emission, sample_scene_light,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VertexKind {
+    /// On the lens.
+    Camera,
+    /// On an emitter, where a light subpath starts.
+    Light,
+    /// Where a subpath hit something.
+    Surface,
+}
+
+/// A point of a camera or light subpath.
+///
+/// Densities are by area at the vertex. A density of 0 stands for sampling
+/// through a delta scatter, which is the same for every strategy that can
+/// make the path, so it drops out of the MIS weights.
+#[derive(Debug, Clone)]
+struct Vertex {
+    kind: VertexKind,
+    point: Point,
+    /// the geometric normal, or zero on the lens
+    normal: Vector3,
+    /// the surface the vertex lies on, for all but the lens
+    hit: Option<RayHit>,
+    /// the medium the subpath arrived through
+    medium: Medium,
+    /// the subpath's throughput up to the vertex. On emitters, the emittance
+    /// over the density the vertex was sampled with
+    beta: Spectrum,
+    /// whether the vertex scatters into single directions, so connections
+    /// to it can't carry any light. On the lens, whether the camera can't be
+    /// connected to at all
+    delta: bool,
+    /// the density of sampling this vertex from the previous one of its subpath
+    pdf_fwd: f64,
+    /// the density of sampling this vertex from the next one, going the other way
+    pdf_rev: f64,
+}
+
+impl Vertex {
+    fn camera(camera: &Camera, point: Point) -> Self {
+        Self {
+            kind: VertexKind::Camera,
+            point,
+            normal: Vector3::ZERO,
+            hit: None,
+            medium: Medium::Vacuum,
+            beta: Spectrum::ONE,
+            delta: matches!(camera.projection, Projection::Orthographic { .. }),
+            pdf_fwd: 1.0,
+            pdf_rev: 0.0,
+        }
+    }
+
+    fn light(ray_hit: RayHit, point: Point, normal: Vector3, beta: Spectrum, pdf: f64) -> Self {
+        Self {
+            kind: VertexKind::Light,
+            point,
+            normal,
+            hit: Some(ray_hit),
+            medium: Medium::Vacuum,
+            beta,
+            delta: false,
+            pdf_fwd: pdf,
+            pdf_rev: 0.0,
+        }
+    }
+
+    /// Converts a solid-angle density of sampling the direction from this
+    /// vertex to `next` into a density by area at `next`.
+    fn convert_density(&self, density: f64, next: &Vertex) -> f64 {
+        let offset = self.point.to(next.point);
+        let distance_squared = offset.squared_length();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+        let cos_theta = match next.kind {
+            VertexKind::Camera => 1.0,
+            _ => next.normal.dot(offset).abs() / distance_squared.sqrt(),
+        };
+        density * cos_theta / distance_squared
+    }
+
+    /// The light arriving from `light_side` that this vertex sends toward
+    /// `camera_side`, per unit of incident light: the BRDF on surfaces, and 1
+    /// on emitters, which send their emittance every way alike.
+    fn scattering(
+        &self,
+        camera_side: Point,
+        light_side: Point,
+        hw: &HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+    ) -> Spectrum {
+        match &self.hit {
+            Some(hit) if self.kind == VertexKind::Surface => brdf(
+                hit,
+                self.point.to(camera_side).unit_vector(),
+                self.point.to(light_side).unit_vector(),
+                hw,
+            ),
+            _ => Spectrum::ONE,
+        }
+    }
+}
+
+/// What all the vertices of one sample's subpaths share.
+struct PathContext<'a> {
+    hw: &'a HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+    scene_world: &'a SceneWorld,
+    time: f64,
+    /// how many pixels the light subpaths that connect to the camera are
+    /// spread over
+    sampled_pixel_count: f64,
+}
+
+impl PathContext<'_> {
+    /// The density by area that light subpaths start at any point of the
+    /// emitters, or 0 if they can't start anywhere.
+    fn light_density(&self) -> f64 {
+        let area = self.scene_world.emissive_list.surface_area();
+        if area.is_finite() && area > 0.0 {
+            1.0 / area
+        } else {
+            0.0
+        }
+    }
+
+    /// Whether nothing lies between `from` and `to`. The gap left before `to`
+    /// is relative and tiny, unlike the one after `from`, so that a surface
+    /// `to` sits just behind, like the far side of a wall it meets, still
+    /// hides it.
+    fn is_visible(&self, from: Point, to: Point) -> bool {
+        let offset = from.to(to);
+        let distance = offset.length();
+        let ray = Ray::new(from, offset / distance, self.time);
+        self.scene_world
+            .world
+            .intersect(ray, Interval::new(0.001, distance * (1.0 - 1e-6)))
+            .is_none()
+    }
+}
+
+/// The light from one of the scene's `lights` that `vertex` sends toward
+/// `camera_side`, times the subpath's throughput up to it.
+fn scene_light_at(
+    context: &PathContext,
+    vertex: &Vertex,
+    camera_side: Point,
+    sampler: &mut dyn Sampler,
+) -> Spectrum {
+    let Some(hit) = &vertex.hit else {
+        return Spectrum::ZERO;
+    };
+    if vertex.kind != VertexKind::Surface || vertex.delta {
+        return Spectrum::ZERO;
+    }
+    let Some((direction, irradiance)) = sample_scene_light(
+        context.scene_world,
+        vertex.point,
+        context.time,
+        vertex.medium,
+        context.hw,
+        sampler,
+    ) else {
+        return Spectrum::ZERO;
+    };
+
+    let outgoing = vertex.point.to(camera_side).unit_vector();
+    let cos_theta = hit.normal.dot(direction).abs();
+    vertex.beta * brdf(hit, outgoing, direction, context.hw) * irradiance * cos_theta
+}
+
+/// Which way a subpath carries light, which decides what a BRDF sees as the
+/// outgoing direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Flow {
+    /// From the camera, against the flow of light.
+    Importance,
+    /// From an emitter, with the flow of light.
+    Radiance,
+}
+
+impl Camera {
+    /// Like `ray_color_with_aovs`, but with bidirectional path tracing. A light
+    /// subpath is traced along with `ray`'s camera subpath, and every way of
+    /// joining the two is weighted by multiple importance sampling.
+    ///
+    /// Joins that go straight from the light subpath to the camera land in
+    /// other pixels, so they're splatted into `light_image` instead of being
+    /// returned, and aren't part of the AOVs. The first hit is only worked
+    /// out if `store_aovs` is set.
+    pub fn ray_color_bidirectional(
+        &self,
+        ray: Ray,
+        scene_world: &SceneWorld,
+        sampler: &mut dyn Sampler,
+        light_image: &LightImage,
+        store_aovs: bool,
+    ) -> (ColorRgb, AovSample) {
+        let hw = HeroWavelengths::<HERO_WAVELENGTH_COUNT>::from_sample(sampler.get_1d());
+        // unit directions keep hit distances in world units, for the media
+        let ray = Ray {
+            direction: ray.direction.unit_vector(),
+            ..ray
+        };
+        let context = PathContext {
+            hw: &hw,
+            scene_world,
+            time: ray.time,
+            sampled_pixel_count: light_image.sampled_pixel_count() as f64,
+        };
+
+        // a path with `max` bounces has `max + 2` vertices, counting both ends
+        let max_vertices = self.bounces.max as usize + 2;
+        let mut radiance = PathRadiance::<HERO_WAVELENGTH_COUNT>::ZERO;
+        let camera = self.camera_subpath(ray, &context, sampler, &mut radiance);
+        let light = self.light_subpath(&context, sampler);
+        light_image.add_path();
+
+        for t in 1..=camera.len() {
+            // joining a single light vertex samples its own, so it doesn't
+            // need the light subpath to have started
+            for s in 0..=light.len().max(1) {
+                // a camera vertex alone sees nothing, and the light seen
+                // straight on is left to the camera subpath finding it
+                if s + t < 2 || s + t > max_vertices || (s == 1 && t == 1) {
+                    continue;
+                }
+                let Some((contribution, film_position)) =
+                    self.connect(&context, &camera, &light, s, t, sampler)
+                else {
+                    continue;
+                };
+                match film_position {
+                    Some(film_position) => {
+                        light_image.splat(film_position, hw.to_color_rgb(contribution))
+                    }
+                    None => radiance.add((s + t - 2) as u32, contribution),
+                }
+            }
+        }
+        // rays can't hit the scene's lights, so joining them to the camera
+        // subpath is the only way to find them, and needs no weighting
+        for t in 2..=camera.len().min(max_vertices - 1) {
+            let contribution =
+                scene_light_at(&context, &camera[t - 1], camera[t - 2].point, sampler);
+            radiance.add((t - 1) as u32, contribution);
+        }
+
+        let sample = AovSample {
+            first_hit: camera
+                .get(1)
+                .filter(|_| store_aovs)
+                .and_then(|vertex| vertex.hit.as_ref())
+                .map(|ray_hit| first_hit(&ray, ray_hit, scene_world)),
+            emission: hw.to_color_rgb(radiance.emission),
+            direct: hw.to_color_rgb(radiance.direct),
+            indirect: hw.to_color_rgb(radiance.indirect),
+        };
+        (sample.emission + sample.direct + sample.indirect, sample)
+    }
+
+    /// Traces the camera subpath of `ray`. Light that only the camera subpath
+    /// can find, i.e. the background and glowing media, goes straight into
+    /// `radiance`.
+    fn camera_subpath(
+        &self,
+        ray: Ray,
+        context: &PathContext,
+        sampler: &mut dyn Sampler,
+        radiance: &mut PathRadiance<HERO_WAVELENGTH_COUNT>,
+    ) -> Vec<Vertex> {
+        let frame = self.frame_at(ray.time);
+        let density = self
+            .film_position_of(&frame, ray.origin, ray.direction)
+            .map_or(0.0, |(_, density)| density / context.sampled_pixel_count);
+
+        let mut path = vec![Vertex::camera(self, ray.origin)];
+        self.random_walk(
+            ray,
+            Spectrum::ONE,
+            density,
+            Flow::Importance,
+            context,
+            sampler,
+            &mut path,
+            Some(radiance),
+        );
+        path
+    }
+
+    /// Traces a light subpath from a point on the emitters, chosen uniformly
+    /// by area, leaving either side of the surface with a cosine-weighted
+    /// direction.
+    fn light_subpath(&self, context: &PathContext, sampler: &mut dyn Sampler) -> Vec<Vertex> {
+        let light_density = context.light_density();
+        if light_density <= 0.0 {
+            return Vec::new();
+        }
+        let Some(emission) = sample_emission(context.scene_world, context.time, sampler) else {
+            return Vec::new();
+        };
+
+        let beta = emission.emittance.sample(context.hw) * (1.0 / light_density);
+        let mut path = vec![Vertex::light(
+            emission.ray_hit,
+            emission.point,
+            emission.normal,
+            beta,
+            light_density,
+        )];
+        self.random_walk(
+            Ray::new(emission.point, emission.direction, context.time),
+            beta * (emission.cos_theta / emission.density),
+            emission.density,
+            Flow::Radiance,
+            context,
+            sampler,
+            &mut path,
+            None,
+        );
+        path
+    }
+
+    /// Extends `path` by following `ray`, which leaves its last vertex with
+    /// throughput `beta` in a direction sampled with solid-angle `density`,
+    /// scattering by the materials it hits.
+    #[allow(clippy::too_many_arguments)]
+    fn random_walk(
+        &self,
+        mut ray: Ray,
+        mut beta: Spectrum,
+        mut density: f64,
+        flow: Flow,
+        context: &PathContext,
+        sampler: &mut dyn Sampler,
+        path: &mut Vec<Vertex>,
+        mut radiance: Option<&mut PathRadiance<HERO_WAVELENGTH_COUNT>>,
+    ) {
+        let hw = context.hw;
+        // camera subpaths end on the emitter, light subpaths connect to the camera
+        let max_vertices = self.bounces.max as usize
+            + match flow {
+                Flow::Importance => 2,
+                Flow::Radiance => 1,
+            };
+        let initial_beta = beta.iter().fold(0.0_f64, |a, &b| a.max(b));
+        let mut medium_stack: Vec<Medium> = Vec::new();
+
+        while path.len() < max_vertices {
+            // every vertex but the first has scattered once
+            let bounces = path.len() as u32 - 1;
+            let Some(ray_hit) = context
+                .scene_world
+                .world
+                .intersect(ray, Interval::new(0.001, f64::INFINITY))
+            else {
+                if let Some(radiance) = radiance.as_deref_mut() {
+                    radiance.add(
+                        bounces,
+                        beta * self.background(ray.direction, hw, context.scene_world),
+                    );
+                }
+                return;
+            };
+
+            if let Some(radiance) = radiance.as_deref_mut() {
+                radiance.add(bounces, beta * ray.current_medium.emission(hw, ray_hit.t));
+            }
+            beta *= ray.current_medium.transmittance(hw, ray_hit.t);
+
+            let previous = path.len() - 1;
+            let mut vertex = Vertex {
+                kind: VertexKind::Surface,
+                point: ray_hit.point,
+                normal: ray_hit.normal,
+                hit: None,
+                medium: ray.current_medium,
+                beta,
+                delta: false,
+                pdf_fwd: 0.0,
+                pdf_rev: 0.0,
+            };
+            vertex.pdf_fwd = path[previous].convert_density(density, &vertex);
+
+            let reflectance = ray_hit
+                .material
+                .reflectance(ray_hit.u, ray_hit.v, ray_hit.point);
+            let scatter = if path.len() + 1 < max_vertices && !reflectance.is_black() {
+                ray_hit.material.scatter(ray, &ray_hit, hw, sampler)
+            } else {
+                None
+            };
+            let Some(scatter) = scatter else {
+                vertex.hit = Some(ray_hit);
+                path.push(vertex);
+                return;
+            };
+
+            let toward_previous = -ray.direction;
+            let (next_ray, reverse_density) = match scatter {
+                ScatterRecord::Pdf(pdf) => {
+                    let (direction, _) = pdf.sample(sampler);
+                    density = pdf.density(direction);
+                    let f = match flow {
+                        Flow::Importance => brdf(&ray_hit, toward_previous, direction, hw),
+                        Flow::Radiance => brdf(&ray_hit, direction, toward_previous, hw),
+                    };
+                    if density <= 0.0 || f.iter().all(|&f| f == 0.0) {
+                        vertex.hit = Some(ray_hit);
+                        path.push(vertex);
+                        return;
+                    }
+                    beta *= f * (ray_hit.normal.dot(direction).abs() / density);
+
+                    let medium = medium_after_scatter(&ray, &ray_hit, direction, &mut medium_stack);
+                    let next_ray = Ray::new_with_medium(ray_hit.point, direction, ray.time, medium);
+                    // the density of the same bounce, taken the other way
+                    let reverse_arrival = Ray::new_with_medium(
+                        ray_hit.point + direction,
+                        -direction,
+                        ray.time,
+                        medium,
+                    );
+                    let reverse_density =
+                        scattering_density(&ray_hit, reverse_arrival, toward_previous, hw);
+                    (next_ray, reverse_density)
+                }
+                ScatterRecord::Delta { scattered } => {
+                    beta *= reflectance.sample(hw);
+                    vertex.delta = true;
+                    density = 0.0;
+                    (scattered, 0.0)
+                }
+                ScatterRecord::Spectral(spectral) => {
+                    vertex.delta = true;
+                    density = 0.0;
+                    let Some(next_ray) = follow_spectral_scatter(
+                        &ray,
+                        &ray_hit,
+                        *spectral,
+                        &mut beta,
+                        &mut medium_stack,
+                        sampler,
+                    ) else {
+                        vertex.hit = Some(ray_hit);
+                        path.push(vertex);
+                        return;
+                    };
+                    (next_ray, 0.0)
+                }
+            };
+
+            vertex.hit = Some(ray_hit);
+            path[previous].pdf_rev = vertex.convert_density(reverse_density, &path[previous]);
+            path.push(vertex);
+
+            if let Some(after) = self.bounces.use_russian_roulette_after
+                && bounces + 1 > after
+            {
+                let p = (beta.iter().fold(0.0_f64, |a, &b| a.max(b)) / initial_beta).min(1.0);
+                if sampler.get_1d() > p {
+                    return;
+                }
+                beta /= p;
+            }
+
+            ray = Ray {
+                direction: next_ray.direction.unit_vector(),
+                ..next_ray
+            };
+        }
+    }
+
+    /// The light carried by the path that joins the first `t` vertices of the
+    /// camera subpath to the first `s` of the light subpath, weighted by
+    /// multiple importance sampling. Paths that end on the lens also return
+    /// the film position they land on.
+    ///
+    /// Joining a single light vertex samples a new one on the emitters, toward
+    /// the camera subpath, and joining a single camera vertex samples a new
+    /// point on the lens.
+    fn connect(
+        &self,
+        context: &PathContext,
+        camera: &[Vertex],
+        light: &[Vertex],
+        s: usize,
+        t: usize,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(Spectrum, Option<(f64, f64)>)> {
+        let hw = context.hw;
+        let mut camera_end = None;
+        let mut light_end = None;
+        let mut film_position = None;
+
+        let radiance = if s == 0 {
+            // the camera subpath hit an emitter itself
+            let z = &camera[t - 1];
+            let hit = z.hit.as_ref()?;
+            let emittance = hit
+                .material
+                .emittance(hit.u, hit.v, hit.point, context.time);
+            if emittance.is_black() {
+                return None;
+            }
+            z.beta * emittance.sample(hw)
+        } else if t == 1 {
+            // the light subpath seen by the camera
+            let y = &light[s - 1];
+            if y.delta {
+                return None;
+            }
+            let frame = self.frame_at(context.time);
+            let lens_point = self.lens_point(&frame, sampler.get_2d())?;
+            let offset = lens_point.to(y.point);
+            let (position, film_density) = self.film_position_of(&frame, lens_point, offset)?;
+            if !context.is_visible(lens_point, y.point) {
+                return None;
+            }
+
+            let distance = offset.length();
+            let cos_theta = y.normal.dot(offset).abs() / distance;
+            let f = match s {
+                1 => Spectrum::ONE,
+                _ => y.scattering(lens_point, light[s - 2].point, hw),
+            };
+            film_position = Some(position);
+            camera_end = Some(Vertex::camera(self, lens_point));
+            y.beta
+                * f
+                * y.medium.transmittance(hw, distance)
+                * (cos_theta * film_density / (distance * distance))
+        } else if s == 1 {
+            // a new point on the emitters, sampled toward the camera subpath
+            let z = &camera[t - 1];
+            let lights = &context.scene_world.emissive_list;
+            if z.delta || lights.is_empty() {
+                return None;
+            }
+            let direction = lights.sample_direction_from(z.point, sampler.get_2d());
+            let density = lights.direction_pdf(z.point, direction);
+            if density <= 0.0 {
+                return None;
+            }
+            let shadow_ray = Ray::new_with_medium(z.point, direction, context.time, z.medium);
+            let light_hit = context
+                .scene_world
+                .world
+                .intersect(shadow_ray, Interval::new(0.001, f64::INFINITY))?;
+            let emittance = light_hit.material.emittance(
+                light_hit.u,
+                light_hit.v,
+                light_hit.point,
+                context.time,
+            );
+            if emittance.is_black() {
+                return None;
+            }
+
+            let f = z.scattering(camera[t - 2].point, light_hit.point, hw);
+            let cos_theta = z.normal.dot(direction).abs();
+            let radiance = z.beta
+                * f
+                * emittance.sample(hw)
+                * z.medium.transmittance(hw, light_hit.t)
+                * (cos_theta / density);
+            // weighted as if it had been sampled by area, like light subpaths
+            // start, which is all the weights of the other strategies need
+            let (point, normal) = (light_hit.point, light_hit.normal);
+            light_end = Some(Vertex::light(
+                light_hit,
+                point,
+                normal,
+                Spectrum::ZERO,
+                context.light_density(),
+            ));
+            radiance
+        } else {
+            let (z, y) = (&camera[t - 1], &light[s - 1]);
+            if z.delta || y.delta || !context.is_visible(z.point, y.point) {
+                return None;
+            }
+            let offset = z.point.to(y.point);
+            let distance_squared = offset.squared_length();
+            let geometry = z.normal.dot(offset).abs() * y.normal.dot(offset).abs()
+                / (distance_squared * distance_squared);
+            z.beta
+                * z.scattering(camera[t - 2].point, y.point, hw)
+                * y.scattering(z.point, light[s - 2].point, hw)
+                * y.beta
+                * z.medium.transmittance(hw, distance_squared.sqrt())
+                * geometry
+        };
+
+        if radiance.iter().all(|&r| r == 0.0) {
+            return None;
+        }
+        let weight = self.mis_weight(
+            context,
+            camera,
+            light,
+            s,
+            t,
+            camera_end.as_ref(),
+            light_end.as_ref(),
+        );
+        Some((radiance * weight, film_position))
+    }
+
+    /// The power heuristic weight of the strategy joining `s` light vertices
+    /// to `t` camera vertices, against all the other strategies that could
+    /// have made the same path. `camera_end` and `light_end` stand in for the
+    /// last vertices of the subpaths when the join sampled new ones.
+    #[allow(clippy::too_many_arguments)]
+    fn mis_weight(
+        &self,
+        context: &PathContext,
+        camera: &[Vertex],
+        light: &[Vertex],
+        s: usize,
+        t: usize,
+        camera_end: Option<&Vertex>,
+        light_end: Option<&Vertex>,
+    ) -> f64 {
+        if s + t == 2 {
+            return 1.0;
+        }
+
+        // the vertices of the path, from the lens to the emitter
+        let k = s + t - 1;
+        let vertex = |i: usize| -> &Vertex {
+            if i < t {
+                match camera_end {
+                    Some(end) if i == t - 1 => end,
+                    _ => &camera[i],
+                }
+            } else {
+                match light_end {
+                    Some(end) if i == k => end,
+                    _ => &light[k - i],
+                }
+            }
+        };
+
+        // the densities of sampling each vertex from the camera's and the
+        // emitter's side of the path
+        let mut camera_density = (0..=k)
+            .map(|i| {
+                let v = vertex(i);
+                if i < t { v.pdf_fwd } else { v.pdf_rev }
+            })
+            .collect::<Vec<_>>();
+        let mut light_density = (0..=k)
+            .map(|i| {
+                let v = vertex(i);
+                if i < t { v.pdf_rev } else { v.pdf_fwd }
+            })
+            .collect::<Vec<_>>();
+        // the emitter end is never scattered from
+        let delta = (0..=k)
+            .map(|i| i < k && vertex(i).delta)
+            .collect::<Vec<_>>();
+
+        // the densities at the join depend on both subpaths
+        let z = vertex(t - 1);
+        let direction_density = |from: &Vertex, previous: Option<&Vertex>, to: &Vertex| {
+            from.convert_density(self.direction_density(context, from, previous, to), to)
+        };
+        if s == 0 {
+            let is_sampled_light = z.hit.as_ref().is_some_and(|hit| hit.material.is_emissive());
+            light_density[k] = if is_sampled_light {
+                context.light_density()
+            } else {
+                0.0
+            };
+            if t >= 3 {
+                let emitter = Vertex {
+                    kind: VertexKind::Light,
+                    ..z.clone()
+                };
+                light_density[t - 2] = direction_density(&emitter, None, vertex(t - 2));
+            }
+        } else {
+            let y = vertex(t);
+            light_density[t - 1] = direction_density(y, (s >= 2).then(|| vertex(t + 1)), z);
+            camera_density[t] = direction_density(z, (t >= 2).then(|| vertex(t - 2)), y);
+            if t >= 3 {
+                light_density[t - 2] = direction_density(z, Some(y), vertex(t - 2));
+            }
+            if s >= 2 {
+                camera_density[t + 1] = direction_density(y, Some(z), vertex(t + 1));
+            }
+        }
+
+        // densities through delta scatters are 0, and cancel out, except the
+        // emitter end's, which is 0 when light subpaths can't start there
+        let remap = |density: f64| if density == 0.0 { 1.0 } else { density };
+        let camera_at = |i: usize| remap(camera_density[i]);
+        let light_at = |i: usize| {
+            if i == k {
+                light_density[k]
+            } else {
+                remap(light_density[i])
+            }
+        };
+        let ratio = |numerator: f64, denominator: f64| {
+            if denominator > 0.0 {
+                numerator / denominator
+            } else {
+                0.0
+            }
+        };
+
+        let mut sum = 1.0;
+        // moving the join toward the emitter
+        let mut r = 1.0;
+        for i in t..=k {
+            r *= ratio(camera_at(i), light_at(i));
+            // joins x_i to x_(i + 1), or for i = k, hits the emitter
+            if i == k || (!delta[i] && !delta[i + 1]) {
+                sum += r * r;
+            }
+        }
+        // moving the join toward the camera
+        let mut r = 1.0;
+        for i in (1..t).rev() {
+            r *= ratio(light_at(i), camera_at(i));
+            // joins x_(i - 1) to x_i, except the lens to an emitter, which
+            // is never sampled
+            let is_lens_to_emitter = i == 1 && k == 1;
+            if !delta[i - 1] && !delta[i] && !is_lens_to_emitter {
+                sum += r * r;
+            }
+        }
+
+        1.0 / sum
+    }
+
+    /// The solid-angle density of `vertex` sending its subpath on toward
+    /// `next`, having arrived from `previous`. The lens and emitters don't
+    /// need a previous vertex.
+    fn direction_density(
+        &self,
+        context: &PathContext,
+        vertex: &Vertex,
+        previous: Option<&Vertex>,
+        next: &Vertex,
+    ) -> f64 {
+        let direction = vertex.point.to(next.point).unit_vector();
+        match vertex.kind {
+            VertexKind::Camera => {
+                let frame = self.frame_at(context.time);
+                self.film_position_of(&frame, vertex.point, direction)
+                    .map_or(0.0, |(_, density)| density / context.sampled_pixel_count)
+            }
+            VertexKind::Light => vertex.normal.dot(direction).abs() / (2.0 * PI),
+            VertexKind::Surface => match (&vertex.hit, previous) {
+                (Some(hit), Some(previous)) => scattering_density(
+                    hit,
+                    Ray::new_with_medium(
+                        previous.point,
+                        previous.point.to(vertex.point).unit_vector(),
+                        context.time,
+                        vertex.medium,
+                    ),
+                    direction,
+                    context.hw,
+                ),
+                _ => 0.0,
+            },
+        }
+    }
+}
+
+/// The solid-angle density of the material at `hit` scattering a ray that
+/// arrived along `arrival` into `direction`, or 0 for delta scatters.
+fn scattering_density(
+    hit: &RayHit,
+    arrival: Ray,
+    direction: Vector3,
+    hw: &HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+) -> f64 {
+    // scatter densities don't depend on random decisions, only delta
+    // scatters make any, so it's given a sampler of its own
+    match hit
+        .material
+        .scatter(arrival, hit, hw, &mut IndependentSampler)
+    {
+        Some(ScatterRecord::Pdf(pdf)) => pdf.density(direction),
+        _ => 0.0,
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{
+    geometry::{Onb, Ray, Vector3, compounds::take_bvh_nodes_visited},
+    shading::{ColorRgb, HeroWavelengths, hero_wavelengths::HERO_WAVELENGTH_COUNT},
+    tracing::{AovSample, DebugConfig, DebugView, Sampler, SceneWorld, heatmap},
+    utils::Interval,
+};
+
+use super::{Camera, first_hit};
+
+impl Camera {
+    /// What `view` shows along `ray`. Rays that miss everything are black,
+    /// except in the BVH cost heatmap, which counts them too.
+    pub fn ray_color_debug(
+        &self,
+        view: DebugView,
+        config: &DebugConfig,
+        ray: Ray,
+        scene_world: &SceneWorld,
+        sampler: &mut dyn Sampler,
+    ) -> (ColorRgb, AovSample) {
+        let ray = Ray {
+            direction: ray.direction.unit_vector(),
+            ..ray
+        };
+
+        take_bvh_nodes_visited();
+        let ray_hit = scene_world
+            .world
+            .intersect(ray, Interval::new(0.001, f64::INFINITY));
+        let nodes_visited = take_bvh_nodes_visited();
+
+        let sample = AovSample {
+            first_hit: ray_hit
+                .as_ref()
+                .map(|ray_hit| first_hit(&ray, ray_hit, scene_world)),
+            ..Default::default()
+        };
+        if view == DebugView::BvhCost {
+            let color = heatmap(nodes_visited as f64 / config.max_bvh_cost as f64);
+            return (color, sample);
+        }
+        let (Some(ray_hit), Some(hit)) = (ray_hit, sample.first_hit) else {
+            return (ColorRgb::BLACK, sample);
+        };
+
+        let color = match view {
+            DebugView::Normals => ColorRgb::from_vector(hit.normal * 0.5 + Vector3::ONE * 0.5),
+            DebugView::Uv => ColorRgb::new(ray_hit.u, ray_hit.v, 0.0),
+            DebugView::Albedo => hit.albedo,
+            DebugView::AmbientOcclusion => {
+                let direction = Onb::from_w(hit.normal)
+                    .to_world(Vector3::cosine_weighted_direction_from(sampler.get_2d()));
+                let occlusion_ray = Ray::new(ray_hit.point, direction, ray.time);
+                let distance = config.ambient_occlusion_distance.unwrap_or(f64::INFINITY);
+                match scene_world
+                    .world
+                    .intersect(occlusion_ray, Interval::new(0.001, distance))
+                {
+                    Some(_) => ColorRgb::BLACK,
+                    None => ColorRgb::WHITE,
+                }
+            }
+            DebugView::PathLength => {
+                let hw = HeroWavelengths::<HERO_WAVELENGTH_COUNT>::from_sample(sampler.get_1d());
+                let path = self.trace_spectral::<HERO_WAVELENGTH_COUNT>(
+                    ray,
+                    &hw,
+                    scene_world,
+                    0,
+                    Vec::new(),
+                    sampler,
+                );
+                // a path hits up to one surface more than it bounces
+                heatmap(path.surfaces_hit as f64 / (self.bounces.max + 1) as f64)
+            }
+            DebugView::BvhCost => unreachable!("handled before the first hit is needed"),
+        };
+        (color, sample)
+    }
+}
@@ -0,0 +1,333 @@
+use std::f64::consts::PI;
+
+use rayon::prelude::*;
+
+use crate::{
+    geometry::Ray,
+    shading::{
+        ColorRgb, HeroWavelengths, hero_wavelengths::HERO_WAVELENGTH_COUNT,
+        materials::ScatterRecord,
+    },
+    tracing::{
+        AovSample, IndependentSampler, Photon, PhotonMap, Sampler, SceneWorld, VisiblePoint,
+    },
+    utils::{Interval, hash_u64s, seed_thread_rng},
+};
+
+use super::{
+    Camera, PathRadiance, Projection, Spectrum, brdf, first_hit, follow_spectral_scatter,
+    medium_after_scatter, sample_emission,
+};
+
+/// Photons traced from each random stream. Chunks are traced in parallel, and
+/// a pass comes out the same however they're scheduled.
+const PHOTONS_PER_CHUNK: u32 = 1024;
+
+impl Camera {
+    /// Traces a pass of `count` photons from the emitters, chosen uniformly by
+    /// area, and the scene's lights, drawing random numbers from streams
+    /// derived from `seed`. All the emitters together are as likely to send
+    /// a photon as each light.
+    ///
+    /// Photons are only kept where they reach a surface that isn't a mirror
+    /// or glass after bouncing at least once, since the visible points sample
+    /// the light coming straight from the emitters and lights themselves.
+    pub fn trace_photons(&self, scene_world: &SceneWorld, count: u32, seed: u64) -> PhotonMap {
+        let area = scene_world.emissive_list.surface_area();
+        let area = if area.is_finite() && area > 0.0 {
+            area
+        } else {
+            0.0
+        };
+        if area == 0.0 && scene_world.lights.is_empty() {
+            return PhotonMap::new(Vec::new(), u64::from(count));
+        }
+
+        let chunks = (0..count.div_ceil(PHOTONS_PER_CHUNK))
+            .into_par_iter()
+            .map(|chunk| {
+                // pixels' streams are seeded with coordinates that fit in 32 bits
+                seed_thread_rng(hash_u64s(&[seed, u64::MAX, u64::from(chunk)]));
+                let mut photons = Vec::new();
+                let first = chunk * PHOTONS_PER_CHUNK;
+                for _ in first..(first + PHOTONS_PER_CHUNK).min(count) {
+                    self.trace_photon(scene_world, area, &mut IndependentSampler, &mut photons);
+                }
+                photons
+            })
+            .collect::<Vec<_>>();
+
+        PhotonMap::new(chunks.concat(), u64::from(count))
+    }
+
+    /// Traces a photon from the emitters, whose area is `area`, or from one
+    /// of the scene's lights.
+    fn trace_photon(
+        &self,
+        scene_world: &SceneWorld,
+        area: f64,
+        sampler: &mut dyn Sampler,
+        photons: &mut Vec<Photon>,
+    ) {
+        let time = self.frame_start_time + self.shutter.time_at(sampler.get_1d());
+        let hw = HeroWavelengths::<HERO_WAVELENGTH_COUNT>::from_sample(sampler.get_1d());
+
+        let lights = &scene_world.lights;
+        let sources = lights.len() + usize::from(area > 0.0);
+        let source = if lights.is_empty() {
+            0
+        } else {
+            ((sampler.get_1d() * sources as f64) as usize).min(sources - 1)
+        };
+        // the power sent out over the densities of the source, the point and
+        // the direction
+        let (mut beta, mut ray): (Spectrum, Ray) = if source < lights.len() {
+            let Some(emission) = lights[source].sample_emission(
+                scene_world.bounds,
+                sampler.get_2d(),
+                sampler.get_2d(),
+            ) else {
+                return;
+            };
+            (
+                emission.power.sample(&hw) * sources as f64,
+                Ray::new(emission.origin, emission.direction, time),
+            )
+        } else {
+            let Some(emission) = sample_emission(scene_world, time, sampler) else {
+                return;
+            };
+            (
+                emission.emittance.sample(&hw)
+                    * (area * emission.cos_theta / emission.density * sources as f64),
+                Ray::new(emission.point, emission.direction, time),
+            )
+        };
+        let initial_beta = beta.iter().fold(0.0_f64, |a, &b| a.max(b));
+        let mut medium_stack = Vec::new();
+
+        // a photon that has bounced `bounces` times lights a visible point's
+        // path with one bounce more
+        for bounces in 0..self.bounces.max {
+            let Some(ray_hit) = scene_world
+                .world
+                .intersect(ray, Interval::new(0.001, f64::INFINITY))
+            else {
+                return;
+            };
+            beta *= ray.current_medium.transmittance(&hw, ray_hit.t);
+
+            let reflectance = ray_hit
+                .material
+                .reflectance(ray_hit.u, ray_hit.v, ray_hit.point);
+            if reflectance.is_black() {
+                return;
+            }
+            let Some(scatter) = ray_hit.material.scatter(ray, &ray_hit, &hw, sampler) else {
+                return;
+            };
+
+            let toward_previous = -ray.direction;
+            let next_ray = match scatter {
+                ScatterRecord::Pdf(pdf) => {
+                    if bounces > 0 {
+                        photons.push(Photon {
+                            position: ray_hit.point,
+                            incident: toward_previous,
+                            wavelengths: hw,
+                            power: beta,
+                        });
+                    }
+                    if bounces + 1 == self.bounces.max {
+                        return;
+                    }
+
+                    let (direction, _) = pdf.sample(sampler);
+                    let density = pdf.density(direction);
+                    let f = brdf(&ray_hit, direction, toward_previous, &hw);
+                    if density <= 0.0 || f.iter().all(|&f| f == 0.0) {
+                        return;
+                    }
+                    beta *= f * (ray_hit.normal.dot(direction).abs() / density);
+                    let medium = medium_after_scatter(&ray, &ray_hit, direction, &mut medium_stack);
+                    Ray::new_with_medium(ray_hit.point, direction, ray.time, medium)
+                }
+                ScatterRecord::Delta { scattered } => {
+                    beta *= reflectance.sample(&hw);
+                    scattered
+                }
+                ScatterRecord::Spectral(spectral) => {
+                    let Some(next_ray) = follow_spectral_scatter(
+                        &ray,
+                        &ray_hit,
+                        *spectral,
+                        &mut beta,
+                        &mut medium_stack,
+                        sampler,
+                    ) else {
+                        return;
+                    };
+                    next_ray
+                }
+            };
+
+            if let Some(after) = self.bounces.use_russian_roulette_after
+                && bounces + 1 > after
+            {
+                let p = (beta.iter().fold(0.0_f64, |a, &b| a.max(b)) / initial_beta).min(1.0);
+                if sampler.get_1d() > p {
+                    return;
+                }
+                beta /= p;
+            }
+
+            ray = Ray {
+                direction: next_ray.direction.unit_vector(),
+                ..next_ray
+            };
+        }
+    }
+
+    /// Like `ray_color_with_aovs`, but for progressive photon mapping. `ray`
+    /// is followed through mirrors and smooth glass to the first other
+    /// surface it reaches, its visible point, where the light coming straight
+    /// from the emitters is sampled. The rest of the light there is left to the
+    /// photons the returned visible point gathers, and isn't in the AOVs.
+    /// The first hit is only worked out if `store_aovs` is set.
+    pub fn ray_color_photon_mapping(
+        &self,
+        ray: Ray,
+        scene_world: &SceneWorld,
+        sampler: &mut dyn Sampler,
+        store_aovs: bool,
+    ) -> (ColorRgb, AovSample, Option<VisiblePoint>) {
+        let hw = HeroWavelengths::<HERO_WAVELENGTH_COUNT>::from_sample(sampler.get_1d());
+        let mut ray = Ray {
+            direction: ray.direction.unit_vector(),
+            ..ray
+        };
+
+        let mut radiance = PathRadiance::<HERO_WAVELENGTH_COUNT>::ZERO;
+        let mut beta = Spectrum::ONE;
+        let mut medium_stack = Vec::new();
+        let mut sample = AovSample::default();
+        let mut visible_point = None;
+        let mut distance = 0.0;
+
+        for bounces in 0..=self.bounces.max {
+            let Some(ray_hit) = scene_world
+                .world
+                .intersect(ray, Interval::new(0.001, f64::INFINITY))
+            else {
+                radiance.add(
+                    bounces,
+                    beta * self.background(ray.direction, &hw, scene_world),
+                );
+                break;
+            };
+            if bounces == 0 && store_aovs {
+                sample.first_hit = Some(first_hit(&ray, &ray_hit, scene_world));
+            }
+            distance += ray_hit.t;
+
+            radiance.add(bounces, beta * ray.current_medium.emission(&hw, ray_hit.t));
+            beta *= ray.current_medium.transmittance(&hw, ray_hit.t);
+            let emittance =
+                ray_hit
+                    .material
+                    .emittance(ray_hit.u, ray_hit.v, ray_hit.point, ray.time);
+            radiance.add(bounces, beta * emittance.sample(&hw));
+
+            let reflectance = ray_hit
+                .material
+                .reflectance(ray_hit.u, ray_hit.v, ray_hit.point);
+            if bounces == self.bounces.max || reflectance.is_black() {
+                break;
+            }
+            let Some(scatter) = ray_hit.material.scatter(ray, &ray_hit, &hw, sampler) else {
+                break;
+            };
+
+            ray = match scatter {
+                ScatterRecord::Pdf(_) => {
+                    let outgoing = -ray.direction;
+                    let direct =
+                        self.sample_light(ray, &ray_hit, outgoing, None, &hw, scene_world, sampler);
+                    radiance.add(bounces + 1, beta * direct);
+                    let direct = self.sample_scene_lights(
+                        ray,
+                        &ray_hit,
+                        outgoing,
+                        &hw,
+                        scene_world,
+                        sampler,
+                    );
+                    radiance.add(bounces + 1, beta * direct);
+
+                    // relative to white, so a path through clear glass keeps
+                    // the photons' color exactly
+                    let white: [f64; 3] = hw.to_color_rgb(Spectrum::ONE).into();
+                    let color: [f64; 3] = hw.to_color_rgb(beta).into();
+                    visible_point = Some(VisiblePoint {
+                        hit: ray_hit,
+                        outgoing,
+                        weight: [0, 1, 2]
+                            .map(|c| {
+                                if white[c] > 0.0 {
+                                    color[c] / white[c]
+                                } else {
+                                    0.0
+                                }
+                            })
+                            .into(),
+                        footprint: self.pixel_footprint(distance),
+                    });
+                    break;
+                }
+                ScatterRecord::Delta { scattered } => {
+                    beta *= reflectance.sample(&hw);
+                    scattered
+                }
+                ScatterRecord::Spectral(spectral) => {
+                    let Some(next_ray) = follow_spectral_scatter(
+                        &ray,
+                        &ray_hit,
+                        *spectral,
+                        &mut beta,
+                        &mut medium_stack,
+                        sampler,
+                    ) else {
+                        break;
+                    };
+                    next_ray
+                }
+            };
+            ray.direction = ray.direction.unit_vector();
+        }
+
+        sample.emission = hw.to_color_rgb(radiance.emission);
+        sample.direct = hw.to_color_rgb(radiance.direct);
+        sample.indirect = hw.to_color_rgb(radiance.indirect);
+        (
+            sample.emission + sample.direct + sample.indirect,
+            sample,
+            visible_point,
+        )
+    }
+
+    /// Roughly how wide a pixel is `distance` along a camera ray.
+    fn pixel_footprint(&self, distance: f64) -> f64 {
+        let (width, height) = (self.image_width as f64, self.image_height as f64);
+        match self.projection {
+            Projection::Perspective => {
+                self.frame.pixel_delta_u.length() * distance / self.focus_distance
+            }
+            Projection::Orthographic { .. } => self.frame.pixel_delta_u.length(),
+            Projection::Equirectangular => 2.0 * PI / width * distance,
+            Projection::Fisheye {
+                field_of_view_degrees,
+                ..
+            } => field_of_view_degrees.to_radians() / width.min(height) * distance,
+        }
+    }
+}
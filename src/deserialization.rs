@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
 use cameras::{CameraRefOrInline, FocusDistance, FocusDistanceType};
 use geometrics::GeometricRefOrInline;
@@ -8,16 +8,21 @@ use serde::{Deserialize, Serialize};
 use textures::TextureRefOrInline;
 
 use crate::{
-    camera::Camera,
+    camera::{Camera, Projection, Shutter},
     geometry::Geometric,
     shading::textures::ImageLinearF64,
-    shading::{ColorRgb, Texture, materials::Material},
+    shading::{
+        ColorRgb, Texture,
+        lights::IesProfile,
+        materials::{Material, Metal},
+    },
     tracing::ResourceID,
-    tracing::{RenderParameters, Scene},
-    utils::{Angle, Around},
+    tracing::{Region, RenderParameters, Scene},
+    utils::{Angle, Around, Interpolate, Keyframe, Keyframes},
 };
 
 mod scenes;
+pub use self::scenes::EnvironmentData;
 use self::scenes::{SceneData, SceneRefOrInline};
 
 mod cameras;
@@ -27,15 +32,24 @@ mod geometrics;
 use self::geometrics::GeometricData;
 
 mod materials;
-use self::materials::{MaterialData, MediumData};
+use self::materials::{MaterialData, MediumData, MetalData, ScalarOrTexture};
 
 mod textures;
 pub use self::textures::TextureData;
 
+mod lights;
+
 pub trait Build<Entity> {
     fn build(&self, builts: &Builts<'_>) -> Result<Entity, String>;
 }
 
+/// The resources a config uses, loaded before it's compiled.
+#[derive(Default)]
+pub struct LoadedResources {
+    pub images: IndexMap<ResourceID, Arc<ImageLinearF64>>,
+    pub ies_profiles: IndexMap<ResourceID, Arc<IesProfile>>,
+}
+
 pub struct Builts<'a> {
     scenes: IndexMap<String, Scene>,
     cameras: IndexMap<String, Camera>,
@@ -43,11 +57,11 @@ pub struct Builts<'a> {
     materials: IndexMap<String, Arc<dyn Material>>,
     textures: IndexMap<String, Arc<dyn Texture>>,
 
-    resources: Option<&'a IndexMap<ResourceID, Arc<ImageLinearF64>>>,
+    resources: Option<&'a LoadedResources>,
 }
 
 impl<'a> Builts<'a> {
-    fn new(resources: Option<&'a IndexMap<ResourceID, Arc<ImageLinearF64>>>) -> Self {
+    fn new(resources: Option<&'a LoadedResources>) -> Self {
         Self {
             scenes: IndexMap::new(),
             cameras: IndexMap::new(),
@@ -58,6 +72,36 @@ impl<'a> Builts<'a> {
             resources,
         }
     }
+
+    /// The pre-loaded image resource `resource_id`, or in validation mode,
+    /// when nothing is loaded, a 1x1 placeholder.
+    fn image(&self, resource_id: &ResourceID) -> Result<Arc<ImageLinearF64>, String> {
+        match self.resources {
+            None => Ok(Arc::new(ImageLinearF64 {
+                width: 1,
+                height: 1,
+                data: vec![[1.0, 0.0, 1.0]],
+            })),
+            Some(resources) => resources
+                .images
+                .get(resource_id)
+                .cloned()
+                .ok_or_else(|| format!("Resource {} not found in pre-loaded data", resource_id)),
+        }
+    }
+
+    /// The pre-loaded IES profile resource `resource_id`, or in validation
+    /// mode, when nothing is loaded, one that shines alike every way.
+    fn ies_profile(&self, resource_id: &ResourceID) -> Result<Arc<IesProfile>, String> {
+        match self.resources {
+            None => Ok(Arc::new(IesProfile::uniform())),
+            Some(resources) => resources
+                .ies_profiles
+                .get(resource_id)
+                .cloned()
+                .ok_or_else(|| format!("Resource {} not found in pre-loaded data", resource_id)),
+        }
+    }
 }
 
 pub struct RenderConfigBuilder(RenderConfig);
@@ -105,10 +149,40 @@ pub struct RenderConfig {
 }
 
 impl RenderConfig {
-    pub fn compile(
-        &self,
-        resources: Option<&IndexMap<ResourceID, Arc<ImageLinearF64>>>,
-    ) -> Result<RenderData, String> {
+    /// The image resources the config's textures and environments use.
+    pub fn image_resource_ids(&self) -> HashSet<ResourceID> {
+        let mut ids = HashSet::new();
+        for texture in self.textures.values() {
+            if let TextureData::Image { resource_id } = texture {
+                ids.insert(*resource_id);
+            }
+        }
+        for scene in self.scene_data() {
+            if let Some(EnvironmentData::Image { resource_id, .. }) = &scene.environment {
+                ids.insert(*resource_id);
+            }
+        }
+        ids
+    }
+
+    /// The IES profile resources the config's lights use.
+    pub fn ies_profile_resource_ids(&self) -> HashSet<ResourceID> {
+        self.scene_data()
+            .flat_map(|scene| &scene.lights)
+            .filter_map(|light| light.ies_profile())
+            .collect()
+    }
+
+    /// The named scenes, and the active scene if it's inline.
+    fn scene_data(&self) -> impl Iterator<Item = &SceneData> {
+        let active_scene = match &self.active_scene {
+            SceneRefOrInline::Inline(data) => Some(data),
+            SceneRefOrInline::Ref(_) => None,
+        };
+        self.scenes.values().chain(active_scene)
+    }
+
+    pub fn compile(&self, resources: Option<&LoadedResources>) -> Result<RenderData, String> {
         let mut builts = Builts::new(resources);
 
         // setup named properties
@@ -137,6 +211,34 @@ impl RenderConfig {
             .importance_sampling
             .validate(&active_scene.world)?;
 
+        self.parameters.filter.validate()?;
+        if let Some(adaptive_sampling) = self.parameters.adaptive_sampling {
+            adaptive_sampling.validate()?;
+        }
+        if let Some(tone_mapping) = self.parameters.tone_mapping {
+            tone_mapping.validate()?;
+        }
+        self.parameters.photon_mapping.validate()?;
+        self.parameters.debug.validate()?;
+
+        if let Some(animation) = self.parameters.animation {
+            animation.validate()?;
+            // checkpoints are cleared frame by frame instead
+            if self.parameters.saved_checkpoint_limit.is_some() {
+                return Err(
+                    "Animations keep the latest checkpoint of each frame, so they can't have a saved_checkpoint_limit"
+                        .to_string(),
+                );
+            }
+        }
+
+        if let Some(region) = self.parameters.region {
+            region.validate(Region {
+                origin: (0, 0),
+                dimensions: self.parameters.image_dimensions,
+            })?;
+        }
+
         Ok(())
     }
 }
@@ -197,14 +299,49 @@ fn build_textures(
     Ok(())
 }
 
+fn get_material_dependencies(material: &MaterialData) -> Vec<String> {
+    let mut deps = Vec::new();
+    // mixes contain the materials they blend, which must be built first
+    if let MaterialData::Mix { first, second, .. } = material {
+        for m in [first, second] {
+            match m {
+                MaterialRefOrInline::Ref(ref_name) => deps.push(ref_name.clone()),
+                MaterialRefOrInline::Inline(data) => {
+                    deps.append(&mut get_material_dependencies(data))
+                }
+            }
+        }
+    }
+    deps
+}
+
 fn build_materials(
     material_data: &IndexMap<String, MaterialData>,
     builts: &mut Builts<'_>,
 ) -> Result<(), String> {
-    for (name, material) in material_data {
-        builts
-            .materials
-            .insert((*name).clone(), material.build(builts)?);
+    let mut building = std::collections::HashSet::new();
+
+    // Function to build and insert a material
+    let build_and_insert = |name: &str, material: &MaterialData, builts: &mut Builts<'_>| {
+        if !builts.materials.contains_key(name) {
+            let built = material.build(builts)?;
+            builts.materials.insert(name.to_string(), built);
+        }
+        Ok(())
+    };
+
+    // Build all materials
+    for name in material_data.keys() {
+        build_entity_recursive(
+            name,
+            material_data,
+            builts,
+            &mut building,
+            get_material_dependencies,
+            build_and_insert,
+            "material",
+            0,
+        )?
     }
     Ok(())
 }
@@ -412,22 +549,27 @@ fn get_builtin_materials() -> IndexMap<String, MaterialData> {
         prefix_builtin_key("lambertian_white") => MaterialData::Lambertian {
             reflectance_texture: texture_fn("white"),
             emittance_texture: texture_fn("black"),
+            emittance_scale_keyframes: None,
         },
         prefix_builtin_key("lambertian_black") => MaterialData::Lambertian {
             reflectance_texture: texture_fn("black"),
             emittance_texture: texture_fn("black"),
+            emittance_scale_keyframes: None,
         },
         prefix_builtin_key("lambertian_red") => MaterialData::Lambertian {
             reflectance_texture: texture_fn("red"),
             emittance_texture: texture_fn("black"),
+            emittance_scale_keyframes: None,
         },
         prefix_builtin_key("lambertian_green") => MaterialData::Lambertian {
             reflectance_texture: texture_fn("green"),
             emittance_texture: texture_fn("black"),
+            emittance_scale_keyframes: None,
         },
         prefix_builtin_key("lambertian_blue") => MaterialData::Lambertian {
             reflectance_texture: texture_fn("blue"),
             emittance_texture: texture_fn("black"),
+            emittance_scale_keyframes: None,
         },
 
         // specular
@@ -435,11 +577,73 @@ fn get_builtin_materials() -> IndexMap<String, MaterialData> {
             reflectance_texture: texture_fn("white"),
             emittance_texture: texture_fn("black"),
             roughness: 0.0,
+            emittance_scale_keyframes: None,
         },
         prefix_builtin_key("specular_mirror_rough") => MaterialData::Specular {
             reflectance_texture: texture_fn("white"),
             emittance_texture: texture_fn("black"),
             roughness: 0.1,
+            emittance_scale_keyframes: None,
+        },
+
+        // conductor
+        prefix_builtin_key("conductor_gold") => MaterialData::Conductor {
+            metal: MetalData::Named(Metal::Gold),
+            roughness: 0.2,
+            anisotropy: 0.0,
+            emittance_texture: texture_fn("black"),
+            emittance_scale_keyframes: None,
+        },
+        prefix_builtin_key("conductor_copper") => MaterialData::Conductor {
+            metal: MetalData::Named(Metal::Copper),
+            roughness: 0.2,
+            anisotropy: 0.0,
+            emittance_texture: texture_fn("black"),
+            emittance_scale_keyframes: None,
+        },
+        prefix_builtin_key("conductor_silver") => MaterialData::Conductor {
+            metal: MetalData::Named(Metal::Silver),
+            roughness: 0.2,
+            anisotropy: 0.0,
+            emittance_texture: texture_fn("black"),
+            emittance_scale_keyframes: None,
+        },
+        prefix_builtin_key("conductor_aluminium") => MaterialData::Conductor {
+            metal: MetalData::Named(Metal::Aluminium),
+            roughness: 0.2,
+            anisotropy: 0.0,
+            emittance_texture: texture_fn("black"),
+            emittance_scale_keyframes: None,
+        },
+
+        // principled
+        prefix_builtin_key("principled_plastic") => MaterialData::Principled {
+            base_color_texture: texture_fn("white"),
+            emittance_texture: texture_fn("black"),
+            metallic: None,
+            roughness: Some(ScalarOrTexture::Constant(0.3)),
+            specular: None,
+            sheen: None,
+            clearcoat: None,
+            clearcoat_roughness: None,
+            transmission: None,
+            subsurface_tint_texture: None,
+            index_of_refraction: None,
+            emittance_scale_keyframes: None,
+        },
+        prefix_builtin_key("principled_car_paint") => MaterialData::Principled {
+            base_color_texture: texture_fn("red"),
+            emittance_texture: texture_fn("black"),
+            metallic: Some(ScalarOrTexture::Constant(0.5)),
+            roughness: Some(ScalarOrTexture::Constant(0.4)),
+            specular: None,
+            sheen: None,
+            clearcoat: Some(ScalarOrTexture::Constant(1.0)),
+            clearcoat_roughness: Some(ScalarOrTexture::Constant(0.05)),
+            transmission: None,
+            subsurface_tint_texture: None,
+            index_of_refraction: None,
+            emittance_scale_keyframes: None,
         },
 
         // dielectric
@@ -449,6 +653,8 @@ fn get_builtin_materials() -> IndexMap<String, MaterialData> {
             index_of_refraction: 1.52,
             abbe_number: Some(64.0),
             medium_data: Some(MediumData::Vacuum),
+            roughness: None,
+            emittance_scale_keyframes: None,
         },
         prefix_builtin_key("dielectric_water") => MaterialData::Dielectric {
             reflectance_texture: texture_fn("white"),
@@ -456,6 +662,17 @@ fn get_builtin_materials() -> IndexMap<String, MaterialData> {
             index_of_refraction: 1.333,
             abbe_number: Some(72.0),
             medium_data: Some(MediumData::Vacuum),
+            roughness: None,
+            emittance_scale_keyframes: None,
+        },
+        prefix_builtin_key("dielectric_frosted_glass") => MaterialData::Dielectric {
+            reflectance_texture: texture_fn("white"),
+            emittance_texture: texture_fn("black"),
+            index_of_refraction: 1.52,
+            abbe_number: Some(64.0),
+            medium_data: Some(MediumData::Vacuum),
+            roughness: Some(0.3),
+            emittance_scale_keyframes: None,
         },
         prefix_builtin_key("dielectric_diamond") => MaterialData::Dielectric {
             reflectance_texture: texture_fn("white"),
@@ -463,24 +680,30 @@ fn get_builtin_materials() -> IndexMap<String, MaterialData> {
             index_of_refraction: 2.417,
             abbe_number: Some(55.0),
             medium_data: Some(MediumData::Vacuum),
+            roughness: None,
+            emittance_scale_keyframes: None,
         },
 
         // cornell box materials
         prefix_builtin_key("lambertian_cornell_box_white") => MaterialData::Lambertian {
             reflectance_texture: texture_fn("cornell_box_white"),
             emittance_texture: texture_fn("black"),
+            emittance_scale_keyframes: None,
         },
         prefix_builtin_key("lambertian_cornell_box_white_light") => MaterialData::Lambertian {
             reflectance_texture: texture_fn("black"),
             emittance_texture: texture_fn("cornell_box_white_light"),
+            emittance_scale_keyframes: None,
         },
         prefix_builtin_key("lambertian_cornell_box_red") => MaterialData::Lambertian {
             reflectance_texture: texture_fn("cornell_box_red"),
             emittance_texture: texture_fn("black"),
+            emittance_scale_keyframes: None,
         },
         prefix_builtin_key("lambertian_cornell_box_green") => MaterialData::Lambertian {
             reflectance_texture: texture_fn("cornell_box_green"),
             emittance_texture: texture_fn("black"),
+            emittance_scale_keyframes: None,
         },
     }
 }
@@ -565,6 +788,10 @@ fn get_builtin_cameras() -> IndexMap<String, CameraData> {
             view_up: [0.0, 1.0, 0.0],
             defocus_angle_degrees: 0.0,
             focus_distance: FocusDistance::Type(FocusDistanceType::EyeToTarget),
+            projection: Projection::Perspective,
+            shutter: Shutter::default(),
+            eye_location_keyframes: None,
+            target_location_keyframes: None,
         }
     }
 }
@@ -580,6 +807,8 @@ fn get_builtin_scenes() -> IndexMap<String, SceneData> {
             use_bvh: true,
             camera: CameraRefOrInline::Ref(prefix_builtin_key("cornell_box")),
             background_color: [0.0, 0.0, 0.0],
+            environment: None,
+            lights: Vec::new(),
         }
     }
 }
@@ -660,3 +889,15 @@ pub fn upgrade_legacy_is_culled(value: &mut serde_json::Value) {
         _ => {}
     }
 }
+
+/// The keyframes if there are any, otherwise the fixed `value`.
+fn keyframes_or_constant<D: Copy, T: Interpolate>(
+    value: D,
+    keyframes: &Option<Vec<Keyframe<D>>>,
+    convert: impl Fn(D) -> T,
+) -> Result<Keyframes<T>, String> {
+    match keyframes {
+        Some(keyframes) => Keyframes::new(keyframes.iter().map(|k| k.map(&convert))),
+        None => Ok(Keyframes::constant(convert(value))),
+    }
+}
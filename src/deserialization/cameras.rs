@@ -1,14 +1,18 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{camera::Camera, geometry::Point};
+use crate::{
+    camera::{Camera, Projection, Shutter},
+    geometry::Point,
+    utils::Keyframe,
+};
 
-use super::{Build, Builts};
+use super::{Build, Builts, keyframes_or_constant};
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", untagged)]
 pub enum CameraRefOrInline {
     Ref(String),
-    Inline(CameraData),
+    Inline(Box<CameraData>),
 }
 
 impl Build<Camera> for CameraRefOrInline {
@@ -36,25 +40,52 @@ pub struct CameraData {
     pub view_up: [f64; 3],
     pub defocus_angle_degrees: f64,
     pub focus_distance: FocusDistance,
+    /// Perspective when omitted, using `vertical_field_of_view_degrees`.
+    #[serde(default)]
+    pub projection: Projection,
+    #[serde(default)]
+    pub shutter: Shutter,
+    /// If set, the eye moves along these instead of staying at `eye_location`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eye_location_keyframes: Option<Vec<Keyframe<[f64; 3]>>>,
+    /// If set, the target moves along these instead of staying at `target_location`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_location_keyframes: Option<Vec<Keyframe<[f64; 3]>>>,
 }
 
 impl Build<Camera> for CameraData {
     fn build(&self, _builts: &Builts<'_>) -> Result<Camera, String> {
-        let eye_location: Point = self.eye_location.into();
-        let target_location: Point = self.target_location.into();
+        self.projection.validate()?;
+        self.shutter.validate()?;
+
+        let eye_location = keyframes_or_constant(
+            self.eye_location,
+            &self.eye_location_keyframes,
+            Into::<Point>::into,
+        )?;
+        let target_location = keyframes_or_constant(
+            self.target_location,
+            &self.target_location_keyframes,
+            Into::<Point>::into,
+        )?;
+
         let camera = Camera::new(
             self.vertical_field_of_view_degrees,
-            eye_location,
-            target_location,
+            eye_location.first(),
+            target_location.first(),
             self.view_up.into(),
             self.defocus_angle_degrees,
             match self.focus_distance {
                 FocusDistance::Exact(distance) => distance,
+                // for a moving camera, this is the distance at the first keyframes
                 FocusDistance::Type(FocusDistanceType::EyeToTarget) => {
-                    eye_location.to(target_location).length()
+                    eye_location.first().to(target_location.first()).length()
                 }
             },
-        );
+        )
+        .with_projection(self.projection)
+        .with_shutter(self.shutter)
+        .with_motion(eye_location, target_location);
 
         Ok(camera)
     }
@@ -12,10 +12,10 @@ use crate::{
         },
         volumes,
     },
-    utils::{Angle, Around},
+    utils::{Angle, Around, Keyframe},
 };
 
-use super::{Build, Builts, materials::MaterialRefOrInline};
+use super::{Build, Builts, keyframes_or_constant, materials::MaterialRefOrInline};
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", untagged)]
@@ -90,17 +90,26 @@ pub enum GeometricData {
         geometric: GeometricRefOrInline,
         quaternion: [f64; 4],
         around: Around,
+        /// If set, the rotation is animated along these instead of fixed at `quaternion`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        quaternion_keyframes: Option<Vec<Keyframe<[f64; 4]>>>,
     },
     #[serde(rename = "scale")]
     InstanceScale {
         geometric: GeometricRefOrInline,
         scale: [f64; 3],
         around: Around,
+        /// If set, the scale is animated along these instead of fixed at `scale`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        scale_keyframes: Option<Vec<Keyframe<[f64; 3]>>>,
     },
     #[serde(rename = "translate")]
     InstanceTranslate {
         geometric: GeometricRefOrInline,
         translation: [f64; 3],
+        /// If set, the translation is animated along these instead of fixed at `translation`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        translation_keyframes: Option<Vec<Keyframe<[f64; 3]>>>,
     },
     #[serde(rename = "parallelogram")]
     PrimitiveParallelogram {
@@ -256,31 +265,40 @@ impl Build<Arc<dyn Geometric>> for GeometricData {
                 geometric: geometric_ref,
                 quaternion,
                 around,
+                quaternion_keyframes,
             } => {
                 let geometric = geometric_ref.build(builts)?;
+                let quaternion = keyframes_or_constant(
+                    *quaternion,
+                    quaternion_keyframes,
+                    Quaternion::from_array,
+                )?;
 
-                Ok(Arc::new(RotateQuaternion::new(
-                    geometric,
-                    Quaternion::from_array(*quaternion),
-                    *around,
+                Ok(Arc::new(RotateQuaternion::new_animated(
+                    geometric, quaternion, *around,
                 )))
             }
             Self::InstanceScale {
                 geometric: geometric_ref,
                 scale,
                 around,
+                scale_keyframes,
             } => {
                 let geometric = geometric_ref.build(builts)?;
+                let scale = keyframes_or_constant(*scale, scale_keyframes, Vector3::from)?;
 
-                Ok(Arc::new(Scale::new(geometric, (*scale).into(), *around)?))
+                Ok(Arc::new(Scale::new_animated(geometric, scale, *around)?))
             }
             Self::InstanceTranslate {
                 geometric: geometric_ref,
                 translation,
+                translation_keyframes,
             } => {
                 let geometric = geometric_ref.build(builts)?;
+                let translation =
+                    keyframes_or_constant(*translation, translation_keyframes, Vector3::from)?;
 
-                Ok(Arc::new(Translate::new(geometric, (*translation).into())))
+                Ok(Arc::new(Translate::new_animated(geometric, translation)))
             }
             Self::PrimitiveParallelogram {
                 lower_left,
@@ -0,0 +1,229 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    geometry::{
+        Geometric, Vector3,
+        primitives::{Parallelogram, Sphere},
+    },
+    shading::{
+        ColorRgb, ColorSpectrum, Light, Texture, lights::PointLight, materials::Lambertian,
+        textures::SolidColor,
+    },
+    tracing::ResourceID,
+};
+
+use super::{Build, Builts};
+
+/// A light in a scene. Point, spot and directional lights can't be hit by
+/// rays, while sphere and rectangle lights are built as glowing geometry.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type", deny_unknown_fields)]
+pub enum LightData {
+    /// Shines every way from a point.
+    Point {
+        position: [f64; 3],
+        /// The power it sends out in all, in watts.
+        power: f64,
+        #[serde(default = "LightData::default_color")]
+        color: [f64; 3],
+        /// An IES photometric file resource whose straight down is along -y.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ies_profile: Option<ResourceID>,
+    },
+    /// Shines within a cone from a point.
+    Spot {
+        position: [f64; 3],
+        direction: [f64; 3],
+        /// The angle off `direction` where the cone ends.
+        cone_angle_degrees: f64,
+        /// How far inside its edge the cone starts to fade.
+        #[serde(default = "LightData::default_cone_delta_degrees")]
+        cone_delta_degrees: f64,
+        power: f64,
+        #[serde(default = "LightData::default_color")]
+        color: [f64; 3],
+        /// An IES photometric file resource whose straight down is along
+        /// `direction`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ies_profile: Option<ResourceID>,
+    },
+    /// Shines along `direction` from infinitely far away.
+    Directional {
+        direction: [f64; 3],
+        /// The light falling on a surface facing it, in watts per square
+        /// meter.
+        irradiance: f64,
+        #[serde(default = "LightData::default_color")]
+        color: [f64; 3],
+    },
+    /// A glowing sphere.
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        power: f64,
+        #[serde(default = "LightData::default_color")]
+        color: [f64; 3],
+    },
+    /// A glowing rectangle with sides `u` and `v` from `lower_left`, which
+    /// shines from both faces.
+    Rectangle {
+        lower_left: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        power: f64,
+        #[serde(default = "LightData::default_color")]
+        color: [f64; 3],
+    },
+}
+
+impl LightData {
+    fn default_color() -> [f64; 3] {
+        [1.0, 1.0, 1.0]
+    }
+
+    fn default_cone_delta_degrees() -> f64 {
+        5.0
+    }
+
+    /// The IES profile resource shaping the light, if any.
+    pub fn ies_profile(&self) -> Option<ResourceID> {
+        match self {
+            Self::Point { ies_profile, .. } | Self::Spot { ies_profile, .. } => *ies_profile,
+            _ => None,
+        }
+    }
+}
+
+/// What a [`LightData`] builds.
+pub enum BuiltLight {
+    Light(Light),
+    Geometric(Arc<dyn Geometric>),
+}
+
+impl Build<BuiltLight> for LightData {
+    fn build(&self, builts: &Builts<'_>) -> Result<BuiltLight, String> {
+        match self {
+            Self::Point {
+                position,
+                power,
+                color,
+                ies_profile,
+            } => Ok(BuiltLight::Light(Light::Point(PointLight::new(
+                (*position).into(),
+                validate_power(*power)?,
+                validate_color(color)?,
+                -Vector3::UNIT_Y,
+                None,
+                ies_profile.map(|id| builts.ies_profile(&id)).transpose()?,
+            )))),
+            Self::Spot {
+                position,
+                direction,
+                cone_angle_degrees,
+                cone_delta_degrees,
+                power,
+                color,
+                ies_profile,
+            } => {
+                if !(0.0..=180.0).contains(cone_angle_degrees) {
+                    return Err(
+                        "Spot light cone angle must be between 0 and 180 degrees".to_string()
+                    );
+                }
+                if !(0.0..=*cone_angle_degrees).contains(cone_delta_degrees) {
+                    return Err(
+                        "Spot light cone delta must be between 0 and the cone angle".to_string()
+                    );
+                }
+                Ok(BuiltLight::Light(Light::Point(PointLight::new(
+                    (*position).into(),
+                    validate_power(*power)?,
+                    validate_color(color)?,
+                    validate_direction(direction)?,
+                    Some((cone_angle_degrees - cone_delta_degrees, *cone_angle_degrees)),
+                    ies_profile.map(|id| builts.ies_profile(&id)).transpose()?,
+                ))))
+            }
+            Self::Directional {
+                direction,
+                irradiance,
+                color,
+            } => Ok(BuiltLight::Light(Light::Directional {
+                direction: validate_direction(direction)?,
+                irradiance: ColorSpectrum::from(validate_color(color)?)
+                    * validate_power(*irradiance)?,
+            })),
+            Self::Sphere {
+                center,
+                radius,
+                power,
+                color,
+            } => {
+                if !(radius.is_finite() && *radius > 0.0) {
+                    return Err("Sphere light radius must be positive".to_string());
+                }
+                // glowing alike every way, from an area of 4πr²
+                let area = 4.0 * PI * radius * radius;
+                let material = glowing(validate_color(color)?, *power / (PI * area));
+                Ok(BuiltLight::Geometric(Arc::new(Sphere::new(
+                    (*center).into(),
+                    *radius,
+                    material,
+                ))))
+            }
+            Self::Rectangle {
+                lower_left,
+                u,
+                v,
+                power,
+                color,
+            } => {
+                let (u, v) = (Vector3::from(*u), Vector3::from(*v));
+                let area = u.cross(v).length();
+                if !(area.is_finite() && area > 0.0) {
+                    return Err("Rectangle light sides must span an area".to_string());
+                }
+                // both faces glow
+                let material = glowing(validate_color(color)?, *power / (2.0 * PI * area));
+                Ok(BuiltLight::Geometric(Arc::new(Parallelogram::new(
+                    (*lower_left).into(),
+                    u,
+                    v,
+                    material,
+                ))))
+            }
+        }
+    }
+}
+
+fn validate_power(power: f64) -> Result<f64, String> {
+    if power.is_finite() && power >= 0.0 {
+        Ok(power)
+    } else {
+        Err("Light power must be non-negative".to_string())
+    }
+}
+
+fn validate_color(color: &[f64; 3]) -> Result<ColorRgb, String> {
+    if color.iter().any(|c| c.is_nan() || *c < 0.0) {
+        return Err("Light color must be non-negative".to_string());
+    }
+    Ok(ColorRgb::from(*color))
+}
+
+fn validate_direction(direction: &[f64; 3]) -> Result<Vector3, String> {
+    let direction = Vector3::from(*direction);
+    let length = direction.length();
+    if !(length.is_finite() && length > 0.0) {
+        return Err("Light direction must be non-zero".to_string());
+    }
+    Ok(direction / length)
+}
+
+fn glowing(color: ColorRgb, radiance: f64) -> Arc<Lambertian> {
+    let emittance: Arc<dyn Texture> =
+        Arc::new(SolidColor::new(ColorSpectrum::from(color) * radiance));
+    Arc::new(Lambertian::new(Arc::new(SolidColor::BLACK), emittance))
+}
@@ -2,18 +2,25 @@ use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 
-use crate::shading::{
-    ColorRgb, ColorSpectrum, Medium,
-    materials::{Dielectric, Lambertian, Material, Specular},
+use crate::{
+    shading::{
+        ColorRgb, ColorSpectrum, Medium, Texture, TrowbridgeReitz,
+        color_spectrum::SPECTRAL_SAMPLE_COUNT,
+        materials::{
+            Conductor, Dielectric, Lambertian, Material, Metal, Mix, Principled, Specular,
+        },
+        textures::SolidColor,
+    },
+    utils::Keyframe,
 };
 
-use super::{Build, Builts, textures::TextureRefOrInline};
+use super::{Build, Builts, keyframes_or_constant, textures::TextureRefOrInline};
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", untagged)]
 pub enum MaterialRefOrInline {
     Ref(String),
-    Inline(MaterialData),
+    Inline(Box<MaterialData>),
 }
 
 impl Build<Arc<dyn Material>> for MaterialRefOrInline {
@@ -63,9 +70,100 @@ impl From<MediumData> for Medium {
     }
 }
 
+/// A metal's complex index of refraction over the spectrum.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", untagged)]
+pub enum MetalData {
+    /// One of the built-in measured metals.
+    Named(Metal),
+    /// `n` and `k` measured at ascending `wavelengths_nm`.
+    Measured {
+        wavelengths_nm: Vec<f64>,
+        n: Vec<f64>,
+        k: Vec<f64>,
+    },
+}
+
+impl MetalData {
+    fn index_of_refraction(
+        &self,
+    ) -> Result<
+        (
+            ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+            ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+        ),
+        String,
+    > {
+        match self {
+            Self::Named(metal) => Ok(metal.index_of_refraction()),
+            Self::Measured {
+                wavelengths_nm,
+                n,
+                k,
+            } => {
+                if wavelengths_nm.is_empty()
+                    || n.len() != wavelengths_nm.len()
+                    || k.len() != wavelengths_nm.len()
+                {
+                    return Err(
+                        "Metal n and k must have one value for each of at least one wavelength"
+                            .to_string(),
+                    );
+                }
+                if !wavelengths_nm.is_sorted() {
+                    return Err("Metal wavelengths must be ascending".to_string());
+                }
+                if n.iter().chain(k).any(|v| !(v.is_finite() && *v >= 0.0)) {
+                    return Err("Metal n and k must be non-negative".to_string());
+                }
+                Ok((
+                    ColorSpectrum::from_tabulated(wavelengths_nm, n),
+                    ColorSpectrum::from_tabulated(wavelengths_nm, k),
+                ))
+            }
+        }
+    }
+}
+
+/// A parameter from 0 to 1, either constant or read from a texture's
+/// average over the spectrum.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ScalarOrTexture {
+    Constant(f64),
+    Texture(TextureRefOrInline),
+}
+
+impl ScalarOrTexture {
+    fn build(&self, name: &str, builts: &Builts<'_>) -> Result<Arc<dyn Texture>, String> {
+        match self {
+            Self::Constant(value) => {
+                if !(0.0..=1.0).contains(value) {
+                    return Err(format!("{name} must be between 0 and 1, got {value}"));
+                }
+                Ok(Arc::new(SolidColor::new(ColorSpectrum::ONE * *value)))
+            }
+            Self::Texture(texture) => texture.build(builts),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type", deny_unknown_fields)]
 pub enum MaterialData {
+    /// Metal with GGX microfacets, reflecting by the Fresnel equations for
+    /// its complex index of refraction at each wavelength.
+    Conductor {
+        metal: MetalData,
+        roughness: f64,
+        /// From 0 to 1, how much the highlights stretch along the surface.
+        #[serde(default)]
+        anisotropy: f64,
+        emittance_texture: TextureRefOrInline,
+        /// If set, the emitted light is scaled by these over time.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        emittance_scale_keyframes: Option<Vec<Keyframe<f64>>>,
+    },
     Dielectric {
         reflectance_texture: TextureRefOrInline,
         emittance_texture: TextureRefOrInline,
@@ -74,30 +172,120 @@ pub enum MaterialData {
         abbe_number: Option<f64>,
         #[serde(skip_serializing_if = "Option::is_none")]
         medium_data: Option<MediumData>,
+        /// From 0 to 1, how frosted the surface is. Smooth if unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        roughness: Option<f64>,
+        /// If set, the emitted light is scaled by these over time.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        emittance_scale_keyframes: Option<Vec<Keyframe<f64>>>,
     },
     Lambertian {
         reflectance_texture: TextureRefOrInline,
         emittance_texture: TextureRefOrInline,
+        /// If set, the emitted light is scaled by these over time.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        emittance_scale_keyframes: Option<Vec<Keyframe<f64>>>,
+    },
+    /// Picks `second` at a point with the chance of `weight` there, and
+    /// `first` otherwise.
+    Mix {
+        first: MaterialRefOrInline,
+        second: MaterialRefOrInline,
+        weight: ScalarOrTexture,
+    },
+    /// Disney-style all-round material. Unset parameters keep their
+    /// defaults: roughness and specular 0.5, clearcoat roughness 0.1, a white
+    /// subsurface tint, an index of refraction of 1.5 and the rest 0.
+    Principled {
+        base_color_texture: TextureRefOrInline,
+        emittance_texture: TextureRefOrInline,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metallic: Option<ScalarOrTexture>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        roughness: Option<ScalarOrTexture>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        specular: Option<ScalarOrTexture>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sheen: Option<ScalarOrTexture>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        clearcoat: Option<ScalarOrTexture>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        clearcoat_roughness: Option<ScalarOrTexture>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transmission: Option<ScalarOrTexture>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        subsurface_tint_texture: Option<TextureRefOrInline>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        index_of_refraction: Option<f64>,
+        /// If set, the emitted light is scaled by these over time.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        emittance_scale_keyframes: Option<Vec<Keyframe<f64>>>,
     },
     Specular {
         reflectance_texture: TextureRefOrInline,
         emittance_texture: TextureRefOrInline,
         roughness: f64,
+        /// If set, the emitted light is scaled by these over time.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        emittance_scale_keyframes: Option<Vec<Keyframe<f64>>>,
     },
 }
 
 impl Build<Arc<dyn Material>> for MaterialData {
     fn build(&self, builts: &Builts<'_>) -> Result<Arc<dyn Material>, String> {
         match self {
+            Self::Conductor {
+                metal,
+                roughness,
+                anisotropy,
+                emittance_texture,
+                emittance_scale_keyframes,
+            } => {
+                if !(0.0..=1.0).contains(roughness) {
+                    return Err(format!(
+                        "roughness must be between 0 and 1, got {roughness}"
+                    ));
+                }
+                if !(0.0..=1.0).contains(anisotropy) {
+                    return Err(format!(
+                        "anisotropy must be between 0 and 1, got {anisotropy}"
+                    ));
+                }
+                let (eta, k) = metal.index_of_refraction()?;
+                let emittance_texture = emittance_texture.build(builts)?;
+                let emittance_scale =
+                    keyframes_or_constant(1.0, emittance_scale_keyframes, |scale| scale)?;
+
+                Ok(Arc::new(
+                    Conductor::new(
+                        eta,
+                        k,
+                        TrowbridgeReitz::from_roughness(*roughness, *anisotropy),
+                        emittance_texture,
+                    )
+                    .with_emittance_scale(emittance_scale),
+                ))
+            }
             Self::Dielectric {
                 reflectance_texture,
                 emittance_texture,
                 index_of_refraction,
                 abbe_number,
                 medium_data,
+                roughness,
+                emittance_scale_keyframes,
             } => {
                 let reflectance_texture = reflectance_texture.build(builts)?;
                 let emittance_texture = emittance_texture.build(builts)?;
+                let emittance_scale =
+                    keyframes_or_constant(1.0, emittance_scale_keyframes, |scale| scale)?;
+
+                let roughness = roughness.unwrap_or(0.0);
+                if !(0.0..=1.0).contains(&roughness) {
+                    return Err(format!(
+                        "roughness must be between 0 and 1, got {roughness}"
+                    ));
+                }
 
                 if let Some(MediumData::Homogeneous {
                     attenuation_distance,
@@ -112,39 +300,113 @@ impl Build<Arc<dyn Material>> for MaterialData {
 
                 let medium = medium_data.clone().map(Medium::from);
 
-                Ok(Arc::new(Dielectric::new(
-                    reflectance_texture,
-                    emittance_texture,
-                    *index_of_refraction,
-                    *abbe_number,
-                    medium,
-                )?))
+                Ok(Arc::new(
+                    Dielectric::new(
+                        reflectance_texture,
+                        emittance_texture,
+                        *index_of_refraction,
+                        *abbe_number,
+                        medium,
+                    )?
+                    .with_roughness(roughness)
+                    .with_emittance_scale(emittance_scale),
+                ))
             }
             Self::Lambertian {
                 reflectance_texture,
                 emittance_texture,
+                emittance_scale_keyframes,
             } => {
                 let reflectance_texture = reflectance_texture.build(builts)?;
                 let emittance_texture = emittance_texture.build(builts)?;
+                let emittance_scale =
+                    keyframes_or_constant(1.0, emittance_scale_keyframes, |scale| scale)?;
 
-                Ok(Arc::new(Lambertian::new(
-                    reflectance_texture,
+                Ok(Arc::new(
+                    Lambertian::new(reflectance_texture, emittance_texture)
+                        .with_emittance_scale(emittance_scale),
+                ))
+            }
+            Self::Mix {
+                first,
+                second,
+                weight,
+            } => Ok(Arc::new(Mix::new(
+                first.build(builts)?,
+                second.build(builts)?,
+                weight.build("weight", builts)?,
+            ))),
+            Self::Principled {
+                base_color_texture,
+                emittance_texture,
+                metallic,
+                roughness,
+                specular,
+                sheen,
+                clearcoat,
+                clearcoat_roughness,
+                transmission,
+                subsurface_tint_texture,
+                index_of_refraction,
+                emittance_scale_keyframes,
+            } => {
+                let base_color_texture = base_color_texture.build(builts)?;
+                let emittance_texture = emittance_texture.build(builts)?;
+                let emittance_scale =
+                    keyframes_or_constant(1.0, emittance_scale_keyframes, |scale| scale)?;
+                let mut material = Principled::new(
+                    base_color_texture,
                     emittance_texture,
-                )))
+                    index_of_refraction.unwrap_or(1.5),
+                )?
+                .with_emittance_scale(emittance_scale);
+
+                if let Some(metallic) = metallic {
+                    material = material.with_metallic(metallic.build("metallic", builts)?);
+                }
+                if let Some(roughness) = roughness {
+                    material = material.with_roughness(roughness.build("roughness", builts)?);
+                }
+                if let Some(specular) = specular {
+                    material = material.with_specular(specular.build("specular", builts)?);
+                }
+                if let Some(sheen) = sheen {
+                    material = material.with_sheen(sheen.build("sheen", builts)?);
+                }
+                if let Some(clearcoat) = clearcoat {
+                    material = material.with_clearcoat(clearcoat.build("clearcoat", builts)?);
+                }
+                if let Some(clearcoat_roughness) = clearcoat_roughness {
+                    material = material.with_clearcoat_roughness(
+                        clearcoat_roughness.build("clearcoat_roughness", builts)?,
+                    );
+                }
+                if let Some(transmission) = transmission {
+                    material =
+                        material.with_transmission(transmission.build("transmission", builts)?);
+                }
+                if let Some(subsurface_tint_texture) = subsurface_tint_texture {
+                    material =
+                        material.with_subsurface_tint(subsurface_tint_texture.build(builts)?);
+                }
+
+                Ok(Arc::new(material))
             }
             Self::Specular {
                 reflectance_texture,
                 emittance_texture,
                 roughness,
+                emittance_scale_keyframes,
             } => {
                 let reflectance_texture = reflectance_texture.build(builts)?;
                 let emittance_texture = emittance_texture.build(builts)?;
+                let emittance_scale =
+                    keyframes_or_constant(1.0, emittance_scale_keyframes, |scale| scale)?;
 
-                Ok(Arc::new(Specular::new(
-                    reflectance_texture,
-                    emittance_texture,
-                    *roughness,
-                )))
+                Ok(Arc::new(
+                    Specular::new(reflectance_texture, emittance_texture, *roughness)
+                        .with_emittance_scale(emittance_scale),
+                ))
             }
         }
     }
@@ -1,9 +1,16 @@
+use std::sync::Arc;
+
 use serde::{Deserialize, Serialize};
 
-use crate::shading::ColorRgb;
-use crate::tracing::{Scene, SceneWorld};
+use crate::shading::{ColorRgb, Environment, EnvironmentSource, Sky};
+use crate::tracing::{MaterialIds, ResourceID, Scene, SceneWorld};
 
-use super::{Build, Builts, cameras::CameraRefOrInline, geometrics::GeometricRefOrInline};
+use super::{
+    Build, Builts,
+    cameras::CameraRefOrInline,
+    geometrics::GeometricRefOrInline,
+    lights::{BuiltLight, LightData},
+};
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", untagged)]
@@ -36,7 +43,15 @@ pub struct SceneData {
     pub geometrics: Vec<GeometricRefOrInline>,
     pub use_bvh: bool,
     pub camera: CameraRefOrInline,
+    /// What rays that escape the scene see when there's no `environment`.
+    #[serde(default)]
     pub background_color: [f64; 3],
+    /// If set, lights the scene from all around, in place of the background
+    /// color.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<EnvironmentData>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lights: Vec<LightData>,
 }
 
 impl Build<Scene> for SceneData {
@@ -52,9 +67,24 @@ impl Build<Scene> for SceneData {
                 world.push(geometric);
             }
         }
+        let mut lights = Vec::new();
+        for light in &self.lights {
+            match light.build(builts)? {
+                BuiltLight::Light(light) => lights.push(light),
+                BuiltLight::Geometric(geometric) => world.push(geometric),
+            }
+        }
         let camera = self.camera.build(builts)?;
+        let environment = self
+            .environment
+            .as_ref()
+            .map(|environment| environment.build(builts))
+            .transpose()?;
         let scene = Scene {
-            world: SceneWorld::from_geometrics(&world, &world_virtual, self.use_bvh),
+            world: SceneWorld::from_geometrics(&world, &world_virtual, self.use_bvh)
+                .with_material_ids(MaterialIds::from_named(builts.materials.values()))
+                .with_environment(environment)
+                .with_lights(lights),
             camera,
             background_color: ColorRgb::from(self.background_color).into(),
         };
@@ -62,3 +92,121 @@ impl Build<Scene> for SceneData {
         Ok(scene)
     }
 }
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type", deny_unknown_fields)]
+pub enum EnvironmentData {
+    /// An equirectangular image resource, e.g. an HDR light probe.
+    Image {
+        resource_id: ResourceID,
+        /// Turns the image about the vertical axis, counterclockwise seen
+        /// from above.
+        #[serde(default)]
+        rotation_degrees: f64,
+        #[serde(default = "EnvironmentData::default_intensity")]
+        intensity: f64,
+    },
+    /// A blend from the horizon color up to the zenith and down to the
+    /// ground.
+    Gradient {
+        zenith_color: [f64; 3],
+        horizon_color: [f64; 3],
+        ground_color: [f64; 3],
+    },
+    /// A physical daylight sky with the sun's disk in it, in kcd/m².
+    Sky {
+        /// Degrees the sun stands above the horizon.
+        sun_elevation_degrees: f64,
+        /// Turns the sun from -z toward +x.
+        #[serde(default)]
+        sun_azimuth_degrees: f64,
+        /// Haze, from about 2 on a clear day to 10 in thick haze.
+        #[serde(default = "EnvironmentData::default_turbidity")]
+        turbidity: f64,
+        #[serde(default = "EnvironmentData::default_intensity")]
+        intensity: f64,
+    },
+}
+
+impl EnvironmentData {
+    fn default_intensity() -> f64 {
+        1.0
+    }
+
+    fn default_turbidity() -> f64 {
+        3.0
+    }
+}
+
+impl Build<Arc<Environment>> for EnvironmentData {
+    fn build(&self, builts: &Builts<'_>) -> Result<Arc<Environment>, String> {
+        let environment = match self {
+            Self::Image {
+                resource_id,
+                rotation_degrees,
+                intensity,
+            } => {
+                if !(intensity.is_finite() && *intensity >= 0.0) {
+                    return Err("Environment intensity must be non-negative".to_string());
+                }
+                Environment::new(
+                    EnvironmentSource::Map(builts.image(resource_id)?),
+                    *rotation_degrees,
+                    *intensity,
+                )
+            }
+            Self::Gradient {
+                zenith_color,
+                horizon_color,
+                ground_color,
+            } => {
+                let colors = [zenith_color, horizon_color, ground_color];
+                if colors
+                    .iter()
+                    .flat_map(|color| color.iter())
+                    .any(|c| c.is_nan() || *c < 0.0)
+                {
+                    return Err("Environment gradient colors must be non-negative".to_string());
+                }
+                Environment::new(
+                    EnvironmentSource::Gradient {
+                        zenith: ColorRgb::from(*zenith_color),
+                        horizon: ColorRgb::from(*horizon_color),
+                        ground: ColorRgb::from(*ground_color),
+                    },
+                    0.0,
+                    1.0,
+                )
+            }
+            Self::Sky {
+                sun_elevation_degrees,
+                sun_azimuth_degrees,
+                turbidity,
+                intensity,
+            } => {
+                if !(0.0..=90.0).contains(sun_elevation_degrees) {
+                    return Err("Sky sun elevation must be between 0 and 90 degrees".to_string());
+                }
+                if !sun_azimuth_degrees.is_finite() {
+                    return Err("Sky sun azimuth must be finite".to_string());
+                }
+                if !(1.7..=10.0).contains(turbidity) {
+                    return Err("Sky turbidity must be between 1.7 and 10".to_string());
+                }
+                if !(intensity.is_finite() && *intensity >= 0.0) {
+                    return Err("Environment intensity must be non-negative".to_string());
+                }
+                Environment::new(
+                    EnvironmentSource::Sky(Box::new(Sky::new(
+                        *sun_elevation_degrees,
+                        *sun_azimuth_degrees,
+                        *turbidity,
+                    ))),
+                    0.0,
+                    *intensity,
+                )
+            }
+        };
+        Ok(Arc::new(environment))
+    }
+}
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     shading::{
         ColorRgb, Texture,
-        textures::{Checker, ImageLinearF64, SolidColor},
+        textures::{Checker, SolidColor},
     },
     tracing::ResourceID,
 };
@@ -61,22 +61,7 @@ impl Build<Arc<dyn Texture>> for TextureData {
 
                 Ok(Arc::new(Checker::new(*scale, even, odd)))
             }
-            Self::Image { resource_id } => {
-                match builts.resources {
-                    // validation mode - return a 1x1 placeholder, no DB fetch needed
-                    None => Ok(Arc::new(ImageLinearF64 {
-                        width: 1,
-                        height: 1,
-                        data: vec![[1.0, 0.0, 1.0]],
-                    })),
-                    Some(resources) => {
-                        let texture = resources.get(resource_id).ok_or_else(|| {
-                            format!("Resource {} not found in pre-loaded data", resource_id)
-                        })?;
-                        Ok(Arc::clone(texture) as Arc<dyn Texture>)
-                    }
-                }
-            }
+            Self::Image { resource_id } => Ok(builts.image(resource_id)? as Arc<dyn Texture>),
             Self::SolidColor { color } => Ok(Arc::new(SolidColor::from(ColorRgb::from(*color)))),
         }
     }
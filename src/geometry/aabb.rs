@@ -85,6 +85,37 @@ impl Aabb {
         Self::from_aabbs(self, other)
     }
 
+    /// Grows every side outwards by `margin`.
+    pub fn grow(self, margin: f64) -> Self {
+        Self {
+            x_interval: self.x_interval.expand(2.0 * margin),
+            y_interval: self.y_interval.expand(2.0 * margin),
+            z_interval: self.z_interval.expand(2.0 * margin),
+        }
+    }
+
+    pub fn corners(&self) -> [Point; 8] {
+        std::array::from_fn(|i| {
+            Point::new(
+                if i & 1 == 0 {
+                    self.x_interval.minimum
+                } else {
+                    self.x_interval.maximum
+                },
+                if i & 2 == 0 {
+                    self.y_interval.minimum
+                } else {
+                    self.y_interval.maximum
+                },
+                if i & 4 == 0 {
+                    self.z_interval.minimum
+                } else {
+                    self.z_interval.maximum
+                },
+            )
+        })
+    }
+
     pub fn hit(&self, ray: Ray, ray_t: Interval) -> bool {
         let mut interval = ray_t; // trivial clone since Interval is Copy
         for axis in 0..3 {
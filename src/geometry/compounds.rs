@@ -2,7 +2,10 @@ mod axis_aligned_pbox;
 pub use axis_aligned_pbox::AxisAlignedPBox;
 
 mod bvh;
-pub use bvh::Bvh;
+pub use bvh::{Bvh, take_bvh_nodes_visited};
+
+mod emitter_list;
+pub use emitter_list::EmitterList;
 
 mod list;
 pub use list::List;
@@ -97,11 +97,19 @@ impl Geometric for AxisAlignedPBox {
         self.0.bounding_box()
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
-        self.0.sample_direction_from(origin)
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
+        self.0.sample_direction_from(origin, u)
     }
 
     fn direction_pdf(&self, origin: Point, dir: Vector3) -> f64 {
         self.0.direction_pdf(origin, dir)
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        self.0.sample_point(u)
+    }
+
+    fn parts(&self) -> Option<Vec<Arc<dyn Geometric>>> {
+        self.0.parts()
+    }
 }
@@ -1,12 +1,24 @@
-use std::{cmp::Ordering, sync::Arc};
+use std::{cell::Cell, cmp::Ordering, sync::Arc};
 
 use crate::{
     geometry::{Aabb, Geometric, Point, Ray, RayHit, Vector3},
+    tracing::ONE_MINUS_EPSILON,
     utils::Interval,
 };
 
 use super::List;
 
+thread_local! {
+    // counted for the `bvh_cost` debug integrator
+    static NODES_VISITED: Cell<u64> = const { Cell::new(0) };
+}
+
+/// How many BVH nodes rays traced on the current thread have tested their
+/// bounding boxes against since the last call.
+pub fn take_bvh_nodes_visited() -> u64 {
+    NODES_VISITED.with(|visited| visited.replace(0))
+}
+
 #[derive(Clone, Debug)]
 enum BvhNode {
     Branch { left: Arc<Bvh>, right: Arc<Bvh> },
@@ -94,6 +106,7 @@ impl Bvh {
 
 impl Geometric for Bvh {
     fn intersect(&self, ray: Ray, ray_t: Interval) -> Option<RayHit> {
+        NODES_VISITED.with(|visited| visited.set(visited.get() + 1));
         if matches!(&self.tree, BvhNode::Empty) || !self.bounding_box.hit(ray, ray_t) {
             return None;
         }
@@ -157,23 +170,30 @@ impl Geometric for Bvh {
         self.bounding_box
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
         match &self.tree {
             BvhNode::Branch { left, right } => {
                 let left_area = left.surface_area();
                 let right_area = right.surface_area();
                 let total = left_area + right_area;
                 if total <= 0.0 {
-                    return Vector3::random_unit();
+                    return Vector3::uniform_sphere_direction_from(u);
                 }
-                if rand::random::<f64>() * total <= left_area {
-                    left.sample_direction_from(origin)
+                // rescale the part of the sample that picked a side back to [0, 1)
+                let threshold = u.0 * total;
+                if threshold < left_area {
+                    let u = ((threshold / left_area).min(ONE_MINUS_EPSILON), u.1);
+                    left.sample_direction_from(origin, u)
                 } else {
-                    right.sample_direction_from(origin)
+                    let u = (
+                        ((threshold - left_area) / right_area).clamp(0.0, ONE_MINUS_EPSILON),
+                        u.1,
+                    );
+                    right.sample_direction_from(origin, u)
                 }
             }
-            BvhNode::Leaf(item) => item.sample_direction_from(origin),
-            BvhNode::Empty => Vector3::random_unit(),
+            BvhNode::Leaf(item) => item.sample_direction_from(origin, u),
+            BvhNode::Empty => Vector3::uniform_sphere_direction_from(u),
         }
     }
 
@@ -192,4 +212,41 @@ impl Geometric for Bvh {
             BvhNode::Empty => 0.0,
         }
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        match &self.tree {
+            BvhNode::Branch { left, right } => {
+                let left_area = left.surface_area();
+                let right_area = right.surface_area();
+                let total = left_area + right_area;
+                if total <= 0.0 {
+                    return None;
+                }
+                // the same choice of side as sample_direction_from
+                let threshold = u.0 * total;
+                if threshold < left_area {
+                    left.sample_point(((threshold / left_area).min(ONE_MINUS_EPSILON), u.1))
+                } else {
+                    right.sample_point((
+                        ((threshold - left_area) / right_area).clamp(0.0, ONE_MINUS_EPSILON),
+                        u.1,
+                    ))
+                }
+            }
+            BvhNode::Leaf(item) => item.sample_point(u),
+            BvhNode::Empty => None,
+        }
+    }
+
+    fn parts(&self) -> Option<Vec<Arc<dyn Geometric>>> {
+        Some(match &self.tree {
+            BvhNode::Branch { left, right } => {
+                let mut parts = left.parts().unwrap_or_default();
+                parts.extend(right.parts().unwrap_or_default());
+                parts
+            }
+            BvhNode::Leaf(item) => vec![item.clone()],
+            BvhNode::Empty => Vec::new(),
+        })
+    }
 }
@@ -0,0 +1,336 @@
+use std::sync::Arc;
+
+use crate::{
+    geometry::{Aabb, Geometric, Point, Ray, RayHit, Vector3},
+    shading::ColorRgb,
+    tracing::ONE_MINUS_EPSILON,
+    utils::{Distribution1D, Interval},
+};
+
+use super::List;
+
+/// Points sampled on each emitter to estimate the power it sends out.
+const POWER_SAMPLES: usize = 64;
+
+/// The share of picks made by area rather than by power, so emitters whose
+/// power is underestimated, such as ones lit by a texture the power samples
+/// miss, still get sampled.
+const AREA_SHARE: f64 = 0.1;
+
+/// The scene's emitters, picked for sampling directions toward them by the
+/// power each sends out, so a few bright lights among many dim ones get most
+/// of the samples. Compounds such as meshes are split into their emissive
+/// parts, so each triangle is weighed by its own power. Points on them are
+/// still sampled uniformly by area.
+#[derive(Debug)]
+pub struct EmitterList {
+    list: List,
+    /// the chance of picking each item
+    distribution: Distribution1D,
+}
+
+impl EmitterList {
+    pub fn new(emitters: Vec<Arc<dyn Geometric>>) -> Self {
+        let mut flattened = Vec::with_capacity(emitters.len());
+        for emitter in emitters {
+            flatten_into(emitter, &mut flattened);
+        }
+        let emitters = flattened;
+
+        let areas = emitters
+            .iter()
+            .map(|emitter| {
+                let area = emitter.surface_area();
+                if area.is_finite() && area > 0.0 {
+                    area
+                } else {
+                    0.0
+                }
+            })
+            .collect::<Vec<_>>();
+        let powers = emitters
+            .iter()
+            .zip(&areas)
+            .map(|(emitter, &area)| {
+                if area > 0.0 {
+                    area * mean_peak_luminance(emitter.as_ref())
+                } else {
+                    0.0
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let total_area = areas.iter().sum::<f64>();
+        let total_power = powers.iter().sum::<f64>();
+        let weights = areas
+            .iter()
+            .zip(&powers)
+            .map(|(&area, &power)| {
+                if total_power > 0.0 {
+                    (1.0 - AREA_SHARE) * power / total_power + AREA_SHARE * area / total_area
+                } else if total_area > 0.0 {
+                    area / total_area
+                } else {
+                    0.0
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            list: List::from_vec(emitters),
+            distribution: Distribution1D::new(weights),
+        }
+    }
+
+    /// The chance of picking item `i`.
+    fn probability(&self, i: usize) -> f64 {
+        let count = self.list.items.len() as f64;
+        self.distribution.density((i as f64 + 0.5) / count) / count
+    }
+}
+
+/// Adds the emissive parts of `emitter` to `emitters`, down to the primitives
+/// and instances that can't be split any further.
+fn flatten_into(emitter: Arc<dyn Geometric>, emitters: &mut Vec<Arc<dyn Geometric>>) {
+    match emitter.parts() {
+        Some(parts) => {
+            for part in parts.into_iter().filter(|part| part.is_emissive()) {
+                flatten_into(part, emitters);
+            }
+        }
+        None => emitters.push(emitter),
+    }
+}
+
+/// The peak emitted luminance of `emitter` over time, averaged over points
+/// spread over it. Emitters that brighten and dim over an animation are
+/// weighed by their brightest, so they aren't undersampled in any frame.
+fn mean_peak_luminance(emitter: &dyn Geometric) -> f64 {
+    let side = (POWER_SAMPLES as f64).sqrt() as usize;
+    let mut total = 0.0;
+    for i in 0..side {
+        for j in 0..side {
+            let u = (
+                ((i as f64 + 0.5) / side as f64).min(ONE_MINUS_EPSILON),
+                ((j as f64 + 0.5) / side as f64).min(ONE_MINUS_EPSILON),
+            );
+            let Some((point, normal)) = emitter.sample_point(u) else {
+                continue;
+            };
+            // the material and texture coordinates of the point
+            let probe = Ray::new(point + normal * 0.001, -normal, 0.0);
+            if let Some(ray_hit) = emitter.intersect(probe, Interval::new(0.0, 0.002)) {
+                let emittance =
+                    ray_hit
+                        .material
+                        .peak_emittance(ray_hit.u, ray_hit.v, ray_hit.point);
+                total += ColorRgb::from(emittance).luminance().max(0.0);
+            }
+        }
+    }
+    total / (side * side) as f64
+}
+
+impl Geometric for EmitterList {
+    fn intersect(&self, ray: Ray, ray_t: Interval) -> Option<RayHit> {
+        self.list.intersect(ray, ray_t)
+    }
+
+    fn surface_area(&self) -> f64 {
+        self.list.surface_area()
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.list.is_emissive()
+    }
+
+    fn is_transmissive(&self) -> bool {
+        self.list.is_transmissive()
+    }
+
+    fn is_specular(&self) -> bool {
+        self.list.is_specular()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.list.bounding_box()
+    }
+
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
+        let items = &self.list.items;
+        if self.distribution.integral() <= 0.0 {
+            return Vector3::uniform_sphere_direction_from(u);
+        }
+        let (x, i) = self.distribution.sample(u.0);
+        // the part of `u` that picked the item, rescaled to [0, 1)
+        let u0 = (x * items.len() as f64 - i as f64).clamp(0.0, ONE_MINUS_EPSILON);
+        items[i].sample_direction_from(origin, (u0, u.1))
+    }
+
+    fn direction_pdf(&self, origin: Point, dir: Vector3) -> f64 {
+        if self.distribution.integral() <= 0.0 {
+            return 0.0;
+        }
+        // only the emitters whose boxes the direction passes through can be
+        // hit, which spares intersecting every triangle of an emissive mesh
+        let ray = Ray::new(origin, dir, 0.0);
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+        self.list
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.bounding_box().hit(ray, ray_t))
+            .map(|(i, item)| match self.probability(i) {
+                0.0 => 0.0,
+                probability => probability * item.direction_pdf(origin, dir),
+            })
+            .sum()
+    }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        self.list.sample_point(u)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        geometry::{
+            compounds::{Bvh, List},
+            primitives::{Sphere, Triangle},
+        },
+        shading::{ColorSpectrum, materials::Lambertian, textures::SolidColor},
+        utils::{Keyframe, Keyframes},
+    };
+
+    use super::*;
+
+    #[test]
+    fn brighter_emitters_are_picked_more_often() {
+        let emitter = |center: [f64; 3], radiance: f64| -> Arc<dyn Geometric> {
+            let material = Arc::new(Lambertian::new(
+                Arc::new(SolidColor::BLACK),
+                Arc::new(SolidColor::new(ColorSpectrum::ONE * radiance)),
+            ));
+            Arc::new(Sphere::new(center.into(), 0.5, material))
+        };
+        let emitters = EmitterList::new(vec![
+            emitter([-2.0, 0.0, 0.0], 1.0),
+            emitter([2.0, 0.0, 0.0], 99.0),
+        ]);
+        let bright = (1.0 - AREA_SHARE) * 0.99 + AREA_SHARE * 0.5;
+        assert!((emitters.probability(1) - bright).abs() < 1e-9);
+
+        let n = 64;
+        let (mut picked_bright, mut inverse_density_sum) = (0, 0.0);
+        for i in 0..n {
+            for j in 0..n {
+                let u = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+                let direction = emitters.sample_direction_from(Point::ORIGIN, u);
+                if direction.x > 0.0 {
+                    picked_bright += 1;
+                }
+                inverse_density_sum += 1.0 / emitters.direction_pdf(Point::ORIGIN, direction);
+            }
+        }
+        assert!(
+            (picked_bright as f64 / (n * n) as f64 - bright).abs() < 0.01,
+            "{picked_bright}"
+        );
+        // the solid angle the two spheres cover
+        let solid_angle = 2.0 * 2.0 * std::f64::consts::PI * (1.0 - (1.0 - 0.25_f64 / 4.0).sqrt());
+        let estimate = inverse_density_sum / (n * n) as f64;
+        assert!(
+            (estimate - solid_angle).abs() < 0.05 * solid_angle,
+            "{estimate}"
+        );
+    }
+
+    #[test]
+    fn keyframed_emitters_are_weighed_by_their_peak() {
+        let steady: Arc<dyn Geometric> = Arc::new(Sphere::new(
+            [-2.0, 0.0, 0.0].into(),
+            0.5,
+            Arc::new(Lambertian::new(
+                Arc::new(SolidColor::BLACK),
+                Arc::new(SolidColor::WHITE),
+            )),
+        ));
+        // dark at time 0, then 99 times as bright as the steady one
+        let scale = Keyframes::new([
+            Keyframe {
+                time: 0.0,
+                value: 0.0,
+            },
+            Keyframe {
+                time: 1.0,
+                value: 99.0,
+            },
+        ])
+        .unwrap();
+        let flaring: Arc<dyn Geometric> = Arc::new(Sphere::new(
+            [2.0, 0.0, 0.0].into(),
+            0.5,
+            Arc::new(
+                Lambertian::new(Arc::new(SolidColor::BLACK), Arc::new(SolidColor::WHITE))
+                    .with_emittance_scale(scale),
+            ),
+        ));
+
+        let emitters = EmitterList::new(vec![steady, flaring]);
+        let bright = (1.0 - AREA_SHARE) * 0.99 + AREA_SHARE * 0.5;
+        assert!((emitters.probability(1) - bright).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mesh_triangles_are_weighed_by_their_own_power() {
+        let glowing = |radiance: f64| -> Arc<Lambertian> {
+            Arc::new(Lambertian::new(
+                Arc::new(SolidColor::BLACK),
+                Arc::new(SolidColor::new(ColorSpectrum::ONE * radiance)),
+            ))
+        };
+        // four equal triangles side by side, one of them far brighter
+        let triangles = (0..4)
+            .map(|i| -> Arc<dyn Geometric> {
+                let x = 2.0 * i as f64;
+                let radiance = if i == 2 { 97.0 } else { 1.0 };
+                Arc::new(Triangle::new(
+                    [x, 0.0, -1.0].into(),
+                    [x + 1.0, 0.0, -1.0].into(),
+                    [x, 1.0, -1.0].into(),
+                    glowing(radiance),
+                ))
+            })
+            .collect::<Vec<_>>();
+        let meshes: [Arc<dyn Geometric>; 2] = [
+            Arc::new(List::from_vec(triangles.clone())),
+            Arc::new(Bvh::new(triangles)),
+        ];
+
+        for mesh in meshes {
+            let emitters = EmitterList::new(vec![mesh]);
+            assert_eq!(emitters.list.items.len(), 4);
+            let bright = emitters
+                .list
+                .items
+                .iter()
+                .position(|item| (4.0..5.0).contains(&item.bounding_box().center().0.x))
+                .unwrap();
+            let expected = (1.0 - AREA_SHARE) * 0.97 + AREA_SHARE * 0.25;
+            assert!((emitters.probability(bright) - expected).abs() < 1e-9);
+
+            // straight at the bright triangle, through no other's box, where
+            // its area of 0.5 is seen face on from a distance of 1
+            let origin = Point::new(4.25, 0.25, 0.0);
+            let direction = Vector3::new(0.0, 0.0, -1.0);
+            let pdf = emitters.direction_pdf(origin, direction);
+            let expected = expected / 0.5;
+            assert!((pdf - expected).abs() < 1e-9, "{pdf}");
+        }
+    }
+}
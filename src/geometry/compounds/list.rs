@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use crate::{
     geometry::{Aabb, Geometric, Point, Ray, RayHit, Vector3},
+    tracing::ONE_MINUS_EPSILON,
     utils::Interval,
 };
 
@@ -59,6 +60,42 @@ impl List {
     pub fn take_items(self) -> Vec<Arc<dyn Geometric>> {
         self.items
     }
+
+    /// Picks an item with probability proportional to its area, and rescales
+    /// the part of `u` that picked it back to [0, 1) for the item to use.
+    /// Items with infinite or zero area can't be picked.
+    fn pick_by_area(&self, u: (f64, f64)) -> Option<(&dyn Geometric, (f64, f64))> {
+        let is_sampleable = |item: &&Arc<dyn Geometric>| {
+            let area = item.surface_area();
+            area.is_finite() && area > 0.0
+        };
+
+        let total_area: f64 = self
+            .items
+            .iter()
+            .filter(is_sampleable)
+            .map(|item| item.surface_area())
+            .sum();
+        if total_area <= 0.0 {
+            return None;
+        }
+        let mut threshold: f64 = u.0 * total_area;
+        for item in self.items.iter().filter(is_sampleable) {
+            let area = item.surface_area();
+            if threshold < area {
+                return Some((
+                    item.as_ref(),
+                    ((threshold / area).min(ONE_MINUS_EPSILON), u.1),
+                ));
+            }
+            threshold -= area;
+        }
+        // rounding can leave the threshold just past the last item
+        self.items
+            .iter()
+            .rfind(is_sampleable)
+            .map(|item| (item.as_ref(), u))
+    }
 }
 
 impl From<Vec<Arc<dyn Geometric>>> for List {
@@ -106,38 +143,11 @@ impl Geometric for List {
         self.bounding_box
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
-        let total_area: f64 = self
-            .items
-            .iter()
-            .map(|item| item.surface_area())
-            .filter(|a| a.is_finite() && *a > 0.0)
-            .sum();
-        if total_area <= 0.0 {
-            return Vector3::random_unit();
-        }
-        let mut threshold: f64 = rand::random::<f64>() * total_area;
-        for item in &self.items {
-            let area = item.surface_area();
-            // skip children with infinite or zero area — they can't be importance-sampled
-            if !area.is_finite() || area <= 0.0 {
-                continue;
-            }
-            if threshold <= area {
-                return item.sample_direction_from(origin);
-            }
-            threshold -= area;
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
+        match self.pick_by_area(u) {
+            Some((item, u)) => item.sample_direction_from(origin, u),
+            None => Vector3::uniform_sphere_direction_from(u),
         }
-        // fallback to last finite-area child, or random unit if none
-        self.items
-            .iter()
-            .rev()
-            .find(|item| {
-                let a = item.surface_area();
-                a.is_finite() && a > 0.0
-            })
-            .map(|item| item.sample_direction_from(origin))
-            .unwrap_or_else(Vector3::random_unit)
     }
 
     fn direction_pdf(&self, origin: Point, dir: Vector3) -> f64 {
@@ -159,4 +169,13 @@ impl Geometric for List {
             .map(|item| (item.surface_area() / total_area) * item.direction_pdf(origin, dir))
             .sum()
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        let (item, u) = self.pick_by_area(u)?;
+        item.sample_point(u)
+    }
+
+    fn parts(&self) -> Option<Vec<Arc<dyn Geometric>>> {
+        Some(self.items.clone())
+    }
 }
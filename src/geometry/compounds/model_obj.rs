@@ -161,13 +161,21 @@ impl Geometric for ModelObj {
         self.geometric.bounding_box()
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
-        self.geometric.sample_direction_from(origin)
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
+        self.geometric.sample_direction_from(origin, u)
     }
 
     fn direction_pdf(&self, origin: Point, dir: Vector3) -> f64 {
         self.geometric.direction_pdf(origin, dir)
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        self.geometric.sample_point(u)
+    }
+
+    fn parts(&self) -> Option<Vec<Arc<dyn Geometric>>> {
+        self.geometric.parts()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -226,10 +234,10 @@ impl Geometric for ListOrBvh {
         }
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
         match self {
-            ListOrBvh::List(list) => list.sample_direction_from(origin),
-            ListOrBvh::Bvh(bvh) => bvh.sample_direction_from(origin),
+            ListOrBvh::List(list) => list.sample_direction_from(origin, u),
+            ListOrBvh::Bvh(bvh) => bvh.sample_direction_from(origin, u),
         }
     }
 
@@ -239,4 +247,18 @@ impl Geometric for ListOrBvh {
             ListOrBvh::Bvh(bvh) => bvh.direction_pdf(origin, dir),
         }
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        match self {
+            ListOrBvh::List(list) => list.sample_point(u),
+            ListOrBvh::Bvh(bvh) => bvh.sample_point(u),
+        }
+    }
+
+    fn parts(&self) -> Option<Vec<Arc<dyn Geometric>>> {
+        match self {
+            ListOrBvh::List(list) => list.parts(),
+            ListOrBvh::Bvh(bvh) => bvh.parts(),
+        }
+    }
 }
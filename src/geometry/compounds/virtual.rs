@@ -58,11 +58,15 @@ impl Geometric for Virtual {
         self.inner.bounding_box()
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
-        self.inner.sample_direction_from(origin)
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
+        self.inner.sample_direction_from(origin, u)
     }
 
     fn direction_pdf(&self, origin: Point, dir: Vector3) -> f64 {
         self.inner.direction_pdf(origin, dir)
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        self.inner.sample_point(u)
+    }
 }
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use crate::{
     geometry::{Point, Vector3},
     utils::Interval,
@@ -34,9 +36,15 @@ pub trait Geometric: std::fmt::Debug + Sync + Send {
         self.bounding_box().center()
     }
 
-    /// Sample a random direction from `origin` toward a point on this object's
-    /// surface, chosen uniformly by area. The returned direction is a unit vector.
-    fn sample_direction_from(&self, origin: Point) -> Vector3;
+    /// Sample a direction from `origin` toward a point on this object's
+    /// surface, chosen uniformly by area, except that an
+    /// [`EmitterList`](super::compounds::EmitterList) picks its items by power.
+    /// The returned direction is a unit vector.
+    ///
+    /// `u` is a uniform sample in [0, 1)² from the render's sampler. Compounds
+    /// choose a child with the first component, then rescale it to [0, 1) again
+    /// before passing it on.
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3;
     /// The solid-angle probability density that [`sample_direction_from`] would
     /// assign to `dir`. Returns 0.0 if `dir` does not intersect this object.
     ///
@@ -52,6 +60,23 @@ pub trait Geometric: std::fmt::Debug + Sync + Send {
     /// the hit, and `area` is the total surface area.
     fn direction_pdf(&self, origin: Point, dir: Vector3) -> f64;
 
+    /// Sample a point on this object's surface, chosen uniformly by area, and
+    /// the surface normal there. Any point is picked with area density
+    /// `1 / surface_area()`. Used to start light subpaths on emitters.
+    ///
+    /// Returns `None` for objects without a finite surface to sample (e.g.
+    /// planes, infinite cylinders and volumes). Moving objects are sampled at
+    /// their first keyframe, as in [`sample_direction_from`].
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)>;
+
+    /// The children of a compound, in world space, for an
+    /// [`EmitterList`](super::compounds::EmitterList) to weigh each emitter
+    /// among them by its own power. `None` for primitives, and for instances,
+    /// whose children aren't in world space.
+    fn parts(&self) -> Option<Vec<Arc<dyn Geometric>>> {
+        None
+    }
+
     /// Whether this geometric is "virtual" — excluded from ray intersections
     /// but included in importance sampling for faster convergence.
     fn is_virtual(&self) -> bool {
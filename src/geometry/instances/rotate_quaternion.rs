@@ -2,23 +2,37 @@ use std::sync::Arc;
 
 use crate::{
     geometry::{Aabb, Geometric, Matrix3, Point, Quaternion, Ray, RayHit, Vector3},
-    utils::{Around, Interval},
+    utils::{Around, Interval, Keyframes},
 };
 
 #[derive(Clone, Debug)]
 pub struct RotateQuaternion {
     geometric: Arc<dyn Geometric>,
     translation: Vector3,
+    quaternion: Keyframes<Quaternion>,
+    // the rotation at the first keyframe, which is the only one when static
     rotation: Matrix3,
     inv_rotation: Matrix3,
     bounding_box: Aabb,
 }
 
 impl RotateQuaternion {
+    /// How many steps each keyframe segment is split into when bounding the
+    /// swept volume of an animated rotation.
+    const BOUNDING_STEPS: u32 = 16;
+
     pub fn new(geometric: Arc<dyn Geometric>, quaternion: Quaternion, around: Around) -> Self {
+        Self::new_animated(geometric, Keyframes::constant(quaternion), around)
+    }
+
+    pub fn new_animated(
+        geometric: Arc<dyn Geometric>,
+        quaternion: Keyframes<Quaternion>,
+        around: Around,
+    ) -> Self {
         let translation = around.point(&geometric).0;
 
-        let rotation: Matrix3 = quaternion.into();
+        let rotation: Matrix3 = quaternion.first().into();
         let inv_rotation = rotation.transpose();
 
         let child_bbox = geometric.bounding_box();
@@ -28,75 +42,83 @@ impl RotateQuaternion {
             // UNIVERSE to route it to the scene's unbounded list.
             Aabb::UNIVERSE
         } else {
-            let geometric_bbox = child_bbox - translation;
-
-            let mut min_extent = Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
-            let mut max_extent =
-                Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
-
-            for i in 0..2 {
-                for j in 0..2 {
-                    for k in 0..2 {
-                        // grab one of the eight corners of the bounding box
-                        let x = i as f64 * geometric_bbox.x_interval.maximum
-                            + (1 - i) as f64 * geometric_bbox.x_interval.minimum;
-                        let y = j as f64 * geometric_bbox.y_interval.maximum
-                            + (1 - j) as f64 * geometric_bbox.y_interval.minimum;
-                        let z = k as f64 * geometric_bbox.z_interval.maximum
-                            + (1 - k) as f64 * geometric_bbox.z_interval.minimum;
-
-                        // rotate it
-                        let rotated = rotation * Vector3::new(x, y, z);
-                        let rotated_point = Point::new(rotated.x, rotated.y, rotated.z);
-
-                        min_extent = min_extent.min_components_point(rotated_point);
-                        max_extent = max_extent.max_components_point(rotated_point);
-                    }
-                }
+            let corners = (child_bbox - translation).corners();
+
+            // the orientations to bound: each keyframe, plus evenly spaced
+            // steps along each segment between them
+            let mut orientations = vec![quaternion.first()];
+            let mut max_step_angle: f64 = 0.0;
+            for (a, b) in quaternion.segments() {
+                max_step_angle = max_step_angle.max(a.angle_to(b) / Self::BOUNDING_STEPS as f64);
+                orientations.extend(
+                    (1..=Self::BOUNDING_STEPS)
+                        .map(|i| a.slerp(b, i as f64 / Self::BOUNDING_STEPS as f64)),
+                );
             }
 
-            Aabb::from_points(&[min_extent, max_extent]) + translation
+            let rotated_corners: Vec<Point> = orientations
+                .into_iter()
+                .flat_map(|q| {
+                    let rotation: Matrix3 = q.into();
+                    corners.map(|corner| Point::from_vector3(rotation * corner.0))
+                })
+                .collect();
+
+            // between steps, corners travel along arcs that can bulge past the
+            // stepped corners by at most r * (1 - cos(step / 2))
+            let max_radius = corners
+                .iter()
+                .map(|corner| corner.0.length())
+                .fold(0.0, f64::max);
+            let arc_margin = max_radius * (1.0 - (max_step_angle / 2.0).cos());
+
+            Aabb::from_points(&rotated_corners).grow(arc_margin) + translation
         };
 
         Self {
             geometric: Arc::clone(&geometric),
             translation,
+            quaternion,
             rotation,
             inv_rotation,
             bounding_box,
         }
     }
 
-    fn world_to_local_point(&self, v: Point) -> Point {
-        Point::from_vector3(self.world_to_local_vector(v.0 - self.translation)) + self.translation
-    }
+    /// The rotation and its inverse at `time`.
+    fn rotation_at(&self, time: f64) -> (Matrix3, Matrix3) {
+        if self.quaternion.is_constant() {
+            return (self.rotation, self.inv_rotation);
+        }
 
-    fn world_to_local_vector(&self, v: Vector3) -> Vector3 {
-        self.inv_rotation * v
+        let rotation: Matrix3 = self.quaternion.at(time).into();
+        (rotation, rotation.transpose())
     }
 
-    fn local_to_world_point(&self, v: Point) -> Point {
-        Point::from_vector3(self.local_to_world_vector(v.0 - self.translation)) + self.translation
+    fn world_to_local_point(&self, v: Point, inv_rotation: &Matrix3) -> Point {
+        Point::from_vector3(inv_rotation * (v.0 - self.translation)) + self.translation
     }
 
-    fn local_to_world_vector(&self, v: Vector3) -> Vector3 {
-        self.rotation * v
+    fn local_to_world_point(&self, v: Point, rotation: &Matrix3) -> Point {
+        Point::from_vector3(rotation * (v.0 - self.translation)) + self.translation
     }
 }
 
 impl Geometric for RotateQuaternion {
     fn intersect(&self, ray: Ray, ray_t: Interval) -> Option<RayHit> {
+        let (rotation, inv_rotation) = self.rotation_at(ray.time);
+
         // change the ray from world coordinates to object coordinates
         let mut local_ray = ray;
-        local_ray.origin = self.world_to_local_point(local_ray.origin);
-        local_ray.direction = self.world_to_local_vector(local_ray.direction);
+        local_ray.origin = self.world_to_local_point(local_ray.origin, &inv_rotation);
+        local_ray.direction = inv_rotation * local_ray.direction;
 
         // check intersection in object coordinates
         let mut rayhit = self.geometric.intersect(local_ray, ray_t)?;
 
         // change the ray back to world coordinates
-        rayhit.point = self.local_to_world_point(rayhit.point);
-        rayhit.normal = self.local_to_world_vector(rayhit.normal);
+        rayhit.point = self.local_to_world_point(rayhit.point, &rotation);
+        rayhit.normal = rotation * rayhit.normal;
 
         Some(rayhit)
     }
@@ -125,16 +147,83 @@ impl Geometric for RotateQuaternion {
         self.bounding_box
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
-        let local_origin = self.world_to_local_point(origin);
-        let local_dir = self.geometric.sample_direction_from(local_origin);
-        self.local_to_world_vector(local_dir)
+    // there's no ray time here, so sample towards the first keyframe's orientation
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
+        let local_origin = self.world_to_local_point(origin, &self.inv_rotation);
+        let local_dir = self.geometric.sample_direction_from(local_origin, u);
+        self.rotation * local_dir
     }
 
     fn direction_pdf(&self, origin: Point, dir: Vector3) -> f64 {
-        let local_origin = self.world_to_local_point(origin);
-        let local_dir = self.world_to_local_vector(dir);
+        let local_origin = self.world_to_local_point(origin, &self.inv_rotation);
+        let local_dir = self.inv_rotation * dir;
         // PDF is invariant under rigid transforms
         self.geometric.direction_pdf(local_origin, local_dir)
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        let (point, normal) = self.geometric.sample_point(u)?;
+        Some((
+            self.local_to_world_point(point, &self.rotation),
+            self.rotation * normal,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        geometry::primitives::Parallelogram, shading::materials::Lambertian, utils::Keyframe,
+    };
+
+    use super::*;
+
+    #[test]
+    fn animated_rotation_moves_hits_and_bounds_the_sweep() {
+        // a strip along +x, spinning half a turn around z over [0, 1]
+        let strip: Arc<dyn Geometric> = Arc::new(Parallelogram::new(
+            Point::new(0.5, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.1, 0.0),
+            Arc::new(Lambertian::white()),
+        ));
+        let quaternion = Keyframes::new([
+            Keyframe {
+                time: 0.0,
+                value: Quaternion::IDENTITY,
+            },
+            Keyframe {
+                time: 1.0,
+                value: Quaternion::new(0.0, 0.0, 0.0, 1.0),
+            },
+        ])
+        .unwrap();
+        let rotate = RotateQuaternion::new_animated(strip, quaternion, Around::Origin);
+
+        // a quarter turn in, the strip lies along +y
+        let ray_t = Interval::new(0.0, f64::INFINITY);
+        let ray_at = |time| {
+            Ray::new(
+                Point::new(-0.05, 1.0, 1.0),
+                Vector3::new(0.0, 0.0, -1.0),
+                time,
+            )
+        };
+        assert!(rotate.intersect(ray_at(0.0), ray_t).is_none());
+        let hit = rotate.intersect(ray_at(0.5), ray_t).unwrap();
+        assert!((hit.point.0 - Vector3::new(-0.05, 1.0, 0.0)).length() < 1e-9);
+
+        let bbox = rotate.bounding_box();
+        for step in 0..=100 {
+            let rotation: Matrix3 = rotate.quaternion.at(step as f64 / 100.0).into();
+            for corner in [(0.5, 0.0), (1.5, 0.0), (0.5, 0.1), (1.5, 0.1)] {
+                let p = rotation * Vector3::new(corner.0, corner.1, 0.0);
+                assert!(
+                    bbox.x_interval.contains_including(p.x)
+                        && bbox.y_interval.contains_including(p.y),
+                    "{p:?} at step {step} outside {bbox:?}"
+                );
+            }
+        }
+    }
 }
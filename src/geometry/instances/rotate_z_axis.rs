@@ -133,9 +133,9 @@ impl Geometric for RotateZAxis {
         self.bounding_box
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
         let local_origin = self.world_to_local_point(origin);
-        let local_dir = self.geometric.sample_direction_from(local_origin);
+        let local_dir = self.geometric.sample_direction_from(local_origin, u);
         self.local_to_world_vector(local_dir)
     }
 
@@ -145,4 +145,12 @@ impl Geometric for RotateZAxis {
         // PDF is invariant under rigid transforms
         self.geometric.direction_pdf(local_origin, local_dir)
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        let (point, normal) = self.geometric.sample_point(u)?;
+        Some((
+            self.local_to_world_point(point),
+            self.local_to_world_vector(normal),
+        ))
+    }
 }
@@ -2,15 +2,14 @@ use std::sync::Arc;
 
 use crate::{
     geometry::{Aabb, Geometric, Point, Ray, RayHit, Vector3},
-    utils::{Around, Interval},
+    utils::{Around, Interval, Keyframes},
 };
 
 #[derive(Clone, Debug)]
 pub struct Scale {
     geometric: Arc<dyn Geometric>,
     translation: Vector3,
-    scale: Vector3,
-    inv_scale: Vector3,
+    scale: Keyframes<Vector3>,
     bounding_box: Aabb,
 }
 
@@ -20,72 +19,78 @@ impl Scale {
         scale: Vector3,
         around: Around,
     ) -> Result<Self, String> {
-        if scale.x == 0.0 || scale.y == 0.0 || scale.z == 0.0 {
+        Self::new_animated(geometric, Keyframes::constant(scale), around)
+    }
+
+    pub fn new_animated(
+        geometric: Arc<dyn Geometric>,
+        scale: Keyframes<Vector3>,
+        around: Around,
+    ) -> Result<Self, String> {
+        if scale
+            .values()
+            .any(|s| s.x == 0.0 || s.y == 0.0 || s.z == 0.0)
+        {
             return Err("scale factors must be non-zero in all axes".to_string());
         }
+        // interpolating between factors of opposite sign would pass through zero
+        if scale
+            .segments()
+            .any(|(a, b)| a.x * b.x < 0.0 || a.y * b.y < 0.0 || a.z * b.z < 0.0)
+        {
+            return Err("animated scale factors must not change sign".to_string());
+        }
 
-        let inv_scale = Vector3::new(1.0 / scale.x, 1.0 / scale.y, 1.0 / scale.z);
         let translation = around.point(&geometric).0;
 
-        let geometric_bbox = geometric.bounding_box() - translation;
-
-        let mut min_extent = Point::INFINITY;
-        let mut max_extent = -Point::INFINITY;
-
-        for i in 0..2 {
-            for j in 0..2 {
-                for k in 0..2 {
-                    let x = i as f64 * geometric_bbox.x_interval.maximum
-                        + (1 - i) as f64 * geometric_bbox.x_interval.minimum;
-                    let y = j as f64 * geometric_bbox.y_interval.maximum
-                        + (1 - j) as f64 * geometric_bbox.y_interval.minimum;
-                    let z = k as f64 * geometric_bbox.z_interval.maximum
-                        + (1 - k) as f64 * geometric_bbox.z_interval.minimum;
+        // scaled corners move in straight lines between keyframes, so the
+        // corners at the keyframes bound the whole motion
+        let corners = (geometric.bounding_box() - translation).corners();
+        let scaled_corners: Vec<Point> = scale
+            .values()
+            .flat_map(|s| corners.map(|corner| Point::from_vector3(corner.0 * s)))
+            .collect();
 
-                    let corner = Point::new(x, y, z);
-                    let scaled_corner = Point::from_vector3(corner.0 * scale);
-
-                    min_extent = min_extent.min_components_point(scaled_corner);
-                    max_extent = max_extent.max_components_point(scaled_corner);
-                }
-            }
-        }
-
-        let bbox_final = Aabb::from_points(&[min_extent, max_extent]) + translation;
+        let bbox_final = Aabb::from_points(&scaled_corners) + translation;
 
         Ok(Self {
             geometric: Arc::clone(&geometric),
             translation,
             scale,
-            inv_scale,
             bounding_box: bbox_final,
         })
     }
+
+    fn to_local(&self, p: Point, scale: Vector3) -> Point {
+        Point::from_vector3((p.0 - self.translation) / scale + self.translation)
+    }
 }
 
 impl Geometric for Scale {
     fn intersect(&self, ray: Ray, ray_t: Interval) -> Option<RayHit> {
+        let scale = self.scale.at(ray.time);
+
         // transform ray to pivot-centered scaled local space.
         // subtract pivot, apply transform, add pivot back.
         let mut local_ray = ray;
-        local_ray.origin =
-            Point::from_vector3((ray.origin.0 - self.translation) / self.scale + self.translation);
-        local_ray.direction = ray.direction / self.scale;
+        local_ray.origin = self.to_local(ray.origin, scale);
+        local_ray.direction = ray.direction / scale;
 
         let mut rayhit = self.geometric.intersect(local_ray, ray_t)?;
 
         // transform hit back: subtract pivot, scale, add pivot
-        rayhit.point.0 = (rayhit.point.0 - self.translation) * self.scale + self.translation;
+        rayhit.point.0 = (rayhit.point.0 - self.translation) * scale + self.translation;
         // transform normal via inverse-transpose (translation invariant)
-        rayhit.normal = (rayhit.normal * self.inv_scale).unit_vector();
+        rayhit.normal = (rayhit.normal / scale).unit_vector();
 
         Some(rayhit)
     }
 
     fn surface_area(&self) -> f64 {
-        let sx = self.scale.x.abs();
-        let sy = self.scale.y.abs();
-        let sz = self.scale.z.abs();
+        let scale = self.scale.first();
+        let sx = scale.x.abs();
+        let sy = scale.y.abs();
+        let sz = scale.z.abs();
         self.geometric.surface_area() * (sy * sz + sx * sz + sx * sy) / 3.0
     }
 
@@ -109,28 +114,40 @@ impl Geometric for Scale {
         self.bounding_box
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
-        let local_origin =
-            Point::from_vector3((origin.0 - self.translation) / self.scale + self.translation);
-        let local_dir = self.geometric.sample_direction_from(local_origin);
-        (local_dir * self.scale).unit_vector()
+    // sampling has no ray time, so it uses the scale at the first keyframe
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
+        let scale = self.scale.first();
+        let local_origin = self.to_local(origin, scale);
+        let local_dir = self.geometric.sample_direction_from(local_origin, u);
+        (local_dir * scale).unit_vector()
     }
 
     fn direction_pdf(&self, origin: Point, dir: Vector3) -> f64 {
-        let local_origin =
-            Point::from_vector3((origin.0 - self.translation) / self.scale + self.translation);
-        let local_dir = (dir / self.scale).unit_vector();
+        let scale = self.scale.first();
+        let local_origin = self.to_local(origin, scale);
+        let local_dir = (dir / scale).unit_vector();
 
         let p_local = self.geometric.direction_pdf(local_origin, local_dir);
 
         // solid-angle Jacobian for the direction map ω → normalize(scale * ω):
         // p_world = p_local / |J|  where  |J| = |det(S)| / |S·ω_local|³
-        let a_sq = self.scale.x * self.scale.x * local_dir.x * local_dir.x
-            + self.scale.y * self.scale.y * local_dir.y * local_dir.y
-            + self.scale.z * self.scale.z * local_dir.z * local_dir.z;
-        let det = self.scale.x.abs() * self.scale.y.abs() * self.scale.z.abs();
+        let a_sq = scale.x * scale.x * local_dir.x * local_dir.x
+            + scale.y * scale.y * local_dir.y * local_dir.y
+            + scale.z * scale.z * local_dir.z * local_dir.z;
+        let det = scale.x.abs() * scale.y.abs() * scale.z.abs();
         let jacobian = det / (a_sq * a_sq.sqrt());
 
         p_local / jacobian
     }
+
+    // a uniform scale keeps the sampling uniform by area; other scales only
+    // roughly so, as with surface_area
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        let scale = self.scale.first();
+        let (point, normal) = self.geometric.sample_point(u)?;
+        Some((
+            Point::from_vector3((point.0 - self.translation) * scale + self.translation),
+            (normal / scale).unit_vector(),
+        ))
+    }
 }
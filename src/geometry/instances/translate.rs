@@ -2,19 +2,31 @@ use std::sync::Arc;
 
 use crate::{
     geometry::{Aabb, Geometric, Point, Ray, RayHit, Vector3},
-    utils::Interval,
+    utils::{Interval, Keyframes},
 };
 
 #[derive(Clone, Debug)]
 pub struct Translate {
     geometric: Arc<dyn Geometric>,
-    translation: Vector3,
+    translation: Keyframes<Vector3>,
     bounding_box: Aabb,
 }
 
 impl Translate {
     pub fn new(geometric: Arc<dyn Geometric>, translation: Vector3) -> Self {
-        let bounding_box = geometric.bounding_box() + translation;
+        Self::new_animated(geometric, Keyframes::constant(translation))
+    }
+
+    pub fn new_animated(geometric: Arc<dyn Geometric>, translation: Keyframes<Vector3>) -> Self {
+        // the translation moves in straight lines between keyframes, so the
+        // boxes at the keyframes cover the whole motion
+        let child_bbox = geometric.bounding_box();
+        let bounding_box = translation
+            .values()
+            .map(|t| child_bbox + t)
+            .reduce(Aabb::expand)
+            .unwrap_or(child_bbox);
+
         Self {
             geometric: Arc::clone(&geometric),
             translation,
@@ -22,12 +34,18 @@ impl Translate {
         }
     }
 
-    fn world_to_local(&self, v: Vector3) -> Vector3 {
-        v - self.translation
+    fn world_to_local(&self, v: Vector3, time: f64) -> Vector3 {
+        v - self.translation.at(time)
     }
 
-    fn local_to_world(&self, v: Vector3) -> Vector3 {
-        v + self.translation
+    fn local_to_world(&self, v: Vector3, time: f64) -> Vector3 {
+        v + self.translation.at(time)
+    }
+
+    /// Importance sampling has no ray time to go by, so it aims at the
+    /// geometric's pose at the first keyframe, as moving spheres do.
+    fn world_to_local_for_sampling(&self, v: Vector3) -> Vector3 {
+        v - self.translation.first()
     }
 }
 
@@ -35,10 +53,10 @@ impl Geometric for Translate {
     fn intersect(&self, ray: Ray, ray_t: Interval) -> Option<RayHit> {
         let mut local_ray = ray;
 
-        local_ray.origin.0 = self.world_to_local(local_ray.origin.0);
+        local_ray.origin.0 = self.world_to_local(local_ray.origin.0, ray.time);
 
         self.geometric.intersect(local_ray, ray_t).map(|mut rh| {
-            rh.point.0 = self.local_to_world(rh.point.0);
+            rh.point.0 = self.local_to_world(rh.point.0, ray.time);
             rh
         })
     }
@@ -67,15 +85,20 @@ impl Geometric for Translate {
         self.bounding_box
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
-        let local_origin = Point::from_vector3(self.world_to_local(origin.0));
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
+        let local_origin = Point::from_vector3(self.world_to_local_for_sampling(origin.0));
         // direction is invariant under translation
-        self.geometric.sample_direction_from(local_origin)
+        self.geometric.sample_direction_from(local_origin, u)
     }
 
     fn direction_pdf(&self, origin: Point, dir: Vector3) -> f64 {
-        let local_origin = Point::from_vector3(self.world_to_local(origin.0));
+        let local_origin = Point::from_vector3(self.world_to_local_for_sampling(origin.0));
         // direction is invariant under translation; PDF is invariant
         self.geometric.direction_pdf(local_origin, dir)
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        let (point, normal) = self.geometric.sample_point(u)?;
+        Some((point + self.translation.first(), normal))
+    }
 }
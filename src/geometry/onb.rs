@@ -42,4 +42,10 @@ impl Onb {
     pub fn to_world(&self, v: Vector3) -> Vector3 {
         v.x * self.u + v.y * self.v + v.z * self.w
     }
+
+    /// Transform a direction from world space to the ONB's local frame, the
+    /// inverse of [`Self::to_world`].
+    pub fn to_local(&self, v: Vector3) -> Vector3 {
+        Vector3::new(v.dot(self.u), v.dot(self.v), v.dot(self.w))
+    }
 }
@@ -171,11 +171,15 @@ impl Geometric for BilinearPatch {
         closest_hit
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
-        self.proxy_triangles_list.sample_direction_from(origin)
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
+        self.proxy_triangles_list.sample_direction_from(origin, u)
     }
 
     fn direction_pdf(&self, origin: Point, direction: Vector3) -> f64 {
         self.proxy_triangles_list.direction_pdf(origin, direction)
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        self.proxy_triangles_list.sample_point(u)
+    }
 }
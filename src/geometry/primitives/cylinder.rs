@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     geometry::{Aabb, Geometric, Onb, Point, Ray, RayHit, Vector3, primitives::Disk},
     shading::materials::Material,
+    tracing::ONE_MINUS_EPSILON,
     utils::{Interval, solve_quadratic},
 };
 
@@ -307,23 +308,23 @@ impl Geometric for Cylinder {
         area
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
         // infinite cylinders are excluded from the importance sampling system
         // (direction_pdf returns 0.0, so MIS gives the geometric strategy zero
         // weight). a direction must still be returned for the mixture PDF, but
         // computing an accurate sample is not worth the effort — the geometric
         // strategy contributes nothing regardless.
         if self.a_end == CylinderEnd::Infinite || self.b_end == CylinderEnd::Infinite {
-            return Vector3::random_unit();
+            return Vector3::uniform_sphere_direction_from(u);
         }
 
         let area = self.surface_area();
-        let r = rand::random::<f64>() * area;
+        let r = u.0 * area;
 
         // sample lateral surface
         if r < self.lateral_area {
-            let z = rand::random::<f64>() * self.height;
-            let phi = rand::random::<f64>() * TAU;
+            let z = r / self.lateral_area * self.height;
+            let phi = u.1 * TAU;
             let point_on_surface = self.a
                 + self.onb.u * (self.radius * phi.cos())
                 + self.onb.v * (self.radius * phi.sin())
@@ -331,19 +332,24 @@ impl Geometric for Cylinder {
             return origin.to(point_on_surface).unit_vector();
         }
 
-        // sample caps if they exist
+        // sample caps if they exist, rescaling the sample to cover the chosen cap
+        let mut r = r - self.lateral_area;
         if let Some(ref cap) = self.a_cap {
             let cap_area = cap.surface_area();
-            if r < self.lateral_area + cap_area {
-                return cap.sample_direction_from(origin);
+            if r < cap_area {
+                let u = ((r / cap_area).min(ONE_MINUS_EPSILON), u.1);
+                return cap.sample_direction_from(origin, u);
             }
+            r -= cap_area;
         }
         if let Some(ref cap) = self.b_cap {
-            return cap.sample_direction_from(origin);
+            let cap_area = cap.surface_area();
+            let u = ((r / cap_area).clamp(0.0, ONE_MINUS_EPSILON), u.1);
+            return cap.sample_direction_from(origin, u);
         }
 
         // fallback (should not reach here when area > 0)
-        Vector3::random_unit()
+        Vector3::uniform_sphere_direction_from(u)
     }
 
     fn direction_pdf(&self, origin: Point, dir: Vector3) -> f64 {
@@ -364,4 +370,32 @@ impl Geometric for Cylinder {
         let area = self.surface_area();
         (hit.t * hit.t) / (cos_theta * area)
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        if self.a_end == CylinderEnd::Infinite || self.b_end == CylinderEnd::Infinite {
+            return None;
+        }
+
+        // the same choice between the lateral surface and the caps as
+        // sample_direction_from
+        let r = u.0 * self.surface_area();
+        if r < self.lateral_area {
+            let z = r / self.lateral_area * self.height;
+            let phi = u.1 * TAU;
+            let radial = self.onb.u * phi.cos() + self.onb.v * phi.sin();
+            return Some((self.a + radial * self.radius + self.onb.w * z, radial));
+        }
+
+        let mut r = r - self.lateral_area;
+        if let Some(ref cap) = self.a_cap {
+            let cap_area = cap.surface_area();
+            if r < cap_area {
+                return cap.sample_point(((r / cap_area).min(ONE_MINUS_EPSILON), u.1));
+            }
+            r -= cap_area;
+        }
+        self.b_cap.as_ref().and_then(|cap| {
+            cap.sample_point(((r / cap.surface_area()).clamp(0.0, ONE_MINUS_EPSILON), u.1))
+        })
+    }
 }
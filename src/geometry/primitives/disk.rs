@@ -165,17 +165,17 @@ impl Geometric for Disk {
         self.bounding_box
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
         // uniform area sampling on a disk: sample r² uniformly in
         // [inner_radius², radius²] and φ uniformly in [0, 2π].
         // the sqrt correction (r = sqrt(r²)) ensures uniform area
         // distribution — without it, the center would get over-sampled.
         // when inner_radius == 0 this is equivalent to Malley's method
         // used by Vector3::random_cosine_weighted_direction.
-        let r_sq: f64 = rand::random::<f64>() * (self.radius.powi(2) - self.inner_radius.powi(2))
-            + self.inner_radius.powi(2);
+        let r_sq: f64 =
+            u.0 * (self.radius.powi(2) - self.inner_radius.powi(2)) + self.inner_radius.powi(2);
         let r = r_sq.sqrt();
-        let phi: f64 = rand::random::<f64>() * TAU;
+        let phi: f64 = u.1 * TAU;
         let p = self.center + r * phi.cos() * self.onb.u + r * phi.sin() * self.onb.v;
         origin.to(p).unit_vector()
     }
@@ -194,6 +194,16 @@ impl Geometric for Disk {
 
         (hit.t * hit.t) / (cos_theta * self.area)
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        // same area sampling as sample_direction_from
+        let r_sq: f64 =
+            u.0 * (self.radius.powi(2) - self.inner_radius.powi(2)) + self.inner_radius.powi(2);
+        let r = r_sq.sqrt();
+        let phi: f64 = u.1 * TAU;
+        let p = self.center + r * phi.cos() * self.onb.u + r * phi.sin() * self.onb.v;
+        Some((p, self.onb.w))
+    }
 }
 
 #[cfg(test)]
@@ -112,9 +112,8 @@ impl Geometric for Parallelogram {
         self.bounding_box
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
-        let alpha: f64 = rand::random();
-        let beta: f64 = rand::random();
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
+        let (alpha, beta) = u;
         let p = self.lower_left + alpha * self.u + beta * self.v;
 
         origin.to(p).unit_vector()
@@ -139,6 +138,11 @@ impl Geometric for Parallelogram {
 
         (hit.t * hit.t) / (cos_theta * area)
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        let (alpha, beta) = u;
+        Some((self.lower_left + alpha * self.u + beta * self.v, self.normal))
+    }
 }
 
 #[cfg(test)]
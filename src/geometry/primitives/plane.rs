@@ -108,7 +108,7 @@ impl Geometric for Plane {
         self.point
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
         // determine which side of the plane the origin is on
         let dir_to_point = self.point.0 - origin.0;
         let side = dir_to_point.dot(self.normal);
@@ -118,8 +118,7 @@ impl Geometric for Plane {
         let w = self.normal * -sign;
 
         // cosine-weighted hemisphere sampling
-        let r1: f64 = rand::random();
-        let r2: f64 = rand::random();
+        let (r1, r2) = u;
         let phi = 2.0 * std::f64::consts::PI * r1;
         let cos_theta = r2.sqrt();
         let sin_theta = (1.0 - r2).sqrt();
@@ -135,4 +134,8 @@ impl Geometric for Plane {
         // infinite surface area - zero probability density for any finite solid angle
         0.0
     }
+
+    fn sample_point(&self, _u: (f64, f64)) -> Option<(Point, Vector3)> {
+        None
+    }
 }
@@ -62,12 +62,10 @@ impl Sphere {
         }
     }
 
-    /// Generate a random direction within the cone that subtends the sphere
-    /// from a point at `distance_squared` away. The result is in the local
-    /// frame where +Z points toward the sphere center.
-    fn random_to_sphere(radius: f64, distance_squared: f64) -> Vector3 {
-        let r1: f64 = rand::random();
-        let r2: f64 = rand::random();
+    /// Map the uniform sample `(r1, r2)` to a direction within the cone that
+    /// subtends the sphere from a point at `distance_squared` away. The result
+    /// is in the local frame where +Z points toward the sphere center.
+    fn direction_to_sphere(radius: f64, distance_squared: f64, (r1, r2): (f64, f64)) -> Vector3 {
         let cos_theta_max = (1.0 - radius * radius / distance_squared).sqrt();
         let z = 1.0 + r2 * (cos_theta_max - 1.0);
         let phi = 2.0 * PI * r1;
@@ -171,7 +169,7 @@ impl Geometric for Sphere {
         self.bounding_box
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
         // sample the sphere as a cone from origin:
         // build an ONB with w pointing toward the sphere center,
         // then sample a direction uniformly within the cone subtended
@@ -182,11 +180,11 @@ impl Geometric for Sphere {
 
         // degenerate: origin inside the sphere — sample the full sphere
         if distance_squared <= self.radius * self.radius {
-            return Vector3::random_unit();
+            return Vector3::uniform_sphere_direction_from(u);
         }
 
         let onb = Onb::from_w(to_center.unit_vector());
-        let local_dir = Self::random_to_sphere(self.radius, distance_squared);
+        let local_dir = Self::direction_to_sphere(self.radius, distance_squared, u);
         onb.to_world(local_dir)
     }
 
@@ -216,4 +214,9 @@ impl Geometric for Sphere {
         let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
         1.0 / solid_angle
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        let normal = Vector3::uniform_sphere_direction_from(u);
+        Some((self.center_1 + normal * self.radius, normal))
+    }
 }
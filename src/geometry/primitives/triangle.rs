@@ -165,11 +165,10 @@ impl Geometric for Triangle {
         self.bounding_box
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
         // uniform sampling on a triangle using barycentric coordinates
         // with sqrt to correct for area concentration
-        let r1: f64 = rand::random();
-        let r2: f64 = rand::random();
+        let (r1, r2) = u;
         let sqrt_r1 = r1.sqrt();
         let beta = sqrt_r1 * (1.0 - r2);
         let gamma = sqrt_r1 * r2;
@@ -198,4 +197,15 @@ impl Geometric for Triangle {
 
         (hit.t * hit.t) / (cos_theta * self.area)
     }
+
+    fn sample_point(&self, u: (f64, f64)) -> Option<(Point, Vector3)> {
+        // same barycentric sampling as sample_direction_from
+        let (r1, r2) = u;
+        let sqrt_r1 = r1.sqrt();
+        let beta = sqrt_r1 * (1.0 - r2);
+        let gamma = sqrt_r1 * r2;
+
+        let p = self.a + self.a.to(self.b) * beta + self.a.to(self.c) * gamma;
+        Some((p, self.normal_at(1.0 - beta - gamma, beta, gamma)))
+    }
 }
@@ -43,4 +43,50 @@ impl Quaternion {
     pub fn from_array([w, x, y, z]: [f64; 4]) -> Self {
         Self::new(w, x, y, z)
     }
+
+    pub fn dot(&self, other: Self) -> f64 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    /// The angle of the rotation that takes this orientation to `other`.
+    pub fn angle_to(&self, other: Self) -> f64 {
+        2.0 * self.dot(other).abs().min(1.0).acos()
+    }
+
+    /// Spherical linear interpolation: rotates at a constant rate along the
+    /// shortest path from this orientation to `other`.
+    pub fn slerp(&self, other: Self, t: f64) -> Self {
+        // q and -q are the same rotation; pick the nearer one
+        let (other, cos_theta) = match self.dot(other) {
+            d if d < 0.0 => (
+                Self {
+                    w: -other.w,
+                    x: -other.x,
+                    y: -other.y,
+                    z: -other.z,
+                },
+                -d,
+            ),
+            d => (other, d),
+        };
+
+        let (a, b) = if cos_theta > 0.9995 {
+            // nearly parallel, where lerping is accurate and sin(theta) isn't
+            (1.0 - t, t)
+        } else {
+            let theta = cos_theta.acos();
+            let sin_theta = theta.sin();
+            (
+                ((1.0 - t) * theta).sin() / sin_theta,
+                (t * theta).sin() / sin_theta,
+            )
+        };
+
+        Self::new(
+            a * self.w + b * other.w,
+            a * self.x + b * other.x,
+            a * self.y + b * other.y,
+            a * self.z + b * other.z,
+        )
+    }
 }
@@ -5,9 +5,8 @@ use std::{
 
 use auto_ops::{impl_op_ex, impl_op_ex_commutative};
 use bincode::{Decode, Encode};
-use rand::RngExt;
 
-use crate::utils::Angle;
+use crate::utils::{Angle, random_f64, random_range};
 
 #[derive(Debug, Copy, Clone, PartialEq, Default, Encode, Decode)]
 pub struct Vector3 {
@@ -70,11 +69,10 @@ impl Vector3 {
     }
 
     pub fn random_range(min: f64, max: f64) -> Self {
-        let mut rng = rand::rng();
         Self {
-            x: rng.random_range(min..max),
-            y: rng.random_range(min..max),
-            z: rng.random_range(min..max),
+            x: random_range(min, max),
+            y: random_range(min, max),
+            z: random_range(min, max),
         }
     }
 
@@ -88,20 +86,14 @@ impl Vector3 {
     }
 
     pub fn random_in_unit_cube() -> Self {
-        let mut rng = rand::rng();
-        Self {
-            x: rng.random_range(-1.0..1.0),
-            y: rng.random_range(-1.0..1.0),
-            z: rng.random_range(-1.0..1.0),
-        }
+        Self::random_range(-1.0, 1.0)
     }
 
     pub fn random_in_unit_disk() -> Self {
-        let mut rng = rand::rng();
         loop {
             let p = Self {
-                x: rng.random_range(-1.0..1.0),
-                y: rng.random_range(-1.0..1.0),
+                x: random_range(-1.0, 1.0),
+                y: random_range(-1.0, 1.0),
                 z: 0.0,
             };
             if p.squared_length() < 1.0 {
@@ -131,8 +123,12 @@ impl Vector3 {
     /// implemented via malley's method: uniformly sample the unit disk and
     /// project onto the hemisphere.
     pub fn random_cosine_weighted_direction() -> Self {
-        let r1 = rand::random::<f64>();
-        let r2 = rand::random::<f64>();
+        Self::cosine_weighted_direction_from((random_f64(), random_f64()))
+    }
+
+    /// the direction `random_cosine_weighted_direction` generates for the
+    /// uniform sample `(r1, r2)` in [0, 1)².
+    pub fn cosine_weighted_direction_from((r1, r2): (f64, f64)) -> Self {
         let phi = 2.0 * PI * r1;
         let sqrt_r2 = r2.sqrt();
         Self {
@@ -142,6 +138,42 @@ impl Vector3 {
         }
     }
 
+    /// maps the uniform sample `(r1, r2)` in [0, 1)² to a unit vector,
+    /// uniformly distributed over the sphere.
+    pub fn uniform_sphere_direction_from((r1, r2): (f64, f64)) -> Self {
+        let z = 1.0 - 2.0 * r1;
+        let radius = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * r2;
+        Self {
+            x: radius * phi.cos(),
+            y: radius * phi.sin(),
+            z,
+        }
+    }
+
+    /// maps the uniform sample `(r1, r2)` in [0, 1)² to a point uniformly
+    /// distributed over the unit disk in the XY plane.
+    ///
+    /// uses shirley's concentric mapping, which keeps nearby samples nearby,
+    /// so stratified samples stay stratified on the disk.
+    pub fn concentric_disk_from((r1, r2): (f64, f64)) -> Self {
+        let (a, b) = (2.0 * r1 - 1.0, 2.0 * r2 - 1.0);
+        if a == 0.0 && b == 0.0 {
+            return Self::ZERO;
+        }
+
+        let (radius, theta) = if a.abs() > b.abs() {
+            (a, PI / 4.0 * (b / a))
+        } else {
+            (b, PI / 2.0 - PI / 4.0 * (a / b))
+        };
+        Self {
+            x: radius * theta.cos(),
+            y: radius * theta.sin(),
+            z: 0.0,
+        }
+    }
+
     pub fn length(&self) -> f64 {
         self.squared_length().sqrt()
     }
@@ -1,7 +1,5 @@
 use std::sync::Arc;
 
-use rand::RngExt;
-
 use crate::{
     geometry::{Aabb, Geometric, Point, Ray, RayHit, Vector3},
     shading::{
@@ -9,7 +7,7 @@ use crate::{
         materials::{Isotropic, Material},
         textures::SolidColor,
     },
-    utils::Interval,
+    utils::{Interval, random_f64},
 };
 
 #[derive(Clone, Debug)]
@@ -66,7 +64,7 @@ impl Geometric for Constant {
 
         let ray_length = ray.direction.length();
         let distance_inside_boundary = (second_hit.t - first_hit.t) * ray_length;
-        let hit_distance: f64 = self.negative_inverse_density * rand::rng().random::<f64>().ln();
+        let hit_distance: f64 = self.negative_inverse_density * random_f64().ln();
 
         // check if the ray made it through the volume
         if hit_distance > distance_inside_boundary {
@@ -108,11 +106,16 @@ impl Geometric for Constant {
         self.geometric.bounding_box()
     }
 
-    fn sample_direction_from(&self, origin: Point) -> Vector3 {
-        self.geometric.sample_direction_from(origin)
+    fn sample_direction_from(&self, origin: Point, u: (f64, f64)) -> Vector3 {
+        self.geometric.sample_direction_from(origin, u)
     }
 
     fn direction_pdf(&self, origin: Point, dir: Vector3) -> f64 {
         self.geometric.direction_pdf(origin, dir)
     }
+
+    fn sample_point(&self, _u: (f64, f64)) -> Option<(Point, Vector3)> {
+        // a volume has no surface to emit from
+        None
+    }
 }
@@ -70,6 +70,9 @@ pub use not_found::*;
 mod pause_render;
 pub use pause_render::*;
 
+mod refine_render;
+pub use refine_render::*;
+
 mod render_stream_multi;
 pub use render_stream_multi::*;
 
@@ -79,6 +79,7 @@ pub async fn create_resource(
     let resource_type: ResourceType = match resource_type {
         Some(rt) if !rt.is_empty() => match rt.as_str() {
             "texture_image" => ResourceType::TextureImage,
+            "ies_profile" => ResourceType::IesProfile,
             _ => {
                 return (
                     StatusCode::BAD_REQUEST,
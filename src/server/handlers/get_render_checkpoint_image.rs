@@ -2,29 +2,42 @@ use std::{io::Cursor, sync::Arc};
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{StatusCode, header},
     response::{IntoResponse, Response},
 };
 use axum_extra::{
     TypedHeader,
     headers::{ContentLength, ContentType},
 };
-use image::ImageOutputFormat;
+use image::{ImageOutputFormat, RgbaImage};
+use serde::Deserialize;
 
 use crate::{
     server::Claims,
-    tracing::{RenderID, RenderManager, UserID},
+    shading::ToneMappingOverride,
+    tracing::{Aov, CheckpointImageFormat, HdrImage, RenderID, RenderManager, UserID},
 };
 
 use crate::server::{RequestedUserID, resolve_effective_user_id};
 
 use crate::server::LuxideState;
 
+/// Parses `?format=png|exr|exr_half|hdr&denoise=true` from the URL query string.
+#[derive(Deserialize)]
+pub struct CheckpointImageParams {
+    pub format: Option<CheckpointImageFormat>,
+    /// Denoise the image before returning it. The stored checkpoint is untouched.
+    #[serde(default)]
+    pub denoise: bool,
+}
+
 pub async fn get_earliest_render_checkpoint_image(
     State(state): State<LuxideState>,
     claims: Claims,
     Path(id): Path<RenderID>,
     Query(requested_user_id): Query<RequestedUserID>,
+    Query(image_params): Query<CheckpointImageParams>,
+    Query(tone_mapping): Query<ToneMappingOverride>,
 ) -> Response {
     println!(
         "Handing request for get_earliest_render_checkpoint_image (id: {})...",
@@ -50,6 +63,9 @@ pub async fn get_earliest_render_checkpoint_image(
                 id,
                 iteration,
                 effective_user_id,
+                image_params.format.unwrap_or_default(),
+                tone_mapping,
+                image_params.denoise,
             )
             .await
         }
@@ -63,6 +79,8 @@ pub async fn get_latest_render_checkpoint_image(
     claims: Claims,
     Path(id): Path<RenderID>,
     Query(requested_user_id): Query<RequestedUserID>,
+    Query(image_params): Query<CheckpointImageParams>,
+    Query(tone_mapping): Query<ToneMappingOverride>,
 ) -> Response {
     println!(
         "Handing request for get_latest_render_checkpoint_image (id: {})...",
@@ -88,6 +106,54 @@ pub async fn get_latest_render_checkpoint_image(
                 id,
                 iteration,
                 effective_user_id,
+                image_params.format.unwrap_or_default(),
+                tone_mapping,
+                image_params.denoise,
+            )
+            .await
+        }
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => e.into(),
+    }
+}
+
+/// The latest checkpoint of one frame of an animation, i.e. one image of its
+/// image sequence.
+pub async fn get_render_frame_image(
+    State(state): State<LuxideState>,
+    claims: Claims,
+    Path((id, frame)): Path<(RenderID, u32)>,
+    Query(requested_user_id): Query<RequestedUserID>,
+    Query(image_params): Query<CheckpointImageParams>,
+    Query(tone_mapping): Query<ToneMappingOverride>,
+) -> Response {
+    println!(
+        "Handing request for get_render_frame_image (id: {}, frame: {})...",
+        id, frame
+    );
+
+    let effective_user_id =
+        match resolve_effective_user_id(&state.auth_manager, &claims, requested_user_id.user_id)
+            .await
+        {
+            Ok(id) => id,
+            Err((status, message)) => return (status, message).into_response(),
+        };
+
+    match state
+        .render_manager
+        .get_latest_render_frame_checkpoint_iteration(id, frame, effective_user_id)
+        .await
+    {
+        Ok(Some(iteration)) => {
+            get_render_checkpoint_image_response(
+                state.render_manager,
+                id,
+                iteration,
+                effective_user_id,
+                image_params.format.unwrap_or_default(),
+                tone_mapping,
+                image_params.denoise,
             )
             .await
         }
@@ -101,6 +167,8 @@ pub async fn get_render_checkpoint_image(
     claims: Claims,
     Path((id, checkpoint_iteration)): Path<(RenderID, u32)>,
     Query(requested_user_id): Query<RequestedUserID>,
+    Query(image_params): Query<CheckpointImageParams>,
+    Query(tone_mapping): Query<ToneMappingOverride>,
 ) -> Response {
     println!(
         "Handing request for get_render_checkpoint (id: {}, iteration: {})...",
@@ -120,6 +188,87 @@ pub async fn get_render_checkpoint_image(
         id,
         checkpoint_iteration,
         effective_user_id,
+        image_params.format.unwrap_or_default(),
+        tone_mapping,
+        image_params.denoise,
+    )
+    .await
+}
+
+/// One AOV of the latest checkpoint. 404s if the render doesn't store AOVs.
+pub async fn get_latest_render_checkpoint_aov_image(
+    State(state): State<LuxideState>,
+    claims: Claims,
+    Path((id, aov)): Path<(RenderID, Aov)>,
+    Query(requested_user_id): Query<RequestedUserID>,
+    Query(image_params): Query<CheckpointImageParams>,
+    Query(tone_mapping): Query<ToneMappingOverride>,
+) -> Response {
+    println!(
+        "Handing request for get_latest_render_checkpoint_aov_image (id: {}, aov: {:?})...",
+        id, aov
+    );
+
+    let effective_user_id =
+        match resolve_effective_user_id(&state.auth_manager, &claims, requested_user_id.user_id)
+            .await
+        {
+            Ok(id) => id,
+            Err((status, message)) => return (status, message).into_response(),
+        };
+
+    match state
+        .render_manager
+        .get_latest_render_checkpoint_iteration(id, effective_user_id)
+        .await
+    {
+        Ok(Some(iteration)) => {
+            get_render_checkpoint_aov_image_response(
+                state.render_manager,
+                id,
+                iteration,
+                aov,
+                effective_user_id,
+                image_params.format.unwrap_or_default(),
+                tone_mapping,
+            )
+            .await
+        }
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => e.into(),
+    }
+}
+
+/// One AOV of a checkpoint. 404s if the render doesn't store AOVs.
+pub async fn get_render_checkpoint_aov_image(
+    State(state): State<LuxideState>,
+    claims: Claims,
+    Path((id, checkpoint_iteration, aov)): Path<(RenderID, u32, Aov)>,
+    Query(requested_user_id): Query<RequestedUserID>,
+    Query(image_params): Query<CheckpointImageParams>,
+    Query(tone_mapping): Query<ToneMappingOverride>,
+) -> Response {
+    println!(
+        "Handing request for get_render_checkpoint_aov_image (id: {}, iteration: {}, aov: {:?})...",
+        id, checkpoint_iteration, aov
+    );
+
+    let effective_user_id =
+        match resolve_effective_user_id(&state.auth_manager, &claims, requested_user_id.user_id)
+            .await
+        {
+            Ok(id) => id,
+            Err((status, message)) => return (status, message).into_response(),
+        };
+
+    get_render_checkpoint_aov_image_response(
+        state.render_manager,
+        id,
+        checkpoint_iteration,
+        aov,
+        effective_user_id,
+        image_params.format.unwrap_or_default(),
+        tone_mapping,
     )
     .await
 }
@@ -129,31 +278,116 @@ async fn get_render_checkpoint_image_response(
     id: RenderID,
     checkpoint_iteration: u32,
     user_id: UserID,
+    format: CheckpointImageFormat,
+    tone_mapping: ToneMappingOverride,
+    denoise: bool,
 ) -> Response {
+    if format.is_hdr() {
+        return get_render_checkpoint_hdr_image_response(
+            render_manager,
+            id,
+            checkpoint_iteration,
+            user_id,
+            format,
+            denoise,
+        )
+        .await;
+    }
+
     match render_manager
-        .get_render_checkpoint_as_image(id, checkpoint_iteration, user_id)
+        .get_render_checkpoint_as_image(id, checkpoint_iteration, user_id, tone_mapping, denoise)
         .await
     {
-        Ok(Some(image)) => {
-            // write image to intermediate buffer
-            let mut img_buffer = Vec::new();
-
-            if let Err(err) =
-                image.write_to(&mut Cursor::new(&mut img_buffer), ImageOutputFormat::Png)
-            {
-                println!("Failed to write image to buffer: {err}");
-                return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
-            }
-
-            (
-                StatusCode::OK,
-                TypedHeader(ContentType::png()),
-                TypedHeader(ContentLength(img_buffer.len() as u64)),
-                img_buffer,
-            )
-                .into_response()
-        }
+        Ok(Some(image)) => png_image_response(image),
         Ok(None) => StatusCode::NOT_FOUND.into_response(),
         Err(e) => e.into(),
     }
 }
+
+async fn get_render_checkpoint_hdr_image_response(
+    render_manager: Arc<RenderManager>,
+    id: RenderID,
+    checkpoint_iteration: u32,
+    user_id: UserID,
+    format: CheckpointImageFormat,
+    denoise: bool,
+) -> Response {
+    match render_manager
+        .get_render_checkpoint_as_hdr_image(id, checkpoint_iteration, user_id, denoise)
+        .await
+    {
+        Ok(Some(image)) => hdr_image_response(image, format).await,
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => e.into(),
+    }
+}
+
+async fn get_render_checkpoint_aov_image_response(
+    render_manager: Arc<RenderManager>,
+    id: RenderID,
+    checkpoint_iteration: u32,
+    aov: Aov,
+    user_id: UserID,
+    format: CheckpointImageFormat,
+    tone_mapping: ToneMappingOverride,
+) -> Response {
+    if format.is_hdr() {
+        return match render_manager
+            .get_render_checkpoint_aov_as_hdr_image(id, checkpoint_iteration, aov, user_id)
+            .await
+        {
+            Ok(Some(image)) => hdr_image_response(image, format).await,
+            Ok(None) => StatusCode::NOT_FOUND.into_response(),
+            Err(e) => e.into(),
+        };
+    }
+
+    match render_manager
+        .get_render_checkpoint_aov_as_image(id, checkpoint_iteration, aov, user_id, tone_mapping)
+        .await
+    {
+        Ok(Some(image)) => png_image_response(image),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => e.into(),
+    }
+}
+
+fn png_image_response(image: RgbaImage) -> Response {
+    // write image to intermediate buffer
+    let mut img_buffer = Vec::new();
+
+    if let Err(err) = image.write_to(&mut Cursor::new(&mut img_buffer), ImageOutputFormat::Png) {
+        println!("Failed to write image to buffer: {err}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+    }
+
+    (
+        StatusCode::OK,
+        TypedHeader(ContentType::png()),
+        TypedHeader(ContentLength(img_buffer.len() as u64)),
+        img_buffer,
+    )
+        .into_response()
+}
+
+async fn hdr_image_response(image: HdrImage, format: CheckpointImageFormat) -> Response {
+    // encoding a full-resolution float image is CPU-heavy
+    let encoded = tokio::task::spawn_blocking(move || image.encode(format)).await;
+
+    match encoded {
+        Ok(Ok(img_buffer)) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, format.mime_type())],
+            img_buffer,
+        )
+            .into_response(),
+        Ok(Err(err)) => {
+            println!("Failed to encode HDR image: {err}");
+            (StatusCode::INTERNAL_SERVER_ERROR, err).into_response()
+        }
+        Err(err) => {
+            println!("HDR image encoding task failed: {err}");
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+    }
+}
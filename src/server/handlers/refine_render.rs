@@ -0,0 +1,50 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    server::{Claims, LuxideState, RequestedUserID, resolve_effective_user_id},
+    tracing::{Region, RenderID},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct RefineRender {
+    region: Region,
+    samples_per_pixel: Option<u32>,
+}
+
+pub async fn refine_render(
+    State(state): State<LuxideState>,
+    claims: Claims,
+    Path(id): Path<RenderID>,
+    Query(requested_user_id): Query<RequestedUserID>,
+    Json(refine_render): Json<RefineRender>,
+) -> Response {
+    println!("Handing request for refine_render (id: {})...", id);
+
+    let effective_user_id =
+        match resolve_effective_user_id(&state.auth_manager, &claims, requested_user_id.user_id)
+            .await
+        {
+            Ok(id) => id,
+            Err((status, message)) => return (status, message).into_response(),
+        };
+
+    match state
+        .render_manager
+        .refine_render(
+            id,
+            refine_render.region,
+            refine_render.samples_per_pixel,
+            effective_user_id,
+        )
+        .await
+    {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => e.into(),
+    }
+}
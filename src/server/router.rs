@@ -144,6 +144,15 @@ fn build_renders_router() -> Router<LuxideState> {
             "/{id}/checkpoint/{checkpoint_iteration}",
             get(handlers::get_render_checkpoint_image),
         )
+        .route(
+            "/{id}/checkpoint/latest/aov/{aov}",
+            get(handlers::get_latest_render_checkpoint_aov_image),
+        )
+        .route(
+            "/{id}/checkpoint/{checkpoint_iteration}/aov/{aov}",
+            get(handlers::get_render_checkpoint_aov_image),
+        )
+        .route("/{id}/frame/{frame}", get(handlers::get_render_frame_image))
         .route("/{id}/pause", post(handlers::pause_render))
         .route("/{id}/resume", post(handlers::resume_render))
         .route(
@@ -151,6 +160,7 @@ fn build_renders_router() -> Router<LuxideState> {
             put(handlers::update_render_total_checkpoints),
         )
         .route("/{id}/name", put(handlers::update_render_name))
+        .route("/{id}/refine", post(handlers::refine_render))
 }
 
 fn build_auth_router() -> Router<LuxideState> {
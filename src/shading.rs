@@ -4,9 +4,15 @@ pub use color_rgb::ColorRgb;
 pub mod color_spectrum;
 pub use color_spectrum::ColorSpectrum;
 
+mod environment;
+pub use environment::*;
+
 pub mod hero_wavelengths;
 pub use hero_wavelengths::HeroWavelengths;
 
+pub mod lights;
+pub use lights::Light;
+
 pub mod materials;
 pub mod pdf;
 pub mod textures;
@@ -14,3 +20,12 @@ pub use textures::Texture;
 
 pub mod medium;
 pub use medium::Medium;
+
+mod microfacet;
+pub use microfacet::*;
+
+mod sky;
+pub use sky::*;
+
+mod tone_mapping;
+pub use tone_mapping::*;
@@ -68,6 +68,11 @@ impl ColorRgb {
         ])
     }
 
+    /// Relative luminance, using the Rec. 709 primaries.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.0.x + 0.7152 * self.0.y + 0.0722 * self.0.z
+    }
+
     /// Returns the largest RGB component value.
     /// Used for Russian roulette survival probability heuristics.
     pub fn max_component(self) -> f64 {
@@ -98,6 +98,32 @@ impl<const N: usize> ColorSpectrum<N> {
         data
     }
 
+    /// Resample a spectrum tabulated at ascending `wavelengths_nm` onto the
+    /// spectral samples, interpolating linearly and holding the end values
+    /// beyond the table.
+    pub fn from_tabulated(wavelengths_nm: &[f64], values: &[f64]) -> Self {
+        let mut samples = Vector::ZERO;
+        let step = (LAMBDA_MAX - LAMBDA_MIN) / (N - 1) as f64;
+        for (i, sample) in samples.iter_mut().enumerate() {
+            let lambda = LAMBDA_MIN + i as f64 * step;
+            let next = wavelengths_nm
+                .partition_point(|&w| w < lambda)
+                .min(wavelengths_nm.len() - 1);
+            *sample = if next == 0 {
+                values[0]
+            } else {
+                let (w0, w1) = (wavelengths_nm[next - 1], wavelengths_nm[next]);
+                let t = if w1 > w0 {
+                    ((lambda - w0) / (w1 - w0)).clamp(0.0, 1.0)
+                } else {
+                    1.0
+                };
+                values[next - 1] * (1.0 - t) + values[next] * t
+            };
+        }
+        Self(samples)
+    }
+
     /// Returns `true` if all spectral samples are zero.
     pub fn is_black(&self) -> bool {
         self.0.iter().all(|&s| s == 0.0)
@@ -108,6 +134,11 @@ impl<const N: usize> ColorSpectrum<N> {
         self.0.iter().cloned().fold(f64::NEG_INFINITY, f64::max)
     }
 
+    /// Mean value across all spectral samples.
+    pub fn average(&self) -> f64 {
+        self.0.iter().sum::<f64>() / N as f64
+    }
+
     /// Scale all samples proportionally so the maximum equals `limit`.
     /// If the maximum is already at or below `limit`, returns `self` unchanged.
     pub fn scale_down(&self, limit: f64) -> Self {
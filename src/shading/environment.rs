@@ -0,0 +1,329 @@
+use std::{
+    f64::consts::{PI, TAU},
+    sync::Arc,
+};
+
+use crate::{
+    geometry::{Vector, Vector3},
+    shading::{ColorRgb, ColorSpectrum, HeroWavelengths, Sky, textures::ImageLinearF64},
+    utils::Distribution2D,
+};
+
+/// Rows a gradient's sampling distribution is tabulated in.
+const GRADIENT_ROWS: usize = 64;
+
+/// Columns and rows a sky's sampling distribution is tabulated in.
+const SKY_RESOLUTION: (usize, usize) = (128, 64);
+
+/// Where an environment's light comes from.
+#[derive(Debug, Clone)]
+pub enum EnvironmentSource {
+    /// An equirectangular image, longitude across and latitude down, with its
+    /// center facing -z and +x a quarter of the way to its right.
+    Map(Arc<ImageLinearF64>),
+    /// Blends from `horizon` up to `zenith` above the horizon, and down to
+    /// `ground` below it.
+    Gradient {
+        zenith: ColorRgb,
+        horizon: ColorRgb,
+        ground: ColorRgb,
+    },
+    /// A physical daylight sky with the sun in it.
+    Sky(Box<Sky>),
+}
+
+/// Light arriving from infinitely far away, seen by rays that escape the
+/// scene, with up along +y.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    source: EnvironmentSource,
+    /// how far the source is turned about the vertical axis, in radians,
+    /// counterclockwise seen from above
+    rotation: f64,
+    intensity: f64,
+    /// proportional to the luminance on the longitude-latitude square, per
+    /// unit of solid angle, leaving out the sun
+    distribution: Distribution2D,
+    /// how often `sample_direction` aims at the sun's disk rather than
+    /// following the distribution
+    sun_probability: f64,
+}
+
+impl Environment {
+    pub fn new(source: EnvironmentSource, rotation_degrees: f64, intensity: f64) -> Self {
+        let (width, height) = match &source {
+            EnvironmentSource::Map(image) => (image.width as usize, image.height as usize),
+            EnvironmentSource::Gradient { .. } => (1, GRADIENT_ROWS),
+            EnvironmentSource::Sky(_) => SKY_RESOLUTION,
+        };
+        let mut luminance = Vec::with_capacity(width * height);
+        for row in 0..height {
+            let v = (row as f64 + 0.5) / height as f64;
+            // rows near the poles cover less of the sphere
+            let sin_theta = (v * PI).sin();
+            for column in 0..width {
+                let u = (column as f64 + 0.5) / width as f64;
+                let direction = square_direction((u, v));
+                luminance.push(source_luminance(&source, direction) * sin_theta);
+            }
+        }
+        let distribution = Distribution2D::new(&luminance, width, height);
+
+        // the sun is far too small for the distribution to find, so it's
+        // sampled on its own in proportion to its share of the light
+        let sun_probability = match &source {
+            EnvironmentSource::Sky(sky) => {
+                let sky_power = distribution.integral() * 2.0 * PI * PI;
+                let sun_power = sky.sun_power();
+                if sun_power > 0.0 {
+                    sun_power / (sun_power + sky_power)
+                } else {
+                    0.0
+                }
+            }
+            EnvironmentSource::Map(_) | EnvironmentSource::Gradient { .. } => 0.0,
+        };
+
+        Self {
+            source,
+            rotation: rotation_degrees.to_radians(),
+            intensity,
+            distribution,
+            sun_probability,
+        }
+    }
+
+    /// The radiance arriving from the environment along `-direction`.
+    pub fn radiance<const N: usize>(
+        &self,
+        direction: Vector3,
+        hero_wavelengths: &HeroWavelengths<N>,
+    ) -> Vector<N> {
+        let direction = rotated(direction.unit_vector(), -self.rotation);
+        let spectrum = match &self.source {
+            EnvironmentSource::Sky(sky) => sky.radiance(direction),
+            source => ColorSpectrum::from(source_color(source, direction)),
+        };
+        (spectrum * self.intensity).sample(hero_wavelengths)
+    }
+
+    /// A unit direction toward the environment, drawn roughly in proportion
+    /// to its brightness.
+    pub fn sample_direction(&self, (u0, u1): (f64, f64)) -> Vector3 {
+        if let EnvironmentSource::Sky(sky) = &self.source
+            && u0 < self.sun_probability
+        {
+            let direction = sky.sample_sun((u0 / self.sun_probability, u1));
+            return rotated(direction, self.rotation);
+        }
+        let u0 = (u0 - self.sun_probability) / (1.0 - self.sun_probability);
+        let (uv, _) = self.distribution.sample((u0, u1));
+        rotated(square_direction(uv), self.rotation)
+    }
+
+    /// The solid-angle density of `sample_direction` drawing `direction`.
+    pub fn direction_pdf(&self, direction: Vector3) -> f64 {
+        let direction = rotated(direction.unit_vector(), -self.rotation);
+        let sun_pdf = match &self.source {
+            EnvironmentSource::Sky(sky) => self.sun_probability * sky.sun_pdf(direction),
+            EnvironmentSource::Map(_) | EnvironmentSource::Gradient { .. } => 0.0,
+        };
+        let sin_theta = (1.0 - direction.y * direction.y).max(0.0).sqrt();
+        if sin_theta <= 0.0 {
+            return sun_pdf;
+        }
+        // the square maps onto the sphere with 2π²·sinθ steradians per unit area
+        let square_pdf =
+            self.distribution.density(square_point(direction)) / (2.0 * PI * PI * sin_theta);
+        sun_pdf + (1.0 - self.sun_probability) * square_pdf
+    }
+}
+
+/// Where the unit `direction` lands on the longitude-latitude square, with
+/// `v` running down from the zenith.
+fn square_point(direction: Vector3) -> (f64, f64) {
+    let phi = direction.x.atan2(-direction.z);
+    let u = (phi / TAU + 0.5).rem_euclid(1.0);
+    let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+    (u, v)
+}
+
+/// The inverse of [`square_point`].
+fn square_direction((u, v): (f64, f64)) -> Vector3 {
+    let phi = (u - 0.5) * TAU;
+    let theta = v * PI;
+    Vector3::new(
+        theta.sin() * phi.sin(),
+        theta.cos(),
+        -theta.sin() * phi.cos(),
+    )
+}
+
+/// `direction` turned about the vertical axis by `angle` radians,
+/// counterclockwise seen from above.
+fn rotated(direction: Vector3, angle: f64) -> Vector3 {
+    let (sin, cos) = angle.sin_cos();
+    Vector3::new(
+        direction.x * cos - direction.z * sin,
+        direction.y,
+        direction.z * cos + direction.x * sin,
+    )
+}
+
+/// The luminance of `source` along the unit `direction`, before it's
+/// rotated, leaving out the sun.
+fn source_luminance(source: &EnvironmentSource, direction: Vector3) -> f64 {
+    match source {
+        EnvironmentSource::Sky(sky) => sky.sky_luminance(direction),
+        source => source_color(source, direction).luminance(),
+    }
+}
+
+/// The color of an RGB `source` along the unit `direction`, before it's
+/// rotated. The sky has no RGB color, and is black here.
+fn source_color(source: &EnvironmentSource, direction: Vector3) -> ColorRgb {
+    match source {
+        EnvironmentSource::Map(image) => {
+            let (u, v) = square_point(direction);
+            image.pixel_at(u, 1.0 - v).into()
+        }
+        EnvironmentSource::Gradient {
+            zenith,
+            horizon,
+            ground,
+        } => {
+            let t = direction.y;
+            if t >= 0.0 {
+                *horizon * (1.0 - t) + *zenith * t
+            } else {
+                *horizon * (1.0 + t) + *ground * -t
+            }
+        }
+        EnvironmentSource::Sky(_) => ColorRgb::BLACK,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        geometry::Vector3,
+        utils::{random_f64, seed_thread_rng},
+    };
+
+    use super::*;
+
+    #[test]
+    fn sampled_directions_estimate_the_same_light_as_uniform_ones() {
+        // a dim map with a bright spot, turned so the spot isn't at the center
+        let (width, height) = (32, 16);
+        let mut data = vec![[0.1, 0.1, 0.1]; width * height];
+        data[5 * width + 20] = [50.0, 40.0, 30.0];
+        let image = ImageLinearF64 {
+            width: width as u32,
+            height: height as u32,
+            data,
+        };
+        let environment = Environment::new(EnvironmentSource::Map(Arc::new(image)), 30.0, 2.0);
+        let hw = HeroWavelengths::<1>::new([550.0]);
+
+        seed_thread_rng(17);
+        let n = 200_000;
+        let (mut uniform, mut sampled, mut density) = (0.0, 0.0, 0.0);
+        for _ in 0..n {
+            let direction = Vector3::uniform_sphere_direction_from((random_f64(), random_f64()));
+            uniform += environment.radiance(direction, &hw)[0] * 4.0 * PI;
+            density += environment.direction_pdf(direction) * 4.0 * PI;
+
+            let direction = environment.sample_direction((random_f64(), random_f64()));
+            let pdf = environment.direction_pdf(direction);
+            assert!(pdf > 0.0);
+            sampled += environment.radiance(direction, &hw)[0] / pdf;
+        }
+        let (uniform, sampled, density) =
+            (uniform / n as f64, sampled / n as f64, density / n as f64);
+
+        // the density integrates to 1 over the sphere
+        assert!((density - 1.0).abs() < 0.02, "{density}");
+        assert!(
+            (sampled - uniform).abs() < 0.03 * uniform,
+            "{sampled} {uniform}"
+        );
+    }
+
+    #[test]
+    fn sky_sampling_finds_the_sun_and_the_sky() {
+        let sky = Sky::new(35.0, 20.0, 3.0);
+        let rotation = 40.0;
+        let environment =
+            Environment::new(EnvironmentSource::Sky(Box::new(sky.clone())), rotation, 1.0);
+        let hw = HeroWavelengths::<1>::new([550.0]);
+
+        // the sun is too small for uniform directions to find, so its share
+        // is its radiance times the solid angle it covers
+        let sun_center = rotated(sky.sample_sun((0.0, 0.0)), rotation.to_radians());
+        let sun =
+            environment.radiance(sun_center, &hw)[0] / sky.sun_pdf(sky.sample_sun((0.0, 0.0)));
+
+        seed_thread_rng(23);
+        let n = 200_000;
+        let (mut uniform, mut sampled) = (0.0, 0.0);
+        for _ in 0..n {
+            let direction = Vector3::uniform_sphere_direction_from((random_f64(), random_f64()));
+            if environment.direction_pdf(direction) < 1.0 {
+                uniform += environment.radiance(direction, &hw)[0] * 4.0 * PI;
+            }
+
+            let direction = environment.sample_direction((random_f64(), random_f64()));
+            sampled +=
+                environment.radiance(direction, &hw)[0] / environment.direction_pdf(direction);
+        }
+        let expected = uniform / n as f64 + sun;
+        let sampled = sampled / n as f64;
+        assert!(
+            (sampled - expected).abs() < 0.03 * expected,
+            "{sampled} {expected}"
+        );
+    }
+
+    #[test]
+    fn gradients_are_sampled_toward_their_bright_half() {
+        let environment = Environment::new(
+            EnvironmentSource::Gradient {
+                zenith: ColorRgb::new(4.0, 4.0, 5.0),
+                horizon: ColorRgb::new(1.0, 0.9, 0.8),
+                ground: ColorRgb::new(0.1, 0.1, 0.1),
+            },
+            0.0,
+            1.0,
+        );
+        let hw = HeroWavelengths::<1>::new([550.0]);
+        let up = Vector3::new(0.0, 1.0, 0.0);
+        assert!(
+            environment.direction_pdf(up * 0.9 + Vector3::new(0.1, 0.0, 0.0))
+                > environment.direction_pdf(-up * 0.9 + Vector3::new(0.1, 0.0, 0.0))
+        );
+
+        seed_thread_rng(29);
+        let n = 200_000;
+        let (mut uniform, mut sampled, mut density, mut above) = (0.0, 0.0, 0.0, 0);
+        for _ in 0..n {
+            let direction = Vector3::uniform_sphere_direction_from((random_f64(), random_f64()));
+            uniform += environment.radiance(direction, &hw)[0] * 4.0 * PI;
+            density += environment.direction_pdf(direction) * 4.0 * PI;
+
+            let direction = environment.sample_direction((random_f64(), random_f64()));
+            above += usize::from(direction.y > 0.0);
+            sampled +=
+                environment.radiance(direction, &hw)[0] / environment.direction_pdf(direction);
+        }
+        let (uniform, sampled, density) =
+            (uniform / n as f64, sampled / n as f64, density / n as f64);
+
+        assert!((density - 1.0).abs() < 0.02, "{density}");
+        assert!(above > n * 3 / 4, "{above}");
+        assert!(
+            (sampled - uniform).abs() < 0.03 * uniform,
+            "{sampled} {uniform}"
+        );
+    }
+}
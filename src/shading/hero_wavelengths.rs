@@ -30,15 +30,17 @@ impl<const N: usize> HeroWavelengths<N> {
         Self(Vector::new(wavelengths))
     }
 
-    /// Pick 4 stratified hero wavelengths by dividing 380-780nm into equal bins
-    /// and picking a random wavelength within each bin.
-    pub fn new_distributed() -> Self {
-        use rand::RngExt;
-        let mut rng = rand::rng();
+    /// Pick N stratified hero wavelengths by dividing 380-780nm into equal bins
+    /// and offsetting into each bin by the uniform sample `u` in [0, 1).
+    ///
+    /// Using one sample for every bin (a rotation of the wavelengths, as in the
+    /// original paper) keeps the bins evenly spaced, and spends a single
+    /// sampler dimension on the whole set.
+    pub fn from_sample(u: f64) -> Self {
         let bin_width = (LAMBDA_MAX - LAMBDA_MIN) / N as f64;
         let mut lambdas = [0.0; N];
         for (i, lambda) in lambdas.iter_mut().enumerate() {
-            *lambda = LAMBDA_MIN + i as f64 * bin_width + rng.random_range(0.0..bin_width);
+            *lambda = LAMBDA_MIN + (i as f64 + u) * bin_width;
         }
         Self(Vector::new(lambdas))
     }
@@ -0,0 +1,410 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    geometry::{Aabb, Onb, Point, Vector3},
+    shading::{ColorRgb, ColorSpectrum, color_spectrum::SPECTRAL_SAMPLE_COUNT},
+};
+
+mod ies;
+pub use ies::IesProfile;
+
+/// Steps of the polar angle the light a point light sends out is summed over.
+const POLAR_STEPS: usize = 2048;
+
+/// Steps of the azimuth the same sum takes, for lights with a profile.
+const AZIMUTH_STEPS: usize = 64;
+
+/// Light that doesn't come from any geometry, so rays can't hit it and it's
+/// only found by sampling it.
+#[derive(Debug, Clone)]
+pub enum Light {
+    /// Shines from a single point, every way or within a cone.
+    Point(PointLight),
+    /// Shines from infinitely far away along `direction`, e.g. sunlight.
+    Directional {
+        direction: Vector3,
+        /// the light falling on a surface facing it
+        irradiance: ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+    },
+}
+
+/// Light arriving at a point straight from a [`Light`].
+#[derive(Debug, Clone)]
+pub struct LightIncident {
+    /// the unit direction toward the light
+    pub direction: Vector3,
+    pub distance: f64,
+    /// the light falling on a surface facing it
+    pub irradiance: ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+}
+
+/// Light leaving a [`Light`], for tracing it into the scene.
+#[derive(Debug, Clone)]
+pub struct LightEmission {
+    pub origin: Point,
+    /// the unit direction the light leaves in
+    pub direction: Vector3,
+    /// the light's power over the densities the origin and direction were
+    /// sampled with
+    pub power: ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+}
+
+impl Light {
+    /// Light reaching `point`, unless nothing does.
+    pub fn incident(&self, point: Point) -> Option<LightIncident> {
+        match self {
+            Light::Point(light) => {
+                let offset = point.to(light.position);
+                let distance = offset.length();
+                if distance <= 0.0 {
+                    return None;
+                }
+                let direction = offset / distance;
+                let intensity = light.intensity(-direction);
+                (!intensity.is_black()).then(|| LightIncident {
+                    direction,
+                    distance,
+                    irradiance: intensity / (distance * distance),
+                })
+            }
+            Light::Directional {
+                direction,
+                irradiance,
+            } => Some(LightIncident {
+                direction: -*direction,
+                distance: f64::INFINITY,
+                irradiance: *irradiance,
+            }),
+        }
+    }
+
+    /// The power sent out, as luminance, with directional lights covering
+    /// `bounds`. Used to pick lights in proportion to it.
+    pub fn power(&self, bounds: Aabb) -> f64 {
+        match self {
+            Light::Point(light) => light.power,
+            Light::Directional { irradiance, .. } => {
+                if bounds.is_infinite() {
+                    return 0.0;
+                }
+                let corners = bounds.corners();
+                let radius = corners[0].to(corners[7]).length() / 2.0;
+                // the disk facing the light that emission is sampled over
+                ColorRgb::from(*irradiance).luminance() * PI * radius * radius
+            }
+        }
+    }
+
+    /// Samples where and which way light leaves, with directional lights
+    /// covering `bounds` from outside it. `None` if no light leaves there, or
+    /// `bounds` are infinite.
+    pub fn sample_emission(
+        &self,
+        bounds: Aabb,
+        (u0, u1): (f64, f64),
+        (u2, u3): (f64, f64),
+    ) -> Option<LightEmission> {
+        match self {
+            Light::Point(light) => {
+                // uniform over the cone, or the whole sphere
+                let cos_max = light.cone.map_or(-1.0, |(_, cos_outer)| cos_outer);
+                let z = 1.0 - u0 * (1.0 - cos_max);
+                let sin_theta = (1.0 - z * z).max(0.0).sqrt();
+                let phi = 2.0 * PI * u1;
+                let direction = light.frame.to_world(Vector3::new(
+                    phi.cos() * sin_theta,
+                    phi.sin() * sin_theta,
+                    z,
+                ));
+                let density = 1.0 / (2.0 * PI * (1.0 - cos_max));
+                let power = light.intensity(direction) / density;
+                (!power.is_black()).then_some(LightEmission {
+                    origin: light.position,
+                    direction,
+                    power,
+                })
+            }
+            Light::Directional {
+                direction,
+                irradiance,
+            } => {
+                if bounds.is_infinite() {
+                    return None;
+                }
+                let corners = bounds.corners();
+                let radius = corners[0].to(corners[7]).length() / 2.0;
+                if !(radius.is_finite() && radius > 0.0) {
+                    return None;
+                }
+                // a disk facing the light, just outside the bounds
+                let frame = Onb::from_w(*direction);
+                let (r, phi) = (radius * u2.sqrt(), 2.0 * PI * u3);
+                let offset = frame.to_world(Vector3::new(r * phi.cos(), r * phi.sin(), 0.0));
+                Some(LightEmission {
+                    origin: bounds.center() + offset - *direction * radius,
+                    direction: *direction,
+                    power: *irradiance * (PI * radius * radius),
+                })
+            }
+        }
+    }
+}
+
+/// A point light, or a spot light if it has a cone.
+#[derive(Debug, Clone)]
+pub struct PointLight {
+    position: Point,
+    /// `w` is the way a spot light points, and a profile's straight down
+    frame: Onb,
+    /// the cosines of the angles off `w` where the cone starts to fade and
+    /// where it ends
+    cone: Option<(f64, f64)>,
+    profile: Option<Arc<IesProfile>>,
+    /// the intensity where the cone and the profile are at their brightest
+    intensity: ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+    /// the luminance of the light sent out in all
+    power: f64,
+}
+
+impl PointLight {
+    /// A light sending out `power` watts in all, tinted by `color`. The cone
+    /// is the angles off `direction` where it starts to fade and where it
+    /// ends, in degrees.
+    pub fn new(
+        position: Point,
+        power: f64,
+        color: ColorRgb,
+        direction: Vector3,
+        cone_degrees: Option<(f64, f64)>,
+        profile: Option<Arc<IesProfile>>,
+    ) -> Self {
+        let mut light = Self {
+            position,
+            frame: Onb::from_w(direction),
+            cone: cone_degrees
+                .map(|(inner, outer)| (inner.to_radians().cos(), outer.to_radians().cos())),
+            profile,
+            intensity: ColorSpectrum::ONE,
+            power: 0.0,
+        };
+
+        // the light sent out per unit of the peak intensity
+        let azimuth_steps = if light.profile.is_some() {
+            AZIMUTH_STEPS
+        } else {
+            1
+        };
+        let (d_theta, d_phi) = (PI / POLAR_STEPS as f64, 2.0 * PI / azimuth_steps as f64);
+        let mut total = 0.0;
+        for i in 0..POLAR_STEPS {
+            let theta = (i as f64 + 0.5) * d_theta;
+            for j in 0..azimuth_steps {
+                let phi = (j as f64 + 0.5) * d_phi;
+                let local = Vector3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                );
+                total += light.distribution(local) * theta.sin() * d_theta * d_phi;
+            }
+        }
+
+        (light.intensity, light.power) = if total > 0.0 {
+            (
+                ColorSpectrum::from(color) * (power / total),
+                power * color.luminance(),
+            )
+        } else {
+            (ColorSpectrum::ZERO, 0.0)
+        };
+        light
+    }
+
+    /// The intensity sent out in the unit `direction`.
+    fn intensity(&self, direction: Vector3) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        let local = Vector3::new(
+            direction.dot(self.frame.u),
+            direction.dot(self.frame.v),
+            direction.dot(self.frame.w),
+        );
+        self.intensity * self.distribution(local)
+    }
+
+    /// The intensity in the unit `local` direction relative to the peak, with
+    /// +z along `w`.
+    fn distribution(&self, local: Vector3) -> f64 {
+        let cos_theta = local.z.clamp(-1.0, 1.0);
+        let falloff = match self.cone {
+            Some((cos_inner, cos_outer)) => {
+                if cos_inner <= cos_outer {
+                    if cos_theta >= cos_outer { 1.0 } else { 0.0 }
+                } else {
+                    // smoothstep
+                    let t = ((cos_theta - cos_outer) / (cos_inner - cos_outer)).clamp(0.0, 1.0);
+                    t * t * (3.0 - 2.0 * t)
+                }
+            }
+            None => 1.0,
+        };
+        if falloff <= 0.0 {
+            return 0.0;
+        }
+        match &self.profile {
+            Some(profile) => {
+                let vertical = cos_theta.acos().to_degrees();
+                let horizontal = local.y.atan2(local.x).to_degrees();
+                falloff * profile.value(vertical, horizontal)
+            }
+            None => falloff,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        geometry::{Vector, primitives::Sphere},
+        shading::materials::Lambertian,
+        tracing::SceneWorld,
+    };
+
+    use super::*;
+
+    #[test]
+    fn lights_send_out_the_power_they_are_given() {
+        let profile =
+            IesProfile::parse("TILT=NONE\n1 1000 1 3 1 1 2 0 0 0\n1 1 100\n0 60 90\n0\n100 40 0\n")
+                .unwrap();
+        let lights = [
+            PointLight::new(
+                Point::ORIGIN,
+                10.0,
+                ColorRgb::WHITE,
+                -Vector3::UNIT_Y,
+                None,
+                None,
+            ),
+            PointLight::new(
+                Point::ORIGIN,
+                10.0,
+                ColorRgb::WHITE,
+                Vector3::new(1.0, 1.0, 0.0),
+                Some((20.0, 30.0)),
+                None,
+            ),
+            PointLight::new(
+                Point::ORIGIN,
+                10.0,
+                ColorRgb::WHITE,
+                -Vector3::UNIT_Y,
+                None,
+                Some(Arc::new(profile)),
+            ),
+        ];
+
+        for light in lights {
+            let light = Light::Point(light);
+            // the power of uniform directions over the sphere's density
+            let n = 400;
+            let mut total = 0.0;
+            for i in 0..n {
+                for j in 0..n {
+                    let u = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+                    let direction = Vector3::uniform_sphere_direction_from(u);
+                    let incident = light.incident(Point::ORIGIN + direction);
+                    total += incident.map_or(0.0, |incident| incident.irradiance.0[3]) * 4.0 * PI
+                        / (n * n) as f64;
+                }
+            }
+            // a white spectrum is only nearly flat
+            assert!((total - 10.0).abs() < 0.15, "{total}");
+
+            let emission = light
+                .sample_emission(Aabb::unit(), (0.3, 0.6), (0.5, 0.5))
+                .unwrap();
+            assert!(emission.power.0 != Vector::ZERO);
+            assert!((light.power(Aabb::unit()) - 10.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn lights_are_picked_by_their_power() {
+        let point = |power: f64| {
+            Light::Point(PointLight::new(
+                Point::ORIGIN,
+                power,
+                ColorRgb::WHITE,
+                -Vector3::UNIT_Y,
+                None,
+                None,
+            ))
+        };
+        let sun = Light::Directional {
+            direction: -Vector3::UNIT_Y,
+            irradiance: ColorSpectrum::from(ColorRgb::WHITE),
+        };
+        // the disk around the unit box, facing the sun. a white spectrum is
+        // only nearly flat
+        let sun_power = sun.power(Aabb::unit());
+        assert!((sun_power - PI * 3.0 / 4.0).abs() < 0.02, "{sun_power}");
+
+        let world = SceneWorld::from_world_without_importance_sampling(Arc::new(Sphere::new(
+            Point::ORIGIN,
+            0.5,
+            Arc::new(Lambertian::white()),
+        )));
+        assert_eq!(world.bounds, Aabb::unit());
+        assert!(world.light_distribution.is_none());
+        let world = world.with_lights(vec![point(1.0), point(3.0), sun]);
+        let distribution = world.light_distribution.unwrap();
+        let total = 4.0 + sun_power;
+        for (i, power) in [1.0, 3.0, sun_power].into_iter().enumerate() {
+            let probability = distribution.density((i as f64 + 0.5) / 3.0) / 3.0;
+            assert!((probability - power / total).abs() < 1e-9, "{probability}");
+        }
+    }
+
+    #[test]
+    fn point_lights_fall_off_with_distance_and_outside_their_cone() {
+        let irradiance = |light: &PointLight, point: Point| {
+            Light::Point(light.clone()).incident(point).map(|incident| {
+                assert!(
+                    (incident.direction - point.to(Point::ORIGIN).unit_vector()).length() < 1e-12
+                );
+                assert!((incident.distance - point.to(Point::ORIGIN).length()).abs() < 1e-12);
+                incident.irradiance.0[3]
+            })
+        };
+
+        let bulb = PointLight::new(
+            Point::ORIGIN,
+            10.0,
+            ColorRgb::WHITE,
+            -Vector3::UNIT_Y,
+            None,
+            None,
+        );
+        let near = irradiance(&bulb, Point::new(0.0, -1.0, 0.0)).unwrap();
+        let far = irradiance(&bulb, Point::new(2.0, 0.0, 0.0)).unwrap();
+        assert!((far - near / 4.0).abs() < 1e-12, "{near} {far}");
+
+        // full within 20° of straight down, fading out by 30°
+        let spot = PointLight::new(
+            Point::ORIGIN,
+            10.0,
+            ColorRgb::WHITE,
+            -Vector3::UNIT_Y,
+            Some((20.0, 30.0)),
+            None,
+        );
+        let at = |degrees: f64| {
+            let angle = degrees.to_radians();
+            irradiance(&spot, Point::new(angle.sin(), -angle.cos(), 0.0))
+        };
+        let center = at(0.0).unwrap();
+        assert_eq!(at(15.0), Some(center));
+        assert!(at(25.0).unwrap() < center && at(25.0).unwrap() > 0.0);
+        assert!(at(35.0).is_none());
+        assert!(at(180.0).is_none());
+    }
+}
@@ -0,0 +1,205 @@
+/// The relative intensity a luminaire sends each way, from an IES LM-63
+/// photometric file with type C angles: vertical angles run from straight
+/// down at 0° to straight up at 180°, and horizontal angles turn about the
+/// vertical axis.
+#[derive(Debug, Clone)]
+pub struct IesProfile {
+    /// vertical angles in degrees, ascending
+    vertical_angles: Vec<f64>,
+    /// horizontal angles in degrees, ascending
+    horizontal_angles: Vec<f64>,
+    /// the candela values for each horizontal angle, over the vertical
+    /// angles, divided by the largest of them
+    intensities: Vec<Vec<f64>>,
+}
+
+impl IesProfile {
+    /// Parses an uploaded IES file.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        let text =
+            std::str::from_utf8(data).map_err(|_| "IES profile must be ASCII text".to_string())?;
+        Self::parse(text)
+    }
+
+    /// A profile that shines alike every way.
+    pub fn uniform() -> Self {
+        Self {
+            vertical_angles: vec![0.0, 180.0],
+            horizontal_angles: vec![0.0],
+            intensities: vec![vec![1.0, 1.0]],
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        // everything up to the TILT line is keywords and free text
+        let tilt_end = text
+            .find("TILT=")
+            .ok_or("IES profile is missing its TILT line")?;
+        let (tilt, rest) = text[tilt_end..]
+            .split_once('\n')
+            .unwrap_or((&text[tilt_end..], ""));
+        let mut numbers = rest
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .map(|token| {
+                token
+                    .parse::<f64>()
+                    .map_err(|_| format!("IES profile has an invalid number \"{}\"", token))
+            });
+        let mut next = || {
+            numbers
+                .next()
+                .unwrap_or_else(|| Err("IES profile ends early".to_string()))
+        };
+
+        if tilt.trim() == "TILT=INCLUDE" {
+            // the lamp's tilt doesn't change which way it shines
+            let _geometry = next()?;
+            let count = next()? as usize;
+            for _ in 0..2 * count {
+                next()?;
+            }
+        }
+
+        let _lamp_count = next()?;
+        let _lumens_per_lamp = next()?;
+        let _multiplier = next()?;
+        let vertical_count = next()? as usize;
+        let horizontal_count = next()? as usize;
+        let photometric_type = next()?;
+        for _ in 0..7 {
+            // units, luminous dimensions, ballast factor, future use and watts
+            next()?;
+        }
+        if photometric_type != 1.0 {
+            return Err("Only IES profiles with type C photometry are supported".to_string());
+        }
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err("IES profile has no angles".to_string());
+        }
+
+        let vertical_angles = (0..vertical_count)
+            .map(|_| next())
+            .collect::<Result<Vec<_>, _>>()?;
+        let horizontal_angles = (0..horizontal_count)
+            .map(|_| next())
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut intensities = (0..horizontal_count)
+            .map(|_| (0..vertical_count).map(|_| next()).collect())
+            .collect::<Result<Vec<Vec<f64>>, _>>()?;
+
+        if !vertical_angles.is_sorted() || !horizontal_angles.is_sorted() {
+            return Err("IES profile angles must be ascending".to_string());
+        }
+        let max = intensities.iter().flatten().fold(0.0_f64, |a, &b| a.max(b));
+        if !(max.is_finite() && max > 0.0) {
+            return Err("IES profile must have a positive candela value".to_string());
+        }
+        for intensity in intensities.iter_mut().flatten() {
+            *intensity = intensity.max(0.0) / max;
+        }
+
+        Ok(Self {
+            vertical_angles,
+            horizontal_angles,
+            intensities,
+        })
+    }
+
+    /// The intensity `vertical` degrees up from straight down and turned
+    /// `horizontal` degrees about the vertical axis, relative to the
+    /// brightest direction.
+    pub fn value(&self, vertical: f64, horizontal: f64) -> f64 {
+        let horizontal = horizontal.rem_euclid(360.0);
+        // the horizontal angles a profile lists tell its symmetry
+        let last = self.horizontal_angles[self.horizontal_angles.len() - 1];
+        let horizontal = if self.horizontal_angles.len() == 1 {
+            last
+        } else if last <= 90.0 {
+            let folded = if horizontal > 180.0 {
+                360.0 - horizontal
+            } else {
+                horizontal
+            };
+            if folded > 90.0 {
+                180.0 - folded
+            } else {
+                folded
+            }
+        } else if last <= 180.0 && horizontal > 180.0 {
+            360.0 - horizontal
+        } else {
+            horizontal
+        };
+
+        let Some((h, h_fraction)) = interpolation(&self.horizontal_angles, horizontal) else {
+            return 0.0;
+        };
+        let Some((v, v_fraction)) = interpolation(&self.vertical_angles, vertical) else {
+            return 0.0;
+        };
+        let along = |row: &[f64]| {
+            row[v] * (1.0 - v_fraction) + row.get(v + 1).map_or(0.0, |next| next * v_fraction)
+        };
+        let here = along(&self.intensities[h]);
+        match self.intensities.get(h + 1) {
+            Some(next) if h_fraction > 0.0 => here * (1.0 - h_fraction) + along(next) * h_fraction,
+            _ => here,
+        }
+    }
+}
+
+/// The index of the last of `angles` at most `angle`, and how far `angle` is
+/// toward the next one, or `None` if it's outside them.
+fn interpolation(angles: &[f64], angle: f64) -> Option<(usize, f64)> {
+    if angles.len() == 1 {
+        return Some((0, 0.0));
+    }
+    if angle < angles[0] || angle > angles[angles.len() - 1] {
+        return None;
+    }
+    let i = angles
+        .partition_point(|&a| a <= angle)
+        .clamp(1, angles.len() - 1)
+        - 1;
+    let width = angles[i + 1] - angles[i];
+    let fraction = if width > 0.0 {
+        ((angle - angles[i]) / width).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    Some((i, fraction))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_interpolate_and_unfold_their_symmetry() {
+        let text = "IESNA:LM-63-2002\n[TEST] quadrant\nTILT=NONE\n\
+            1 1000 1 3 2 1 2 0 0 0\n1 1 100\n\
+            0 45 90\n0 90\n\
+            200 100 0\n100 50 0\n";
+        let profile = IesProfile::parse(text).unwrap();
+
+        assert_eq!(profile.value(0.0, 0.0), 1.0);
+        assert!((profile.value(22.5, 0.0) - 0.75).abs() < 1e-12);
+        assert!((profile.value(45.0, 45.0) - 0.375).abs() < 1e-12);
+        // quadrant symmetry mirrors 90° across to 180° and 270°
+        assert_eq!(profile.value(0.0, 180.0), profile.value(0.0, 0.0));
+        assert_eq!(profile.value(45.0, 270.0), profile.value(45.0, 90.0));
+        assert_eq!(profile.value(120.0, 0.0), 0.0);
+
+        assert!(IesProfile::parse("TILT=NONE\n1 1000 1 3 2").is_err());
+
+        // uploaded resources arrive as bytes
+        let uploaded = IesProfile::from_bytes(text.as_bytes()).unwrap();
+        assert_eq!(uploaded.value(45.0, 45.0), profile.value(45.0, 45.0));
+        assert!(IesProfile::from_bytes(&[0xff, 0xfe]).is_err());
+
+        let uniform = IesProfile::uniform();
+        assert_eq!(uniform.value(0.0, 0.0), 1.0);
+        assert_eq!(uniform.value(137.0, 250.0), 1.0);
+    }
+}
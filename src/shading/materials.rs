@@ -3,6 +3,11 @@ use super::pdf::Pdf;
 use crate::geometry::{Point, Ray, RayHit, Vector, Vector3};
 use crate::shading::color_spectrum::SPECTRAL_SAMPLE_COUNT;
 use crate::shading::hero_wavelengths::{HERO_WAVELENGTH_COUNT, HeroWavelengths};
+use crate::shading::medium::Medium;
+use crate::tracing::Sampler;
+
+mod conductor;
+pub use conductor::{Conductor, Metal};
 
 mod dielectric;
 pub use dielectric::Dielectric;
@@ -13,11 +18,31 @@ pub use isotropic::Isotropic;
 mod lambertian;
 pub use lambertian::Lambertian;
 
+mod mix;
+pub use mix::Mix;
+
+mod principled;
+pub use principled::Principled;
+
 mod specular;
 pub use specular::Specular;
 
 pub trait Material: std::fmt::Debug + Sync + Send {
-    fn emittance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT>;
+    /// The light emitted at a surface point, at scene time `time`.
+    fn emittance(
+        &self,
+        u: f64,
+        v: f64,
+        p: Point,
+        time: f64,
+    ) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT>;
+
+    /// The most light emitted at a surface point at any time. Used to weigh
+    /// emitters by their power, so ones that only light up later still get
+    /// their share of samples.
+    fn peak_emittance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.emittance(u, v, p, 0.0)
+    }
 
     /// Whether this material emits any light at any point on its surface.
     /// Used to build the lights list for importance sampling.
@@ -40,25 +65,32 @@ pub trait Material: std::fmt::Debug + Sync + Send {
     /// integrator skips scattering entirely.
     fn reflectance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT>;
 
+    /// Decide how `ray` scatters off of `ray_hit`. Any random decisions the
+    /// material makes itself draw their samples from `sampler`.
     fn scatter(
         &self,
         ray: Ray,
         ray_hit: &RayHit,
         hw: &HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+        sampler: &mut dyn Sampler,
     ) -> Option<ScatterRecord>;
 
     /// Evaluate the BRDF (Bidirectional Reflectance Distribution Function).
     ///
     /// Returns the fraction of light arriving from `incident_direction` that
     /// is reflected toward `outgoing_direction`. This is a pure BRDF value —
-    /// no cosine factor, no sampling probability.
+    /// no cosine factor, no sampling probability. Materials that scatter
+    /// light through their surface with a `ScatterRecord::Pdf`, like frosted
+    /// glass, include what they transmit to the other side.
     ///
     /// `outgoing_direction` — unit vector toward the camera (outgoing).
     /// `incident_direction` — unit vector toward the light source (incident).
-    /// `normal` — the surface normal at the hit point.
+    /// `normal` — the geometric surface normal at the hit point, pointing
+    /// out of closed objects, whichever side the directions are on.
     ///
-    /// For delta-function materials (Specular, Dielectric), this method is
-    /// never called — they bypass the BRDF path via `ScatterRecord::Delta`.
+    /// For delta-function materials (Specular, smooth Dielectric), this
+    /// method is never called — they bypass the BRDF path via
+    /// `ScatterRecord::Delta` or `ScatterRecord::Spectral`.
     fn brdf(
         &self,
         outgoing_direction: Vector3,
@@ -68,6 +100,14 @@ pub trait Material: std::fmt::Debug + Sync + Send {
         v: f64,
         p: Point,
     ) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT>;
+
+    /// The medium inside a closed object of this material, that rays passing
+    /// through its surface with a `ScatterRecord::Pdf` enter. `None` for
+    /// materials with nothing inside, which rays pass through into the same
+    /// medium they were in.
+    fn interior_medium(&self, _u: f64, _v: f64, _p: Point) -> Option<Medium> {
+        None
+    }
 }
 
 /// Payload for a dispersive dielectric scatter event.
@@ -0,0 +1,246 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::shading::hero_wavelengths::{HERO_WAVELENGTH_COUNT, HeroWavelengths};
+use crate::shading::pdf::Pdf;
+use crate::tracing::Sampler;
+use crate::utils::Keyframes;
+use crate::{
+    geometry::{Onb, Point, Ray, RayHit, Vector3},
+    shading::{
+        ColorSpectrum, Texture, TrowbridgeReitz, color_spectrum::SPECTRAL_SAMPLE_COUNT,
+        fresnel_conductor,
+    },
+};
+
+use super::{Material, ScatterRecord};
+
+/// Wavelengths, in nm, the metals' optical constants are tabulated at.
+const METAL_WAVELENGTHS: [f64; 10] = [
+    380.0, 400.0, 450.0, 500.0, 550.0, 600.0, 650.0, 700.0, 750.0, 800.0,
+];
+
+/// Metals with measured optical constants: Johnson and Christy (1972) for
+/// gold, copper and silver, and Rakić (1995) for aluminium.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metal {
+    Gold,
+    Copper,
+    Silver,
+    Aluminium,
+}
+
+impl Metal {
+    /// The real and imaginary parts of the index of refraction, `n` and `k`,
+    /// at [`METAL_WAVELENGTHS`].
+    fn optical_constants(self) -> ([f64; 10], [f64; 10]) {
+        match self {
+            Metal::Gold => (
+                [1.70, 1.66, 1.50, 0.97, 0.43, 0.25, 0.17, 0.16, 0.15, 0.16],
+                [1.92, 1.96, 1.88, 1.85, 2.45, 2.98, 3.47, 3.85, 4.37, 4.85],
+            ),
+            Metal::Copper => (
+                [1.27, 1.18, 1.17, 1.13, 1.02, 0.30, 0.21, 0.21, 0.22, 0.26],
+                [1.95, 2.21, 2.40, 2.56, 2.58, 3.03, 3.63, 4.10, 4.50, 4.89],
+            ),
+            Metal::Silver => (
+                [0.15, 0.05, 0.04, 0.05, 0.06, 0.06, 0.05, 0.04, 0.03, 0.03],
+                [1.65, 2.07, 2.65, 3.09, 3.59, 4.00, 4.48, 4.84, 5.25, 5.55],
+            ),
+            Metal::Aluminium => (
+                [0.44, 0.49, 0.62, 0.77, 0.96, 1.20, 1.47, 1.83, 2.40, 2.80],
+                [4.62, 4.86, 5.47, 6.08, 6.69, 7.26, 7.79, 8.31, 8.62, 8.45],
+            ),
+        }
+    }
+
+    /// The metal's `n` and `k` over the spectrum.
+    pub fn index_of_refraction(
+        self,
+    ) -> (
+        ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+        ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+    ) {
+        let (n, k) = self.optical_constants();
+        (
+            ColorSpectrum::from_tabulated(&METAL_WAVELENGTHS, &n),
+            ColorSpectrum::from_tabulated(&METAL_WAVELENGTHS, &k),
+        )
+    }
+}
+
+/// A metal surface of GGX microfacets, reflecting with the Fresnel
+/// reflectance of its complex index of refraction at each wavelength.
+#[derive(Debug, Clone)]
+pub struct Conductor {
+    /// the real part of the index of refraction
+    eta: ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+    /// the imaginary part, how strongly the metal absorbs
+    k: ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+    distribution: TrowbridgeReitz,
+    emittance_texture: Arc<dyn Texture>,
+    emittance_scale: Keyframes<f64>,
+}
+
+impl Conductor {
+    pub fn new(
+        eta: ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+        k: ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+        distribution: TrowbridgeReitz,
+        emittance_texture: Arc<dyn Texture>,
+    ) -> Self {
+        Self {
+            eta,
+            k,
+            distribution,
+            emittance_texture,
+            emittance_scale: Keyframes::constant(1.0),
+        }
+    }
+
+    /// Scales the emitted light over time, e.g. to dim or flicker a light
+    /// during an animation.
+    pub fn with_emittance_scale(mut self, emittance_scale: Keyframes<f64>) -> Self {
+        self.emittance_scale = emittance_scale;
+        self
+    }
+
+    /// The Fresnel reflectance at each spectral sample, at the cosine
+    /// `cos_theta` of the angle of incidence.
+    fn fresnel(&self, cos_theta: f64) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        let mut reflectance = ColorSpectrum::ZERO;
+        for (i, r) in reflectance.0.iter_mut().enumerate() {
+            *r = fresnel_conductor(cos_theta, self.eta.0[i], self.k.0[i]);
+        }
+        reflectance
+    }
+}
+
+impl Material for Conductor {
+    fn reflectance(&self, _u: f64, _v: f64, _p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        // the color seen head-on
+        self.fresnel(1.0)
+    }
+
+    fn emittance(
+        &self,
+        u: f64,
+        v: f64,
+        p: Point,
+        time: f64,
+    ) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.emittance_texture.value(u, v, p) * self.emittance_scale.at(time)
+    }
+
+    fn peak_emittance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.emittance_texture.value(u, v, p) * self.emittance_scale.max()
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.emittance_texture.value(0.5, 0.5, Point::ORIGIN) != ColorSpectrum::ZERO
+    }
+
+    fn is_transmissive(&self) -> bool {
+        false
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    fn scatter(
+        &self,
+        ray: Ray,
+        ray_hit: &RayHit,
+        _hw: &HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let ns = if ray.direction.dot(ray_hit.normal) > 0.0 {
+            -ray_hit.normal
+        } else {
+            ray_hit.normal
+        };
+        Some(ScatterRecord::Pdf(Pdf::Microfacet {
+            frame: Onb::from_w(ns),
+            outgoing: -ray.direction.unit_vector(),
+            distribution: self.distribution,
+        }))
+    }
+
+    fn brdf(
+        &self,
+        outgoing_direction: Vector3,
+        incident_direction: Vector3,
+        normal: Vector3,
+        _u: f64,
+        _v: f64,
+        _p: Point,
+    ) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        // the same frame `scatter` samples in, on the side seen from
+        let ns = if normal.dot(outgoing_direction) < 0.0 {
+            -normal
+        } else {
+            normal
+        };
+        let frame = Onb::from_w(ns);
+        let (wo, wi) = (
+            frame.to_local(outgoing_direction),
+            frame.to_local(incident_direction),
+        );
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return ColorSpectrum::ZERO;
+        }
+        let wm = (wo + wi).unit_vector();
+
+        self.fresnel(wo.dot(wm))
+            * (self.distribution.d(wm) * self.distribution.g(wo, wi) / (4.0 * wo.z * wi.z))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shading::{ColorRgb, for_each_hemisphere_direction, textures::SolidColor};
+
+    use super::*;
+
+    #[test]
+    fn metals_reflect_their_colors_and_never_more_than_they_receive() {
+        let conductor = |metal: Metal, roughness: f64| {
+            let (eta, k) = metal.index_of_refraction();
+            Conductor::new(
+                eta,
+                k,
+                TrowbridgeReitz::from_roughness(roughness, 0.0),
+                Arc::new(SolidColor::BLACK),
+            )
+        };
+
+        let [r, g, b]: [f64; 3] =
+            ColorRgb::from(conductor(Metal::Gold, 0.0).reflectance(0.0, 0.0, Point::ORIGIN)).into();
+        assert!(r > g && g > b, "{r} {g} {b}");
+        let [r, _, b]: [f64; 3] =
+            ColorRgb::from(conductor(Metal::Copper, 0.0).reflectance(0.0, 0.0, Point::ORIGIN))
+                .into();
+        assert!(r > b, "{r} {b}");
+
+        // the light reflected toward any direction out of what arrives from
+        // all over the hemisphere, which rougher surfaces lose more of to
+        // light bouncing between their microfacets
+        let normal = Vector3::UNIT_Z;
+        let mut previous = 1.0;
+        for roughness in [0.1, 0.5, 1.0] {
+            let material = conductor(Metal::Silver, roughness);
+            let outgoing = Vector3::new(0.6, 0.0, 0.8);
+            let mut albedo = 0.0;
+            for_each_hemisphere_direction(1024, |incident, solid_angle| {
+                let f = material.brdf(outgoing, incident, normal, 0.0, 0.0, Point::ORIGIN);
+                albedo += f.0[4] * incident.z * solid_angle;
+            });
+            assert!(albedo < previous, "{roughness}: {albedo}");
+            previous = albedo;
+        }
+        assert!(previous > 0.3);
+    }
+}
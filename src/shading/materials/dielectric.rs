@@ -1,13 +1,16 @@
 use std::sync::Arc;
 
 use crate::{
-    geometry::{Point, Ray, RayHit, Vector, Vector3},
+    geometry::{Onb, Point, Ray, RayHit, Vector, Vector3},
     shading::{
-        ColorSpectrum, Texture,
+        ColorSpectrum, RoughDielectric, Texture, TrowbridgeReitz,
         color_spectrum::SPECTRAL_SAMPLE_COUNT,
         hero_wavelengths::{HERO_WAVELENGTH_COUNT, HeroWavelengths},
         medium::Medium,
+        pdf::Pdf,
     },
+    tracing::Sampler,
+    utils::Keyframes,
 };
 
 use super::{Material, ScatterRecord, SpectralScatter};
@@ -15,10 +18,14 @@ use super::{Material, ScatterRecord, SpectralScatter};
 // crown glass, or close to it
 const DEFAULT_ABBE_NUMBER: f64 = 64.0;
 
+/// The sodium D-line, in nm, that the index of refraction is given at.
+const D_LINE_WAVELENGTH: f64 = 589.3;
+
 #[derive(Debug, Clone)]
 pub struct Dielectric {
     reflectance_texture: Arc<dyn Texture>,
     emittance_texture: Arc<dyn Texture>,
+    emittance_scale: Keyframes<f64>,
     /// Pre-computed one-term Sellmeier coefficients (B₁, C₁), computed
     /// eagerly at construction so `index_of_refraction_at` is a cheap lookup.
     b1: f64,
@@ -26,6 +33,56 @@ pub struct Dielectric {
     /// The interior medium of this dielectric (absorption + emission).
     /// `None` means thin-walled (straight-through transmission, no volume).
     pub medium: Option<Medium>,
+    /// The microfacets of frosted glass. `None` means a smooth surface.
+    distribution: Option<TrowbridgeReitz>,
+}
+
+/// A microfacet normal sampled on rough glass among those the incoming ray
+/// sees, which the ray then reflects off of or refracts through.
+struct Facet {
+    distribution: TrowbridgeReitz,
+    /// the frame of the macrosurface normal, on the side the ray comes from
+    frame: Onb,
+    /// the direction the ray came from, in `frame`
+    outgoing: Vector3,
+    /// the microfacet normal, on the same side as the geometric normal
+    normal: Vector3,
+}
+
+impl Facet {
+    fn sample(
+        distribution: TrowbridgeReitz,
+        ray: &Ray,
+        ray_hit: &RayHit,
+        sampler: &mut dyn Sampler,
+    ) -> Self {
+        let front = ray.direction.dot(ray_hit.normal) < 0.0;
+        let frame = Onb::from_w(if front {
+            ray_hit.normal
+        } else {
+            -ray_hit.normal
+        });
+        let outgoing = frame.to_local(-ray.direction.unit_vector());
+        let normal = frame.to_world(distribution.sample_visible_normal(outgoing, sampler.get_2d()));
+        Self {
+            distribution,
+            frame,
+            outgoing,
+            normal: if front { normal } else { -normal },
+        }
+    }
+
+    /// The weight of scattering off of the facet into `direction`, or `None`
+    /// when the microsurface shadows it or it ends up on the wrong side of
+    /// the macrosurface. With visible normals sampled, only the shadowing of
+    /// the scattered direction is left.
+    fn weight(&self, direction: Vector3, reflected: bool) -> Option<f64> {
+        let incident = self.frame.to_local(direction.unit_vector());
+        if reflected != (incident.z > 0.0) {
+            return None;
+        }
+        Some(self.distribution.g(self.outgoing, incident) / self.distribution.g1(self.outgoing))
+    }
 }
 
 impl Dielectric {
@@ -46,12 +103,29 @@ impl Dielectric {
         Ok(Dielectric {
             reflectance_texture,
             emittance_texture,
+            emittance_scale: Keyframes::constant(1.0),
             b1,
             c1,
             medium,
+            distribution: None,
         })
     }
 
+    /// Scales the emitted light over time, e.g. to dim or flicker a light
+    /// during an animation.
+    pub fn with_emittance_scale(mut self, emittance_scale: Keyframes<f64>) -> Self {
+        self.emittance_scale = emittance_scale;
+        self
+    }
+
+    /// Frosts the surface with GGX microfacets of a perceptual `roughness`
+    /// from 0 (smooth) to 1.
+    pub fn with_roughness(mut self, roughness: f64) -> Self {
+        self.distribution =
+            (roughness > 0.0).then(|| TrowbridgeReitz::from_roughness(roughness, 0.0));
+        self
+    }
+
     pub fn schlick_reflectance(cosine: f64, ref_idx: f64) -> f64 {
         let r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
         let r0 = r0 * r0;
@@ -74,12 +148,14 @@ impl Dielectric {
     /// Compute the scattered direction for a specific wavelength using
     /// Fresnel-weighted Monte Carlo: randomly reflects or refracts
     /// proportional to Schlick reflectance, which converges to
-    /// correct Fresnel over many samples).
+    /// correct Fresnel over many samples). `u` is the uniform sample in
+    /// [0, 1) that makes the choice.
     pub fn refract_at(
         &self,
         incident: Vector3,
         normal: Vector3,
         wavelength_nm: f64,
+        u: f64,
     ) -> (Vector3, bool) {
         let ior = self.index_of_refraction_at(wavelength_nm);
         let (refractive_normal, refraction_ratio) = if incident.dot(normal) < 0.0 {
@@ -95,7 +171,7 @@ impl Dielectric {
 
         let reflectance = Self::schlick_reflectance(cos_theta, refraction_ratio);
 
-        if cannot_refract || reflectance > rand::random() {
+        if cannot_refract || reflectance > u {
             // reflect (TIR or Schlick flip chose reflection)
             (unit_direction.reflect_around(refractive_normal), true)
         } else {
@@ -107,20 +183,52 @@ impl Dielectric {
         }
     }
 
+    /// Scatters `ray` as this glass would with the microfacets of
+    /// `distribution` instead of its own, for materials whose roughness
+    /// varies over their surface. Thin sheets are left smooth.
+    pub(super) fn scatter_through(
+        &self,
+        ray: Ray,
+        ray_hit: &RayHit,
+        hw: &HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+        distribution: Option<TrowbridgeReitz>,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        if let Some(medium) = self.medium {
+            self.scatter_volumetric(&ray, ray_hit, hw, medium, distribution, sampler)
+        } else {
+            self.scatter_thin(&ray, ray_hit, hw, sampler)
+        }
+    }
+
     fn scatter_volumetric(
         &self,
         ray: &Ray,
         ray_hit: &RayHit,
         hw: &HeroWavelengths<HERO_WAVELENGTH_COUNT>,
         medium: Medium,
+        distribution: Option<TrowbridgeReitz>,
+        sampler: &mut dyn Sampler,
     ) -> Option<ScatterRecord> {
         let entering = ray.direction.dot(ray_hit.normal) < 0.0;
+        // every wavelength meets the same microfacet, and only bends
+        // differently through it
+        let facet =
+            distribution.map(|distribution| Facet::sample(distribution, ray, ray_hit, sampler));
+        let normal = facet.as_ref().map_or(ray_hit.normal, |facet| facet.normal);
 
         let mut rays: [Option<Ray>; HERO_WAVELENGTH_COUNT] = std::array::from_fn(|_| None);
         let mut reflectance = Vector::<HERO_WAVELENGTH_COUNT>::ONE;
 
         for (i, &lambda) in hw.iter().enumerate() {
-            let (next_dir, reflected) = self.refract_at(ray.direction, ray_hit.normal, lambda);
+            let (next_dir, reflected) =
+                self.refract_at(ray.direction, normal, lambda, sampler.get_1d());
+            if let Some(facet) = &facet {
+                let Some(weight) = facet.weight(next_dir, reflected) else {
+                    continue;
+                };
+                reflectance[i] *= weight;
+            }
 
             // apply reflectance texture as an optional reflection tint
             // (non-physical but useful for coated-glass effects)
@@ -165,13 +273,16 @@ impl Dielectric {
     ///
     /// R_total = 2R/(1+R), T_total = (1-R)/(1+R).
     /// A single random decision chooses reflection vs. straight-through
-    /// transmission for all wavelengths. The transmitted lobe applies
-    /// the `attenuation_color` as a per-crossing multiply.
+    /// transmission for all wavelengths, by the average of R_total over
+    /// them, so each lobe is scaled up by the chance it isn't taken. The
+    /// transmitted lobe applies the `attenuation_color` as a per-crossing
+    /// multiply.
     fn scatter_thin(
         &self,
         ray: &Ray,
         ray_hit: &RayHit,
         hw: &HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+        sampler: &mut dyn Sampler,
     ) -> Option<ScatterRecord> {
         // cos_theta uses abs() — both faces of a thin sheet are equivalent
         let cos_theta = (-ray.direction.unit_vector())
@@ -195,11 +306,10 @@ impl Dielectric {
         // single direction decision: reflect or transmit straight through.
         // This matches the Mitsuba thindielectric / PBRT thin-dielectric
         // behavior — one decision for all wavelengths.
-        if rand::random::<f64>() < avg_r {
-            // reflect: both faces contribute via the analytic sum
-            let reflected = (-ray.direction)
-                .unit_vector()
-                .reflect_around(ray_hit.normal);
+        if sampler.get_1d() < avg_r {
+            // reflect back toward the side the ray came from: both faces
+            // contribute via the analytic sum
+            let reflected = ray.direction.unit_vector().reflect_around(ray_hit.normal);
             for i in 0..HERO_WAVELENGTH_COUNT {
                 rays[i] = Some(Ray::new_with_medium(
                     ray_hit.point,
@@ -211,7 +321,7 @@ impl Dielectric {
                     .reflectance_texture
                     .value(ray_hit.u, ray_hit.v, ray_hit.point)
                     .sample_wavelength(hw[i]);
-                reflectance[i] = r_total[i] * tint;
+                reflectance[i] = r_total[i] / avg_r * tint;
             }
         } else {
             // "refraction" time
@@ -232,7 +342,7 @@ impl Dielectric {
                     .reflectance_texture
                     .value(ray_hit.u, ray_hit.v, ray_hit.point)
                     .sample_wavelength(hw[i]);
-                reflectance[i] = t_total * tint;
+                reflectance[i] = t_total / (1.0 - avg_r) * tint;
             }
         }
 
@@ -242,6 +352,16 @@ impl Dielectric {
         })))
     }
 
+    /// The microfacets of frosted glass, which refract every wavelength alike
+    /// as they spread it far more than dispersion would.
+    fn rough_bsdf(&self, distribution: TrowbridgeReitz) -> RoughDielectric {
+        RoughDielectric {
+            distribution,
+            eta: self.index_of_refraction_at(D_LINE_WAVELENGTH),
+            thin: self.medium.is_none(),
+        }
+    }
+
     /// Fit (B₁, C₁) of a one-term Sellmeier model satisfying two constraints:
     ///   1. n(589.3 nm) == n_d           (D-line, user-provided IOR)
     ///   2. the model's Abbe number == v
@@ -295,8 +415,13 @@ impl Material for Dielectric {
         u: f64,
         v: f64,
         p: crate::geometry::Point,
+        time: f64,
     ) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
-        self.emittance_texture.value(u, v, p)
+        self.emittance_texture.value(u, v, p) * self.emittance_scale.at(time)
+    }
+
+    fn peak_emittance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.emittance_texture.value(u, v, p) * self.emittance_scale.max()
     }
 
     fn is_emissive(&self) -> bool {
@@ -317,15 +442,35 @@ impl Material for Dielectric {
 
     fn brdf(
         &self,
-        _outgoing_direction: Vector3,
-        _incident_direction: Vector3,
-        _normal: Vector3,
-        _u: f64,
-        _v: f64,
-        _p: Point,
+        outgoing_direction: Vector3,
+        incident_direction: Vector3,
+        normal: Vector3,
+        u: f64,
+        v: f64,
+        p: Point,
     ) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
-        // delta-function BRDF — never called, bypassed via ScatterRecord::Delta
-        ColorSpectrum::ZERO
+        // smooth glass scatters through ScatterRecord::Spectral instead
+        let Some(distribution) = self.distribution else {
+            return ColorSpectrum::ZERO;
+        };
+        let frame = Onb::from_w(normal);
+        let (wo, wi) = (
+            frame.to_local(outgoing_direction),
+            frame.to_local(incident_direction),
+        );
+        let f = self.rough_bsdf(distribution).f(wo, wi);
+
+        // the reflectance texture tints reflections off of solid glass, and
+        // is the color of a thin sheet
+        if wo.z * wi.z > 0.0 || self.medium.is_none() {
+            self.reflectance_texture.value(u, v, p) * f
+        } else {
+            ColorSpectrum::ONE * f
+        }
+    }
+
+    fn interior_medium(&self, _u: f64, _v: f64, _p: Point) -> Option<Medium> {
+        self.medium
     }
 
     fn scatter(
@@ -333,11 +478,184 @@ impl Material for Dielectric {
         ray: Ray,
         ray_hit: &RayHit,
         hw: &HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+        sampler: &mut dyn Sampler,
     ) -> Option<ScatterRecord> {
-        if let Some(medium) = self.medium {
-            self.scatter_volumetric(&ray, ray_hit, hw, medium)
-        } else {
-            self.scatter_thin(&ray, ray_hit, hw)
+        match self.distribution {
+            Some(distribution) => Some(ScatterRecord::Pdf(Pdf::RoughDielectric {
+                frame: Onb::from_w(ray_hit.normal),
+                outgoing: -ray.direction.unit_vector(),
+                bsdf: self.rough_bsdf(distribution),
+            })),
+            None => self.scatter_through(ray, ray_hit, hw, None, sampler),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        shading::{ColorSpectrum, textures::SolidColor},
+        tracing::IndependentSampler,
+        utils::Keyframe,
+    };
+
+    use super::*;
+
+    #[test]
+    fn emittance_is_scaled_by_its_keyframes() {
+        let scale = Keyframes::new([
+            Keyframe {
+                time: 0.0,
+                value: 0.0,
+            },
+            Keyframe {
+                time: 1.0,
+                value: 4.0,
+            },
+        ])
+        .unwrap();
+        let glass = Dielectric::new(
+            Arc::new(SolidColor::WHITE),
+            Arc::new(SolidColor::new(ColorSpectrum::ONE)),
+            1.5,
+            None,
+            None,
+        )
+        .unwrap()
+        .with_emittance_scale(scale);
+
+        let p = Point::ORIGIN;
+        assert!(glass.emittance(0.0, 0.0, p, 0.0).is_black());
+        assert_eq!(glass.emittance(0.0, 0.0, p, 0.5), ColorSpectrum::ONE * 2.0);
+        assert_eq!(glass.peak_emittance(0.0, 0.0, p), ColorSpectrum::ONE * 4.0);
+    }
+
+    #[test]
+    fn frosted_glass_spreads_light_around_the_smooth_directions() {
+        let hw = HeroWavelengths::new([550.0; HERO_WAVELENGTH_COUNT]);
+        let glass = |medium: Option<Medium>, roughness: f64| {
+            let white = Arc::new(SolidColor::new(ColorSpectrum::ONE));
+            let glass = Dielectric::new(white, Arc::new(SolidColor::BLACK), 1.5, None, medium)
+                .unwrap()
+                .with_roughness(roughness);
+            Arc::new(glass) as Arc<dyn Material>
+        };
+        let direction = Vector3::new(0.3, 0.0, -1.0).unit_vector();
+        let ray = Ray::new(Point::new(0.0, 0.0, 1.0) - direction, direction, 0.0);
+        let ray_hit = |material| RayHit {
+            t: 1.0,
+            point: Point::new(0.0, 0.0, 1.0),
+            normal: Vector3::UNIT_Z,
+            material,
+            u: 0.0,
+            v: 0.0,
+        };
+        // the smooth reflection, and the transmission: refracted through
+        // solid glass, straight through a thin sheet
+        let reflected = Vector3::new(direction.x, 0.0, -direction.z);
+        let transmitted = |medium: Option<Medium>| match medium {
+            Some(_) => direction.refract_around(Vector3::UNIT_Z, 1.0 / 1.5),
+            None => direction,
+        };
+        let n = 4096;
+
+        // smooth glass refracts or reflects every wavelength exactly
+        let ray_hit_smooth = ray_hit(glass(Some(Medium::Vacuum), 0.0));
+        for _ in 0..n {
+            let Some(ScatterRecord::Spectral(scatter)) =
+                ray_hit_smooth
+                    .material
+                    .scatter(ray, &ray_hit_smooth, &hw, &mut IndependentSampler)
+            else {
+                panic!("smooth glass scatters every wavelength on its own");
+            };
+            assert!((scatter.reflectance[0] - 1.0).abs() < 1e-9);
+            let d = scatter.rays[0].unwrap().direction;
+            let smooth = d
+                .dot(reflected)
+                .max(d.dot(transmitted(Some(Medium::Vacuum))));
+            assert!(smooth > 0.999, "{d:?}");
+        }
+
+        // frosted glass samples directions it can evaluate the BSDF at
+        for medium in [Some(Medium::Vacuum), None] {
+            let ray_hit = ray_hit(glass(medium, 0.4));
+            let Some(ScatterRecord::Pdf(pdf)) =
+                ray_hit
+                    .material
+                    .scatter(ray, &ray_hit, &hw, &mut IndependentSampler)
+            else {
+                panic!("frosted glass scatters through a pdf");
+            };
+            let (mut throughput, mut off_smooth) = (0.0, 0);
+            for _ in 0..n {
+                let (d, _) = pdf.sample(&mut IndependentSampler);
+                // the microsurface may shadow the scattered ray
+                let density = pdf.density(d);
+                if density <= 0.0 {
+                    continue;
+                }
+                let f =
+                    ray_hit
+                        .material
+                        .brdf(-direction, d, ray_hit.normal, 0.0, 0.0, ray_hit.point);
+                let mut weight = f.0[4] * d.z.abs() / density;
+                if medium.is_some() && d.z < 0.0 {
+                    // radiance refracted into the glass is squeezed into a
+                    // narrower cone
+                    weight *= 1.5 * 1.5;
+                }
+                throughput += weight;
+                if d.dot(reflected).max(d.dot(transmitted(medium))) < 0.999 {
+                    off_smooth += 1;
+                }
+            }
+            let throughput = throughput / n as f64;
+            // little is lost to the microsurface shadowing itself
+            assert!(throughput <= 1.0 + 1e-9 && throughput > 0.9, "{throughput}");
+            assert!(off_smooth > n / 2, "{off_smooth}");
+        }
+    }
+
+    #[test]
+    fn thin_sheets_reflect_back_and_keep_the_light_they_receive() {
+        let hw = HeroWavelengths::new([550.0; HERO_WAVELENGTH_COUNT]);
+        let white = Arc::new(SolidColor::new(ColorSpectrum::ONE));
+        let sheet = Dielectric::new(white, Arc::new(SolidColor::BLACK), 1.5, None, None).unwrap();
+        // at a grazing angle, so a good share is reflected
+        let direction = Vector3::new(2.0, 0.0, -1.0).unit_vector();
+        let ray = Ray::new(Point::new(0.0, 0.0, 1.0) - direction, direction, 0.0);
+        let ray_hit = RayHit {
+            t: 1.0,
+            point: Point::new(0.0, 0.0, 1.0),
+            normal: Vector3::UNIT_Z,
+            material: Arc::new(sheet),
+            u: 0.0,
+            v: 0.0,
+        };
+
+        let n = 4096;
+        let (mut throughput, mut reflections) = (0.0, 0);
+        for _ in 0..n {
+            let Some(ScatterRecord::Spectral(scatter)) =
+                ray_hit
+                    .material
+                    .scatter(ray, &ray_hit, &hw, &mut IndependentSampler)
+            else {
+                panic!("thin sheets scatter every wavelength on their own");
+            };
+            throughput += scatter.reflectance[0];
+            let d = scatter.rays[0].unwrap().direction;
+            if d.z > 0.0 {
+                reflections += 1;
+                assert!((d - Vector3::new(direction.x, 0.0, -direction.z)).length() < 1e-9);
+            } else {
+                assert!((d - direction).length() < 1e-9);
+            }
         }
+        assert!(reflections > n / 10, "{reflections}");
+        // a clear sheet absorbs nothing
+        let throughput = throughput / n as f64;
+        assert!((throughput - 1.0).abs() < 1e-9, "{throughput}");
     }
 }
@@ -1,6 +1,8 @@
 use std::sync::Arc;
 
 use crate::shading::hero_wavelengths::{HERO_WAVELENGTH_COUNT, HeroWavelengths};
+use crate::tracing::Sampler;
+use crate::utils::Keyframes;
 use crate::{
     geometry::{Point, Ray, RayHit, Vector3},
     shading::{ColorSpectrum, Texture, color_spectrum::SPECTRAL_SAMPLE_COUNT},
@@ -12,6 +14,7 @@ use super::{Material, ScatterRecord};
 pub struct Isotropic {
     reflectance_texture: Arc<dyn Texture>,
     emittance_texture: Arc<dyn Texture>,
+    emittance_scale: Keyframes<f64>,
 }
 
 impl Isotropic {
@@ -19,8 +22,16 @@ impl Isotropic {
         Self {
             reflectance_texture,
             emittance_texture,
+            emittance_scale: Keyframes::constant(1.0),
         }
     }
+
+    /// Scales the emitted light over time, e.g. to dim or flicker a light
+    /// during an animation.
+    pub fn with_emittance_scale(mut self, emittance_scale: Keyframes<f64>) -> Self {
+        self.emittance_scale = emittance_scale;
+        self
+    }
 }
 
 impl Material for Isotropic {
@@ -28,8 +39,18 @@ impl Material for Isotropic {
         self.reflectance_texture.value(u, v, p)
     }
 
-    fn emittance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
-        self.emittance_texture.value(u, v, p)
+    fn emittance(
+        &self,
+        u: f64,
+        v: f64,
+        p: Point,
+        time: f64,
+    ) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.emittance_texture.value(u, v, p) * self.emittance_scale.at(time)
+    }
+
+    fn peak_emittance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.emittance_texture.value(u, v, p) * self.emittance_scale.max()
     }
 
     fn is_emissive(&self) -> bool {
@@ -49,12 +70,13 @@ impl Material for Isotropic {
         ray: Ray,
         ray_hit: &RayHit,
         _hw: &HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+        sampler: &mut dyn Sampler,
     ) -> Option<ScatterRecord> {
         // always scatter, and always scatter in a random direction
         Some(ScatterRecord::Delta {
             scattered: Ray::new_with_medium(
                 ray_hit.point,
-                Vector3::random_unit(),
+                Vector3::uniform_sphere_direction_from(sampler.get_2d()),
                 ray.time,
                 ray.current_medium,
             ),
@@ -2,6 +2,8 @@ use std::sync::Arc;
 
 use crate::shading::hero_wavelengths::{HERO_WAVELENGTH_COUNT, HeroWavelengths};
 use crate::shading::pdf::Pdf;
+use crate::tracing::Sampler;
+use crate::utils::Keyframes;
 use crate::{
     geometry::{Onb, Point, Ray, RayHit, Vector3},
     shading::{
@@ -15,6 +17,7 @@ use super::{Material, ScatterRecord};
 pub struct Lambertian {
     reflectance_texture: Arc<dyn Texture>,
     emittance_texture: Arc<dyn Texture>,
+    emittance_scale: Keyframes<f64>,
 }
 
 impl Lambertian {
@@ -22,14 +25,23 @@ impl Lambertian {
         Self {
             reflectance_texture,
             emittance_texture,
+            emittance_scale: Keyframes::constant(1.0),
         }
     }
 
+    /// Scales the emitted light over time, e.g. to dim or flicker a light
+    /// during an animation.
+    pub fn with_emittance_scale(mut self, emittance_scale: Keyframes<f64>) -> Self {
+        self.emittance_scale = emittance_scale;
+        self
+    }
+
     pub fn black() -> Self {
         let black: Arc<dyn Texture> = Arc::new(SolidColor::BLACK);
         Self {
             reflectance_texture: Arc::clone(&black),
             emittance_texture: Arc::clone(&black),
+            emittance_scale: Keyframes::constant(1.0),
         }
     }
 
@@ -38,6 +50,7 @@ impl Lambertian {
         Self {
             reflectance_texture: Arc::clone(&white),
             emittance_texture: Arc::clone(&white),
+            emittance_scale: Keyframes::constant(1.0),
         }
     }
 }
@@ -47,8 +60,18 @@ impl Material for Lambertian {
         self.reflectance_texture.value(u, v, p)
     }
 
-    fn emittance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
-        self.emittance_texture.value(u, v, p)
+    fn emittance(
+        &self,
+        u: f64,
+        v: f64,
+        p: Point,
+        time: f64,
+    ) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.emittance_texture.value(u, v, p) * self.emittance_scale.at(time)
+    }
+
+    fn peak_emittance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.emittance_texture.value(u, v, p) * self.emittance_scale.max()
     }
 
     fn is_emissive(&self) -> bool {
@@ -68,6 +91,7 @@ impl Material for Lambertian {
         ray: Ray,
         ray_hit: &RayHit,
         _hw: &HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+        _sampler: &mut dyn Sampler,
     ) -> Option<ScatterRecord> {
         let ns = if ray.direction.dot(ray_hit.normal) > 0.0 {
             -ray_hit.normal
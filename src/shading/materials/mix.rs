@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use crate::shading::hero_wavelengths::{HERO_WAVELENGTH_COUNT, HeroWavelengths};
+use crate::tracing::Sampler;
+use crate::utils::hash_u64s;
+use crate::{
+    geometry::{Point, Ray, RayHit, Vector3},
+    shading::{ColorSpectrum, Texture, color_spectrum::SPECTRAL_SAMPLE_COUNT, medium::Medium},
+};
+
+use super::{Material, ScatterRecord};
+
+/// Blends two materials, such as dirt over metal, by picking one of them at
+/// each point hit, `second` with the chance of the weight texture's average
+/// there. The pick is a hash of the point, so everything asked of the
+/// material at a hit agrees on it, while the many points hit within a pixel
+/// average out to the blend.
+#[derive(Debug, Clone)]
+pub struct Mix {
+    first: Arc<dyn Material>,
+    second: Arc<dyn Material>,
+    weight_texture: Arc<dyn Texture>,
+}
+
+impl Mix {
+    pub fn new(
+        first: Arc<dyn Material>,
+        second: Arc<dyn Material>,
+        weight_texture: Arc<dyn Texture>,
+    ) -> Self {
+        Self {
+            first,
+            second,
+            weight_texture,
+        }
+    }
+
+    /// The material picked at the point `p`.
+    fn pick(&self, u: f64, v: f64, p: Point) -> &dyn Material {
+        let weight = self.weight_texture.value(u, v, p).average();
+        let hash = hash_u64s(&[p.0.x.to_bits(), p.0.y.to_bits(), p.0.z.to_bits()]);
+        // the top 53 bits, as a uniform value in [0, 1)
+        let chance = (hash >> 11) as f64 / (1u64 << 53) as f64;
+        if chance < weight {
+            self.second.as_ref()
+        } else {
+            self.first.as_ref()
+        }
+    }
+}
+
+impl Material for Mix {
+    fn reflectance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.pick(u, v, p).reflectance(u, v, p)
+    }
+
+    fn emittance(
+        &self,
+        u: f64,
+        v: f64,
+        p: Point,
+        time: f64,
+    ) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.pick(u, v, p).emittance(u, v, p, time)
+    }
+
+    fn peak_emittance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.pick(u, v, p).peak_emittance(u, v, p)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.first.is_emissive() || self.second.is_emissive()
+    }
+
+    fn is_transmissive(&self) -> bool {
+        self.first.is_transmissive() || self.second.is_transmissive()
+    }
+
+    fn is_specular(&self) -> bool {
+        self.first.is_specular() || self.second.is_specular()
+    }
+
+    fn scatter(
+        &self,
+        ray: Ray,
+        ray_hit: &RayHit,
+        hw: &HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        self.pick(ray_hit.u, ray_hit.v, ray_hit.point)
+            .scatter(ray, ray_hit, hw, sampler)
+    }
+
+    fn brdf(
+        &self,
+        outgoing_direction: Vector3,
+        incident_direction: Vector3,
+        normal: Vector3,
+        u: f64,
+        v: f64,
+        p: Point,
+    ) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.pick(u, v, p)
+            .brdf(outgoing_direction, incident_direction, normal, u, v, p)
+    }
+
+    fn interior_medium(&self, u: f64, v: f64, p: Point) -> Option<Medium> {
+        self.pick(u, v, p).interior_medium(u, v, p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shading::{materials::Lambertian, textures::SolidColor};
+
+    use super::*;
+
+    #[test]
+    fn mixes_pick_each_material_by_its_weight_consistently() {
+        let black: Arc<dyn Material> = Arc::new(Lambertian::new(
+            Arc::new(SolidColor::BLACK),
+            Arc::new(SolidColor::WHITE),
+        ));
+        let white: Arc<dyn Material> = Arc::new(Lambertian::new(
+            Arc::new(SolidColor::WHITE),
+            Arc::new(SolidColor::BLACK),
+        ));
+        let mix = Mix::new(
+            black,
+            white,
+            Arc::new(SolidColor::new(ColorSpectrum::ONE * 0.25)),
+        );
+        assert!(mix.is_emissive());
+        assert!(!mix.is_transmissive() && !mix.is_specular());
+
+        let n = 100;
+        let mut picked_white = 0;
+        for i in 0..n {
+            for j in 0..n {
+                let p = Point::new(i as f64 * 0.01, j as f64 * 0.01, 0.5);
+                let reflectance = mix.reflectance(0.0, 0.0, p);
+                if !reflectance.is_black() {
+                    picked_white += 1;
+                    // the same material answers everything at the point
+                    assert!(mix.emittance(0.0, 0.0, p, 0.0).is_black());
+                    assert_eq!(reflectance, mix.reflectance(0.0, 0.0, p));
+                }
+            }
+        }
+        let share = picked_white as f64 / (n * n) as f64;
+        assert!((share - 0.25).abs() < 0.02, "{share}");
+    }
+}
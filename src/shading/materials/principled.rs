@@ -0,0 +1,430 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::shading::hero_wavelengths::{HERO_WAVELENGTH_COUNT, HeroWavelengths};
+use crate::shading::pdf::Pdf;
+use crate::tracing::Sampler;
+use crate::utils::Keyframes;
+use crate::{
+    geometry::{Onb, Point, Ray, RayHit, Vector3},
+    shading::{
+        ColorSpectrum, Medium, Texture, TrowbridgeReitz, color_spectrum::SPECTRAL_SAMPLE_COUNT,
+        textures::SolidColor,
+    },
+};
+
+use super::{Dielectric, Material, ScatterRecord};
+
+/// The head-on reflectance of the dielectric base at `specular` 1, so the
+/// default `specular` of 0.5 reflects the 4% of ordinary glass.
+const MAX_DIELECTRIC_REFLECTANCE: f64 = 0.08;
+
+/// The head-on reflectance of the clearcoat, a layer of lacquer.
+const CLEARCOAT_REFLECTANCE: f64 = 0.04;
+
+/// An all-round material in the style of Disney's principled BSDF: a base
+/// that blends from diffuse plastic to metal to glass, under an optional
+/// clearcoat, with a sheen at grazing angles. Every parameter is read from a
+/// texture, the scalar ones from its average over the spectrum.
+///
+/// Light refracting through the base is scattered as by frosted glass of the
+/// same roughness, and the rest through a mixture of the reflective lobes.
+#[derive(Debug, Clone)]
+pub struct Principled {
+    base_color_texture: Arc<dyn Texture>,
+    emittance_texture: Arc<dyn Texture>,
+    emittance_scale: Keyframes<f64>,
+    metallic_texture: Arc<dyn Texture>,
+    roughness_texture: Arc<dyn Texture>,
+    specular_texture: Arc<dyn Texture>,
+    sheen_texture: Arc<dyn Texture>,
+    clearcoat_texture: Arc<dyn Texture>,
+    clearcoat_roughness_texture: Arc<dyn Texture>,
+    transmission_texture: Arc<dyn Texture>,
+    /// the color diffuse light takes on as it scatters beneath the surface
+    subsurface_tint_texture: Arc<dyn Texture>,
+    /// the untinted glass that transmitted light refracts through
+    glass: Dielectric,
+}
+
+/// The parameters at one point on the surface.
+struct Parameters {
+    base_color: ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+    subsurface_tint: ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    sheen: f64,
+    clearcoat: f64,
+    transmission: f64,
+    distribution: TrowbridgeReitz,
+    clearcoat_distribution: TrowbridgeReitz,
+}
+
+impl Parameters {
+    /// The chance that light refracts into the base rather than reflecting.
+    fn transmission_chance(&self) -> f64 {
+        (1.0 - self.metallic) * self.transmission
+    }
+
+    /// The weight of the opaque dielectric base: the diffuse, sheen and
+    /// dielectric specular lobes.
+    fn opaque(&self) -> f64 {
+        (1.0 - self.metallic) * (1.0 - self.transmission)
+    }
+}
+
+fn constant(value: f64) -> Arc<dyn Texture> {
+    Arc::new(SolidColor::new(ColorSpectrum::ONE * value))
+}
+
+/// Schlick's approximation of the Fresnel reflectance, from the head-on
+/// reflectance `f0`.
+fn schlick(
+    f0: &ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+    cos_theta: f64,
+) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+    let weight = (1.0 - cos_theta).clamp(0.0, 1.0).powi(5);
+    &(f0 * (1.0 - weight)) + &(ColorSpectrum::ONE * weight)
+}
+
+impl Principled {
+    /// A rough, dielectric diffuse surface of `base_color_texture`, whose
+    /// transmission refracts as if into glass of `index_of_refraction`.
+    pub fn new(
+        base_color_texture: Arc<dyn Texture>,
+        emittance_texture: Arc<dyn Texture>,
+        index_of_refraction: f64,
+    ) -> Result<Self, String> {
+        let glass = Dielectric::new(
+            Arc::new(SolidColor::WHITE),
+            Arc::new(SolidColor::BLACK),
+            index_of_refraction,
+            None,
+            Some(Medium::Vacuum),
+        )?;
+        Ok(Self {
+            base_color_texture,
+            emittance_texture,
+            emittance_scale: Keyframes::constant(1.0),
+            metallic_texture: constant(0.0),
+            roughness_texture: constant(0.5),
+            specular_texture: constant(0.5),
+            sheen_texture: constant(0.0),
+            clearcoat_texture: constant(0.0),
+            clearcoat_roughness_texture: constant(0.1),
+            transmission_texture: constant(0.0),
+            subsurface_tint_texture: Arc::new(SolidColor::WHITE),
+            glass,
+        })
+    }
+
+    /// Scales the emitted light over time, e.g. to dim or flicker a light
+    /// during an animation.
+    pub fn with_emittance_scale(mut self, emittance_scale: Keyframes<f64>) -> Self {
+        self.emittance_scale = emittance_scale;
+        self
+    }
+
+    /// From 0, dielectric, to 1, a metal reflecting the base color.
+    pub fn with_metallic(mut self, metallic_texture: Arc<dyn Texture>) -> Self {
+        self.metallic_texture = metallic_texture;
+        self
+    }
+
+    /// From 0, polished, to 1, rough. Defaults to 0.5.
+    pub fn with_roughness(mut self, roughness_texture: Arc<dyn Texture>) -> Self {
+        self.roughness_texture = roughness_texture;
+        self
+    }
+
+    /// How strongly the dielectric base reflects. Defaults to 0.5.
+    pub fn with_specular(mut self, specular_texture: Arc<dyn Texture>) -> Self {
+        self.specular_texture = specular_texture;
+        self
+    }
+
+    /// The soft white glow at grazing angles of cloth.
+    pub fn with_sheen(mut self, sheen_texture: Arc<dyn Texture>) -> Self {
+        self.sheen_texture = sheen_texture;
+        self
+    }
+
+    /// A clear lacquer over the whole surface.
+    pub fn with_clearcoat(mut self, clearcoat_texture: Arc<dyn Texture>) -> Self {
+        self.clearcoat_texture = clearcoat_texture;
+        self
+    }
+
+    /// The roughness of the clearcoat. Defaults to 0.1.
+    pub fn with_clearcoat_roughness(
+        mut self,
+        clearcoat_roughness_texture: Arc<dyn Texture>,
+    ) -> Self {
+        self.clearcoat_roughness_texture = clearcoat_roughness_texture;
+        self
+    }
+
+    /// From 0, opaque, to 1, glass tinted by the base color.
+    pub fn with_transmission(mut self, transmission_texture: Arc<dyn Texture>) -> Self {
+        self.transmission_texture = transmission_texture;
+        self
+    }
+
+    /// Tints the diffuse light, as if it had scattered through colored
+    /// material beneath the surface. Defaults to white.
+    pub fn with_subsurface_tint(mut self, subsurface_tint_texture: Arc<dyn Texture>) -> Self {
+        self.subsurface_tint_texture = subsurface_tint_texture;
+        self
+    }
+
+    fn parameters(&self, u: f64, v: f64, p: Point) -> Parameters {
+        let scalar = |texture: &Arc<dyn Texture>| texture.value(u, v, p).average().clamp(0.0, 1.0);
+        let roughness = scalar(&self.roughness_texture);
+        Parameters {
+            base_color: self.base_color_texture.value(u, v, p),
+            subsurface_tint: self.subsurface_tint_texture.value(u, v, p),
+            metallic: scalar(&self.metallic_texture),
+            roughness,
+            specular: scalar(&self.specular_texture),
+            sheen: scalar(&self.sheen_texture),
+            clearcoat: scalar(&self.clearcoat_texture),
+            transmission: scalar(&self.transmission_texture),
+            distribution: TrowbridgeReitz::from_roughness(roughness, 0.0),
+            clearcoat_distribution: TrowbridgeReitz::from_roughness(
+                scalar(&self.clearcoat_roughness_texture),
+                0.0,
+            ),
+        }
+    }
+}
+
+impl Material for Principled {
+    fn reflectance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        // the base color, plus what the dielectric base and the clearcoat
+        // reflect head-on, so a black base with highlights isn't taken to
+        // absorb everything
+        let parameters = self.parameters(u, v, p);
+        let specular =
+            (1.0 - parameters.metallic) * MAX_DIELECTRIC_REFLECTANCE * parameters.specular
+                + CLEARCOAT_REFLECTANCE * parameters.clearcoat;
+        &parameters.base_color + &(ColorSpectrum::ONE * specular)
+    }
+
+    fn emittance(
+        &self,
+        u: f64,
+        v: f64,
+        p: Point,
+        time: f64,
+    ) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.emittance_texture.value(u, v, p) * self.emittance_scale.at(time)
+    }
+
+    fn peak_emittance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.emittance_texture.value(u, v, p) * self.emittance_scale.max()
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.emittance_texture.value(0.5, 0.5, Point::ORIGIN) != ColorSpectrum::ZERO
+    }
+
+    fn is_transmissive(&self) -> bool {
+        self.parameters(0.5, 0.5, Point::ORIGIN)
+            .transmission_chance()
+            > 0.0
+    }
+
+    fn is_specular(&self) -> bool {
+        self.parameters(0.5, 0.5, Point::ORIGIN).metallic > 0.0
+    }
+
+    fn scatter(
+        &self,
+        ray: Ray,
+        ray_hit: &RayHit,
+        hw: &HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let parameters = self.parameters(ray_hit.u, ray_hit.v, ray_hit.point);
+
+        let transmission_chance = parameters.transmission_chance();
+        if transmission_chance > 0.0 && sampler.get_1d() < transmission_chance {
+            let mut scatter = match self.glass.scatter_through(
+                ray,
+                ray_hit,
+                hw,
+                Some(parameters.distribution),
+                sampler,
+            ) {
+                Some(ScatterRecord::Spectral(scatter)) => scatter,
+                other => return other,
+            };
+            // light passing in and then out takes on the base color
+            for (i, &lambda) in hw.iter().enumerate() {
+                if let Some(scattered) = scatter.rays[i]
+                    && ray.direction.dot(ray_hit.normal) * scattered.direction.dot(ray_hit.normal)
+                        > 0.0
+                {
+                    scatter.reflectance[i] *= parameters
+                        .base_color
+                        .sample_wavelength(lambda)
+                        .max(0.0)
+                        .sqrt();
+                }
+            }
+            return Some(ScatterRecord::Spectral(scatter));
+        }
+
+        let ns = if ray.direction.dot(ray_hit.normal) > 0.0 {
+            -ray_hit.normal
+        } else {
+            ray_hit.normal
+        };
+        let frame = Onb::from_w(ns);
+        let outgoing = -ray.direction.unit_vector();
+
+        // most samples go to the specular lobe on metals, and are shared
+        // with the diffuse one on dielectrics
+        let mut entries = vec![(
+            Pdf::Microfacet {
+                frame,
+                outgoing,
+                distribution: parameters.distribution,
+            },
+            0.5 + 0.5 * parameters.metallic,
+        )];
+        if parameters.opaque() > 0.0 {
+            entries.push((Pdf::CosineHemisphere(frame), parameters.opaque()));
+        }
+        if parameters.clearcoat > 0.0 {
+            entries.push((
+                Pdf::Microfacet {
+                    frame,
+                    outgoing,
+                    distribution: parameters.clearcoat_distribution,
+                },
+                0.25 * parameters.clearcoat,
+            ));
+        }
+        Some(ScatterRecord::Pdf(Pdf::mixture(entries)))
+    }
+
+    fn brdf(
+        &self,
+        outgoing_direction: Vector3,
+        incident_direction: Vector3,
+        normal: Vector3,
+        u: f64,
+        v: f64,
+        p: Point,
+    ) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        let parameters = self.parameters(u, v, p);
+        // `scatter` only reflects when it doesn't transmit, so what it
+        // reflects is scaled up by that chance
+        let reflection_chance = 1.0 - parameters.transmission_chance();
+        if reflection_chance <= 0.0 {
+            return ColorSpectrum::ZERO;
+        }
+
+        // the same frame `scatter` samples in, on the side seen from
+        let ns = if normal.dot(outgoing_direction) < 0.0 {
+            -normal
+        } else {
+            normal
+        };
+        let frame = Onb::from_w(ns);
+        let (wo, wi) = (
+            frame.to_local(outgoing_direction),
+            frame.to_local(incident_direction),
+        );
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return ColorSpectrum::ZERO;
+        }
+        let wm = (wo + wi).unit_vector();
+        let cos_d = wi.dot(wm);
+
+        // Disney's diffuse, brighter toward grazing angles on rough surfaces,
+        // and the sheen
+        let fd90 = 0.5 + 2.0 * parameters.roughness * cos_d * cos_d;
+        let retro_reflection = (1.0 + (fd90 - 1.0) * (1.0 - wo.z).powi(5))
+            * (1.0 + (fd90 - 1.0) * (1.0 - wi.z).powi(5));
+        let diffuse =
+            (&parameters.base_color * &parameters.subsurface_tint) * (retro_reflection / PI);
+        let sheen = ColorSpectrum::ONE * (parameters.sheen * (1.0 - cos_d).powi(5));
+        let mut f = &diffuse + &sheen;
+        f *= parameters.opaque();
+
+        // the specular reflection of the metal and of the dielectric base
+        let microfacets =
+            parameters.distribution.d(wm) * parameters.distribution.g(wo, wi) / (4.0 * wo.z * wi.z);
+        let metal = schlick(&parameters.base_color, cos_d) * parameters.metallic;
+        let dielectric = schlick(
+            &(ColorSpectrum::ONE * (MAX_DIELECTRIC_REFLECTANCE * parameters.specular)),
+            cos_d,
+        ) * parameters.opaque();
+        f += &((&metal + &dielectric) * microfacets);
+
+        // the clearcoat, which the light reaching the base passes through
+        if parameters.clearcoat > 0.0 {
+            let coat = parameters.clearcoat
+                * schlick(&(ColorSpectrum::ONE * CLEARCOAT_REFLECTANCE), cos_d).average();
+            let distribution = parameters.clearcoat_distribution;
+            let coat_microfacets =
+                distribution.d(wm) * distribution.g(wo, wi) / (4.0 * wo.z * wi.z);
+            f *= 1.0 - coat;
+            f += &(ColorSpectrum::ONE * (coat * coat_microfacets));
+        }
+
+        f / reflection_chance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shading::for_each_hemisphere_direction;
+
+    use super::*;
+
+    /// The light reflected toward `outgoing` out of what arrives from all
+    /// over the hemisphere above +z, at each spectral sample.
+    fn albedo(material: &Principled, outgoing: Vector3) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        let mut albedo = ColorSpectrum::ZERO;
+        for_each_hemisphere_direction(256, |incident, solid_angle| {
+            let f = material.brdf(outgoing, incident, Vector3::UNIT_Z, 0.0, 0.0, Point::ORIGIN);
+            albedo += &(f * (incident.z * solid_angle));
+        });
+        albedo
+    }
+
+    #[test]
+    fn principled_lobes_reflect_no_more_than_they_receive() {
+        let gray = || constant(0.8);
+        let outgoing = Vector3::new(0.6, 0.0, 0.8);
+        let materials = [
+            Principled::new(gray(), constant(0.0), 1.5).unwrap(),
+            Principled::new(gray(), constant(0.0), 1.5)
+                .unwrap()
+                .with_metallic(constant(1.0))
+                .with_roughness(constant(0.3)),
+            Principled::new(gray(), constant(0.0), 1.5)
+                .unwrap()
+                .with_sheen(constant(1.0))
+                .with_clearcoat(constant(1.0))
+                .with_clearcoat_roughness(constant(0.05)),
+            Principled::new(gray(), constant(0.0), 1.5)
+                .unwrap()
+                .with_transmission(constant(0.5)),
+        ];
+        for material in &materials {
+            let albedo = albedo(material, outgoing).average();
+            assert!(albedo > 0.5 && albedo < 1.05, "{albedo}");
+        }
+
+        // black, but still with highlights
+        let black = Principled::new(constant(0.0), constant(0.0), 1.5).unwrap();
+        assert!(!black.reflectance(0.0, 0.0, Point::ORIGIN).is_black());
+        let highlight = albedo(&black, outgoing).average();
+        assert!(highlight > 0.02 && highlight < 0.1, "{highlight}");
+    }
+}
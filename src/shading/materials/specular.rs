@@ -1,6 +1,8 @@
 use std::sync::Arc;
 
 use crate::shading::hero_wavelengths::{HERO_WAVELENGTH_COUNT, HeroWavelengths};
+use crate::tracing::Sampler;
+use crate::utils::Keyframes;
 use crate::{
     geometry::{Point, Ray, RayHit, Vector3},
     shading::{ColorSpectrum, Texture, color_spectrum::SPECTRAL_SAMPLE_COUNT},
@@ -12,6 +14,7 @@ use super::{Material, ScatterRecord};
 pub struct Specular {
     reflectance_texture: Arc<dyn Texture>,
     emittance_texture: Arc<dyn Texture>,
+    emittance_scale: Keyframes<f64>,
     roughness: f64,
 }
 
@@ -24,9 +27,17 @@ impl Specular {
         Self {
             reflectance_texture,
             emittance_texture,
+            emittance_scale: Keyframes::constant(1.0),
             roughness: roughness.clamp(0.0, 1.0),
         }
     }
+
+    /// Scales the emitted light over time, e.g. to dim or flicker a light
+    /// during an animation.
+    pub fn with_emittance_scale(mut self, emittance_scale: Keyframes<f64>) -> Self {
+        self.emittance_scale = emittance_scale;
+        self
+    }
 }
 
 impl Material for Specular {
@@ -34,8 +45,18 @@ impl Material for Specular {
         self.reflectance_texture.value(u, v, p)
     }
 
-    fn emittance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
-        self.emittance_texture.value(u, v, p)
+    fn emittance(
+        &self,
+        u: f64,
+        v: f64,
+        p: Point,
+        time: f64,
+    ) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.emittance_texture.value(u, v, p) * self.emittance_scale.at(time)
+    }
+
+    fn peak_emittance(&self, u: f64, v: f64, p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        self.emittance_texture.value(u, v, p) * self.emittance_scale.max()
     }
 
     fn is_emissive(&self) -> bool {
@@ -68,12 +89,13 @@ impl Material for Specular {
         ray: Ray,
         ray_hit: &RayHit,
         _hw: &HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+        sampler: &mut dyn Sampler,
     ) -> Option<ScatterRecord> {
         let reflected = ray.direction.unit_vector().reflect_around(ray_hit.normal);
 
         let scattered = Ray::new_with_medium(
             ray_hit.point,
-            reflected + self.roughness * Vector3::random_unit(),
+            reflected + self.roughness * Vector3::uniform_sphere_direction_from(sampler.get_2d()),
             ray.time,
             ray.current_medium,
         );
@@ -0,0 +1,398 @@
+use std::f64::consts::PI;
+
+use crate::geometry::Vector3;
+
+/// The smallest slope roughness used, so that even polished surfaces have a
+/// finite density to sample and evaluate rather than a delta.
+const MIN_ALPHA: f64 = 1e-3;
+
+/// The GGX (Trowbridge-Reitz) distribution of microfacet normals, with the
+/// surface normal along +z and independent slope roughnesses along x and y.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrowbridgeReitz {
+    alpha_x: f64,
+    alpha_y: f64,
+}
+
+impl TrowbridgeReitz {
+    pub fn new(alpha_x: f64, alpha_y: f64) -> Self {
+        Self {
+            alpha_x: alpha_x.max(MIN_ALPHA),
+            alpha_y: alpha_y.max(MIN_ALPHA),
+        }
+    }
+
+    /// From a perceptual `roughness` in [0, 1], squared into the slope
+    /// roughness, and an `anisotropy` in [0, 1] stretching the highlights
+    /// along x.
+    pub fn from_roughness(roughness: f64, anisotropy: f64) -> Self {
+        let alpha = roughness.clamp(0.0, 1.0).powi(2);
+        let aspect = (1.0 - 0.9 * anisotropy.clamp(0.0, 1.0)).sqrt();
+        Self::new(alpha / aspect, alpha * aspect)
+    }
+
+    /// The density of microfacet normal `wm` over the projected area.
+    pub fn d(&self, wm: Vector3) -> f64 {
+        let cos2_theta = wm.z * wm.z;
+        if cos2_theta <= 0.0 {
+            return 0.0;
+        }
+        let e = ((wm.x / self.alpha_x).powi(2) + (wm.y / self.alpha_y).powi(2)) / cos2_theta;
+        1.0 / (PI * self.alpha_x * self.alpha_y * cos2_theta * cos2_theta * (1.0 + e).powi(2))
+    }
+
+    /// Smith's auxiliary function: the microfacet area hidden from `w` per
+    /// unit of the area it sees.
+    fn lambda(&self, w: Vector3) -> f64 {
+        let cos2_theta = w.z * w.z;
+        if cos2_theta <= 0.0 {
+            return f64::INFINITY;
+        }
+        let alpha2_tan2_theta =
+            ((w.x * self.alpha_x).powi(2) + (w.y * self.alpha_y).powi(2)) / cos2_theta;
+        ((1.0 + alpha2_tan2_theta).sqrt() - 1.0) / 2.0
+    }
+
+    /// The fraction of microfacets facing `w` that it sees.
+    pub fn g1(&self, w: Vector3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// The fraction of microfacets that both `wo` and `wi` see.
+    pub fn g(&self, wo: Vector3, wi: Vector3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// The density of microfacet normal `wm` among those `w` sees.
+    pub fn visible_d(&self, w: Vector3, wm: Vector3) -> f64 {
+        if w.z == 0.0 {
+            return 0.0;
+        }
+        self.g1(w) / w.z.abs() * self.d(wm) * w.dot(wm).abs()
+    }
+
+    /// Samples a microfacet normal among those `w` sees, with `w` above the
+    /// surface, by Heitz's method of sampling the projected hemisphere of the
+    /// stretched microsurface.
+    pub fn sample_visible_normal(&self, w: Vector3, (u0, u1): (f64, f64)) -> Vector3 {
+        // stretch to the hemisphere configuration
+        let mut wh = Vector3::new(self.alpha_x * w.x, self.alpha_y * w.y, w.z).unit_vector();
+        if wh.z < 0.0 {
+            wh = -wh;
+        }
+        let t1 = if wh.z < 0.99999 {
+            Vector3::UNIT_Z.cross(wh).unit_vector()
+        } else {
+            Vector3::UNIT_X
+        };
+        let t2 = wh.cross(t1);
+
+        // a point on the disk, squashed onto the part of it `wh` sees
+        let (r, phi) = (u0.sqrt(), 2.0 * PI * u1);
+        let p1 = r * phi.cos();
+        let h = (1.0 - p1 * p1).max(0.0).sqrt();
+        let s = (1.0 + wh.z) / 2.0;
+        let p2 = (1.0 - s) * h + s * r * phi.sin();
+        let pz = (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+        let nh = p1 * t1 + p2 * t2 + pz * wh;
+
+        // and back to the ellipsoid configuration
+        Vector3::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)).unit_vector()
+    }
+
+    /// The density of reflecting `wo` into `wi` about a microfacet normal
+    /// sampled with [`Self::sample_visible_normal`], both above the surface.
+    pub fn reflection_density(&self, wo: Vector3, wi: Vector3) -> f64 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let wm = (wo + wi).unit_vector();
+        let cos = wo.dot(wm);
+        if cos <= 0.0 {
+            return 0.0;
+        }
+        self.visible_d(wo, wm) / (4.0 * cos)
+    }
+}
+
+/// The Fresnel reflectance of a conductor with complex index of refraction
+/// `eta` + i`k` relative to the outside, at the cosine `cos_theta` of the
+/// angle of incidence.
+pub fn fresnel_conductor(cos_theta: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_theta.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let t0 = eta * eta - k * k - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).max(0.0).sqrt();
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let t2 = 2.0 * a * cos_theta.clamp(0.0, 1.0);
+    let rs = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    ((rs + rp) / 2.0).clamp(0.0, 1.0)
+}
+
+/// The Fresnel reflectance of a dielectric with index of refraction `eta`
+/// inside relative to outside, at the cosine `cos_theta` of the angle of
+/// incidence, negative from inside. Light that can't refract out is all
+/// reflected.
+pub fn fresnel_dielectric(cos_theta: f64, eta: f64) -> f64 {
+    let (cos_i, eta) = if cos_theta < 0.0 {
+        (-cos_theta.max(-1.0), 1.0 / eta)
+    } else {
+        (cos_theta.min(1.0), eta)
+    };
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (parallel * parallel + perpendicular * perpendicular) / 2.0
+}
+
+/// Glass of GGX microfacets, which reflect and refract light by their
+/// Fresnel reflectance, with the surface normal along +z pointing out of the
+/// glass. Directions are unit vectors away from the surface on either side.
+///
+/// A thin sheet has no inside: light passing through leaves along its
+/// reflection mirrored through the sheet, as if both faces were rough, and
+/// the reflectance sums the light bouncing between the faces.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoughDielectric {
+    pub distribution: TrowbridgeReitz,
+    /// the index of refraction inside relative to outside
+    pub eta: f64,
+    pub thin: bool,
+}
+
+/// `w` mirrored through the surface.
+fn mirrored(w: Vector3) -> Vector3 {
+    Vector3::new(w.x, w.y, -w.z)
+}
+
+impl RoughDielectric {
+    /// The chance that light arriving along `-wo` at microfacet `wm`
+    /// reflects rather than passes through.
+    fn reflectance(&self, wo: Vector3, wm: Vector3) -> f64 {
+        if self.thin {
+            let r = fresnel_dielectric(wo.dot(wm).abs(), self.eta);
+            2.0 * r / (1.0 + r)
+        } else {
+            fresnel_dielectric(wo.dot(wm), self.eta)
+        }
+    }
+
+    /// The microfacet normal that scatters `wo` into `wi`, above the surface,
+    /// with the relative index of refraction across it, or `None` if no
+    /// microfacet facing both of them does.
+    fn half_vector(&self, wo: Vector3, wi: Vector3) -> Option<(Vector3, f64)> {
+        let reflected = wo.z * wi.z > 0.0;
+        let (wi, eta) = if reflected {
+            (wi, 1.0)
+        } else if self.thin {
+            (mirrored(wi), 1.0)
+        } else if wo.z > 0.0 {
+            (wi, self.eta)
+        } else {
+            (wi, 1.0 / self.eta)
+        };
+        let wm = wi * eta + wo;
+        if wo.z == 0.0 || wi.z == 0.0 || wm.squared_length() == 0.0 {
+            return None;
+        }
+        let wm = wm.unit_vector();
+        let wm = if wm.z < 0.0 { -wm } else { wm };
+        // microfacets seen from behind can't scatter
+        if wm.dot(wi) * wi.z < 0.0 || wm.dot(wo) * wo.z < 0.0 {
+            return None;
+        }
+        Some((wm, eta))
+    }
+
+    /// Samples the direction `wo` scatters in off of a microfacet among those
+    /// it sees, reflecting it or passing it through by the Fresnel
+    /// reflectance, using `u` for the microfacet and `u_lobe` for the choice.
+    /// Directions the microsurface would shadow come out as the zero vector,
+    /// which has no density.
+    pub fn sample(&self, wo: Vector3, u: (f64, f64), u_lobe: f64) -> Vector3 {
+        let wm = self.distribution.sample_visible_normal(wo, u);
+        let reflected = -wo + 2.0 * wo.dot(wm) * wm;
+        let reflects = u_lobe < self.reflectance(wo, wm);
+        let wi = if reflects {
+            reflected
+        } else if self.thin {
+            mirrored(reflected)
+        } else {
+            // refracted through the microfacet, from the side `wo` is on
+            let (cos_i, eta, wm) = match wo.dot(wm) {
+                cos if cos < 0.0 => (-cos, 1.0 / self.eta, -wm),
+                cos => (cos, self.eta, wm),
+            };
+            let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+            if sin2_t >= 1.0 {
+                return Vector3::ZERO;
+            }
+            -wo / eta + (cos_i / eta - (1.0 - sin2_t).sqrt()) * wm
+        };
+        if (wo.z * wi.z > 0.0) != reflects {
+            return Vector3::ZERO;
+        }
+        wi
+    }
+
+    /// The solid-angle density of [`Self::sample`] returning `wi`.
+    pub fn density(&self, wo: Vector3, wi: Vector3) -> f64 {
+        let Some((wm, eta)) = self.half_vector(wo, wi) else {
+            return 0.0;
+        };
+        let reflectance = self.reflectance(wo, wm);
+        if wo.z * wi.z > 0.0 {
+            return reflectance * self.distribution.visible_d(wo, wm) / (4.0 * wo.dot(wm).abs());
+        }
+        let transmittance = 1.0 - reflectance;
+        if self.thin {
+            return transmittance * self.distribution.visible_d(wo, wm) / (4.0 * wo.dot(wm).abs());
+        }
+        // the Jacobian of the refraction's half vector
+        let denominator = (wi.dot(wm) + wo.dot(wm) / eta).powi(2);
+        transmittance * self.distribution.visible_d(wo, wm) * wi.dot(wm).abs() / denominator
+    }
+
+    /// The BSDF of light arriving from `wi` leaving toward `wo`.
+    pub fn f(&self, wo: Vector3, wi: Vector3) -> f64 {
+        let Some((wm, eta)) = self.half_vector(wo, wi) else {
+            return 0.0;
+        };
+        let reflectance = self.reflectance(wo, wm);
+        let microfacets = self.distribution.d(wm) * self.distribution.g(wo, wi);
+        if wo.z * wi.z > 0.0 {
+            return reflectance * microfacets / (4.0 * wo.z * wi.z).abs();
+        }
+        let transmittance = 1.0 - reflectance;
+        if self.thin {
+            return transmittance * microfacets / (4.0 * wo.z * wi.z).abs();
+        }
+        // radiance is squeezed into a narrower cone on the denser side
+        let denominator = (wi.dot(wm) + wo.dot(wm) / eta).powi(2) * wi.z * wo.z;
+        transmittance * microfacets * (wi.dot(wm) * wo.dot(wm) / denominator).abs() / (eta * eta)
+    }
+}
+
+/// Calls `f` with directions spread over the hemisphere above +z, in a grid
+/// of `steps` by `steps`, and the solid angle around each, for tests to sum
+/// integrals over it.
+#[cfg(test)]
+pub(crate) fn for_each_hemisphere_direction(steps: usize, mut f: impl FnMut(Vector3, f64)) {
+    let (d_theta, d_phi) = (PI / 2.0 / steps as f64, 2.0 * PI / steps as f64);
+    for i in 0..steps {
+        let theta = (i as f64 + 0.5) * d_theta;
+        for j in 0..steps {
+            let phi = (j as f64 + 0.5) * d_phi;
+            let direction = Vector3::new(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            );
+            f(direction, theta.sin() * d_theta * d_phi);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visible_normals_are_sampled_with_their_density() {
+        for distribution in [
+            TrowbridgeReitz::from_roughness(0.5, 0.0),
+            TrowbridgeReitz::from_roughness(0.3, 0.8),
+        ] {
+            let wo = Vector3::new(0.4, -0.2, 0.8).unit_vector();
+            let n = 256;
+            let mut above = 0;
+            for i in 0..n {
+                for j in 0..n {
+                    let u = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+                    let wm = distribution.sample_visible_normal(wo, u);
+                    if (-wo + 2.0 * wo.dot(wm) * wm).z > 0.0 {
+                        above += 1;
+                    }
+                }
+            }
+
+            // the density integrates to the share of samples reflected above
+            // the surface, and the projected microfacet area is the
+            // macrosurface's
+            let (mut reflected, mut projected) = (0.0, 0.0);
+            for_each_hemisphere_direction(1024, |w, solid_angle| {
+                reflected += distribution.reflection_density(wo, w) * solid_angle;
+                projected += distribution.d(w) * w.z * solid_angle;
+            });
+            let share = above as f64 / (n * n) as f64;
+            assert!((reflected - share).abs() < 0.01, "{reflected} {share}");
+            assert!((projected - 1.0).abs() < 0.01, "{projected}");
+        }
+
+        // gold reflects more red than blue, and everything at grazing angles
+        assert!(fresnel_conductor(1.0, 0.16, 3.6) > fresnel_conductor(1.0, 1.53, 1.89));
+        assert!(fresnel_conductor(0.0, 1.53, 1.89) > 0.999);
+    }
+
+    #[test]
+    fn rough_glass_is_sampled_with_its_density_and_keeps_its_energy() {
+        let distribution = TrowbridgeReitz::from_roughness(0.5, 0.0);
+        for thin in [false, true] {
+            let bsdf = RoughDielectric {
+                distribution,
+                eta: 1.5,
+                thin,
+            };
+            // from outside the glass, and from inside it
+            for wo in [
+                Vector3::new(0.4, -0.2, 0.8).unit_vector(),
+                Vector3::new(0.3, 0.1, -0.9).unit_vector(),
+            ] {
+                let n = 256;
+                let (mut sampled, mut weight) = (0, 0.0);
+                for k in 0..n * n {
+                    let u = (
+                        ((k / n) as f64 + 0.5) / n as f64,
+                        ((k % n) as f64 + 0.5) / n as f64,
+                    );
+                    let wi = bsdf.sample(wo, u, (k as f64 * 0.618_033_988_749_895).fract());
+                    if wi == Vector3::ZERO {
+                        continue;
+                    }
+                    sampled += 1;
+                    // refracted radiance is scaled by the squared ratio of
+                    // the indices, which is taken back out
+                    let spread = match (thin, wo.z * wi.z < 0.0, wo.z > 0.0) {
+                        (false, true, true) => 1.5 * 1.5,
+                        (false, true, false) => 1.0 / (1.5 * 1.5),
+                        _ => 1.0,
+                    };
+                    weight += bsdf.f(wo, wi) * wi.z.abs() / bsdf.density(wo, wi) * spread;
+                }
+
+                let mut density = 0.0;
+                for_each_hemisphere_direction(512, |w, solid_angle| {
+                    density += (bsdf.density(wo, w) + bsdf.density(wo, mirrored(w))) * solid_angle;
+                });
+                let share = sampled as f64 / (n * n) as f64;
+                assert!((density - share).abs() < 0.02, "{thin}: {density} {share}");
+                // little is lost to the microsurface shadowing itself
+                let weight = weight / (n * n) as f64;
+                assert!(weight <= 1.0 + 1e-9 && weight > 0.9, "{thin}: {weight}");
+            }
+        }
+
+        // glass reflects 4% head-on, and everything past the critical angle
+        // from inside
+        assert!((fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1e-9);
+        assert_eq!(fresnel_dielectric(-0.5, 1.5), 1.0);
+    }
+}
@@ -1,6 +1,10 @@
 use std::sync::Arc;
 
-use crate::geometry::{Geometric, Onb, Point, Vector3};
+use crate::{
+    geometry::{Geometric, Onb, Point, Vector3},
+    shading::{Environment, RoughDielectric, TrowbridgeReitz},
+    tracing::Sampler,
+};
 
 /// A probability density function over solid angle.
 #[derive(Debug, Clone)]
@@ -23,39 +27,81 @@ pub enum Pdf {
         origin: Point,
     },
 
+    /// Samples directions toward the environment, by its brightness.
+    Environment(Arc<Environment>),
+
+    /// Reflects `outgoing` about a microfacet normal sampled among those it
+    /// sees, with the surface normal along `frame.w`.
+    Microfacet {
+        frame: Onb,
+        outgoing: Vector3,
+        distribution: TrowbridgeReitz,
+    },
+
+    /// Reflects `outgoing` off of, or passes it through, rough glass, with
+    /// the surface normal along `frame.w` pointing out of the glass.
+    RoughDielectric {
+        frame: Onb,
+        outgoing: Vector3,
+        bsdf: RoughDielectric,
+    },
+
     /// Blends any number of weighted PDFs. `sample()` picks by CDF,
     /// `density()` returns the weighted sum.
     Mixture { entries: Vec<(Pdf, f64)> },
 }
 
 impl Pdf {
-    /// Draw a random unit direction and the index of the strategy that produced it.
-    /// For non-Mixture variants, the index is always 0.
-    pub fn sample(&self) -> (Vector3, usize) {
+    /// Draw a unit direction, using samples from `sampler`, and the index of the
+    /// strategy that produced it. For non-Mixture variants, the index is always 0.
+    pub fn sample(&self, sampler: &mut dyn Sampler) -> (Vector3, usize) {
         match self {
-            Pdf::CosineHemisphere(onb) => {
-                (onb.to_world(Vector3::random_cosine_weighted_direction()), 0)
-            }
+            Pdf::CosineHemisphere(onb) => (
+                onb.to_world(Vector3::cosine_weighted_direction_from(sampler.get_2d())),
+                0,
+            ),
             Pdf::UniformHemisphere(onb) => {
-                let mut direction = Vector3::random_unit();
+                let mut direction = Vector3::uniform_sphere_direction_from(sampler.get_2d());
                 if direction.z < 0.0 {
                     direction = -direction;
                 }
                 (onb.to_world(direction), 0)
             }
-            Pdf::UniformSphere => (Vector3::random_unit(), 0),
-            Pdf::Geometric { geometric, origin } => (geometric.sample_direction_from(*origin), 0),
+            Pdf::UniformSphere => (Vector3::uniform_sphere_direction_from(sampler.get_2d()), 0),
+            Pdf::Geometric { geometric, origin } => (
+                geometric.sample_direction_from(*origin, sampler.get_2d()),
+                0,
+            ),
+            Pdf::Environment(environment) => (environment.sample_direction(sampler.get_2d()), 0),
+            Pdf::Microfacet {
+                frame,
+                outgoing,
+                distribution,
+            } => {
+                let wo = frame.to_local(*outgoing);
+                let wm = distribution.sample_visible_normal(wo, sampler.get_2d());
+                (frame.to_world(-wo + 2.0 * wo.dot(wm) * wm), 0)
+            }
+            Pdf::RoughDielectric {
+                frame,
+                outgoing,
+                bsdf,
+            } => {
+                let wo = frame.to_local(*outgoing);
+                let wi = bsdf.sample(wo, sampler.get_2d(), sampler.get_1d());
+                (frame.to_world(wi), 0)
+            }
             Pdf::Mixture { entries } => {
-                let threshold: f64 = rand::random();
+                let threshold = sampler.get_1d();
                 let mut cumulative = 0.0;
                 for (i, (pdf, _weight)) in entries.iter().enumerate() {
                     cumulative += entries[i].1; // pre-normalized weight
                     if threshold <= cumulative {
-                        return (pdf.sample().0, i);
+                        return (pdf.sample(sampler).0, i);
                     }
                 }
                 let last = entries.last().unwrap();
-                (last.0.sample().0, entries.len() - 1)
+                (last.0.sample(sampler).0, entries.len() - 1)
             }
         }
     }
@@ -80,6 +126,18 @@ impl Pdf {
             }
             Pdf::UniformSphere => 1.0 / (4.0 * std::f64::consts::PI),
             Pdf::Geometric { geometric, origin } => geometric.direction_pdf(*origin, direction),
+            Pdf::Environment(environment) => environment.direction_pdf(direction),
+            Pdf::Microfacet {
+                frame,
+                outgoing,
+                distribution,
+            } => distribution
+                .reflection_density(frame.to_local(*outgoing), frame.to_local(direction)),
+            Pdf::RoughDielectric {
+                frame,
+                outgoing,
+                bsdf,
+            } => bsdf.density(frame.to_local(*outgoing), frame.to_local(direction)),
             Pdf::Mixture { entries } => entries
                 .iter()
                 .map(|(pdf, weight)| weight * pdf.density(direction))
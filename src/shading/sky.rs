@@ -0,0 +1,295 @@
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::{
+    geometry::{Onb, Vector, Vector3},
+    shading::{
+        ColorSpectrum,
+        color_spectrum::{LAMBDA_MAX, LAMBDA_MIN, SPECTRAL_SAMPLE_COUNT, cie_1931_xyz},
+    },
+};
+
+/// Half the angle the sun's disk subtends, in radians.
+const SUN_ANGULAR_RADIUS: f64 = 0.004_651;
+
+/// Luminance of the sun's disk outside the atmosphere, in kcd/m².
+const SUN_LUMINANCE: f64 = 1.96e6;
+
+/// Temperature of the blackbody the sun's spectrum follows, in kelvin.
+const SUN_TEMPERATURE: f64 = 5778.0;
+
+/// The CIE daylight basis S0, S1 and S2 from 380nm to 780nm in 10nm steps.
+#[rustfmt::skip]
+const DAYLIGHT_BASIS: [[f64; 3]; 41] = [
+    [63.4, 38.5, 3.0], [65.8, 35.0, 1.2], [94.8, 43.4, -1.1], [104.8, 46.3, -0.5],
+    [105.9, 43.9, -0.7], [96.8, 37.1, -1.2], [113.9, 36.7, -2.6], [125.6, 35.9, -2.9],
+    [125.5, 32.6, -2.8], [121.3, 27.9, -2.6], [121.3, 24.3, -2.6], [113.5, 20.1, -1.8],
+    [113.1, 16.2, -1.5], [110.8, 13.2, -1.3], [106.5, 8.6, -1.2], [108.8, 6.1, -1.0],
+    [105.3, 4.2, -0.5], [104.4, 1.9, -0.3], [100.0, 0.0, 0.0], [96.0, -1.6, 0.2],
+    [95.1, -3.5, 0.5], [89.1, -3.5, 2.1], [90.5, -5.8, 3.2], [90.3, -7.2, 4.1],
+    [88.4, -8.6, 4.7], [84.0, -9.5, 5.1], [85.1, -10.9, 6.7], [81.9, -10.7, 7.3],
+    [82.6, -12.0, 8.6], [84.9, -14.0, 9.8], [81.3, -13.6, 10.2], [71.9, -12.0, 8.3],
+    [74.3, -13.3, 9.6], [76.4, -12.9, 8.5], [63.3, -10.6, 7.0], [71.7, -11.6, 7.6],
+    [77.0, -12.2, 8.0], [65.2, -10.2, 6.7], [47.7, -7.8, 5.2], [68.6, -11.2, 7.4],
+    [65.0, -10.4, 6.8],
+];
+
+/// Sky light from the Preetham et al. analytic daylight model, plus the sun's
+/// disk, with up along +y.
+///
+/// The sky's luminance and chromaticity come from the model, and its spectrum
+/// from the CIE daylight basis at that chromaticity. The sun is a blackbody
+/// dimmed by Rayleigh and aerosol scattering along its path through the
+/// atmosphere. Radiance is in kcd/m², and there's no light below the horizon.
+#[derive(Debug, Clone)]
+pub struct Sky {
+    sun_direction: Vector3,
+    /// Perez coefficients A to E for luminance and the x and y chromaticity
+    perez: [[f64; 5]; 3],
+    /// luminance and chromaticity at the zenith, divided by the Perez
+    /// function there so that directions only need multiplying by theirs
+    zenith: [f64; 3],
+    /// S0, S1 and S2 at the spectral samples
+    daylight_basis: [ColorSpectrum<SPECTRAL_SAMPLE_COUNT>; 3],
+    sun_radiance: ColorSpectrum<SPECTRAL_SAMPLE_COUNT>,
+}
+
+impl Sky {
+    /// `sun_azimuth_degrees` turns the sun from -z toward +x. Turbidity
+    /// measures haze, from about 2 on a clear day to 10 in thick haze.
+    pub fn new(sun_elevation_degrees: f64, sun_azimuth_degrees: f64, turbidity: f64) -> Self {
+        let elevation = sun_elevation_degrees.to_radians();
+        let azimuth = sun_azimuth_degrees.to_radians();
+        let sun_direction = Vector3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+        let theta_sun = FRAC_PI_2 - elevation;
+        let t = turbidity;
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_chromaticity = |m: [[f64; 4]; 3]| {
+            let thetas = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+            let row = |r: [f64; 4]| r.iter().zip(thetas).map(|(a, b)| a * b).sum::<f64>();
+            t * t * row(m[0]) + t * row(m[1]) + row(m[2])
+        };
+        let zenith_x = zenith_chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = zenith_chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+        let zenith = [zenith_luminance, zenith_x, zenith_y];
+        let zenith =
+            [0, 1, 2].map(|k| zenith[k].max(0.0) / perez_function(perez[k], 1.0, theta_sun));
+
+        let daylight_basis = [0, 1, 2].map(|k| {
+            ColorSpectrum(Vector::new(
+                spectral_samples().map(|lambda| daylight_basis_at(lambda)[k]),
+            ))
+        });
+
+        Self {
+            sun_direction,
+            perez,
+            zenith,
+            daylight_basis,
+            sun_radiance: sun_radiance(theta_sun, turbidity),
+        }
+    }
+
+    /// The sky's radiance arriving along `-direction`, including the sun.
+    pub fn radiance(&self, direction: Vector3) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        let direction = direction.unit_vector();
+        let Some([luminance, x, y]) = self.sky_values(direction) else {
+            return ColorSpectrum::ZERO;
+        };
+
+        // the CIE daylight weights for chromaticity (x, y)
+        let denominator = 0.0241 + 0.2562 * x - 0.7341 * y;
+        let m1 = (-1.3515 - 1.7703 * x + 5.9114 * y) / denominator;
+        let m2 = (0.0300 - 31.4424 * x + 30.0717 * y) / denominator;
+        let [s0, s1, s2] = &self.daylight_basis;
+        let spectrum = &(&s0.0 + &(s1.0 * m1)) + &(s2.0 * m2);
+        let spectrum = ColorSpectrum(Vector::new(spectrum.0.map(|s| s.max(0.0))));
+        let spectrum_luminance = luminance_of(&spectrum);
+        let mut spectrum = if spectrum_luminance > 0.0 {
+            spectrum * (luminance / spectrum_luminance)
+        } else {
+            ColorSpectrum::ZERO
+        };
+
+        if direction.dot(self.sun_direction) >= SUN_ANGULAR_RADIUS.cos() {
+            spectrum += &self.sun_radiance;
+        }
+        spectrum
+    }
+
+    /// The luminance of the sky along `-direction`, leaving out the sun.
+    pub fn sky_luminance(&self, direction: Vector3) -> f64 {
+        self.sky_values(direction.unit_vector())
+            .map_or(0.0, |[luminance, ..]| luminance)
+    }
+
+    /// The luminance of the sun's disk times the solid angle it covers.
+    pub fn sun_power(&self) -> f64 {
+        luminance_of(&self.sun_radiance) * 2.0 * PI * (1.0 - SUN_ANGULAR_RADIUS.cos())
+    }
+
+    /// A direction toward the sun's disk, uniform over its solid angle.
+    pub fn sample_sun(&self, (r1, r2): (f64, f64)) -> Vector3 {
+        let z = 1.0 + r2 * (SUN_ANGULAR_RADIUS.cos() - 1.0);
+        let phi = 2.0 * PI * r1;
+        let sin_theta = (1.0 - z * z).max(0.0).sqrt();
+        Onb::from_w(self.sun_direction).to_world(Vector3::new(
+            phi.cos() * sin_theta,
+            phi.sin() * sin_theta,
+            z,
+        ))
+    }
+
+    /// The solid-angle density of `sample_sun` drawing `direction`.
+    pub fn sun_pdf(&self, direction: Vector3) -> f64 {
+        let cos_radius = SUN_ANGULAR_RADIUS.cos();
+        if direction.unit_vector().dot(self.sun_direction) >= cos_radius {
+            1.0 / (2.0 * PI * (1.0 - cos_radius))
+        } else {
+            0.0
+        }
+    }
+
+    /// The sky's luminance and chromaticity along the unit `direction`, or
+    /// `None` below the horizon.
+    fn sky_values(&self, direction: Vector3) -> Option<[f64; 3]> {
+        if direction.y <= 0.0 {
+            return None;
+        }
+        let cos_gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0);
+        let gamma = cos_gamma.acos();
+        Some([0, 1, 2].map(|k| self.zenith[k] * perez_function(self.perez[k], direction.y, gamma)))
+    }
+}
+
+/// The Perez sky distribution, for a direction `theta` from the zenith and
+/// `gamma` from the sun.
+fn perez_function([a, b, c, d, e]: [f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+    // keeps the horizon finite
+    let cos_theta = cos_theta.max(1e-3);
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+/// The wavelengths of the spectral samples, in nm.
+fn spectral_samples() -> [f64; SPECTRAL_SAMPLE_COUNT] {
+    let step = (LAMBDA_MAX - LAMBDA_MIN) / (SPECTRAL_SAMPLE_COUNT - 1) as f64;
+    std::array::from_fn(|i| LAMBDA_MIN + i as f64 * step)
+}
+
+fn daylight_basis_at(lambda: f64) -> [f64; 3] {
+    let t = ((lambda - 380.0) / 10.0).clamp(0.0, (DAYLIGHT_BASIS.len() - 1) as f64);
+    let index = (t as usize).min(DAYLIGHT_BASIS.len() - 2);
+    let fraction = t - index as f64;
+    [0, 1, 2].map(|k| {
+        DAYLIGHT_BASIS[index][k] * (1.0 - fraction) + DAYLIGHT_BASIS[index + 1][k] * fraction
+    })
+}
+
+/// The luminance of `spectrum`, where a flat spectrum of 1 has luminance 1.
+fn luminance_of(spectrum: &ColorSpectrum<SPECTRAL_SAMPLE_COUNT>) -> f64 {
+    let (weighted, total) = spectral_samples().iter().zip(spectrum.0.iter()).fold(
+        (0.0, 0.0),
+        |(weighted, total), (&lambda, &s)| {
+            let y_bar = cie_1931_xyz(lambda).1;
+            (weighted + s * y_bar, total + y_bar)
+        },
+    );
+    weighted / total
+}
+
+/// The sun's spectral radiance seen from the ground, with the sun
+/// `theta_sun` radians from the zenith.
+fn sun_radiance(theta_sun: f64, turbidity: f64) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+    // relative optical mass of the air the light crosses
+    let optical_mass =
+        1.0 / (theta_sun.cos() + 0.15 * (93.885 - theta_sun.to_degrees()).powf(-1.253));
+    let beta = 0.046_083_658_220_5 * turbidity - 0.045_860_259_285_22;
+
+    let blackbody = |lambda: f64| {
+        let c2 = 1.438_776_9e7; // nm·K
+        lambda.powi(-5) / ((c2 / (lambda * SUN_TEMPERATURE)).exp() - 1.0)
+    };
+    let outside = ColorSpectrum(Vector::new(spectral_samples().map(blackbody)));
+    let outside = outside * (SUN_LUMINANCE / luminance_of(&outside));
+
+    let transmittance = spectral_samples().map(|lambda| {
+        let micrometers = lambda / 1000.0;
+        let rayleigh = 0.008_735 * micrometers.powf(-4.08);
+        let aerosol = beta * micrometers.powf(-1.3);
+        (-optical_mass * (rayleigh + aerosol)).exp()
+    });
+    ColorSpectrum(outside.0 * Vector::new(transmittance))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shading::ColorRgb;
+
+    use super::*;
+
+    #[test]
+    fn sky_is_bluer_overhead_and_the_sun_redder_when_low() {
+        let sky = Sky::new(30.0, 45.0, 3.0);
+        let blueness = |spectrum| {
+            let [r, _, b]: [f64; 3] = ColorRgb::from(spectrum).into();
+            b / r
+        };
+        let zenith = sky.radiance(Vector3::new(0.0, 1.0, 0.0));
+        let near_sun = sky.radiance(Vector3::new(0.6, 0.6, -0.6));
+        assert!(blueness(zenith) > blueness(near_sun));
+        assert!(sky.sky_luminance(Vector3::new(0.0, -1.0, 0.0)) == 0.0);
+
+        let redness = |elevation| {
+            let sky = Sky::new(elevation, 0.0, 3.0);
+            1.0 / blueness(sky.sun_radiance)
+        };
+        assert!(redness(5.0) > redness(60.0));
+
+        // the sun outshines the sky many times over, but lights less than it
+        // covers
+        let sky = Sky::new(60.0, 0.0, 3.0);
+        let overhead = sky.sky_luminance(Vector3::new(0.0, 1.0, 0.0));
+        assert!(luminance_of(&sky.sun_radiance) > 1e4 * overhead);
+        assert!(sky.sun_power() < 100.0 * overhead);
+        let toward_sun = sky.sample_sun((0.3, 0.7));
+        assert!(sky.sun_pdf(toward_sun) > 0.0);
+        assert!(luminance_of(&sky.radiance(toward_sun)) > 1e4 * overhead);
+    }
+}
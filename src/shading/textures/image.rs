@@ -1,3 +1,5 @@
+use image::{DynamicImage, GenericImageView};
+
 use crate::{
     geometry::Point,
     shading::{ColorRgb, ColorSpectrum, color_spectrum::SPECTRAL_SAMPLE_COUNT},
@@ -15,50 +17,52 @@ pub struct ImageLinearF64 {
 
 impl ImageLinearF64 {
     pub fn from_filename(filename: &str) -> Result<Self, image::ImageError> {
-        let image = image::open(filename)?.into_rgba8();
-        let (width, height) = image.dimensions();
-
-        let mut data = Vec::with_capacity((width * height) as usize);
-        for p in image.pixels() {
-            let linear: [f64; 3] = ColorRgb::decode_from_srgb_u8(p).into();
-            data.push(linear);
-        }
+        Ok(Self::from_image(image::open(filename)?))
+    }
 
-        Ok(Self {
-            width,
-            height,
-            data,
-        })
+    /// Decodes an image file. HDR formats like Radiance `.hdr` and OpenEXR
+    /// keep their linear values, and everything else is decoded from sRGB.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, image::ImageError> {
+        Ok(Self::from_image(image::load_from_memory(bytes)?))
     }
 
-    pub fn from_srgb_bytes(bytes: &[u8]) -> Result<Self, image::ImageError> {
-        let image = image::load_from_memory(bytes)?.into_rgba8();
+    fn from_image(image: DynamicImage) -> Self {
         let (width, height) = image.dimensions();
+        let data = match image {
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => image
+                .into_rgb32f()
+                .pixels()
+                .map(|p| p.0.map(f64::from))
+                .collect(),
+            _ => image
+                .into_rgba8()
+                .pixels()
+                .map(|p| ColorRgb::decode_from_srgb_u8(p).into())
+                .collect(),
+        };
 
-        let mut data = Vec::with_capacity((width * height) as usize);
-        for p in image.pixels() {
-            let linear: [f64; 3] = ColorRgb::decode_from_srgb_u8(p).into();
-            data.push(linear);
-        }
-
-        Ok(Self {
+        Self {
             width,
             height,
             data,
-        })
+        }
     }
-}
 
-impl Texture for ImageLinearF64 {
-    fn value(&self, u: f64, v: f64, _p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+    /// The pixel at `(u, v)`, with `v` running up from the bottom row.
+    pub fn pixel_at(&self, u: f64, v: f64) -> [f64; 3] {
         let u = Interval::new(0.0, 1.0).clamp(u);
         let v = 1.0 - Interval::new(0.0, 1.0).clamp(v);
 
         let x = ((u * self.width as f64) as u32).clamp(0, self.width.saturating_sub(1));
         let y = ((v * self.height as f64) as u32).clamp(0, self.height.saturating_sub(1));
 
-        let idx = (y * self.width + x) as usize;
-        let pixel = self.data[idx];
+        self.data[(y * self.width + x) as usize]
+    }
+}
+
+impl Texture for ImageLinearF64 {
+    fn value(&self, u: f64, v: f64, _p: Point) -> ColorSpectrum<SPECTRAL_SAMPLE_COUNT> {
+        let pixel = self.pixel_at(u, v);
 
         ColorRgb::new(pixel[0], pixel[1], pixel[2]).into()
     }
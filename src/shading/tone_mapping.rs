@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+
+use super::ColorRgb;
+
+/// Display transform applied when turning linear radiance into an 8-bit image.
+///
+/// The stored accumulation is never tone mapped, so the same checkpoint can be
+/// previewed with different settings.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ToneMapping {
+    #[serde(default)]
+    pub operator: ToneMappingOperator,
+    /// Exposure adjustment in stops (EV), applied before the operator.
+    #[serde(default)]
+    pub exposure: f64,
+    /// The luminance that maps to pure white. Only used by `extended_reinhard`.
+    #[serde(default = "default_white_point")]
+    pub white_point: f64,
+}
+
+fn default_white_point() -> f64 {
+    4.0
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        Self {
+            operator: ToneMappingOperator::default(),
+            exposure: 0.0,
+            white_point: default_white_point(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToneMappingOperator {
+    /// Clamps each channel to [0, 1], which can shift hues of bright colors.
+    #[default]
+    Clamp,
+    /// Scales the color down so its largest channel is at most 1.
+    ScalingTruncation,
+    /// `L / (1 + L)` on luminance.
+    Reinhard,
+    /// Reinhard with a configurable white point.
+    ExtendedReinhard,
+    /// Stephen Hill's fit of the ACES reference rendering and output transforms.
+    AcesFilmic,
+    /// Troy Sobotka's AgX, via the polynomial approximation of its base contrast curve.
+    Agx,
+}
+
+/// Per-request changes to a render's configured tone mapping,
+/// e.g. `?tone_mapping=agx&exposure=1.5`. Unset fields keep their configured value.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ToneMappingOverride {
+    pub tone_mapping: Option<ToneMappingOperator>,
+    pub exposure: Option<f64>,
+    pub white_point: Option<f64>,
+}
+
+impl ToneMappingOverride {
+    pub fn apply_to(&self, tone_mapping: ToneMapping) -> ToneMapping {
+        ToneMapping {
+            operator: self.tone_mapping.unwrap_or(tone_mapping.operator),
+            exposure: self.exposure.unwrap_or(tone_mapping.exposure),
+            white_point: self.white_point.unwrap_or(tone_mapping.white_point),
+        }
+    }
+}
+
+impl ToneMapping {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.exposure.is_finite() {
+            return Err("Tone mapping exposure must be finite".to_string());
+        }
+        if !(self.white_point.is_finite() && self.white_point > 0.0) {
+            return Err("Tone mapping white point must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    /// Maps linear scene radiance to linear display values in [0, 1].
+    pub fn apply(&self, color: ColorRgb) -> ColorRgb {
+        let color = color.de_nan() * 2f64.powf(self.exposure);
+
+        match self.operator {
+            ToneMappingOperator::Clamp => map_channels(color, |c| c.clamp(0.0, 1.0)),
+            ToneMappingOperator::ScalingTruncation => color.scale_down(1.0),
+            ToneMappingOperator::Reinhard => scale_luminance(color, |l| l / (1.0 + l)),
+            ToneMappingOperator::ExtendedReinhard => {
+                let white_squared = self.white_point.max(f64::EPSILON).powi(2);
+                scale_luminance(color, |l| l * (1.0 + l / white_squared) / (1.0 + l))
+            }
+            ToneMappingOperator::AcesFilmic => aces_filmic(color),
+            ToneMappingOperator::Agx => agx(color),
+        }
+    }
+}
+
+fn map_channels(color: ColorRgb, f: impl Fn(f64) -> f64) -> ColorRgb {
+    ColorRgb::from(<[f64; 3]>::from(color).map(f))
+}
+
+fn multiply(matrix: &[[f64; 3]; 3], color: ColorRgb) -> ColorRgb {
+    let [r, g, b] = <[f64; 3]>::from(color);
+    ColorRgb::from(matrix.map(|row| row[0] * r + row[1] * g + row[2] * b))
+}
+
+fn scale_luminance(color: ColorRgb, f: impl Fn(f64) -> f64) -> ColorRgb {
+    let l = color.luminance();
+    if l <= 0.0 {
+        return ColorRgb::BLACK;
+    }
+    // hue is preserved, but saturated colors can still exceed 1 in a single channel
+    map_channels(color * (f(l) / l), |c| c.clamp(0.0, 1.0))
+}
+
+fn aces_filmic(color: ColorRgb) -> ColorRgb {
+    // sRGB => XYZ => D65_2_D60 => AP1 => RRT_SAT
+    const INPUT: [[f64; 3]; 3] = [
+        [0.59719, 0.35458, 0.04823],
+        [0.07600, 0.90834, 0.01566],
+        [0.02840, 0.13383, 0.83777],
+    ];
+    // ODT_SAT => XYZ => D60_2_D65 => sRGB
+    const OUTPUT: [[f64; 3]; 3] = [
+        [1.60475, -0.53108, -0.07367],
+        [-0.10208, 1.10813, -0.00605],
+        [-0.00327, -0.07276, 1.07602],
+    ];
+
+    let color = map_channels(multiply(&INPUT, color), |v| {
+        (v * (v + 0.0245786) - 0.000090537) / (v * (0.983729 * v + 0.4329510) + 0.238081)
+    });
+    map_channels(multiply(&OUTPUT, color), |c| c.clamp(0.0, 1.0))
+}
+
+fn agx(color: ColorRgb) -> ColorRgb {
+    const INSET: [[f64; 3]; 3] = [
+        [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+        [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+        [0.0423756549057051, 0.0784336, 0.879142973793104],
+    ];
+    const OUTSET: [[f64; 3]; 3] = [
+        [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+        [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+        [-0.0529716355144438, -0.0980434501171241, 1.15107367264116],
+    ];
+    const MIN_EV: f64 = -12.47393;
+    const MAX_EV: f64 = 4.026069;
+
+    let color = map_channels(multiply(&INSET, color), |c| {
+        let x = (c.max(1e-10).log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.00232
+    });
+
+    // the curve's output is display encoded, so undo that to stay in linear
+    map_channels(multiply(&OUTSET, color), |c| c.clamp(0.0, 1.0).powf(2.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPERATORS: [ToneMappingOperator; 6] = [
+        ToneMappingOperator::Clamp,
+        ToneMappingOperator::ScalingTruncation,
+        ToneMappingOperator::Reinhard,
+        ToneMappingOperator::ExtendedReinhard,
+        ToneMappingOperator::AcesFilmic,
+        ToneMappingOperator::Agx,
+    ];
+
+    fn tone_mapping(operator: ToneMappingOperator) -> ToneMapping {
+        ToneMapping {
+            operator,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn operators_keep_black_and_brighten_with_their_input() {
+        for operator in OPERATORS {
+            let tone_mapping = tone_mapping(operator);
+            let black: [f64; 3] = tone_mapping.apply(ColorRgb::BLACK).into();
+            assert!(
+                black.iter().all(|&c| c.abs() < 1e-9),
+                "{operator:?}: {black:?}"
+            );
+
+            let mut previous = 0.0;
+            for i in 1..=200 {
+                let input = ColorRgb::new(0.9, 0.5, 0.2) * (i as f64 * 0.05);
+                let output = tone_mapping.apply(input);
+                let [r, g, b]: [f64; 3] = output.into();
+                assert!(
+                    [r, g, b].iter().all(|c| (0.0..=1.0).contains(c)),
+                    "{operator:?}: {r} {g} {b}"
+                );
+                let luminance = output.luminance();
+                assert!(luminance >= previous - 1e-12, "{operator:?} at {i}");
+                previous = luminance;
+            }
+        }
+    }
+
+    #[test]
+    fn white_points_map_to_white() {
+        let white = ColorRgb::new(1.0, 1.0, 1.0);
+        let gray = |l: f64| white * l;
+
+        // plain Reinhard only approaches white, and halves a luminance of 1
+        let reinhard = tone_mapping(ToneMappingOperator::Reinhard);
+        assert!((reinhard.apply(gray(1.0)).luminance() - 0.5).abs() < 1e-9);
+        assert!(reinhard.apply(gray(1000.0)).luminance() < 1.0);
+
+        let extended = ToneMapping {
+            operator: ToneMappingOperator::ExtendedReinhard,
+            white_point: 3.0,
+            ..Default::default()
+        };
+        let at_white_point = extended.apply(gray(3.0)).luminance();
+        assert!((at_white_point - 1.0).abs() < 1e-9, "{at_white_point}");
+        assert!(extended.apply(gray(2.0)).luminance() < 1.0);
+
+        // the ACES curve levels off just past 1, so bright grays clip to white
+        let aces: [f64; 3] = tone_mapping(ToneMappingOperator::AcesFilmic)
+            .apply(gray(100.0))
+            .into();
+        assert!(aces.iter().all(|&c| c > 0.999), "{aces:?}");
+    }
+
+    #[test]
+    fn overrides_replace_only_what_they_set() {
+        let stored = ToneMapping {
+            operator: ToneMappingOperator::ExtendedReinhard,
+            exposure: -1.0,
+            white_point: 8.0,
+        };
+        assert_eq!(ToneMappingOverride::default().apply_to(stored), stored);
+
+        let tone_mapping_override = ToneMappingOverride {
+            tone_mapping: Some(ToneMappingOperator::Agx),
+            exposure: Some(1.5),
+            white_point: None,
+        };
+        assert_eq!(
+            tone_mapping_override.apply_to(stored),
+            ToneMapping {
+                operator: ToneMappingOperator::Agx,
+                exposure: 1.5,
+                white_point: 8.0,
+            }
+        );
+    }
+}
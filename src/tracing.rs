@@ -1,6 +1,30 @@
+mod aov;
+pub use aov::*;
+
+mod denoise;
+pub use denoise::*;
+
+mod export;
+pub use export::*;
+
+mod filter;
+pub use filter::*;
+
+mod integrator;
+pub use integrator::*;
+
+mod light_image;
+pub use light_image::*;
+
 mod parameters;
 pub use parameters::*;
 
+mod photon_map;
+pub use photon_map::*;
+
+mod pixel;
+pub use pixel::*;
+
 mod render_manager;
 pub use render_manager::*;
 
@@ -9,6 +33,9 @@ pub use resource_manager::*;
 
 mod texture_cache;
 
+mod sampler;
+pub use sampler::*;
+
 mod scene;
 pub use scene::*;
 
@@ -0,0 +1,353 @@
+use std::{collections::HashMap, sync::Arc};
+
+use bincode::{Decode, Encode};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    geometry::{Point, Vector3},
+    shading::{ColorRgb, ToneMapping, materials::Material},
+    utils::hash_u64s,
+};
+
+use super::PixelData;
+
+/// An arbitrary output variable: a buffer rendered alongside the image, for
+/// denoising and compositing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aov {
+    /// Reflectance of the surface the camera sees first.
+    Albedo,
+    /// Shading normal of the first surface, facing the camera.
+    Normal,
+    /// Distance from the camera to the first surface.
+    Depth,
+    /// World-space position of the first surface.
+    Position,
+    /// 1-based index of the first surface's named material, or 0.
+    MaterialId,
+    /// Light reaching the camera without bouncing, from emitters, media and
+    /// the background.
+    Emission,
+    /// Light reaching the camera after a single bounce.
+    Direct,
+    /// Light reaching the camera after two or more bounces.
+    Indirect,
+}
+
+/// What one camera sample saw, for the AOVs.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct AovSample {
+    /// `None` when the camera ray left the scene
+    pub first_hit: Option<FirstHit>,
+    pub emission: ColorRgb,
+    pub direct: ColorRgb,
+    pub indirect: ColorRgb,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct FirstHit {
+    pub albedo: ColorRgb,
+    pub normal: Vector3,
+    pub depth: f64,
+    pub position: Point,
+    pub material_id: u32,
+}
+
+/// The AOVs of a single pixel, averaged over the samples taken in it.
+///
+/// Surface values are averaged over the samples that hit something, and the
+/// light paths over all samples. The material ID isn't averaged; the first one
+/// seen is kept.
+#[derive(Debug, Copy, Clone, PartialEq, Default, Encode, Decode)]
+pub struct AovPixel {
+    pub sample_count: u32,
+    pub hit_count: u32,
+    pub albedo: ColorRgb,
+    pub normal: Vector3,
+    pub depth: f64,
+    pub position: Vector3,
+    pub material_id: u32,
+    pub emission: ColorRgb,
+    pub direct: ColorRgb,
+    pub indirect: ColorRgb,
+}
+
+impl AovPixel {
+    pub fn add(&mut self, sample: &AovSample) {
+        self.merge_with(&AovPixel::from_sample(sample));
+    }
+
+    fn from_sample(sample: &AovSample) -> Self {
+        let mut pixel = AovPixel {
+            sample_count: 1,
+            emission: sample.emission.de_nan(),
+            direct: sample.direct.de_nan(),
+            indirect: sample.indirect.de_nan(),
+            ..Default::default()
+        };
+        if let Some(hit) = sample.first_hit {
+            pixel.hit_count = 1;
+            pixel.albedo = hit.albedo.de_nan();
+            pixel.normal = hit.normal;
+            pixel.depth = hit.depth;
+            pixel.position = hit.position.as_vector3();
+            pixel.material_id = hit.material_id;
+        }
+        pixel
+    }
+
+    /// Combines the samples of two pixels, as if they had been taken together.
+    pub fn merge(&self, other: &AovPixel) -> AovPixel {
+        let mut merged = *self;
+        merged.merge_with(other);
+        merged
+    }
+
+    fn merge_with(&mut self, other: &AovPixel) {
+        fn mean<T>(a: T, n_a: u32, b: T, n_b: u32) -> T
+        where
+            T: std::ops::Mul<f64, Output = T> + std::ops::Add<Output = T>,
+        {
+            let n = (n_a + n_b) as f64;
+            a * (n_a as f64 / n) + b * (n_b as f64 / n)
+        }
+
+        let hits = (self.hit_count, other.hit_count);
+        if hits.0 + hits.1 > 0 {
+            self.albedo = mean(self.albedo, hits.0, other.albedo, hits.1);
+            self.normal = mean(self.normal, hits.0, other.normal, hits.1);
+            self.depth = mean(self.depth, hits.0, other.depth, hits.1);
+            self.position = mean(self.position, hits.0, other.position, hits.1);
+            if hits.0 == 0 {
+                self.material_id = other.material_id;
+            }
+        }
+
+        let samples = (self.sample_count, other.sample_count);
+        if samples.0 + samples.1 > 0 {
+            self.emission = mean(self.emission, samples.0, other.emission, samples.1);
+            self.direct = mean(self.direct, samples.0, other.direct, samples.1);
+            self.indirect = mean(self.indirect, samples.0, other.indirect, samples.1);
+        }
+
+        self.hit_count += other.hit_count;
+        self.sample_count += other.sample_count;
+    }
+
+    /// The raw value of an AOV in this pixel, e.g. for HDR output.
+    pub fn value(&self, aov: Aov) -> ColorRgb {
+        let vector = |v: Vector3| ColorRgb::new(v.x, v.y, v.z);
+        match aov {
+            Aov::Albedo => self.albedo,
+            Aov::Normal => vector(self.normal),
+            Aov::Depth => ColorRgb::new(self.depth, self.depth, self.depth),
+            Aov::Position => vector(self.position),
+            Aov::MaterialId => {
+                let id = self.material_id as f64;
+                ColorRgb::new(id, id, id)
+            }
+            Aov::Emission => self.emission,
+            Aov::Direct => self.direct,
+            Aov::Indirect => self.indirect,
+        }
+    }
+}
+
+impl Aov {
+    pub const ALL: [Aov; 8] = [
+        Aov::Albedo,
+        Aov::Normal,
+        Aov::Depth,
+        Aov::Position,
+        Aov::MaterialId,
+        Aov::Emission,
+        Aov::Direct,
+        Aov::Indirect,
+    ];
+
+    /// The name used for this AOV in URLs and file names.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Position => "position",
+            Aov::MaterialId => "material_id",
+            Aov::Emission => "emission",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+        }
+    }
+
+    /// Renders this AOV of the pixel data for viewing. Light paths are tone
+    /// mapped like the image itself, normals are mapped from [-1, 1] to
+    /// [0, 1], depth and position are scaled to the range they cover, and
+    /// every material ID gets its own color.
+    ///
+    /// Returns `None` when the pixel data has no AOVs.
+    pub fn as_image(
+        &self,
+        pixel_data: &PixelData,
+        dimensions: (u32, u32),
+        tone_mapping: &ToneMapping,
+    ) -> Option<RgbaImage> {
+        let aovs = pixel_data
+            .iter()
+            .filter_map(|(coords, pixel)| pixel.aovs.as_deref().map(|aovs| (*coords, aovs)))
+            .collect::<Vec<_>>();
+        if aovs.is_empty() {
+            return None;
+        }
+
+        // the range covered by the surfaces in view, for depth and position
+        let hits = aovs.iter().filter(|(_, aov)| aov.hit_count > 0);
+        let max_depth = hits.clone().map(|(_, aov)| aov.depth).fold(0.0, f64::max);
+        let (min_position, max_position) = hits.fold(
+            (
+                Vector3::ONE * f64::INFINITY,
+                Vector3::ONE * f64::NEG_INFINITY,
+            ),
+            |(min, max), (_, aov)| {
+                let p = aov.position;
+                (
+                    Vector3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                    Vector3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+                )
+            },
+        );
+
+        let mut image = RgbaImage::new(dimensions.0, dimensions.1);
+        for ((x, y), aov) in aovs {
+            let pixel = match self {
+                Aov::Emission | Aov::Direct | Aov::Indirect => {
+                    tone_mapping.apply(aov.value(*self)).encode_to_srgb_u8()
+                }
+                Aov::Albedo => aov.albedo.encode_to_srgb_u8(),
+                _ if aov.hit_count == 0 => ColorRgb::BLACK.as_rgba_u8(),
+                Aov::Normal => {
+                    ColorRgb::from_vector(aov.normal * 0.5 + Vector3::ONE * 0.5).as_rgba_u8()
+                }
+                Aov::Depth => {
+                    let depth = if max_depth > 0.0 {
+                        aov.depth / max_depth
+                    } else {
+                        0.0
+                    };
+                    ColorRgb::new(depth, depth, depth).as_rgba_u8()
+                }
+                Aov::Position => {
+                    let extent = max_position - min_position;
+                    let scale = |offset: f64, extent: f64| {
+                        if extent > 0.0 { offset / extent } else { 0.5 }
+                    };
+                    let offset = aov.position - min_position;
+                    ColorRgb::new(
+                        scale(offset.x, extent.x),
+                        scale(offset.y, extent.y),
+                        scale(offset.z, extent.z),
+                    )
+                    .as_rgba_u8()
+                }
+                Aov::MaterialId => id_color(aov.material_id).as_rgba_u8(),
+            };
+            image.put_pixel(x, y, pixel);
+        }
+
+        Some(image)
+    }
+}
+
+/// An arbitrary but stable, reasonably bright color for an ID. 0 is black.
+fn id_color(id: u32) -> ColorRgb {
+    if id == 0 {
+        return ColorRgb::BLACK;
+    }
+    let hash = hash_u64s(&[id as u64]);
+    let channel = |shift: u32| 0.25 + 0.75 * ((hash >> shift) & 0xFF) as f64 / 255.0;
+    ColorRgb::new(channel(0), channel(8), channel(16))
+}
+
+/// Numbers the named materials of a scene, so the material ID AOV can tell
+/// which one a surface uses. Materials are identified by their allocation, so
+/// inline materials (which have no name) get 0.
+#[derive(Debug, Clone, Default)]
+pub struct MaterialIds(Arc<HashMap<usize, u32>>);
+
+impl MaterialIds {
+    pub fn from_named<'a>(materials: impl IntoIterator<Item = &'a Arc<dyn Material>>) -> Self {
+        Self(Arc::new(
+            materials
+                .into_iter()
+                .enumerate()
+                .map(|(i, material)| (Self::key(material), i as u32 + 1))
+                .collect(),
+        ))
+    }
+
+    pub fn get(&self, material: &Arc<dyn Material>) -> u32 {
+        self.0.get(&Self::key(material)).copied().unwrap_or(0)
+    }
+
+    fn key(material: &Arc<dyn Material>) -> usize {
+        Arc::as_ptr(material) as *const () as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merging_matches_accumulating_together() {
+        let samples = [
+            AovSample {
+                first_hit: Some(FirstHit {
+                    albedo: ColorRgb::new(0.2, 0.4, 0.6),
+                    normal: Vector3::new(0.0, 1.0, 0.0),
+                    depth: 2.0,
+                    position: Point::new(1.0, 0.0, 0.0),
+                    material_id: 3,
+                }),
+                emission: ColorRgb::new(1.0, 1.0, 1.0),
+                ..Default::default()
+            },
+            AovSample {
+                direct: ColorRgb::new(0.5, 0.0, 0.0),
+                ..Default::default()
+            },
+            AovSample {
+                first_hit: Some(FirstHit {
+                    albedo: ColorRgb::new(0.4, 0.4, 0.4),
+                    normal: Vector3::new(1.0, 0.0, 0.0),
+                    depth: 4.0,
+                    position: Point::new(3.0, 0.0, 0.0),
+                    material_id: 5,
+                }),
+                indirect: ColorRgb::new(0.0, 0.3, 0.0),
+                ..Default::default()
+            },
+        ];
+
+        let mut all = AovPixel::default();
+        let mut first = AovPixel::default();
+        let mut second = AovPixel::default();
+        for (i, sample) in samples.iter().enumerate() {
+            all.add(sample);
+            if i < 2 {
+                first.add(sample);
+            } else {
+                second.add(sample);
+            }
+        }
+        let merged = first.merge(&second);
+
+        assert_eq!((merged.sample_count, merged.hit_count), (3, 2));
+        assert_eq!(merged.material_id, 3);
+        assert!((merged.depth - 3.0).abs() < 1e-12);
+        assert!((merged.position.x - all.position.x).abs() < 1e-12);
+        assert!((merged.emission.luminance() - 1.0 / 3.0).abs() < 1e-12);
+        assert!((merged.direct.luminance() - all.direct.luminance()).abs() < 1e-12);
+    }
+}
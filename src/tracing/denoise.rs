@@ -0,0 +1,320 @@
+use rayon::prelude::*;
+
+use crate::{geometry::Vector3, shading::ColorRgb};
+
+use super::{AovPixel, PixelData};
+
+/// Number of à-trous passes. Each pass doubles the spacing of its taps, so five
+/// passes of a 5×5 kernel reach 61 pixels across.
+const PASSES: u32 = 5;
+
+/// How many standard deviations of a pixel's noise two luminances may differ by
+/// and still be blended. Larger values blur more.
+const LUMINANCE_SIGMA: f64 = 4.0;
+/// The cosine between two normals is squared this many times, i.e. raised to
+/// the 64th power.
+const NORMAL_SQUARINGS: u32 = 6;
+/// How far two albedos may differ, per channel.
+const ALBEDO_SIGMA: f64 = 0.1;
+/// How far two depths may differ, relative to the depth and per pixel of tap
+/// spacing.
+const DEPTH_SIGMA: f64 = 0.02;
+
+/// The B3 spline weights of the à-trous kernel.
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// Albedos below this aren't divided out, so black surfaces don't blow up.
+const MIN_ALBEDO: f64 = 0.01;
+
+/// Denoises pixel data with an edge-avoiding à-trous wavelet filter, i.e. a
+/// stack of joint bilateral filters with growing footprints.
+///
+/// Neighbours are blended by how close their luminance is, relative to the
+/// pixel's sample variance. When the pixels have AOVs, similar normals, albedo
+/// and depth are also required, and the albedo is divided out before filtering
+/// and multiplied back in after, so textures stay sharp.
+///
+/// Only the colors of the returned pixels are changed.
+pub fn denoise(pixel_data: &PixelData, dimensions: (u32, u32)) -> PixelData {
+    let (width, height) = dimensions;
+    let index = |x: u32, y: u32| (y * width + x) as usize;
+
+    let mut guides: Vec<Option<Guide>> = vec![None; (width * height) as usize];
+    for ((x, y), pixel) in pixel_data {
+        if *x < width && *y < height {
+            guides[index(*x, *y)] = Some(Guide::new(
+                pixel.color,
+                pixel.aovs.as_deref(),
+                luminance_variance_of_mean(pixel.sample_count, pixel.luminance_m2),
+            ));
+        }
+    }
+
+    let mut colors = guides
+        .iter()
+        .map(|g| g.map(|g| (g.demodulate(g.color), g.variance)))
+        .collect::<Vec<_>>();
+
+    for pass in 0..PASSES {
+        let step = 1_i64 << pass;
+        let deviations = blurred_deviations(&colors, dimensions);
+        let luminances = colors
+            .iter()
+            .map(|c| c.map_or(0.0, |(color, _)| color.luminance()))
+            .collect::<Vec<_>>();
+        colors = (0..width * height)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = ((i % width) as i64, (i / width) as i64);
+                let i = i as usize;
+                let (Some(guide), Some(_)) = (&guides[i], colors[i]) else {
+                    return colors[i];
+                };
+                let luminance = luminances[i];
+
+                let mut color_sum = ColorRgb::BLACK;
+                let mut variance_sum = 0.0;
+                let mut weight_sum = 0.0;
+                for (ky, kernel_y) in KERNEL.iter().enumerate() {
+                    let qy = y + (ky as i64 - 2) * step;
+                    if qy < 0 || qy >= height as i64 {
+                        continue;
+                    }
+                    for (kx, kernel_x) in KERNEL.iter().enumerate() {
+                        let qx = x + (kx as i64 - 2) * step;
+                        if qx < 0 || qx >= width as i64 {
+                            continue;
+                        }
+                        let j = index(qx as u32, qy as u32);
+                        let (Some(other), Some((other_color, other_variance))) =
+                            (&guides[j], colors[j])
+                        else {
+                            continue;
+                        };
+
+                        // the noisier of the two decides, so a dark pixel whose few samples
+                        // all missed the light can still take in its brighter neighbours
+                        let luminance_scale =
+                            LUMINANCE_SIGMA * deviations[i].max(deviations[j]) + 1e-4;
+                        let luminance_exponent =
+                            (luminance - luminances[j]).abs() / luminance_scale;
+                        let weight = kernel_x
+                            * kernel_y
+                            * guide.weight(other, step as f64, luminance_exponent);
+
+                        color_sum += other_color * weight;
+                        variance_sum += other_variance * weight * weight;
+                        weight_sum += weight;
+                    }
+                }
+
+                // the pixel itself always has a weight, so the sum can't be 0
+                Some((
+                    color_sum / weight_sum,
+                    variance_sum / (weight_sum * weight_sum),
+                ))
+            })
+            .collect();
+    }
+
+    pixel_data
+        .iter()
+        .map(|(&(x, y), pixel)| {
+            let mut pixel = pixel.clone();
+            if x < width
+                && y < height
+                && let (Some(guide), Some((color, _))) = (&guides[index(x, y)], colors[index(x, y)])
+            {
+                pixel.color = guide.remodulate(color);
+            }
+            ((x, y), pixel)
+        })
+        .collect()
+}
+
+/// The standard deviations of the pixels, from their variances blurred over
+/// each pixel's 3×3 neighbourhood, which steadies the estimates of pixels with
+/// few samples.
+fn blurred_deviations(colors: &[Option<(ColorRgb, f64)>], dimensions: (u32, u32)) -> Vec<f64> {
+    const BLUR: [f64; 3] = [0.25, 0.5, 0.25];
+    let (width, height) = (dimensions.0 as i64, dimensions.1 as i64);
+
+    (0..width * height)
+        .into_par_iter()
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            let mut sum = 0.0;
+            let mut weight_sum = 0.0;
+            for (dy, blur_y) in BLUR.iter().enumerate() {
+                for (dx, blur_x) in BLUR.iter().enumerate() {
+                    let (qx, qy) = (x + dx as i64 - 1, y + dy as i64 - 1);
+                    if qx < 0 || qx >= width || qy < 0 || qy >= height {
+                        continue;
+                    }
+                    if let Some((_, variance)) = colors[(qy * width + qx) as usize] {
+                        sum += variance * blur_x * blur_y;
+                        weight_sum += blur_x * blur_y;
+                    }
+                }
+            }
+            if weight_sum > 0.0 {
+                (sum / weight_sum).sqrt()
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+/// The variance of a pixel's mean luminance, from its samples.
+fn luminance_variance_of_mean(sample_count: u32, luminance_m2: f64) -> f64 {
+    if sample_count < 2 {
+        return 0.0;
+    }
+    let n = sample_count as f64;
+    luminance_m2 / (n - 1.0) / n
+}
+
+/// What a pixel's neighbours are compared against.
+#[derive(Debug, Clone, Copy)]
+struct Guide {
+    color: ColorRgb,
+    variance: f64,
+    /// `None` when the pixel has no AOVs, or its samples all missed
+    surface: Option<Surface>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Surface {
+    albedo: ColorRgb,
+    normal: Vector3,
+    depth: f64,
+}
+
+impl Guide {
+    fn new(color: ColorRgb, aovs: Option<&AovPixel>, variance: f64) -> Self {
+        let surface = aovs.filter(|aovs| aovs.hit_count > 0).map(|aovs| Surface {
+            albedo: aovs.albedo,
+            normal: aovs.normal,
+            depth: aovs.depth,
+        });
+        let mut guide = Self {
+            color,
+            variance,
+            surface,
+        };
+        // the variance is of the color, so it has to be demodulated with it
+        let albedo_luminance = guide.demodulation().luminance();
+        guide.variance /= albedo_luminance * albedo_luminance;
+        guide
+    }
+
+    /// What the color is divided by before filtering.
+    fn demodulation(&self) -> ColorRgb {
+        let albedo: [f64; 3] = self
+            .surface
+            .map_or(ColorRgb::WHITE, |surface| surface.albedo)
+            .into();
+        albedo.map(|a| if a < MIN_ALBEDO { 1.0 } else { a }).into()
+    }
+
+    fn demodulate(&self, color: ColorRgb) -> ColorRgb {
+        let demodulation: [f64; 3] = self.demodulation().into();
+        let color: [f64; 3] = color.into();
+        [0, 1, 2].map(|c| color[c] / demodulation[c]).into()
+    }
+
+    fn remodulate(&self, color: ColorRgb) -> ColorRgb {
+        color * self.demodulation()
+    }
+
+    /// How much a neighbour `step` pixels away can be blended in, judging by
+    /// the surfaces the two pixels see, and the exponent the luminance
+    /// difference already calls for.
+    fn weight(&self, other: &Guide, step: f64, luminance_exponent: f64) -> f64 {
+        match (&self.surface, &other.surface) {
+            (Some(a), Some(b)) => {
+                let mut normal_weight = a.normal.dot(b.normal).max(0.0);
+                for _ in 0..NORMAL_SQUARINGS {
+                    normal_weight *= normal_weight;
+                }
+
+                let albedo_difference: [f64; 3] = (a.albedo - b.albedo).into();
+                let albedo_distance_squared = albedo_difference.iter().map(|d| d * d).sum::<f64>();
+                let depth_scale = DEPTH_SIGMA * a.depth.max(1e-6) * step;
+
+                // one exponential for all three terms
+                let exponent = luminance_exponent
+                    + albedo_distance_squared / (2.0 * ALBEDO_SIGMA * ALBEDO_SIGMA)
+                    + (a.depth - b.depth).abs() / depth_scale;
+                normal_weight * (-exponent).exp()
+            }
+            // background only blends with background
+            (None, None) => (-luminance_exponent).exp(),
+            _ => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracing::Pixel;
+
+    #[test]
+    fn denoising_smooths_noise_but_keeps_albedo_edges() {
+        let (width, height) = (32, 32);
+        let albedo = |x: u32| if x < width / 2 { 0.2 } else { 0.8 };
+
+        let mut rng_state = 12345_u64;
+        let mut noise = || {
+            rng_state = rng_state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (rng_state >> 11) as f64 / (1_u64 << 53) as f64 - 0.5
+        };
+
+        let mut pixel_data = PixelData::new();
+        for y in 0..height {
+            for x in 0..width {
+                // the mean of 16 samples spread uniformly over ±albedo / 2
+                let value = albedo(x) * (1.0 + noise() / 4.0);
+                let a = albedo(x);
+                pixel_data.insert(
+                    (x, y),
+                    Pixel {
+                        color: ColorRgb::new(value, value, value),
+                        weight_sum: 16.0,
+                        sample_count: 16,
+                        luminance_mean: value,
+                        luminance_m2: a * a / 12.0 * 15.0,
+                        aovs: Some(Box::new(AovPixel {
+                            sample_count: 16,
+                            hit_count: 16,
+                            albedo: ColorRgb::new(a, a, a),
+                            normal: Vector3::new(0.0, 0.0, 1.0),
+                            depth: 1.0,
+                            ..Default::default()
+                        })),
+                        photons: None,
+                    },
+                );
+            }
+        }
+
+        let denoised = denoise(&pixel_data, (width, height));
+
+        let error = |data: &PixelData| {
+            data.iter()
+                .map(|((x, _), pixel)| (pixel.color.luminance() - albedo(*x)).powi(2))
+                .sum::<f64>()
+        };
+        assert!(error(&denoised) < error(&pixel_data) * 0.1);
+
+        // the pixels either side of the edge keep their own albedo
+        let left = denoised[&(width / 2 - 1, height / 2)].color.luminance();
+        let right = denoised[&(width / 2, height / 2)].color.luminance();
+        assert!((left - 0.2).abs() < 0.05, "{left}");
+        assert!((right - 0.8).abs() < 0.1, "{right}");
+    }
+}
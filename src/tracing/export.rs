@@ -0,0 +1,271 @@
+use std::io::{Cursor, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::shading::ColorRgb;
+
+use super::RenderID;
+
+/// The file formats a checkpoint can be exported as.
+///
+/// `Png` is the tone-clamped 8-bit preview; every other format carries the
+/// linear, scene-referred radiance straight out of the accumulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckpointImageFormat {
+    #[default]
+    Png,
+    /// OpenEXR with 32-bit float colour channels
+    Exr,
+    /// OpenEXR with 16-bit half float colour channels
+    ExrHalf,
+    /// Radiance RGBE
+    Hdr,
+}
+
+impl CheckpointImageFormat {
+    /// Picks a format from a file extension, e.g. `exr` or `hdr`.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "exr" => Some(Self::Exr),
+            "hdr" => Some(Self::Hdr),
+            _ => None,
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Exr | Self::ExrHalf => "image/x-exr",
+            Self::Hdr => "image/vnd.radiance",
+        }
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        !matches!(self, Self::Png)
+    }
+}
+
+/// The linear radiance of a single checkpoint, along with the metadata that
+/// HDR exports carry with them.
+#[derive(Debug, Clone)]
+pub struct HdrImage {
+    pub render_id: RenderID,
+    pub iteration: u32,
+    pub width: u32,
+    pub height: u32,
+    /// linear radiance, row-major from the top-left pixel
+    pub pixels: Vec<ColorRgb>,
+    /// samples accumulated into each pixel, in the same order as `pixels`
+    pub samples_per_pixel: Vec<u32>,
+}
+
+impl HdrImage {
+    pub fn new(render_id: RenderID, iteration: u32, width: u32, height: u32) -> Self {
+        let pixel_count = width as usize * height as usize;
+        Self {
+            render_id,
+            iteration,
+            width,
+            height,
+            pixels: vec![ColorRgb::BLACK; pixel_count],
+            samples_per_pixel: vec![0; pixel_count],
+        }
+    }
+
+    pub fn index_of(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// The highest sample count of any pixel in the image.
+    pub fn total_samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Encodes the image in the given format.
+    ///
+    /// `Png` is not handled here, as it is produced from the tone-mapped
+    /// preview rather than from the raw radiance.
+    pub fn encode(&self, format: CheckpointImageFormat) -> Result<Vec<u8>, String> {
+        match format {
+            CheckpointImageFormat::Png => {
+                Err("PNG is not an HDR format, use the checkpoint preview instead".to_string())
+            }
+            CheckpointImageFormat::Exr => self.encode_exr(false),
+            CheckpointImageFormat::ExrHalf => self.encode_exr(true),
+            CheckpointImageFormat::Hdr => self.encode_radiance_hdr(),
+        }
+    }
+
+    fn encode_exr(&self, use_half: bool) -> Result<Vec<u8>, String> {
+        use exr::prelude::{
+            AnyChannel, AnyChannels, AttributeValue, Encoding, FlatSamples, Image, Layer,
+            LayerAttributes, SmallVec, Text, WritableImage, f16,
+        };
+
+        let channel = |name: &str, component: usize| {
+            let values = self
+                .pixels
+                .iter()
+                .map(|color| <[f64; 3]>::from(color.de_nan())[component] as f32);
+            let samples = if use_half {
+                FlatSamples::F16(values.map(f16::from_f32).collect())
+            } else {
+                FlatSamples::F32(values.collect())
+            };
+            AnyChannel::new(name, samples)
+        };
+
+        let sample_count_channel =
+            AnyChannel::new("samples", FlatSamples::U32(self.samples_per_pixel.clone()));
+
+        let mut attributes = LayerAttributes::named("luxide");
+        attributes.software_name = Some(Text::from("luxide"));
+        for (name, value) in [
+            ("luxide.render_id", self.render_id),
+            ("luxide.iteration", self.iteration),
+            ("luxide.samples_per_pixel", self.total_samples_per_pixel()),
+        ] {
+            attributes
+                .other
+                .insert(Text::from(name), AttributeValue::I32(value as i32));
+        }
+
+        let layer = Layer::new(
+            (self.width as usize, self.height as usize),
+            attributes,
+            Encoding::SMALL_LOSSLESS,
+            AnyChannels::sort(SmallVec::from_vec(vec![
+                channel("R", 0),
+                channel("G", 1),
+                channel("B", 2),
+                sample_count_channel,
+            ])),
+        );
+
+        let mut buffer = Vec::new();
+        Image::from_layer(layer)
+            .write()
+            .to_buffered(Cursor::new(&mut buffer))
+            .map_err(|e| e.to_string())?;
+
+        Ok(buffer)
+    }
+
+    fn encode_radiance_hdr(&self) -> Result<Vec<u8>, String> {
+        let mut buffer = Vec::new();
+
+        // radiance headers allow arbitrary `NAME=value` lines, which readers ignore
+        write!(
+            buffer,
+            "#?RADIANCE\n\
+             SOFTWARE=luxide\n\
+             FORMAT=32-bit_rle_rgbe\n\
+             LUXIDE_RENDER_ID={}\n\
+             LUXIDE_ITERATION={}\n\
+             LUXIDE_SAMPLES_PER_PIXEL={}\n\
+             \n\
+             -Y {} +X {}\n",
+            self.render_id,
+            self.iteration,
+            self.total_samples_per_pixel(),
+            self.height,
+            self.width,
+        )
+        .map_err(|e| e.to_string())?;
+
+        let width = self.width as usize;
+        for row in self.pixels.chunks(width.max(1)) {
+            let rgbe = row.iter().map(|color| to_rgbe(*color)).collect::<Vec<_>>();
+
+            // the run-length scanline format is only defined for these widths,
+            // otherwise pixels are written flat
+            if !(8..=0x7fff).contains(&width) {
+                rgbe.iter()
+                    .for_each(|pixel| buffer.extend_from_slice(pixel));
+                continue;
+            }
+
+            buffer.extend_from_slice(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8]);
+            for component in 0..4 {
+                // literal runs only: each run is its length followed by up to 128 bytes
+                for run in rgbe.chunks(128) {
+                    buffer.push(run.len() as u8);
+                    buffer.extend(run.iter().map(|pixel| pixel[component]));
+                }
+            }
+        }
+
+        Ok(buffer)
+    }
+}
+
+fn to_rgbe(color: ColorRgb) -> [u8; 4] {
+    let [r, g, b] = <[f64; 3]>::from(color.de_nan()).map(|c| c.max(0.0));
+    let max = r.max(g).max(b);
+    if max < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // split max into mantissa in [0.5, 1) and exponent, like C's frexp
+    let exponent = max.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2f64.powi(exponent);
+
+    [
+        (r * scale).min(255.0) as u8,
+        (g * scale).min(255.0) as u8,
+        (b * scale).min(255.0) as u8,
+        (exponent + 128).clamp(0, 255) as u8,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient_image() -> HdrImage {
+        let mut image = HdrImage::new(7, 3, 16, 4);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let index = image.index_of(x, y);
+                image.pixels[index] = ColorRgb::new(x as f64 * 0.5, y as f64 * 2.0, 10.0);
+                image.samples_per_pixel[index] = 30;
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn radiance_hdr_round_trips_above_one() {
+        let image = gradient_image();
+        let bytes = image.encode(CheckpointImageFormat::Hdr).unwrap();
+
+        let decoder = image::codecs::hdr::HdrDecoder::new(Cursor::new(bytes)).unwrap();
+        let metadata = decoder.metadata();
+        assert_eq!((metadata.width, metadata.height), (16, 4));
+        assert!(
+            metadata
+                .custom_attributes
+                .contains(&("LUXIDE_SAMPLES_PER_PIXEL".to_string(), "30".to_string()))
+        );
+
+        let decoded = decoder.read_image_hdr().unwrap();
+        let pixel = decoded[image.index_of(15, 3)];
+        assert!((pixel.0[0] - 7.5).abs() < 0.1);
+        assert!((pixel.0[1] - 6.0).abs() < 0.1);
+        assert!((pixel.0[2] - 10.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn exr_keeps_float_radiance() {
+        let image = gradient_image();
+        let bytes = image.encode(CheckpointImageFormat::Exr).unwrap();
+
+        let decoded = image::load_from_memory_with_format(&bytes, image::ImageFormat::OpenExr)
+            .unwrap()
+            .to_rgb32f();
+
+        assert_eq!(decoded.get_pixel(15, 3).0, [7.5, 6.0, 10.0]);
+    }
+}
@@ -0,0 +1,216 @@
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+/// The widest filter radius allowed, in pixels. Splats are buffered per tile
+/// out to the radius, so wider filters would cost memory with the square of it.
+const MAX_RADIUS: f64 = 8.0;
+
+/// Pixel reconstruction filter. Each sample is splatted into every pixel whose
+/// center lies within `radius` (in pixels) of it, weighted by the filter, and
+/// each pixel is the weighted average of the samples it received.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type", deny_unknown_fields)]
+pub enum Filter {
+    /// Equal weights within the radius. With the default radius of half a
+    /// pixel, each sample only counts towards the pixel it was taken in.
+    Box {
+        #[serde(default = "Filter::default_box_radius")]
+        radius: f64,
+    },
+    /// A Gaussian, shifted down so it reaches zero at the radius.
+    Gaussian {
+        #[serde(default = "Filter::default_gaussian_radius")]
+        radius: f64,
+        #[serde(default = "Filter::default_gaussian_sigma")]
+        sigma: f64,
+    },
+    /// The Mitchell-Netravali cubic. Its negative lobes sharpen edges, at the
+    /// cost of slight ringing.
+    Mitchell {
+        #[serde(default = "Filter::default_wide_radius")]
+        radius: f64,
+        #[serde(default = "Filter::default_mitchell_parameter")]
+        b: f64,
+        #[serde(default = "Filter::default_mitchell_parameter")]
+        c: f64,
+    },
+    /// The 4-term Blackman-Harris window, which behaves like a Gaussian with
+    /// less blur.
+    BlackmanHarris {
+        #[serde(default = "Filter::default_wide_radius")]
+        radius: f64,
+    },
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::Box {
+            radius: Self::default_box_radius(),
+        }
+    }
+}
+
+impl Filter {
+    fn default_box_radius() -> f64 {
+        0.5
+    }
+
+    fn default_gaussian_radius() -> f64 {
+        1.5
+    }
+
+    fn default_gaussian_sigma() -> f64 {
+        0.5
+    }
+
+    fn default_wide_radius() -> f64 {
+        2.0
+    }
+
+    fn default_mitchell_parameter() -> f64 {
+        1.0 / 3.0
+    }
+
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. }
+            | Filter::BlackmanHarris { radius } => radius,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let radius = self.radius();
+        if radius.is_nan() || radius <= 0.0 || radius > MAX_RADIUS {
+            return Err(format!(
+                "Filter radius must be positive and at most {MAX_RADIUS} pixels"
+            ));
+        }
+        if let Filter::Gaussian { sigma, .. } = *self
+            && (!sigma.is_finite() || sigma <= 0.0)
+        {
+            return Err("Gaussian filter sigma must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    /// The weight of a sample at offset `(dx, dy)`, in pixels, from a pixel center.
+    pub fn evaluate(&self, (dx, dy): (f64, f64)) -> f64 {
+        let radius = self.radius();
+        // the box is half-open, so a sample on the border between two pixels
+        // only counts towards one of them
+        if let Filter::Box { .. } = self {
+            let inside = |d: f64| (-radius..radius).contains(&d);
+            return if inside(dx) && inside(dy) { 1.0 } else { 0.0 };
+        }
+        if dx.abs() >= radius || dy.abs() >= radius {
+            return 0.0;
+        }
+
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+
+    fn evaluate_1d(&self, d: f64) -> f64 {
+        match *self {
+            Filter::Box { .. } => 1.0,
+            Filter::Gaussian { radius, sigma } => {
+                let gaussian = |d: f64| (-d * d / (2.0 * sigma * sigma)).exp();
+                (gaussian(d) - gaussian(radius)).max(0.0)
+            }
+            Filter::Mitchell { radius, b, c } => {
+                let x = (2.0 * d / radius).abs();
+                if x > 1.0 {
+                    ((-b - 6.0 * c) * x.powi(3)
+                        + (6.0 * b + 30.0 * c) * x.powi(2)
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2)
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                }
+            }
+            Filter::BlackmanHarris { radius } => {
+                let x = PI * d / radius;
+                0.35875 + 0.48829 * x.cos() + 0.14128 * (2.0 * x).cos() + 0.01168 * (3.0 * x).cos()
+            }
+        }
+    }
+
+    /// The range of pixel indices along one axis (inclusive) whose centers lie
+    /// within the radius of a sample at `position`, in continuous pixel coordinates.
+    pub fn pixel_range(&self, position: f64) -> (i64, i64) {
+        let radius = self.radius();
+        // pixel i is centered on i + 0.5
+        (
+            (position - 0.5 - radius).floor() as i64 + 1,
+            (position - 0.5 + radius).floor() as i64,
+        )
+    }
+
+    /// How many pixels past its own pixel a sample's splat can reach.
+    pub fn pixel_reach(&self) -> u32 {
+        (self.radius() - 0.5).ceil().max(0.0) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_range_covers_every_weighted_pixel() {
+        let filters = [
+            Filter::default(),
+            Filter::Box { radius: 1.0 },
+            Filter::Gaussian {
+                radius: 1.5,
+                sigma: 0.5,
+            },
+            Filter::Mitchell {
+                radius: 2.0,
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            },
+            Filter::BlackmanHarris { radius: 2.0 },
+        ];
+
+        for filter in filters {
+            for position in [3.0, 3.25, 3.5, 3.999] {
+                let (min, max) = filter.pixel_range(position);
+                assert!(max - min <= 2 * filter.pixel_reach() as i64);
+                for pixel in (min - 2)..=(max + 2) {
+                    let weight = filter.evaluate((position - (pixel as f64 + 0.5), 0.0));
+                    if weight != 0.0 {
+                        assert!((min..=max).contains(&pixel), "{filter:?} at {position}");
+                    }
+                }
+            }
+        }
+
+        // the default box keeps every sample in its own pixel
+        assert_eq!(Filter::default().pixel_range(3.999), (3, 3));
+        assert_eq!(Filter::default().pixel_range(3.0), (3, 3));
+    }
+
+    #[test]
+    fn radii_must_be_positive_and_small() {
+        assert!(
+            Filter::BlackmanHarris { radius: MAX_RADIUS }
+                .validate()
+                .is_ok()
+        );
+        for radius in [0.0, -1.0, f64::NAN, MAX_RADIUS + 0.1, 1e9, f64::INFINITY] {
+            assert!(Filter::Box { radius }.validate().is_err(), "{radius}");
+        }
+        let gaussian = Filter::Gaussian {
+            radius: 1.5,
+            sigma: f64::INFINITY,
+        };
+        assert!(gaussian.validate().is_err());
+    }
+}
@@ -0,0 +1,359 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{camera::Camera, geometry::Ray, shading::ColorRgb};
+
+use super::{
+    AovSample, DebugConfig, LightImage, PhotonMap, RenderParameters, Sampler, SceneWorld,
+    VisiblePoint,
+};
+
+/// The algorithm that finds light paths from the emitters to the camera, or
+/// the debug view rendered instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegratorType {
+    /// Paths are traced from the camera until they hit an emitter, or find
+    /// one by importance sampling.
+    #[default]
+    PathTracing,
+    /// Subpaths are traced from both the camera and the emitters, and every
+    /// way of joining them is weighted by multiple importance sampling. Finds
+    /// caustics and light reaching the scene through small openings far more
+    /// easily, at a higher cost per sample.
+    Bidirectional,
+    /// Stochastic progressive photon mapping. Camera paths stop at the first
+    /// surface that isn't a mirror or smooth glass, and every checkpoint
+    /// traces a pass of photons from the emitters that those points gather
+    /// within a radius that shrinks from one checkpoint to the next. Converges on
+    /// caustics that path tracing can't find, though it stays slightly
+    /// blurred until the radius has shrunk.
+    ProgressivePhotonMapping,
+    /// The shading normal of the first surface hit, facing the camera and
+    /// mapped from [-1, 1] to [0, 1].
+    Normals,
+    /// The texture coordinates of the first surface hit, as red and green.
+    Uv,
+    /// The reflectance of the first surface hit.
+    Albedo,
+    /// How much of the hemisphere above the first surface hit is open.
+    AmbientOcclusion,
+    /// A heatmap of the BVH nodes each camera ray visits.
+    BvhCost,
+    /// A heatmap of how many surfaces each path tracing sample hits.
+    PathLength,
+}
+
+impl IntegratorType {
+    /// Sets up the integrator for one round of samples. Some trace light from
+    /// the emitters first, drawing from random streams derived from `seed`.
+    pub fn create(
+        &self,
+        camera: &Camera,
+        scene_world: &SceneWorld,
+        parameters: &RenderParameters,
+        seed: u64,
+    ) -> Box<dyn Integrator> {
+        let debug = |view| {
+            Box::new(DebugIntegrator {
+                view,
+                config: parameters.debug,
+            })
+        };
+        match self {
+            IntegratorType::PathTracing => Box::new(PathTracer {
+                store_aovs: parameters.store_aovs,
+            }),
+            IntegratorType::Bidirectional => Box::new(BidirectionalTracer {
+                light_image: LightImage::new(parameters),
+                store_aovs: parameters.store_aovs,
+            }),
+            IntegratorType::ProgressivePhotonMapping => Box::new(PhotonMapper {
+                photon_map: camera.trace_photons(
+                    scene_world,
+                    parameters.photons_per_checkpoint(),
+                    seed,
+                ),
+                store_aovs: parameters.store_aovs,
+            }),
+            IntegratorType::Normals => debug(DebugView::Normals),
+            IntegratorType::Uv => debug(DebugView::Uv),
+            IntegratorType::Albedo => debug(DebugView::Albedo),
+            IntegratorType::AmbientOcclusion => debug(DebugView::AmbientOcclusion),
+            IntegratorType::BvhCost => debug(DebugView::BvhCost),
+            IntegratorType::PathLength => debug(DebugView::PathLength),
+        }
+    }
+}
+
+/// What a camera sample found.
+#[derive(Debug, Clone)]
+pub struct IntegratorSample {
+    pub color: ColorRgb,
+    pub aovs: AovSample,
+    /// where the sample gathers photons, for photon mapping
+    pub visible_point: Option<VisiblePoint>,
+}
+
+impl From<(ColorRgb, AovSample)> for IntegratorSample {
+    fn from((color, aovs): (ColorRgb, AovSample)) -> Self {
+        Self {
+            color,
+            aovs,
+            visible_point: None,
+        }
+    }
+}
+
+/// Turns camera rays into colors, for one round of samples.
+pub trait Integrator: Send + Sync {
+    fn sample(
+        &self,
+        camera: &Camera,
+        ray: Ray,
+        scene_world: &SceneWorld,
+        sampler: &mut dyn Sampler,
+    ) -> IntegratorSample;
+
+    /// Light the round's samples splatted onto any pixel, besides their own.
+    fn light_image(&self) -> Option<&LightImage> {
+        None
+    }
+
+    /// Photons the round's visible points gather.
+    fn photon_map(&self) -> Option<&PhotonMap> {
+        None
+    }
+}
+
+struct PathTracer {
+    store_aovs: bool,
+}
+
+impl Integrator for PathTracer {
+    fn sample(
+        &self,
+        camera: &Camera,
+        ray: Ray,
+        scene_world: &SceneWorld,
+        sampler: &mut dyn Sampler,
+    ) -> IntegratorSample {
+        if self.store_aovs {
+            camera.ray_color_with_aovs(ray, scene_world, sampler).into()
+        } else {
+            let color = camera.ray_color(ray, scene_world, sampler);
+            (color, AovSample::default()).into()
+        }
+    }
+}
+
+struct BidirectionalTracer {
+    light_image: LightImage,
+    store_aovs: bool,
+}
+
+impl Integrator for BidirectionalTracer {
+    fn sample(
+        &self,
+        camera: &Camera,
+        ray: Ray,
+        scene_world: &SceneWorld,
+        sampler: &mut dyn Sampler,
+    ) -> IntegratorSample {
+        camera
+            .ray_color_bidirectional(
+                ray,
+                scene_world,
+                sampler,
+                &self.light_image,
+                self.store_aovs,
+            )
+            .into()
+    }
+
+    fn light_image(&self) -> Option<&LightImage> {
+        Some(&self.light_image)
+    }
+}
+
+struct PhotonMapper {
+    photon_map: PhotonMap,
+    store_aovs: bool,
+}
+
+impl Integrator for PhotonMapper {
+    fn sample(
+        &self,
+        camera: &Camera,
+        ray: Ray,
+        scene_world: &SceneWorld,
+        sampler: &mut dyn Sampler,
+    ) -> IntegratorSample {
+        let (color, aovs, visible_point) =
+            camera.ray_color_photon_mapping(ray, scene_world, sampler, self.store_aovs);
+        IntegratorSample {
+            color,
+            aovs,
+            visible_point,
+        }
+    }
+
+    fn photon_map(&self) -> Option<&PhotonMap> {
+        Some(&self.photon_map)
+    }
+}
+
+/// What a debug integrator shows instead of the light reaching the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugView {
+    Normals,
+    Uv,
+    Albedo,
+    AmbientOcclusion,
+    BvhCost,
+    PathLength,
+}
+
+struct DebugIntegrator {
+    view: DebugView,
+    config: DebugConfig,
+}
+
+impl Integrator for DebugIntegrator {
+    fn sample(
+        &self,
+        camera: &Camera,
+        ray: Ray,
+        scene_world: &SceneWorld,
+        sampler: &mut dyn Sampler,
+    ) -> IntegratorSample {
+        camera
+            .ray_color_debug(self.view, &self.config, ray, scene_world, sampler)
+            .into()
+    }
+}
+
+/// A color running from blue through cyan, green and yellow to red as `t`
+/// goes from 0 to 1.
+pub fn heatmap(t: f64) -> ColorRgb {
+    const STOPS: [[f64; 3]; 5] = [
+        [0.0, 0.0, 1.0],
+        [0.0, 1.0, 1.0],
+        [0.0, 1.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [1.0, 0.0, 0.0],
+    ];
+    let position = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let i = (position as usize).min(STOPS.len() - 2);
+    let fraction = position - i as f64;
+    [0, 1, 2]
+        .map(|c| STOPS[i][c] + (STOPS[i + 1][c] - STOPS[i][c]) * fraction)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        deserialization::RenderConfig,
+        geometry::{Geometric, Point, Vector3, primitives::Sphere},
+        shading::{materials::Lambertian, textures::SolidColor},
+        tracing::IndependentSampler,
+        utils::seed_thread_rng,
+    };
+
+    use super::*;
+
+    /// A white sphere 2 in front of the origin, alone in the dark, and a ray
+    /// from the origin straight at it.
+    fn lone_sphere() -> (SceneWorld, Ray) {
+        let white = Arc::new(Lambertian::new(
+            Arc::new(SolidColor::WHITE),
+            Arc::new(SolidColor::BLACK),
+        ));
+        let sphere: Arc<dyn Geometric> =
+            Arc::new(Sphere::new(Point::new(0.0, 0.0, -2.0), 0.5, white));
+        let scene_world = SceneWorld::from_geometrics(&vec![sphere], &[], false);
+        let ray = Ray::new(Point::ORIGIN, Vector3::new(0.0, 0.0, -1.0), 0.0);
+        (scene_world, ray)
+    }
+
+    fn parameters() -> RenderParameters {
+        let config: RenderConfig =
+            serde_json::from_str(include_str!("../../configs/template.json")).unwrap();
+        config.parameters
+    }
+
+    #[test]
+    fn the_heatmap_runs_from_blue_to_red() {
+        assert_eq!(heatmap(0.0), ColorRgb::new(0.0, 0.0, 1.0));
+        assert_eq!(heatmap(0.5), ColorRgb::new(0.0, 1.0, 0.0));
+        assert_eq!(heatmap(1.0), ColorRgb::new(1.0, 0.0, 0.0));
+        assert_eq!(heatmap(-1.0), heatmap(0.0));
+        assert_eq!(heatmap(2.0), heatmap(1.0));
+    }
+
+    #[test]
+    fn debug_views_show_the_first_surface_hit() {
+        let (scene_world, ray) = lone_sphere();
+        let camera = Camera::default();
+        let config = DebugConfig::default();
+        let color = |view| {
+            camera
+                .ray_color_debug(view, &config, ray, &scene_world, &mut IndependentSampler)
+                .0
+        };
+        seed_thread_rng(3);
+
+        // the sphere faces the ray along +z there
+        assert_eq!(color(DebugView::Normals), ColorRgb::new(0.5, 0.5, 1.0));
+        // white reflectance converts to just over 1
+        let [r, g, b]: [f64; 3] = color(DebugView::Albedo).into();
+        assert!([r, g, b].iter().all(|c| (0.99..=1.001).contains(c)));
+        // nothing else is there to occlude it
+        assert_eq!(color(DebugView::AmbientOcclusion), ColorRgb::WHITE);
+        // one surface hit is somewhere short of the hot end
+        let path_length = color(DebugView::PathLength);
+        assert!(path_length != heatmap(0.0) && path_length != heatmap(1.0));
+
+        let miss = Ray::new(Point::ORIGIN, Vector3::new(0.0, 0.0, 1.0), 0.0);
+        let (color, aovs) = camera.ray_color_debug(
+            DebugView::Albedo,
+            &config,
+            miss,
+            &scene_world,
+            &mut IndependentSampler,
+        );
+        assert_eq!(color, ColorRgb::BLACK);
+        assert!(aovs.first_hit.is_none());
+    }
+
+    #[test]
+    fn every_light_transport_integrator_stores_aovs_when_asked() {
+        let (scene_world, ray) = lone_sphere();
+        let camera = Camera::default();
+        seed_thread_rng(11);
+        for integrator in [
+            IntegratorType::PathTracing,
+            IntegratorType::Bidirectional,
+            IntegratorType::ProgressivePhotonMapping,
+        ] {
+            for store_aovs in [false, true] {
+                let parameters = RenderParameters {
+                    store_aovs,
+                    ..parameters()
+                };
+                let sample = integrator
+                    .create(&camera, &scene_world, &parameters, 1)
+                    .sample(&camera, ray, &scene_world, &mut IndependentSampler);
+
+                let first_hit = sample.aovs.first_hit;
+                assert_eq!(first_hit.is_some(), store_aovs, "{integrator:?}");
+                if let Some(first_hit) = first_hit {
+                    assert!((first_hit.depth - 1.5).abs() < 1e-9, "{integrator:?}");
+                    assert!((first_hit.normal - Vector3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+                }
+            }
+        }
+    }
+}
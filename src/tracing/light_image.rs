@@ -0,0 +1,151 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::shading::ColorRgb;
+
+use super::RenderParameters;
+
+/// Fixed-point units per unit of radiance. Sums of integers come out the same
+/// whatever order the threads add them in, which keeps seeded renders
+/// reproducible. This leaves 32 bits for whole units, and resolves splats
+/// down to a few times 1e-10.
+const FIXED_POINT_SCALE: f64 = (1_u64 << 32) as f64;
+
+/// Light that light subpaths carry straight to the camera, for one round of
+/// bidirectional path tracing.
+///
+/// Each connection is splatted into the pixel it lands in, and every pixel is
+/// averaged over all the light subpaths traced in the round, not just those
+/// that reached it. Splats aren't weighted by the render's filter, so light
+/// found this way always looks as if it went through a box filter.
+#[derive(Debug)]
+pub struct LightImage {
+    width: u32,
+    height: u32,
+    /// pixels whose camera samples start light subpaths
+    sampled_pixel_count: u64,
+    /// unsigned, as connections carry light from the emitters and their
+    /// colors are never negative
+    sums: Vec<[AtomicU64; 3]>,
+    path_count: AtomicU64,
+}
+
+impl LightImage {
+    pub fn new(parameters: &RenderParameters) -> Self {
+        let (width, height) = parameters.image_dimensions;
+        Self {
+            width,
+            height,
+            sampled_pixel_count: parameters.render_region().pixel_count(),
+            sums: (0..u64::from(width) * u64::from(height))
+                .map(|_| Default::default())
+                .collect(),
+            path_count: AtomicU64::new(0),
+        }
+    }
+
+    /// How many pixels light subpaths are shared between. A light subpath
+    /// connecting to the camera lands in any of them, where a camera sample
+    /// only ever lands in its own.
+    pub fn sampled_pixel_count(&self) -> u64 {
+        self.sampled_pixel_count
+    }
+
+    /// Counts a light subpath, whether or not it reached the camera.
+    pub fn add_path(&self) {
+        self.path_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Adds light arriving at `film_position`, in continuous pixel coordinates.
+    pub fn splat(&self, film_position: (f64, f64), color: ColorRgb) {
+        let (x, y) = (film_position.0.floor(), film_position.1.floor());
+        if x < 0.0 || y < 0.0 || x >= self.width as f64 || y >= self.height as f64 {
+            return;
+        }
+
+        let color: [f64; 3] = color.de_nan().into();
+        let sums = &self.sums[y as usize * self.width as usize + x as usize];
+        for (sum, value) in sums.iter().zip(color) {
+            // the cast saturates too, for values past the range
+            let value = (value * FIXED_POINT_SCALE).round() as u64;
+            // a firefly saturates its pixel rather than wrapping around
+            let _ = sum.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |sum| {
+                Some(sum.saturating_add(value))
+            });
+        }
+    }
+
+    /// The light splatted into pixel `(x, y)`, per light subpath.
+    pub fn pixel(&self, (x, y): (u32, u32)) -> ColorRgb {
+        let path_count = self.path_count.load(Ordering::Relaxed);
+        if path_count == 0 || x >= self.width || y >= self.height {
+            return ColorRgb::BLACK;
+        }
+
+        let sums = &self.sums[(y * self.width + x) as usize];
+        let [r, g, b] = sums
+            .each_ref()
+            .map(|sum| sum.load(Ordering::Relaxed) as f64 / FIXED_POINT_SCALE);
+        ColorRgb::new(r, g, b) / path_count as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{deserialization::RenderConfig, tracing::Region};
+
+    use super::*;
+
+    fn parameters(image_dimensions: (u32, u32)) -> RenderParameters {
+        let config: RenderConfig =
+            serde_json::from_str(include_str!("../../configs/template.json")).unwrap();
+        RenderParameters {
+            image_dimensions,
+            region: None,
+            ..config.parameters
+        }
+    }
+
+    #[test]
+    fn faint_and_bright_splats_are_kept() {
+        let light_image = LightImage::new(&parameters((2, 1)));
+        for _ in 0..1000 {
+            light_image.add_path();
+            light_image.splat((0.5, 0.5), ColorRgb::new(1e-8, 0.0, 0.0));
+            light_image.splat((1.5, 0.5), ColorRgb::new(0.0, 1e6, 0.5));
+        }
+
+        let [r, _, _]: [f64; 3] = light_image.pixel((0, 0)).into();
+        assert!((r - 1e-8).abs() < 1e-9, "{r}");
+        let [_, g, b]: [f64; 3] = light_image.pixel((1, 0)).into();
+        assert!((g - 1e6).abs() < 1e-6 && (b - 0.5).abs() < 1e-9, "{g} {b}");
+    }
+
+    #[test]
+    fn light_is_averaged_over_every_light_path_traced() {
+        let mut parameters = parameters((4, 2));
+        parameters.region = Some(Region {
+            origin: (1, 0),
+            dimensions: (2, 2),
+        });
+        let light_image = LightImage::new(&parameters);
+        assert_eq!(light_image.sampled_pixel_count(), 4);
+
+        for _ in 0..4 {
+            light_image.add_path();
+        }
+        light_image.splat((1.5, 0.5), ColorRgb::new(2.0, 1.0, 0.5));
+        light_image.splat((1.25, 0.75), ColorRgb::new(2.0, 1.0, 0.5));
+        // off the image, so lost, though its path still counts
+        light_image.splat((-0.5, 0.5), ColorRgb::new(8.0, 8.0, 8.0));
+        light_image.splat((4.0, 1.0), ColorRgb::new(8.0, 8.0, 8.0));
+
+        let [r, g, b]: [f64; 3] = light_image.pixel((1, 0)).into();
+        assert!((r - 1.0).abs() < 1e-9 && (g - 0.5).abs() < 1e-9 && (b - 0.25).abs() < 1e-9);
+        let total = (0..4)
+            .flat_map(|x| (0..2).map(move |y| (x, y)))
+            .map(|pixel| light_image.pixel(pixel).luminance())
+            .sum::<f64>();
+        assert!((total - light_image.pixel((1, 0)).luminance()).abs() < 1e-9);
+        assert_eq!(light_image.pixel((9, 9)), ColorRgb::BLACK);
+    }
+}
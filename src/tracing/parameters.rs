@@ -1,4 +1,7 @@
-use crate::tracing::SceneWorld;
+use crate::{
+    shading::{ToneMapping, ToneMappingOperator},
+    tracing::{Filter, IntegratorType, SamplerType, SceneWorld},
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Debug, Clone)]
@@ -23,6 +26,286 @@ pub struct RenderParameters {
     /// The integrator normalizes these internally — raw values are fine.
     #[serde(default)]
     pub importance_sampling: ImportanceSamplingConfig,
+    /// Display transform for checkpoint images. When omitted,
+    /// `use_scaling_truncation` picks between the legacy clamp and
+    /// scaling truncation operators.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tone_mapping: Option<ToneMapping>,
+    /// If set, samples are concentrated on noisy pixels instead of being
+    /// spread evenly, and converged pixels stop being sampled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adaptive_sampling: Option<AdaptiveSamplingConfig>,
+    /// Where the random numbers for each pixel sample come from.
+    #[serde(default)]
+    pub sampler: SamplerType,
+    /// If set, every pixel of every checkpoint draws from a random stream
+    /// derived from this seed, so the same config always renders the same
+    /// pixels, whatever the thread count.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// How samples are weighted into the pixels around them.
+    #[serde(default)]
+    pub filter: Filter,
+    /// If set, the render is a sequence of frames, each rendered to
+    /// `total_checkpoints` checkpoints one after the other. Only the latest
+    /// checkpoint of each frame is kept, in place of `saved_checkpoint_limit`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation: Option<AnimationConfig>,
+    /// If set, only this crop window of the image is rendered, and pixels
+    /// outside it are left empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<Region>,
+    /// If set, AOVs (first-hit albedo, normal, depth, position and material ID,
+    /// and the emission, direct and indirect light) are stored with each
+    /// checkpoint. The bidirectional and photon mapping integrators leave the
+    /// light splatted by light subpaths and brought by photons out of them.
+    #[serde(default)]
+    pub store_aovs: bool,
+    /// How light paths are found, or which debug view is rendered instead.
+    #[serde(default)]
+    pub integrator: IntegratorType,
+    /// Photon passes of the `progressive_photon_mapping` integrator.
+    #[serde(default)]
+    pub photon_mapping: PhotonMappingConfig,
+    /// Settings of the debug integrators.
+    #[serde(default)]
+    pub debug: DebugConfig,
+}
+
+impl RenderParameters {
+    /// The tone mapping checkpoint images are displayed with by default.
+    pub fn effective_tone_mapping(&self) -> ToneMapping {
+        self.tone_mapping.unwrap_or_else(|| ToneMapping {
+            operator: if self.use_scaling_truncation {
+                ToneMappingOperator::ScalingTruncation
+            } else {
+                ToneMappingOperator::Clamp
+            },
+            ..Default::default()
+        })
+    }
+
+    /// The pixels each checkpoint renders: the crop window, or else the whole image.
+    pub fn render_region(&self) -> Region {
+        self.region.unwrap_or(Region {
+            origin: (0, 0),
+            dimensions: self.image_dimensions,
+        })
+    }
+
+    /// Photons traced each checkpoint of a progressive photon mapping render.
+    pub fn photons_per_checkpoint(&self) -> u32 {
+        self.photon_mapping
+            .photons_per_checkpoint
+            .unwrap_or_else(|| self.render_region().pixel_count().min(u32::MAX as u64) as u32)
+    }
+
+    /// How many frames the render has; a still image is a single frame.
+    pub fn frame_count(&self) -> u32 {
+        self.animation.map_or(1, |animation| animation.frame_count)
+    }
+
+    /// Checkpoint iterations across all frames, i.e. the iteration the render
+    /// finishes at.
+    pub fn total_iterations(&self) -> u32 {
+        self.frame_count() * self.total_checkpoints
+    }
+
+    /// Which frame a checkpoint iteration belongs to. Iterations are numbered
+    /// across all frames, so frame 0 takes the first `total_checkpoints`,
+    /// frame 1 the next, and so on. Iteration 0, before any checkpoint, is
+    /// taken as the first.
+    pub fn frame_iteration(&self, iteration: u32) -> FrameIteration {
+        let total_checkpoints = self.total_checkpoints.max(1);
+        let index = iteration.saturating_sub(1);
+        FrameIteration {
+            frame: index / total_checkpoints,
+            checkpoint: index % total_checkpoints + 1,
+        }
+    }
+
+    /// The scene time `frame` starts at. Camera shutters open relative to it.
+    pub fn frame_start_time(&self, frame: u32) -> f64 {
+        self.animation.map_or(0.0, |animation| {
+            animation.start_time + frame as f64 * animation.frame_duration
+        })
+    }
+}
+
+/// A rectangle of pixels within the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Region {
+    pub origin: (u32, u32),
+    pub dimensions: (u32, u32),
+}
+
+impl Region {
+    pub fn pixel_count(&self) -> u64 {
+        u64::from(self.dimensions.0) * u64::from(self.dimensions.1)
+    }
+
+    /// Makes sure the region has pixels, all of which lie within `bounds`.
+    pub fn validate(&self, bounds: Region) -> Result<(), String> {
+        if self.dimensions.0 == 0 || self.dimensions.1 == 0 {
+            return Err("Region must be at least one pixel wide and tall".to_string());
+        }
+
+        let end = |region: &Region| {
+            (
+                u64::from(region.origin.0) + u64::from(region.dimensions.0),
+                u64::from(region.origin.1) + u64::from(region.dimensions.1),
+            )
+        };
+        let (end_x, end_y) = end(self);
+        let (bounds_end_x, bounds_end_y) = end(&bounds);
+        if self.origin.0 < bounds.origin.0
+            || self.origin.1 < bounds.origin.1
+            || end_x > bounds_end_x
+            || end_y > bounds_end_y
+        {
+            return Err(format!(
+                "Region at {:?} of size {:?} doesn't fit within {:?} of size {:?}",
+                self.origin, self.dimensions, bounds.origin, bounds.dimensions
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// A checkpoint iteration, located within its frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameIteration {
+    /// Index of the frame, starting at 0.
+    pub frame: u32,
+    /// The checkpoint within the frame, starting at 1.
+    pub checkpoint: u32,
+}
+
+/// Configuration for rendering a sequence of frames.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnimationConfig {
+    pub frame_count: u32,
+    /// Scene time between the starts of consecutive frames, in the same units
+    /// as keyframe times.
+    #[serde(default = "AnimationConfig::default_frame_duration")]
+    pub frame_duration: f64,
+    /// Scene time the first frame starts at.
+    #[serde(default)]
+    pub start_time: f64,
+}
+
+impl AnimationConfig {
+    fn default_frame_duration() -> f64 {
+        1.0
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.frame_count == 0 {
+            return Err("Animations must have at least one frame".to_string());
+        }
+        if !self.frame_duration.is_finite() || self.frame_duration <= 0.0 {
+            return Err("Animation frame duration must be positive".to_string());
+        }
+        if !self.start_time.is_finite() {
+            return Err("Animation start time must be finite".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Configuration for the photon passes of progressive photon mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PhotonMappingConfig {
+    /// Photons traced from the emitters each checkpoint. Defaults to one per
+    /// pixel rendered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub photons_per_checkpoint: Option<u32>,
+    /// The radius, in world units, that pixels gather photons within at
+    /// first. Defaults to the width of two pixels at the first surface each
+    /// pixel sees.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_radius: Option<f64>,
+    /// The fraction of each pass's photons a pixel keeps as it shrinks its
+    /// radius, between 0 and 1. Lower values shrink the radius faster, which
+    /// sharpens the image sooner but leaves it noisier.
+    #[serde(default = "PhotonMappingConfig::default_alpha")]
+    pub alpha: f64,
+}
+
+impl PhotonMappingConfig {
+    fn default_alpha() -> f64 {
+        2.0 / 3.0
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.photons_per_checkpoint == Some(0) {
+            return Err("Photon mapping must trace at least one photon per checkpoint".to_string());
+        }
+        if let Some(radius) = self.initial_radius
+            && !(radius.is_finite() && radius > 0.0)
+        {
+            return Err("Photon mapping initial radius must be positive".to_string());
+        }
+        if !(self.alpha > 0.0 && self.alpha < 1.0) {
+            return Err("Photon mapping alpha must be between 0 and 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for PhotonMappingConfig {
+    fn default() -> Self {
+        Self {
+            photons_per_checkpoint: None,
+            initial_radius: None,
+            alpha: Self::default_alpha(),
+        }
+    }
+}
+
+/// Configuration for the debug integrators.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DebugConfig {
+    /// How far `ambient_occlusion` looks for occluders. Defaults to any
+    /// distance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ambient_occlusion_distance: Option<f64>,
+    /// The BVH nodes a camera ray visits that `bvh_cost` shows at the hot
+    /// end of its scale.
+    #[serde(default = "DebugConfig::default_max_bvh_cost")]
+    pub max_bvh_cost: u32,
+}
+
+impl DebugConfig {
+    fn default_max_bvh_cost() -> u32 {
+        100
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(distance) = self.ambient_occlusion_distance
+            && (distance.is_nan() || distance <= 0.0)
+        {
+            return Err("Ambient occlusion distance must be positive".to_string());
+        }
+        if self.max_bvh_cost == 0 {
+            return Err("Maximum BVH cost must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for DebugConfig {
+    fn default() -> Self {
+        Self {
+            ambient_occlusion_distance: None,
+            max_bvh_cost: Self::default_max_bvh_cost(),
+        }
+    }
 }
 
 /// Configuration for ray bounce behavior.
@@ -45,6 +328,41 @@ impl Default for BouncesConfig {
     }
 }
 
+/// Configuration for variance-driven adaptive sampling.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveSamplingConfig {
+    /// Pixels whose relative standard error (of their mean luminance) falls
+    /// below this threshold are considered converged and stop being sampled.
+    pub noise_threshold: f64,
+    /// Samples every pixel takes before its error estimate is trusted.
+    #[serde(default = "AdaptiveSamplingConfig::default_min_samples")]
+    pub min_samples: u32,
+    /// Upper bound on the samples per pixel a noisy tile gets in a single
+    /// checkpoint, as a multiple of `samples_per_checkpoint`.
+    #[serde(default = "AdaptiveSamplingConfig::default_max_sample_multiplier")]
+    pub max_sample_multiplier: f64,
+}
+
+impl AdaptiveSamplingConfig {
+    fn default_min_samples() -> u32 {
+        16
+    }
+
+    fn default_max_sample_multiplier() -> f64 {
+        4.0
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.noise_threshold.is_nan() || self.noise_threshold < 0.0 {
+            return Err("Adaptive sampling noise threshold must not be negative".to_string());
+        }
+        if self.max_sample_multiplier.is_nan() || self.max_sample_multiplier <= 0.0 {
+            return Err("Adaptive sampling max sample multiplier must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct ImportanceSamplingConfig {
     /// Weight for the material's own BRDF-based PDF (diffuse/Lambertian).
@@ -63,10 +381,19 @@ pub struct ImportanceSamplingConfig {
     /// importance sampling only, no visual contribution).
     #[serde(default)]
     pub virtual_weight: f64,
+    /// Weight for sampling toward the bright parts of the scene's
+    /// environment.
+    #[serde(default)]
+    pub environment_weight: f64,
     /// Whether to use Multiple Importance Sampling to blend
     /// BRDF and light sampling strategies (power heuristic).
     #[serde(default)]
     pub use_multiple_importance_sampling: bool,
+    /// Whether to sample a light with a shadow ray at every non-specular hit,
+    /// weighted against finding the light by bouncing. Replaces the emissive
+    /// category, so `emissive_weight` is ignored.
+    #[serde(default)]
+    pub use_next_event_estimation: bool,
 }
 
 impl ImportanceSamplingConfig {
@@ -76,7 +403,8 @@ impl ImportanceSamplingConfig {
             + self.emissive_weight
             + self.transmissive_weight
             + self.specular_weight
-            + self.virtual_weight;
+            + self.virtual_weight
+            + self.environment_weight;
 
         if total > 0.0 {
             self.brdf_weight /= total;
@@ -84,6 +412,7 @@ impl ImportanceSamplingConfig {
             self.transmissive_weight /= total;
             self.specular_weight /= total;
             self.virtual_weight /= total;
+            self.environment_weight /= total;
         }
     }
 
@@ -93,6 +422,7 @@ impl ImportanceSamplingConfig {
             + self.transmissive_weight
             + self.specular_weight
             + self.virtual_weight
+            + self.environment_weight
     }
 
     pub fn validate(&self, world: &SceneWorld) -> Result<(), String> {
@@ -101,6 +431,7 @@ impl ImportanceSamplingConfig {
             || self.transmissive_weight < 0.0
             || self.specular_weight < 0.0
             || self.virtual_weight < 0.0
+            || self.environment_weight < 0.0
         {
             return Err("Importance sampling weights must be non-negative".to_string());
         }
@@ -135,6 +466,9 @@ impl ImportanceSamplingConfig {
         if self.virtual_weight > 0.0 && !world.virtual_list.is_empty() {
             return Ok(());
         }
+        if self.environment_weight > 0.0 && world.environment.is_some() {
+            return Ok(());
+        }
         Err("at least one importance sampling category must have a non-zero weight with matching objects in the scene".to_string())
     }
 }
@@ -147,7 +481,134 @@ impl Default for ImportanceSamplingConfig {
             transmissive_weight: 0.0,
             specular_weight: 0.0,
             virtual_weight: 0.0,
+            environment_weight: 0.0,
             use_multiple_importance_sampling: false,
+            use_next_event_estimation: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deserialization::{RenderConfig, RenderConfigBuilder};
+
+    use super::*;
+
+    #[test]
+    fn animation_iterations_run_through_each_frame_in_turn() {
+        let config: RenderConfig =
+            serde_json::from_str(include_str!("../../configs/template.json")).unwrap();
+        let mut parameters = config.parameters;
+        parameters.total_checkpoints = 3;
+        parameters.animation = Some(AnimationConfig {
+            frame_count: 2,
+            frame_duration: 0.5,
+            start_time: 1.0,
+        });
+
+        assert_eq!(parameters.total_iterations(), 6);
+        let frames = (1..=6)
+            .map(|iteration| {
+                let FrameIteration { frame, checkpoint } = parameters.frame_iteration(iteration);
+                (frame, checkpoint)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(frames, [(0, 1), (0, 2), (0, 3), (1, 1), (1, 2), (1, 3)]);
+        assert_eq!(parameters.frame_iteration(0).checkpoint, 1);
+        assert_eq!(parameters.frame_start_time(1), 1.5);
+
+        // a still image is a single frame at time 0
+        parameters.animation = None;
+        assert_eq!(parameters.total_iterations(), 3);
+        assert_eq!(parameters.frame_iteration(3).frame, 0);
+        assert_eq!(parameters.frame_start_time(0), 0.0);
+    }
+
+    #[test]
+    fn animations_cannot_limit_saved_checkpoints() {
+        let mut config: RenderConfig =
+            serde_json::from_str(include_str!("../../configs/template.json")).unwrap();
+        config.parameters.animation = Some(AnimationConfig {
+            frame_count: 2,
+            frame_duration: 0.5,
+            start_time: 0.0,
+        });
+        let compile = |config: &RenderConfig| {
+            RenderConfigBuilder::from(config.clone())
+                .with_builtins()
+                .build()
+                .compile(None)
+                .map(|_| ())
+        };
+
+        config.parameters.saved_checkpoint_limit = Some(1);
+        assert!(compile(&config).is_err());
+        config.parameters.saved_checkpoint_limit = None;
+        assert!(compile(&config).is_ok());
+    }
+
+    #[test]
+    fn adaptive_sampling_and_tone_mapping_settings_are_checked() {
+        let config: RenderConfig =
+            serde_json::from_str(include_str!("../../configs/template.json")).unwrap();
+        let compile = |parameters: RenderParameters| {
+            let config = RenderConfig {
+                parameters,
+                ..config.clone()
+            };
+            RenderConfigBuilder::from(config)
+                .with_builtins()
+                .build()
+                .compile(None)
+                .map(|_| ())
+        };
+        let adaptive_sampling = AdaptiveSamplingConfig {
+            noise_threshold: 0.01,
+            min_samples: 16,
+            max_sample_multiplier: 4.0,
+        };
+        let with_adaptive_sampling = |adaptive_sampling| RenderParameters {
+            adaptive_sampling: Some(adaptive_sampling),
+            ..config.parameters
+        };
+        let with_tone_mapping = |tone_mapping| RenderParameters {
+            tone_mapping: Some(tone_mapping),
+            ..config.parameters
+        };
+
+        assert!(compile(with_adaptive_sampling(adaptive_sampling)).is_ok());
+        for noise_threshold in [f64::NAN, -0.1] {
+            let parameters = with_adaptive_sampling(AdaptiveSamplingConfig {
+                noise_threshold,
+                ..adaptive_sampling
+            });
+            assert!(compile(parameters).is_err(), "{noise_threshold}");
+        }
+        let parameters = with_adaptive_sampling(AdaptiveSamplingConfig {
+            max_sample_multiplier: 0.0,
+            ..adaptive_sampling
+        });
+        assert!(compile(parameters).is_err());
+
+        assert!(compile(with_tone_mapping(ToneMapping::default())).is_ok());
+        for tone_mapping in [
+            ToneMapping {
+                exposure: f64::INFINITY,
+                ..Default::default()
+            },
+            ToneMapping {
+                white_point: 0.0,
+                ..Default::default()
+            },
+            ToneMapping {
+                white_point: f64::NAN,
+                ..Default::default()
+            },
+        ] {
+            assert!(
+                compile(with_tone_mapping(tone_mapping)).is_err(),
+                "{tone_mapping:?}"
+            );
         }
     }
 }
@@ -0,0 +1,330 @@
+use std::f64::consts::PI;
+
+use bincode::{Decode, Encode};
+
+use crate::{
+    geometry::{Point, RayHit, Vector, Vector3},
+    shading::{ColorRgb, HeroWavelengths, hero_wavelengths::HERO_WAVELENGTH_COUNT},
+};
+
+/// How many pixels wide the radius a pixel gathers photons within starts out,
+/// unless it's configured.
+const INITIAL_RADIUS_PIXELS: f64 = 2.0;
+
+/// Light a photon brought to a surface.
+#[derive(Debug, Clone, Copy)]
+pub struct Photon {
+    pub position: Point,
+    /// unit direction back toward where the photon came from
+    pub incident: Vector3,
+    pub wavelengths: HeroWavelengths<HERO_WAVELENGTH_COUNT>,
+    /// the photon's flux at each of its wavelengths, as if it had been the
+    /// only one traced
+    pub power: Vector<HERO_WAVELENGTH_COUNT>,
+}
+
+/// The photons of one pass of progressive photon mapping, in a k-d tree for
+/// finding those near a point.
+#[derive(Debug, Default)]
+pub struct PhotonMap {
+    /// the tree, stored implicitly: any range of photons is split at its
+    /// middle photon, along the axis `axes` holds at the same index, into the
+    /// ranges before and after it
+    photons: Vec<Photon>,
+    axes: Vec<u8>,
+    /// photons traced, including those that never reached a surface
+    emitted: u64,
+}
+
+impl PhotonMap {
+    pub fn new(mut photons: Vec<Photon>, emitted: u64) -> Self {
+        let mut axes = vec![0; photons.len()];
+        build(&mut photons, &mut axes);
+        Self {
+            photons,
+            axes,
+            emitted,
+        }
+    }
+
+    pub fn emitted(&self) -> u64 {
+        self.emitted
+    }
+
+    /// Calls `f` with every photon within `radius` of `point`.
+    pub fn for_each_within(&self, point: Point, radius: f64, mut f: impl FnMut(&Photon)) {
+        search(&self.photons, &self.axes, point, radius * radius, &mut f);
+    }
+
+    /// The photons `visible_point` gathers within `radius`: how many there
+    /// are, and the flux they send toward the camera.
+    pub fn gather(&self, visible_point: &VisiblePoint, radius: f64) -> (f64, ColorRgb) {
+        let VisiblePoint { hit, outgoing, .. } = visible_point;
+
+        let mut count = 0.0;
+        let mut flux = ColorRgb::BLACK;
+        self.for_each_within(hit.point, radius, |photon| {
+            let brdf = hit.material.brdf(
+                *outgoing,
+                photon.incident,
+                hit.normal,
+                hit.u,
+                hit.v,
+                hit.point,
+            );
+            count += 1.0;
+            flux += photon
+                .wavelengths
+                .to_color_rgb(brdf.sample(&photon.wavelengths) * photon.power);
+        });
+        (count, flux * visible_point.weight)
+    }
+}
+
+/// Orders `photons` into the implicit k-d tree, splitting each range along
+/// its longest side.
+fn build(photons: &mut [Photon], axes: &mut [u8]) {
+    if photons.len() < 2 {
+        return;
+    }
+
+    let (mut minimum, mut maximum) = ([f64::INFINITY; 3], [f64::NEG_INFINITY; 3]);
+    for photon in photons.iter() {
+        for axis in 0..3 {
+            minimum[axis] = minimum[axis].min(photon.position[axis]);
+            maximum[axis] = maximum[axis].max(photon.position[axis]);
+        }
+    }
+    let axis = (0..3)
+        .max_by(|&a, &b| (maximum[a] - minimum[a]).total_cmp(&(maximum[b] - minimum[b])))
+        .unwrap_or(0);
+
+    let middle = photons.len() / 2;
+    photons.select_nth_unstable_by(middle, |a, b| a.position[axis].total_cmp(&b.position[axis]));
+    axes[middle] = axis as u8;
+
+    let (before, rest) = photons.split_at_mut(middle);
+    let (before_axes, rest_axes) = axes.split_at_mut(middle);
+    build(before, before_axes);
+    build(&mut rest[1..], &mut rest_axes[1..]);
+}
+
+fn search(
+    photons: &[Photon],
+    axes: &[u8],
+    point: Point,
+    radius_squared: f64,
+    f: &mut impl FnMut(&Photon),
+) {
+    if photons.is_empty() {
+        return;
+    }
+
+    let middle = photons.len() / 2;
+    let photon = &photons[middle];
+    if photon.position.to(point).squared_length() <= radius_squared {
+        f(photon);
+    }
+    if photons.len() == 1 {
+        return;
+    }
+
+    let axis = axes[middle] as usize;
+    let offset = point[axis] - photon.position[axis];
+    let (before, after) = (..middle, middle + 1..);
+    let (near, far) = if offset < 0.0 {
+        (
+            (&photons[before], &axes[before]),
+            (&photons[after.clone()], &axes[after]),
+        )
+    } else {
+        (
+            (&photons[after.clone()], &axes[after]),
+            (&photons[before], &axes[before]),
+        )
+    };
+    search(near.0, near.1, point, radius_squared, f);
+    if offset * offset <= radius_squared {
+        search(far.0, far.1, point, radius_squared, f);
+    }
+}
+
+/// Where a camera path stops to gather photons: the first surface it reaches
+/// that isn't a mirror or glass.
+#[derive(Debug, Clone)]
+pub struct VisiblePoint {
+    pub hit: RayHit,
+    /// unit direction back along the camera path
+    pub outgoing: Vector3,
+    /// how much of the light leaving the point reaches the camera
+    pub weight: ColorRgb,
+    /// how wide a pixel is at the point
+    pub footprint: f64,
+}
+
+/// What a pixel's camera samples gathered from one pass of photons.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PhotonPass {
+    /// the radius the photons were gathered within. `None` until the pixel
+    /// has one, which a new pixel takes from its first visible point
+    pub radius: Option<f64>,
+    /// camera samples taken, whether or not they found a visible point
+    pub samples: u32,
+    pub photon_count: f64,
+    pub flux: ColorRgb,
+}
+
+impl PhotonPass {
+    /// Counts a camera sample, and gathers photons from `photon_map` at its
+    /// visible point, if it found one. A pixel without a radius yet takes
+    /// `initial_radius`, or else one a couple of pixels wide at the point.
+    pub fn gather(
+        &mut self,
+        photon_map: &PhotonMap,
+        visible_point: Option<&VisiblePoint>,
+        initial_radius: Option<f64>,
+    ) {
+        self.samples += 1;
+        let Some(visible_point) = visible_point else {
+            return;
+        };
+        let radius = *self.radius.get_or_insert_with(|| {
+            initial_radius.unwrap_or(INITIAL_RADIUS_PIXELS * visible_point.footprint)
+        });
+
+        let (count, flux) = photon_map.gather(visible_point, radius);
+        self.photon_count += count;
+        self.flux += flux;
+    }
+}
+
+/// The progressive photon mapping state of a pixel.
+#[derive(Debug, Copy, Clone, PartialEq, Default, Encode, Decode)]
+pub struct PhotonPixel {
+    /// the filter-weighted mean of the camera samples, i.e. the pixel's color
+    /// without the light the photons bring
+    pub camera_color: ColorRgb,
+    /// the radius photons are gathered within
+    pub radius: f64,
+    /// the photons gathered so far, discounted as the radius shrank
+    pub photon_count: f64,
+    /// the flux those photons send toward the camera
+    pub flux: ColorRgb,
+    /// photons traced in the passes the pixel gathered from
+    pub emitted: u64,
+}
+
+impl PhotonPixel {
+    pub fn new(radius: f64) -> Self {
+        Self {
+            radius,
+            ..Default::default()
+        }
+    }
+
+    /// Takes in a pass of `emitted` photons, shrinking the radius so that
+    /// `alpha` of the photons it gathered are kept.
+    pub fn add_pass(&mut self, pass: &PhotonPass, emitted: u64, alpha: f64) {
+        if pass.samples == 0 {
+            return;
+        }
+        let samples = pass.samples as f64;
+        let (new_count, new_flux) = (pass.photon_count / samples, pass.flux / samples);
+
+        if new_count > 0.0 {
+            let photon_count = self.photon_count + alpha * new_count;
+            let radius = self.radius * (photon_count / (self.photon_count + new_count)).sqrt();
+            let shrinkage = radius / self.radius;
+            self.flux = (self.flux + new_flux) * (shrinkage * shrinkage);
+            self.photon_count = photon_count;
+            self.radius = radius;
+        }
+        self.emitted += emitted;
+    }
+
+    /// The light the photons bring to the pixel.
+    pub fn radiance(&self) -> ColorRgb {
+        if self.emitted == 0 || self.radius <= 0.0 {
+            return ColorRgb::BLACK;
+        }
+        self.flux / (self.emitted as f64 * PI * self.radius * self.radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_tree_finds_the_same_photons_as_checking_them_all() {
+        let mut rng_state = 9876_u64;
+        let mut random = || {
+            rng_state = rng_state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (rng_state >> 11) as f64 / (1_u64 << 53) as f64
+        };
+
+        let photons = (0..2000)
+            .map(|i| Photon {
+                // flattened, so the split axes differ
+                position: Point::new(random() * 4.0, random(), random() * 2.0),
+                incident: Vector3::new(0.0, 1.0, 0.0),
+                wavelengths: HeroWavelengths::from_sample(0.5),
+                power: Vector::new([i as f64; HERO_WAVELENGTH_COUNT]),
+            })
+            .collect::<Vec<_>>();
+        let map = PhotonMap::new(photons.clone(), 2000);
+        let index = |photon: &Photon| photon.power[0];
+
+        for _ in 0..50 {
+            let point = Point::new(random() * 4.0, random(), random() * 2.0);
+            let radius = random() * 0.3;
+
+            let mut found = Vec::new();
+            map.for_each_within(point, radius, |photon| found.push(index(photon)));
+            found.sort_by(f64::total_cmp);
+            let expected = photons
+                .iter()
+                .filter(|photon| photon.position.to(point).length() <= radius)
+                .map(index)
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn radii_shrink_while_an_even_spread_of_photons_keeps_its_radiance() {
+        // photons landing evenly, `density` per unit area for each one traced
+        let (density, power, emitted, alpha) = (3.0, ColorRgb::new(0.5, 0.25, 1.0), 1000, 0.7);
+        let mut pixel = PhotonPixel::new(0.2);
+        let expected = power * density;
+
+        let mut previous_radius = pixel.radius;
+        for _ in 0..10 {
+            // two camera samples, each gathering within the pixel's radius
+            let count = density * emitted as f64 * PI * pixel.radius * pixel.radius;
+            let pass = PhotonPass {
+                radius: Some(pixel.radius),
+                samples: 2,
+                photon_count: 2.0 * count,
+                flux: power * (2.0 * count),
+            };
+            pixel.add_pass(&pass, emitted, alpha);
+
+            assert!(pixel.radius < previous_radius);
+            previous_radius = pixel.radius;
+            let [r, g, b]: [f64; 3] = (pixel.radiance() - expected).into();
+            assert!(
+                r.abs().max(g.abs()).max(b.abs()) < 1e-9,
+                "{:?}",
+                pixel.radiance()
+            );
+        }
+
+        // a pass without camera samples leaves the pixel alone
+        let before = pixel;
+        pixel.add_pass(&PhotonPass::default(), emitted, alpha);
+        assert_eq!(pixel, before);
+    }
+}
@@ -0,0 +1,222 @@
+use bincode::{Decode, Encode};
+
+use crate::shading::ColorRgb;
+
+use super::{AovPixel, AovSample, PhotonPass, PhotonPixel};
+
+/// The accumulated state of a single pixel across all checkpoints so far.
+///
+/// The color is the filter-weighted average of every sample splatted into the
+/// pixel, which can include samples taken in neighbouring pixels. Separately,
+/// the luminance mean and variance of the samples taken in this pixel itself
+/// are tracked (the latter as Welford's sum of squared differences), so
+/// adaptive sampling can tell which pixels are still noisy.
+#[derive(Debug, Clone, PartialEq, Default, Encode, Decode)]
+pub struct Pixel {
+    /// filter-weighted mean of all samples splatted into this pixel
+    pub color: ColorRgb,
+    /// sum of the filter weights `color` is the average over. zero means it is
+    /// unknown, for pixels loaded from data written before weights were stored
+    pub weight_sum: f64,
+    /// number of samples taken in this pixel. zero means the count is
+    /// unknown, for pixels loaded from data written before counts were stored
+    pub sample_count: u32,
+    /// mean luminance of the samples taken in this pixel
+    pub luminance_mean: f64,
+    /// sum of squared differences of each sample's luminance from the mean
+    pub luminance_m2: f64,
+    /// the AOVs of the samples taken in this pixel, for renders that store them
+    pub aovs: Option<Box<AovPixel>>,
+    /// the photons gathered, for progressive photon mapping renders. `color`
+    /// includes the light they bring
+    pub photons: Option<Box<PhotonPixel>>,
+}
+
+impl Pixel {
+    pub fn from_color(color: ColorRgb) -> Self {
+        Self {
+            color,
+            weight_sum: 0.0,
+            sample_count: 0,
+            luminance_mean: color.luminance(),
+            luminance_m2: 0.0,
+            aovs: None,
+            photons: None,
+        }
+    }
+
+    /// Combines the samples of two pixels, as if they had been taken together.
+    ///
+    /// Photons can't be combined that way, so `other`'s are kept if it has
+    /// any; [`Pixel::merge_photon_pass`] adds them up.
+    pub fn merge(&self, other: &Pixel) -> Pixel {
+        let weight_sum = self.weight_sum + other.weight_sum;
+        // negative filter lobes can cancel the weights out entirely
+        let color = if weight_sum != 0.0 {
+            ((self.color * self.weight_sum + other.color * other.weight_sum) / weight_sum).de_nan()
+        } else {
+            self.color
+        };
+
+        let aovs = match (&self.aovs, &other.aovs) {
+            (Some(a), Some(b)) => Some(Box::new(a.merge(b))),
+            (a, b) => a.clone().or_else(|| b.clone()),
+        };
+        let photons = other.photons.clone().or_else(|| self.photons.clone());
+
+        let total = self.sample_count + other.sample_count;
+        if total == 0 {
+            return Pixel {
+                color,
+                weight_sum,
+                aovs,
+                photons,
+                ..self.clone()
+            };
+        }
+
+        let (n_a, n_b, n) = (
+            self.sample_count as f64,
+            other.sample_count as f64,
+            total as f64,
+        );
+        let delta = other.luminance_mean - self.luminance_mean;
+
+        Pixel {
+            color,
+            weight_sum,
+            sample_count: total,
+            luminance_mean: (self.luminance_mean * n_a + other.luminance_mean * n_b) / n,
+            luminance_m2: self.luminance_m2 + other.luminance_m2 + delta * delta * n_a * n_b / n,
+            aovs,
+            photons,
+        }
+    }
+
+    /// Like [`Pixel::merge`], for progressive photon mapping: `round`'s camera
+    /// samples are merged with this pixel's, and the photons of the pass they
+    /// gathered from, `emitted` in all, are taken in.
+    pub fn merge_photon_pass(
+        &self,
+        round: &Pixel,
+        pass: &PhotonPass,
+        emitted: u64,
+        alpha: f64,
+    ) -> Pixel {
+        let camera_color = self
+            .photons
+            .as_ref()
+            .map_or(self.color, |photons| photons.camera_color);
+        let merged = Pixel {
+            color: camera_color,
+            photons: None,
+            ..self.clone()
+        }
+        .merge(round);
+
+        let mut photons = match (&self.photons, pass.radius) {
+            (Some(photons), _) => **photons,
+            (None, Some(radius)) => PhotonPixel::new(radius),
+            // nothing has been gathered yet
+            (None, None) => return merged,
+        };
+        photons.add_pass(pass, emitted, alpha);
+        photons.camera_color = merged.color;
+
+        Pixel {
+            color: merged.color + photons.radiance(),
+            photons: Some(Box::new(photons)),
+            ..merged
+        }
+    }
+
+    /// The standard error of the mean luminance, relative to that luminance.
+    ///
+    /// Returns `None` when there aren't enough samples to estimate it.
+    pub fn relative_error(&self) -> Option<f64> {
+        if self.sample_count < 2 {
+            return None;
+        }
+
+        let n = self.sample_count as f64;
+        let variance = self.luminance_m2 / (n - 1.0);
+        let standard_error = (variance / n).sqrt();
+
+        // the small offset keeps near-black pixels from needing endless samples
+        Some(standard_error / (self.luminance_mean + 1e-2))
+    }
+}
+
+/// Accumulates the samples taken in a pixel, with Welford's online algorithm
+/// for their luminance.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PixelSamples {
+    sum: ColorRgb,
+    count: u32,
+    luminance_mean: f64,
+    luminance_m2: f64,
+    aovs: Option<AovPixel>,
+}
+
+impl PixelSamples {
+    pub fn add(&mut self, sample: ColorRgb) {
+        let sample = sample.de_nan();
+        self.sum += sample;
+        self.count += 1;
+
+        let luminance = sample.luminance();
+        let delta = luminance - self.luminance_mean;
+        self.luminance_mean += delta / self.count as f64;
+        self.luminance_m2 += delta * (luminance - self.luminance_mean);
+    }
+
+    pub fn add_aovs(&mut self, sample: &AovSample) {
+        self.aovs.get_or_insert_default().add(sample);
+    }
+
+    pub fn into_pixel(self) -> Pixel {
+        if self.count == 0 {
+            return Pixel::default();
+        }
+
+        // equivalent to a box filter that only covers this pixel
+        Pixel {
+            color: self.sum / self.count as f64,
+            weight_sum: self.count as f64,
+            sample_count: self.count,
+            luminance_mean: self.luminance_mean,
+            luminance_m2: self.luminance_m2,
+            aovs: self.aovs.map(Box::new),
+            photons: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merging_matches_accumulating_together() {
+        let colors = [0.1, 0.7, 0.3, 2.5, 0.0, 1.2].map(|c| ColorRgb::new(c, c * 0.5, c * 2.0));
+
+        let mut all = PixelSamples::default();
+        let mut first = PixelSamples::default();
+        let mut second = PixelSamples::default();
+        for (i, color) in colors.iter().enumerate() {
+            all.add(*color);
+            if i < 2 {
+                first.add(*color);
+            } else {
+                second.add(*color);
+            }
+        }
+
+        let all = all.into_pixel();
+        let merged = first.into_pixel().merge(&second.into_pixel());
+
+        assert_eq!(merged.sample_count, all.sample_count);
+        assert!((merged.color.luminance() - all.color.luminance()).abs() < 1e-12);
+        assert!((merged.luminance_m2 - all.luminance_m2).abs() < 1e-12);
+    }
+}
@@ -11,13 +11,19 @@ use tokio::sync::mpsc;
 
 use crate::{
     deserialization::{RenderConfig, RenderConfigBuilder},
-    tracing::{PixelData, RenderState, Threads, Tracer},
-    utils::{ProgressInfo, ProgressTracker},
+    shading::ToneMappingOverride,
+    tracing::{
+        PixelData, Region, RenderParameters, RenderState, Threads, Tracer, recover_legacy_pixels,
+    },
+    utils::{FrameProgress, ProgressInfo, ProgressTracker},
 };
 
 use crate::server::{RenderStateSnapshot, RenderStreamRegistry};
 
-use super::{Render, RenderCheckpoint, RenderID, RenderStorage, Role, StorageError, User, UserID};
+use super::{
+    Aov, HdrImage, Render, RenderCheckpoint, RenderID, RenderStorage, Role, StorageError, User,
+    UserID,
+};
 
 use std::collections::HashSet;
 
@@ -34,6 +40,13 @@ pub struct RenderManager {
 
 const POLLING_INTERVAL_MS: u64 = 100;
 
+/// Extra samples for part of a finished render.
+#[derive(Debug, Clone, Copy)]
+struct Refinement {
+    region: Region,
+    samples_per_pixel: u32,
+}
+
 impl RenderManager {
     pub async fn new(
         storage: Arc<dyn RenderStorage>,
@@ -121,14 +134,14 @@ impl RenderManager {
                         && !self.running_renders.lock().unwrap().contains(&r.id)
                 });
                 for render in created_renders.cloned() {
-                    self.spawn_tracer_thread_to_next_checkpoint(render, None)
+                    self.spawn_tracer_thread_to_next_checkpoint(render, None, None)
                         .await;
                 }
 
                 // move FinishedCheckpoint renders to next checkpoint if applicable
                 let checkpointed_renders = renders.iter().filter(|r| match r.state {
                     RenderState::FinishedCheckpointIteration(checkpoint) => {
-                        checkpoint < r.config.parameters.total_checkpoints
+                        checkpoint < r.config.parameters.total_iterations()
                             && !self.running_renders.lock().unwrap().contains(&r.id)
                     }
                     _ => false,
@@ -152,7 +165,7 @@ impl RenderManager {
                         _ => None,
                     };
 
-                    self.spawn_tracer_thread_to_next_checkpoint(render, previous_checkpoint)
+                    self.spawn_tracer_thread_to_next_checkpoint(render, previous_checkpoint, None)
                         .await;
                 }
             }
@@ -166,6 +179,7 @@ impl RenderManager {
         &self,
         render: Render,
         previous_checkpoint: Option<RenderCheckpoint>,
+        refinement: Option<Refinement>,
     ) -> tokio::task::JoinHandle<()> {
         // add render to running set
         self.running_renders.lock().unwrap().insert(render.id);
@@ -213,7 +227,12 @@ impl RenderManager {
                 updated_at: chrono::Utc::now(),
             });
 
-            let initial_pixel_data = match previous_checkpoint {
+            let parameters = render.config.parameters;
+            let frame_iteration = parameters.frame_iteration(iteration);
+
+            let mut initial_pixel_data = match previous_checkpoint {
+                // each frame of an animation starts from a blank image
+                Some(_) if frame_iteration.checkpoint == 1 => PixelData::new(),
                 Some(rcp) => match rcp.pixel_data {
                     Some(pixel_data) => pixel_data,
                     None => {
@@ -229,7 +248,7 @@ impl RenderManager {
             };
 
             let resources = match resource_manager
-                .get_textures_for_config(&render.config)
+                .get_resources_for_config(&render.config)
                 .await
             {
                 Ok(data) => data,
@@ -243,7 +262,7 @@ impl RenderManager {
                 }
             };
 
-            let render_data = match render.config.compile(Some(&resources)) {
+            let mut render_data = match render.config.compile(Some(&resources)) {
                 Ok(data) => data,
                 Err(e) => {
                     println!(
@@ -287,19 +306,40 @@ impl RenderManager {
                 }
             });
 
-            let (width, height) = render.config.parameters.image_dimensions;
+            // a refinement only adds samples to its region, whether or not they're converged
+            if let Some(refinement) = refinement {
+                // its parameters no longer tell how many samples the stored checkpoints took
+                recover_legacy_pixels(
+                    &mut initial_pixel_data,
+                    (iteration - 1) * parameters.samples_per_checkpoint,
+                );
+                render_data.parameters.region = Some(refinement.region);
+                render_data.parameters.samples_per_checkpoint = refinement.samples_per_pixel;
+                render_data.parameters.adaptive_sampling = None;
+            }
+
+            let pixel_count = render_data.parameters.render_region().pixel_count();
             let started_at = chrono::Utc::now();
             let new_pixel_data = {
                 let render_state_streams = Arc::clone(&render_state_streams);
 
                 let (_, new_pixel_data) = tokio::join!(
                     async move {
-                        let total = u64::from(width) * u64::from(height);
+                        let total = pixel_count;
                         let mut progress_tracker = ProgressTracker::new(
                             total,
                             50,
                             (total / 1000).max(1),
-                            |progress_info| {
+                            |mut progress_info: ProgressInfo| {
+                                if let Some(animation) = parameters.animation {
+                                    progress_info.frame = Some(FrameProgress {
+                                        frame: frame_iteration.frame,
+                                        frame_count: animation.frame_count,
+                                        progress: ((frame_iteration.checkpoint - 1) as f64
+                                            + progress_info.progress)
+                                            / parameters.total_checkpoints as f64,
+                                    });
+                                }
                                 let state = if render_state_streams.is_pausing(render.id) {
                                     RenderState::Pausing {
                                         checkpoint_iteration: iteration,
@@ -388,51 +428,75 @@ impl RenderManager {
                 }
             }
 
-            println!("Checking if old checkpoints' pixel data needs to be cleared...");
-            let total_checkpoints_saved = match storage.get_render_checkpoint_count(render.id).await
+            // a refinement's checkpoint is past the render's total until it's saved,
+            // so one interrupted before then reverts to the finished render as it was
+            if refinement.is_some()
+                && let Err(e) = storage
+                    .update_render_total_checkpoints(render.id, iteration)
+                    .await
             {
-                Ok(count) => count,
-                Err(e) => {
-                    println!("  Failed to get checkpoint count: {e}");
-                    running_renders.lock().unwrap().remove(&render.id);
-                    return;
+                println!("Failed to update render total_checkpoints: {e}");
+                running_renders.lock().unwrap().remove(&render.id);
+                return;
+            }
+
+            println!("Checking if old checkpoints' pixel data needs to be cleared...");
+            let checkpoint_to_clear = if parameters.animation.is_some() {
+                // an animation keeps the latest checkpoint of every frame, which is all its
+                // image sequence (and resuming) needs, so only the one this replaced goes.
+                // it can't have a saved_checkpoint_limit, which this would skip
+                (frame_iteration.checkpoint > 1).then(|| iteration - 1)
+            } else {
+                let total_checkpoints_saved =
+                    match storage.get_render_checkpoint_count(render.id).await {
+                        Ok(count) => count,
+                        Err(e) => {
+                            println!("  Failed to get checkpoint count: {e}");
+                            running_renders.lock().unwrap().remove(&render.id);
+                            return;
+                        }
+                    };
+
+                if parameters
+                    .saved_checkpoint_limit
+                    .is_some_and(|limit| limit < total_checkpoints_saved)
+                {
+                    println!("    Finding earliest checkpoint...");
+                    match storage
+                        .get_earliest_render_checkpoint_iteration(render.id)
+                        .await
+                    {
+                        Ok(Some(iteration)) => {
+                            println!("    Found earliest checkpoint: {}", iteration);
+                            Some(iteration)
+                        }
+                        Ok(None) => {
+                            println!("    No checkpoints found for render {}", render.id);
+                            running_renders.lock().unwrap().remove(&render.id);
+                            return;
+                        }
+                        Err(e) => {
+                            println!(
+                                "    Failed to get earliest render checkpoint for id {}: {}",
+                                render.id, e
+                            );
+                            running_renders.lock().unwrap().remove(&render.id);
+                            return;
+                        }
+                    }
+                } else {
+                    None
                 }
             };
 
-            if render
-                .config
-                .parameters
-                .saved_checkpoint_limit
-                .is_some_and(|limit| limit < total_checkpoints_saved)
-            {
+            if let Some(checkpoint_to_clear) = checkpoint_to_clear {
                 println!("  Clearing old checkpoint pixel data...");
-                println!("    Finding earliest checkpoint...");
-                let earliest_checkpoint = match storage
-                    .get_earliest_render_checkpoint_iteration(render.id)
-                    .await
-                {
-                    Ok(Some(iteration)) => iteration,
-                    Ok(None) => {
-                        println!("    No checkpoints found for render {}", render.id);
-                        running_renders.lock().unwrap().remove(&render.id);
-                        return;
-                    }
-                    Err(e) => {
-                        println!(
-                            "    Failed to get earliest render checkpoint for id {}: {}",
-                            render.id, e
-                        );
-                        running_renders.lock().unwrap().remove(&render.id);
-                        return;
-                    }
-                };
-                println!("    Found earliest checkpoint: {}", earliest_checkpoint);
                 println!(
                     "    Clearing pixel data for checkpoint {}...",
-                    earliest_checkpoint
+                    checkpoint_to_clear
                 );
                 if let Err(e) = storage
-                    .clear_checkpoint_pixel_data(render.id, earliest_checkpoint)
+                    .clear_checkpoint_pixel_data(render.id, checkpoint_to_clear)
                     .await
                 {
                     println!("Failed to clear old render checkpoint pixel data: {e}");
@@ -574,7 +638,7 @@ impl RenderManager {
             .await?;
 
         // get iteration numbers
-        let total_iterations = render.config.parameters.total_checkpoints;
+        let total_iterations = render.config.parameters.total_iterations();
         let (completed_iterations, unstarted_iterations, progress_info) = match render.state {
             RenderState::Created => (0, total_iterations, None),
             RenderState::Running {
@@ -710,6 +774,23 @@ impl RenderManager {
             render_config.parameters.saved_checkpoint_limit,
             user.max_checkpoints_per_render,
         ) {
+            // animations keep one checkpoint per frame instead of following a limit
+            (_, Some(user_limit))
+                if render_config
+                    .parameters
+                    .animation
+                    .is_some_and(|animation| animation.frame_count > user_limit) =>
+            {
+                return Err(RenderManagerError::ClientError(
+                    StatusCode::FORBIDDEN,
+                    format!(
+                        "Animation frame count ({}) exceeds user maximum saved checkpoints ({})",
+                        render_config.parameters.frame_count(),
+                        user_limit,
+                    ),
+                ));
+            }
+            (_, Some(_)) if render_config.parameters.animation.is_some() => {}
             // user specified a limit that exceeds their max
             (Some(render_limit), Some(user_limit)) if render_limit > user_limit => {
                 return Err(RenderManagerError::ClientError(
@@ -795,6 +876,34 @@ impl RenderManager {
             .map_err(|e| e.into())
     }
 
+    /// The latest saved checkpoint iteration of one frame of an animation.
+    /// Still renders are a single frame, 0.
+    pub async fn get_latest_render_frame_checkpoint_iteration(
+        &self,
+        id: RenderID,
+        frame: u32,
+        user_id: UserID,
+    ) -> Result<Option<u32>, RenderManagerError> {
+        let Some(render) = self.get_render(id, user_id).await? else {
+            return Ok(None);
+        };
+
+        let parameters = render.config.parameters;
+        if frame >= parameters.frame_count() {
+            return Ok(None);
+        }
+
+        Ok(self
+            .storage
+            .get_render_checkpoints_excluding_pixel_data(id)
+            .await?
+            .iter()
+            .map(|c| c.iteration)
+            // the latest checkpoint of a frame is never cleared
+            .filter(|&iteration| parameters.frame_iteration(iteration).frame == frame)
+            .max())
+    }
+
     pub async fn get_render_checkpoint(
         &self,
         id: RenderID,
@@ -972,6 +1081,15 @@ impl RenderManager {
             }
         };
 
+        // frames are laid out by their checkpoint count, so changing it would
+        // shuffle the checkpoints already rendered between frames
+        if render.config.parameters.animation.is_some() {
+            return Err(RenderManagerError::ClientError(
+                StatusCode::BAD_REQUEST,
+                "total_checkpoints cannot be changed for an animation".to_string(),
+            ));
+        }
+
         let current_iteration = match render.state {
             // created renders might be picked up immediately to work on the first iteration,
             // so, just to be safe, we'll say we're currently working on iteration 1
@@ -1014,6 +1132,103 @@ impl RenderManager {
             .map_err(|e| e.into())
     }
 
+    /// Starts rendering `region` of a finished render again at
+    /// `samples_per_pixel` more samples (by default, `samples_per_checkpoint`).
+    /// The samples are merged into the render's accumulation as one more
+    /// checkpoint iteration, so the rest of the image is left as it was.
+    pub async fn refine_render(
+        &self,
+        id: RenderID,
+        region: Region,
+        samples_per_pixel: Option<u32>,
+        user_id: UserID,
+    ) -> Result<(), RenderManagerError> {
+        // get render (which will also check permissions)
+        let mut render = match self.get_render(id, user_id).await? {
+            Some(r) => r,
+            None => {
+                return Err(RenderManagerError::ClientError(
+                    StatusCode::NOT_FOUND,
+                    "Render not found".to_string(),
+                ));
+            }
+        };
+
+        let parameters = render.config.parameters;
+        if parameters.animation.is_some() {
+            return Err(RenderManagerError::ClientError(
+                StatusCode::BAD_REQUEST,
+                "Animations cannot be refined".to_string(),
+            ));
+        }
+
+        match render.state {
+            RenderState::FinishedCheckpointIteration(iteration)
+                if iteration == parameters.total_iterations() => {}
+            _ => {
+                return Err(RenderManagerError::ClientError(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "Cannot refine render {id} in state {:?}, only finished renders can be refined",
+                        render.state
+                    ),
+                ));
+            }
+        }
+
+        region
+            .validate(parameters.render_region())
+            .map_err(|e| RenderManagerError::ClientError(StatusCode::BAD_REQUEST, e))?;
+
+        let samples_per_pixel = samples_per_pixel.unwrap_or(parameters.samples_per_checkpoint);
+        if samples_per_pixel == 0 {
+            return Err(RenderManagerError::ClientError(
+                StatusCode::BAD_REQUEST,
+                "samples_per_pixel must be at least 1".to_string(),
+            ));
+        }
+
+        // claim the render so it isn't refined twice at once
+        if !self.running_renders.lock().unwrap().insert(id) {
+            return Err(RenderManagerError::ClientError(
+                StatusCode::BAD_REQUEST,
+                format!("Render {id} is already being refined"),
+            ));
+        }
+
+        let iteration = parameters.total_iterations();
+        let previous_checkpoint = match self.storage.get_render_checkpoint(id, iteration).await {
+            Ok(Some(checkpoint)) => checkpoint,
+            Ok(None) => {
+                self.running_renders.lock().unwrap().remove(&id);
+                return Err(RenderManagerError::ClientError(
+                    StatusCode::NOT_FOUND,
+                    format!("Checkpoint {iteration} of render {id} not found"),
+                ));
+            }
+            Err(e) => {
+                self.running_renders.lock().unwrap().remove(&id);
+                return Err(e.into());
+            }
+        };
+
+        // the refinement renders as one more checkpoint, which only counts
+        // towards the stored render's total once it's saved
+        println!("Refining render {id} in {region:?} at {samples_per_pixel} samples per pixel");
+        render.config.parameters.total_checkpoints += 1;
+        self.spawn_tracer_thread_to_next_checkpoint(
+            render,
+            Some(previous_checkpoint),
+            Some(Refinement {
+                region,
+                samples_per_pixel,
+            }),
+        )
+        .await;
+
+        Ok(())
+    }
+
     pub async fn update_render_name(
         &self,
         id: RenderID,
@@ -1041,6 +1256,8 @@ impl RenderManager {
         id: RenderID,
         iteration: u32,
         user_id: UserID,
+        tone_mapping: ToneMappingOverride,
+        denoise: bool,
     ) -> Result<Option<RgbaImage>, RenderManagerError> {
         // get render (which will also check permissions)
         let render = match self.get_render(id, user_id).await? {
@@ -1055,8 +1272,119 @@ impl RenderManager {
 
         let params = render.config.parameters;
 
+        let checkpoint = self
+            .get_checkpoint_for_display(id, iteration, &params, denoise)
+            .await?;
+        let tone_mapping = tone_mapping.apply_to(params.effective_tone_mapping());
+        tone_mapping
+            .validate()
+            .map_err(|e| RenderManagerError::ClientError(StatusCode::BAD_REQUEST, e))?;
+        Ok(checkpoint.map(|rcp| rcp.as_tone_mapped_image(&params, &tone_mapping)))
+    }
+
+    pub async fn get_render_checkpoint_as_hdr_image(
+        &self,
+        id: RenderID,
+        iteration: u32,
+        user_id: UserID,
+        denoise: bool,
+    ) -> Result<Option<HdrImage>, RenderManagerError> {
+        // get render (which will also check permissions)
+        let render = match self.get_render(id, user_id).await? {
+            Some(r) => r,
+            None => {
+                return Err(RenderManagerError::ClientError(
+                    StatusCode::NOT_FOUND,
+                    "Render not found".to_string(),
+                ));
+            }
+        };
+
+        let params = render.config.parameters;
+
+        let checkpoint = self
+            .get_checkpoint_for_display(id, iteration, &params, denoise)
+            .await?;
+        Ok(checkpoint.map(|rcp| rcp.as_hdr_image(&params)))
+    }
+
+    /// Loads a checkpoint, denoising it if asked to. The stored pixel data is
+    /// never changed.
+    async fn get_checkpoint_for_display(
+        &self,
+        id: RenderID,
+        iteration: u32,
+        params: &RenderParameters,
+        denoise: bool,
+    ) -> Result<Option<RenderCheckpoint>, RenderManagerError> {
+        let checkpoint = self.storage.get_render_checkpoint(id, iteration).await?;
+        match checkpoint {
+            Some(checkpoint) if denoise => {
+                let params = *params;
+                // filtering a full-resolution image is CPU-heavy
+                tokio::task::spawn_blocking(move || Some(checkpoint.denoised(&params)))
+                    .await
+                    .map_err(|e| RenderManagerError::ServerError(format!("Denoising failed: {e}")))
+            }
+            checkpoint => Ok(checkpoint),
+        }
+    }
+
+    /// One AOV of a checkpoint, prepared for viewing. `None` if there's no such
+    /// checkpoint or the render didn't store AOVs.
+    pub async fn get_render_checkpoint_aov_as_image(
+        &self,
+        id: RenderID,
+        iteration: u32,
+        aov: Aov,
+        user_id: UserID,
+        tone_mapping: ToneMappingOverride,
+    ) -> Result<Option<RgbaImage>, RenderManagerError> {
+        // get render (which will also check permissions)
+        let render = match self.get_render(id, user_id).await? {
+            Some(r) => r,
+            None => {
+                return Err(RenderManagerError::ClientError(
+                    StatusCode::NOT_FOUND,
+                    "Render not found".to_string(),
+                ));
+            }
+        };
+
+        let params = render.config.parameters;
+
+        let checkpoint = self.storage.get_render_checkpoint(id, iteration).await?;
+        let tone_mapping = tone_mapping.apply_to(params.effective_tone_mapping());
+        tone_mapping
+            .validate()
+            .map_err(|e| RenderManagerError::ClientError(StatusCode::BAD_REQUEST, e))?;
+        Ok(checkpoint.and_then(|rcp| rcp.as_aov_image(&params, aov, &tone_mapping)))
+    }
+
+    /// The raw values of one AOV of a checkpoint. `None` if there's no such
+    /// checkpoint or the render didn't store AOVs.
+    pub async fn get_render_checkpoint_aov_as_hdr_image(
+        &self,
+        id: RenderID,
+        iteration: u32,
+        aov: Aov,
+        user_id: UserID,
+    ) -> Result<Option<HdrImage>, RenderManagerError> {
+        // get render (which will also check permissions)
+        let render = match self.get_render(id, user_id).await? {
+            Some(r) => r,
+            None => {
+                return Err(RenderManagerError::ClientError(
+                    StatusCode::NOT_FOUND,
+                    "Render not found".to_string(),
+                ));
+            }
+        };
+
+        let params = render.config.parameters;
+
         let checkpoint = self.storage.get_render_checkpoint(id, iteration).await?;
-        Ok(checkpoint.map(|rcp| rcp.as_image(&params)))
+        Ok(checkpoint.and_then(|rcp| rcp.as_aov_hdr_image(&params, aov)))
     }
 
     pub async fn get_render_checkpoint_storage_usage_bytes(
@@ -8,19 +8,15 @@ use axum::{
 use chrono::Utc;
 use serde::Serialize;
 
-use crate::shading::textures::ImageLinearF64;
+use crate::shading::{lights::IesProfile, textures::ImageLinearF64};
 
 use super::{
     Resource, ResourceID, ResourceMeta, ResourceStorage, ResourceType, StorageError, User, UserID,
 };
 
-use std::collections::HashSet;
-
 use super::texture_cache::TextureCache;
 
-use indexmap::IndexMap;
-
-use crate::deserialization::{RenderConfig, TextureData};
+use crate::deserialization::{LoadedResources, RenderConfig};
 
 #[derive(Clone)]
 pub struct ResourceManager {
@@ -36,31 +32,24 @@ impl ResourceManager {
         }
     }
 
-    // preload all image textures referenced by a render config.
-    // checks the cache first; on miss, fetches from storage, decodes, and caches.
-    pub async fn get_textures_for_config(
+    // preload all image textures, environments and IES profiles referenced by a
+    // render config. images check the cache first; on miss, they're fetched
+    // from storage, decoded, and cached.
+    pub async fn get_resources_for_config(
         &self,
         config: &RenderConfig,
-    ) -> Result<IndexMap<ResourceID, Arc<ImageLinearF64>>, StorageError> {
-        // collect unique resource IDs from config
-        let mut ids = HashSet::new();
-        for texture in config.textures.values() {
-            if let TextureData::Image { resource_id, .. } = texture {
-                ids.insert(*resource_id);
-            }
-        }
-
-        let mut result = IndexMap::new();
-        for resource_id in ids {
+    ) -> Result<LoadedResources, StorageError> {
+        let mut result = LoadedResources::default();
+        for resource_id in config.image_resource_ids() {
             // check cache first
             if let Some(texture) = self.texture_cache.get(&resource_id) {
-                result.insert(resource_id, texture);
+                result.images.insert(resource_id, texture);
                 continue;
             }
 
             // cache miss: fetch from storage, decode, cache
             if let Some(resource) = self.storage.get_resource(resource_id).await? {
-                let image = ImageLinearF64::from_srgb_bytes(&resource.data).map_err(|e| {
+                let image = ImageLinearF64::from_bytes(&resource.data).map_err(|e| {
                     StorageError(format!(
                         "Failed to decode image for resource {}: {}",
                         resource_id, e
@@ -68,7 +57,20 @@ impl ResourceManager {
                 })?;
                 let texture = Arc::new(image);
                 self.texture_cache.insert(resource_id, Arc::clone(&texture));
-                result.insert(resource_id, texture);
+                result.images.insert(resource_id, texture);
+            }
+        }
+
+        // IES profiles are small text files, quick enough to parse every time
+        for resource_id in config.ies_profile_resource_ids() {
+            if let Some(resource) = self.storage.get_resource(resource_id).await? {
+                let profile = IesProfile::from_bytes(&resource.data).map_err(|e| {
+                    StorageError(format!(
+                        "Failed to parse IES profile for resource {}: {}",
+                        resource_id, e
+                    ))
+                })?;
+                result.ies_profiles.insert(resource_id, Arc::new(profile));
             }
         }
 
@@ -90,13 +92,21 @@ impl ResourceManager {
         // validate that the uploaded data can actually be used as this resource type
         match resource_type {
             ResourceType::TextureImage => {
-                ImageLinearF64::from_srgb_bytes(&data).map_err(|e| {
+                ImageLinearF64::from_bytes(&data).map_err(|e| {
                     ResourceManagerError::ClientError(
                         StatusCode::BAD_REQUEST,
                         format!("Invalid image file: {}", e),
                     )
                 })?;
             }
+            ResourceType::IesProfile => {
+                IesProfile::from_bytes(&data).map_err(|e| {
+                    ResourceManagerError::ClientError(
+                        StatusCode::BAD_REQUEST,
+                        format!("Invalid IES profile: {}", e),
+                    )
+                })?;
+            }
         }
 
         // enforce resource storage quota
@@ -0,0 +1,383 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{hash_u64s, mix_bits, random_f64};
+
+/// The largest `f64` below 1, so samples stay within [0, 1).
+pub const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+/// Which sequence camera rays and scattering decisions draw their samples from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplerType {
+    /// Independent uniform random numbers for every decision.
+    Independent,
+    /// Stratifies the pixel footprint of the first `floor(sqrt(n))²` of each
+    /// checkpoint's samples. Every other decision is uniform random.
+    #[default]
+    Stratified,
+    /// The Halton sequence, with Owen scrambling seeded per pixel and dimension.
+    Halton,
+    /// Padded 2D Sobol points, with Owen scrambling and a shuffled sample order
+    /// seeded per pixel and dimension.
+    Sobol,
+}
+
+impl SamplerType {
+    /// `seed` picks the scrambling of the low-discrepancy sequences. The random
+    /// samplers draw from the thread's random stream, which is seeded separately.
+    pub fn create(&self, seed: u64) -> Box<dyn Sampler> {
+        match self {
+            SamplerType::Independent => Box::new(IndependentSampler),
+            SamplerType::Stratified => Box::new(StratifiedSampler::default()),
+            SamplerType::Halton => Box::new(HaltonSampler {
+                seed,
+                ..Default::default()
+            }),
+            SamplerType::Sobol => Box::new(SobolSampler {
+                seed,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+/// A source of sample values in [0, 1) for each dimension of a pixel sample.
+///
+/// Each 1D or 2D sample drawn advances to the next dimension, so decisions made
+/// in the same order (pixel position, lens position, time, wavelengths, then
+/// each bounce) consistently get the same dimensions across a pixel's samples.
+pub trait Sampler {
+    /// Prepares to take `sample_count` samples of `pixel`, continuing on from
+    /// the `first_sample_index` samples it has already taken in earlier checkpoints.
+    fn start_pixel(&mut self, pixel: (u32, u32), first_sample_index: u32, sample_count: u32);
+
+    /// Starts the `sample`th of the samples announced by `start_pixel`.
+    fn start_sample(&mut self, sample: u32);
+
+    fn get_1d(&mut self) -> f64;
+
+    fn get_2d(&mut self) -> (f64, f64);
+
+    /// The position of the sample within the pixel footprint.
+    fn get_pixel_2d(&mut self) -> (f64, f64) {
+        self.get_2d()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IndependentSampler;
+
+impl Sampler for IndependentSampler {
+    fn start_pixel(&mut self, _pixel: (u32, u32), _first_sample_index: u32, _sample_count: u32) {}
+
+    fn start_sample(&mut self, _sample: u32) {}
+
+    fn get_1d(&mut self) -> f64 {
+        random_f64()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (random_f64(), random_f64())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StratifiedSampler {
+    grid_size: u32,
+    sample: u32,
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel(&mut self, _pixel: (u32, u32), _first_sample_index: u32, sample_count: u32) {
+        self.grid_size = (sample_count as f64).sqrt().floor() as u32;
+    }
+
+    fn start_sample(&mut self, sample: u32) {
+        self.sample = sample;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        random_f64()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (random_f64(), random_f64())
+    }
+
+    fn get_pixel_2d(&mut self) -> (f64, f64) {
+        if self.sample >= self.grid_size * self.grid_size {
+            return self.get_2d();
+        }
+
+        let row = self.sample / self.grid_size;
+        let col = self.sample % self.grid_size;
+        let cell_size = 1.0 / self.grid_size as f64;
+
+        (
+            (col as f64 + random_f64()) * cell_size,
+            (row as f64 + random_f64()) * cell_size,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HaltonSampler {
+    seed: u64,
+    pixel_hash: u64,
+    first_sample_index: u32,
+    sample_index: u64,
+    dimension: u64,
+}
+
+impl HaltonSampler {
+    fn next_dimension(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+
+        // past the last prime, dimensions wrap around, relying on their
+        // different scrambling to decorrelate them
+        owen_scrambled_radical_inverse(
+            PRIMES[dimension as usize % PRIMES.len()],
+            self.sample_index,
+            hash_u64s(&[self.pixel_hash, dimension]),
+        )
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel(&mut self, pixel: (u32, u32), first_sample_index: u32, _sample_count: u32) {
+        self.pixel_hash = hash_u64s(&[self.seed, pixel.0 as u64, pixel.1 as u64]);
+        self.first_sample_index = first_sample_index;
+    }
+
+    fn start_sample(&mut self, sample: u32) {
+        self.sample_index = self.first_sample_index as u64 + sample as u64;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.next_dimension()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.next_dimension(), self.next_dimension())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SobolSampler {
+    seed: u64,
+    pixel_hash: u64,
+    first_sample_index: u32,
+    sample_index: u32,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    /// The hash that seeds the scrambling of the next dimension.
+    fn next_dimension_hash(&mut self) -> u64 {
+        let dimension_hash = hash_u64s(&[self.pixel_hash, self.dimension]);
+        self.dimension += 1;
+        dimension_hash
+    }
+
+    /// Each dimension shuffles the order of its points differently, so that
+    /// padding dimensions together doesn't correlate them.
+    fn shuffled_index(&self, dimension_hash: u64) -> u32 {
+        fast_owen_scramble(self.sample_index, dimension_hash as u32)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel(&mut self, pixel: (u32, u32), first_sample_index: u32, _sample_count: u32) {
+        self.pixel_hash = hash_u64s(&[self.seed, pixel.0 as u64, pixel.1 as u64]);
+        self.first_sample_index = first_sample_index;
+    }
+
+    fn start_sample(&mut self, sample: u32) {
+        self.sample_index = self.first_sample_index.wrapping_add(sample);
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension_hash = self.next_dimension_hash();
+        let index = self.shuffled_index(dimension_hash);
+
+        to_unit_float(fast_owen_scramble(
+            sobol(index, 0),
+            (dimension_hash >> 32) as u32,
+        ))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dimension_hash = self.next_dimension_hash();
+        let index = self.shuffled_index(dimension_hash);
+        let scramble_hash = mix_bits(dimension_hash);
+
+        (
+            to_unit_float(fast_owen_scramble(sobol(index, 0), scramble_hash as u32)),
+            to_unit_float(fast_owen_scramble(
+                sobol(index, 1),
+                (scramble_hash >> 32) as u32,
+            )),
+        )
+    }
+}
+
+fn to_unit_float(value: u32) -> f64 {
+    (value as f64 * 2f64.powi(-32)).min(ONE_MINUS_EPSILON)
+}
+
+/// The `index`th point of the first two dimensions of the Sobol sequence, as a
+/// 32-bit fixed-point fraction.
+fn sobol(index: u32, dimension: usize) -> u32 {
+    match dimension {
+        // the van der Corput sequence
+        0 => index.reverse_bits(),
+        // direction numbers from the primitive polynomial x + 1
+        _ => {
+            let mut value = 0;
+            let mut direction = 1 << 31;
+            let mut index = index;
+            while index != 0 {
+                if index & 1 == 1 {
+                    value ^= direction;
+                }
+                direction ^= direction >> 1;
+                index >>= 1;
+            }
+            value
+        }
+    }
+}
+
+/// Owen scrambling of a 32-bit fixed-point fraction, approximated by hashing
+/// (Burley 2020, with the constants pbrt-v4 uses).
+fn fast_owen_scramble(value: u32, seed: u32) -> u32 {
+    let mut value = value.reverse_bits();
+    value ^= value.wrapping_mul(0x3d20adea);
+    value = value.wrapping_add(seed);
+    value = value.wrapping_mul((seed >> 16) | 1);
+    value ^= value.wrapping_mul(0x05526c56);
+    value ^= value.wrapping_mul(0x53a22864);
+    value.reverse_bits()
+}
+
+/// The radical inverse of `index` in `base`, with each digit permuted based on
+/// the digits before it.
+fn owen_scrambled_radical_inverse(base: u64, mut index: u64, hash: u64) -> f64 {
+    let limit = u64::MAX / base - base;
+    let inverse_base = 1.0 / base as f64;
+
+    let mut inverse_base_m = 1.0;
+    let mut reversed_digits: u64 = 0;
+    // keep going past the index's last digit, since its trailing zeros get scrambled too
+    while 1.0 - (base - 1) as f64 * inverse_base_m < 1.0 && reversed_digits < limit {
+        let next = index / base;
+        let digit = index - next * base;
+        let digit_hash = mix_bits(hash ^ reversed_digits);
+        let digit = permutation_element(digit as u32, base as u32, digit_hash as u32) as u64;
+
+        reversed_digits = reversed_digits * base + digit;
+        inverse_base_m *= inverse_base;
+        index = next;
+    }
+
+    (reversed_digits as f64 * inverse_base_m).min(ONE_MINUS_EPSILON)
+}
+
+/// The `index`th element of a random permutation of `0..length`, chosen by
+/// `seed` (Kensler 2013).
+fn permutation_element(mut index: u32, length: u32, seed: u32) -> u32 {
+    let mut mask = length - 1;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+
+    loop {
+        index ^= seed;
+        index = index.wrapping_mul(0xe170893d);
+        index ^= seed >> 16;
+        index ^= (index & mask) >> 4;
+        index ^= seed >> 8;
+        index = index.wrapping_mul(0x0929eb3f);
+        index ^= seed >> 23;
+        index ^= (index & mask) >> 1;
+        index = index.wrapping_mul(1 | seed >> 27);
+        index = index.wrapping_mul(0x6935fa69);
+        index ^= (index & mask) >> 11;
+        index = index.wrapping_mul(0x74dcb303);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0x9e501cc3);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0xc860a3df);
+        index &= mask;
+        index ^= index >> 5;
+
+        // cycle walk until landing back within the permutation's range
+        if index < length {
+            return (index + seed) % length;
+        }
+    }
+}
+
+const PRIMES: [u64; 1000] = first_primes();
+
+const fn first_primes<const N: usize>() -> [u64; N] {
+    let mut primes = [0; N];
+    let mut count = 0;
+    let mut candidate = 2;
+    while count < N {
+        let mut is_prime = true;
+        let mut i = 0;
+        while i < count && primes[i] * primes[i] <= candidate {
+            if candidate % primes[i] == 0 {
+                is_prime = false;
+                break;
+            }
+            i += 1;
+        }
+        if is_prime {
+            primes[count] = candidate;
+            count += 1;
+        }
+        candidate += 1;
+    }
+    primes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every aligned power-of-two run of a pixel's samples should put exactly one
+    /// sample in each of that many equal intervals, in every dimension.
+    #[test]
+    fn scrambled_sequences_stay_stratified() {
+        for sampler_type in [SamplerType::Sobol, SamplerType::Halton] {
+            let mut sampler = sampler_type.create(0);
+            sampler.start_pixel((3, 7), 16, 16);
+
+            let mut strata = [[false; 16]; 2];
+            for sample in 0..16 {
+                sampler.start_sample(sample);
+                let (x, y) = sampler.get_2d();
+                for (dimension, value) in [x, y].into_iter().enumerate() {
+                    assert!((0.0..1.0).contains(&value));
+                    // Halton's second dimension is in base 3, so only check base 2
+                    if sampler_type == SamplerType::Halton && dimension == 1 {
+                        continue;
+                    }
+                    let stratum = (value * 16.0) as usize;
+                    assert!(
+                        !strata[dimension][stratum],
+                        "{sampler_type:?} repeated a stratum"
+                    );
+                    strata[dimension][stratum] = true;
+                }
+            }
+        }
+    }
+}
@@ -3,12 +3,15 @@ use std::sync::Arc;
 use crate::{
     camera::Camera,
     geometry::{
-        Geometric,
-        compounds::{Bvh, List},
+        Aabb, Geometric,
+        compounds::{Bvh, EmitterList, List},
     },
-    shading::{ColorSpectrum, color_spectrum::SPECTRAL_SAMPLE_COUNT},
+    shading::{ColorSpectrum, Environment, Light, color_spectrum::SPECTRAL_SAMPLE_COUNT},
+    utils::Distribution1D,
 };
 
+use super::MaterialIds;
+
 #[derive(Clone)]
 pub struct Scene {
     pub world: SceneWorld,
@@ -25,6 +28,21 @@ pub struct SceneWorld {
     pub transmissive_list: Arc<dyn Geometric>,
     pub specular_list: Arc<dyn Geometric>,
     pub virtual_list: Arc<dyn Geometric>,
+
+    /// Lights that aren't geometry, which rays can't hit.
+    pub lights: Arc<Vec<Light>>,
+    /// The chance of picking each of `lights`, by the power it sends out.
+    /// `None` if there are none.
+    pub light_distribution: Option<Arc<Distribution1D>>,
+    /// The bounds of everything in the world that has any, which directional
+    /// lights shine onto.
+    pub bounds: Aabb,
+
+    /// If set, what rays that escape the scene see, in place of the
+    /// background color.
+    pub environment: Option<Arc<Environment>>,
+
+    pub material_ids: MaterialIds,
 }
 
 impl SceneWorld {
@@ -32,11 +50,16 @@ impl SceneWorld {
     /// with empty category lists (no importance sampling).
     pub fn from_world_without_importance_sampling(world: Arc<dyn Geometric>) -> Self {
         SceneWorld {
+            bounds: world.bounding_box(),
             world,
             emissive_list: Arc::new(List::from_vec(Vec::new())),
             transmissive_list: Arc::new(List::from_vec(Vec::new())),
             specular_list: Arc::new(List::from_vec(Vec::new())),
             virtual_list: Arc::new(List::from_vec(Vec::new())),
+            lights: Arc::new(Vec::new()),
+            light_distribution: None,
+            environment: None,
+            material_ids: MaterialIds::default(),
         }
     }
 
@@ -58,6 +81,9 @@ impl SceneWorld {
             }
         }
 
+        let bounds = bounded.iter().fold(Aabb::EMPTY, |bounds, geometric| {
+            bounds.expand(geometric.bounding_box())
+        });
         let compiled_world: Arc<dyn Geometric> = {
             let bounded_list = List::from_vec(bounded);
             let bounded_compiled: Arc<dyn Geometric> =
@@ -97,10 +123,41 @@ impl SceneWorld {
         SceneWorld {
             world: compiled_world,
 
-            emissive_list: Arc::new(List::from_vec(emissives)),
+            emissive_list: Arc::new(EmitterList::new(emissives)),
             transmissive_list: Arc::new(List::from_vec(transmissives)),
             specular_list: Arc::new(List::from_vec(speculars)),
             virtual_list: Arc::new(List::from_vec(world_virtual.to_owned())),
+
+            lights: Arc::new(Vec::new()),
+            light_distribution: None,
+            bounds,
+
+            environment: None,
+
+            material_ids: MaterialIds::default(),
         }
     }
+
+    pub fn with_material_ids(mut self, material_ids: MaterialIds) -> Self {
+        self.material_ids = material_ids;
+        self
+    }
+
+    pub fn with_lights(mut self, lights: Vec<Light>) -> Self {
+        self.light_distribution = (!lights.is_empty()).then(|| {
+            Arc::new(Distribution1D::new(
+                lights
+                    .iter()
+                    .map(|light| light.power(self.bounds))
+                    .collect(),
+            ))
+        });
+        self.lights = Arc::new(lights);
+        self
+    }
+
+    pub fn with_environment(mut self, environment: Option<Arc<Environment>>) -> Self {
+        self.environment = environment;
+        self
+    }
 }
@@ -16,9 +16,13 @@ use image::RgbaImage;
 
 use serde::{Deserialize, Serialize};
 
-use crate::{deserialization::RenderConfig, shading::ColorRgb, utils::ProgressInfo};
+use crate::{
+    deserialization::RenderConfig,
+    shading::{ColorRgb, ToneMapping},
+    utils::ProgressInfo,
+};
 
-use super::{PixelData, RenderParameters};
+use super::{Aov, HdrImage, Pixel, PixelData, RenderParameters, denoise};
 
 pub type RenderID = u32;
 
@@ -95,6 +99,14 @@ pub struct RenderCheckpoint {
 
 impl RenderCheckpoint {
     pub fn as_image(&self, params: &RenderParameters) -> RgbaImage {
+        self.as_tone_mapped_image(params, &params.effective_tone_mapping())
+    }
+
+    pub fn as_tone_mapped_image(
+        &self,
+        params: &RenderParameters,
+        tone_mapping: &ToneMapping,
+    ) -> RgbaImage {
         // we have to turn our pixel_data into an image
         let (width, height) = params.image_dimensions;
         let mut img = RgbaImage::new(width, height);
@@ -103,18 +115,84 @@ impl RenderCheckpoint {
             Some(pd) => pd,
             None => return RgbaImage::new(params.image_dimensions.0, params.image_dimensions.1),
         };
-        for ((x, y), color) in pixel_data.iter() {
+        for ((x, y), pixel_state) in pixel_data.iter() {
             let pixel = img.get_pixel_mut(*x, *y);
-            *pixel = if params.use_scaling_truncation {
-                color.scale_down(1.0).encode_to_srgb_u8()
-            } else {
-                color.encode_to_srgb_u8()
-            }
+            *pixel = tone_mapping.apply(pixel_state.color).encode_to_srgb_u8();
         }
 
         img
     }
 
+    /// The linear radiance of this checkpoint, without any tone mapping or clamping.
+    pub fn as_hdr_image(&self, params: &RenderParameters) -> HdrImage {
+        let (width, height) = params.image_dimensions;
+        let mut image = HdrImage::new(self.render_id, self.iteration, width, height);
+
+        let pixel_data = match &self.pixel_data {
+            Some(pd) => pd,
+            None => return image,
+        };
+        // pixels without a stored count were sampled evenly
+        let legacy_sample_count = self.iteration * params.samples_per_checkpoint;
+        for ((x, y), pixel) in pixel_data.iter() {
+            let index = image.index_of(*x, *y);
+            image.pixels[index] = pixel.color;
+            image.samples_per_pixel[index] = match pixel.sample_count {
+                0 => legacy_sample_count,
+                n => n,
+            };
+        }
+
+        image
+    }
+
+    /// A copy of this checkpoint with its colors denoised, guided by the AOVs if
+    /// the render stored them.
+    pub fn denoised(&self, params: &RenderParameters) -> RenderCheckpoint {
+        RenderCheckpoint {
+            pixel_data: self
+                .pixel_data
+                .as_ref()
+                .map(|pixel_data| denoise(pixel_data, params.image_dimensions)),
+            ..self.clone()
+        }
+    }
+
+    /// An AOV of this checkpoint, prepared for viewing. `None` if the render
+    /// didn't store AOVs.
+    pub fn as_aov_image(
+        &self,
+        params: &RenderParameters,
+        aov: Aov,
+        tone_mapping: &ToneMapping,
+    ) -> Option<RgbaImage> {
+        aov.as_image(
+            self.pixel_data.as_ref()?,
+            params.image_dimensions,
+            tone_mapping,
+        )
+    }
+
+    /// The raw values of an AOV of this checkpoint. `None` if the render
+    /// didn't store AOVs.
+    pub fn as_aov_hdr_image(&self, params: &RenderParameters, aov: Aov) -> Option<HdrImage> {
+        let (width, height) = params.image_dimensions;
+        let mut image = HdrImage::new(self.render_id, self.iteration, width, height);
+
+        let mut has_aovs = false;
+        for ((x, y), pixel) in self.pixel_data.as_ref()? {
+            let Some(aovs) = &pixel.aovs else {
+                continue;
+            };
+            has_aovs = true;
+            let index = image.index_of(*x, *y);
+            image.pixels[index] = aovs.value(aov);
+            image.samples_per_pixel[index] = aovs.sample_count;
+        }
+
+        has_aovs.then_some(image)
+    }
+
     pub fn from_image(
         render_id: RenderID,
         iteration: u32,
@@ -124,7 +202,10 @@ impl RenderCheckpoint {
     ) -> Self {
         let mut pixel_data = PixelData::new();
         for (x, y, pixel) in image.enumerate_pixels() {
-            pixel_data.insert((x, y), ColorRgb::decode_from_srgb_u8(pixel));
+            pixel_data.insert(
+                (x, y),
+                Pixel::from_color(ColorRgb::decode_from_srgb_u8(pixel)),
+            );
         }
         Self {
             render_id,
@@ -167,6 +248,7 @@ impl From<&RenderCheckpoint> for RenderCheckpointMeta {
     }
 }
 
+#[derive(Debug)]
 pub struct StorageError(pub String);
 
 impl From<String> for StorageError {
@@ -520,12 +602,14 @@ pub trait UserStorage: Send + Sync + 'static {
 #[serde(rename_all = "snake_case")]
 pub enum ResourceType {
     TextureImage,
+    IesProfile,
 }
 
 impl Display for ResourceType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ResourceType::TextureImage => write!(f, "texture_image"),
+            ResourceType::IesProfile => write!(f, "ies_profile"),
         }
     }
 }
@@ -534,6 +618,7 @@ impl From<String> for ResourceType {
     fn from(value: String) -> Self {
         match value.as_str() {
             "texture_image" => Self::TextureImage,
+            "ies_profile" => Self::IesProfile,
             _ => Self::TextureImage, // default fallback
         }
     }
@@ -242,14 +242,13 @@ impl RenderStorage for FileStorage {
 
         let checkpoint_dir = dir.join("checkpoints");
         let checkpoint_image_file = checkpoint_dir.join(format!("{}.png", iteration));
+        let checkpoint_data_file = checkpoint_dir.join(format!("{}.bin", iteration));
         let checkpoint_meta_file = checkpoint_dir.join(format!("{}.json", iteration));
-        match (
-            checkpoint_meta_file.exists(),
-            checkpoint_image_file.exists(),
-        ) {
+        let pixel_data_exists = checkpoint_data_file.exists() || checkpoint_image_file.exists();
+        match (checkpoint_meta_file.exists(), pixel_data_exists) {
             (false, true) => {
                 return Err(format!(
-                    "Checkpoint iteration {} image exists but metadata file is missing for render {}",
+                    "Checkpoint iteration {} pixel data exists but metadata file is missing for render {}",
                     iteration, id
                 ).into());
             }
@@ -262,7 +261,7 @@ impl RenderStorage for FileStorage {
         let meta: RenderCheckpointFileMeta =
             serde_json::from_str(&meta).map_err(|e| e.to_string())?;
 
-        if !checkpoint_image_file.exists() {
+        if !pixel_data_exists {
             if meta.pixel_data_cleared {
                 // pixel data was legitimately cleared — return checkpoint without pixel data
                 return Ok(Some(RenderCheckpoint {
@@ -274,15 +273,30 @@ impl RenderStorage for FileStorage {
                     pixel_data_cleared: true,
                 }));
             } else {
-                // meta says data wasn't cleared but image is missing — corruption
+                // meta says data wasn't cleared but pixel data is missing — corruption
                 return Err(format!(
-                    "Checkpoint iteration {} metadata says pixel data not cleared but image is missing for render {}",
+                    "Checkpoint iteration {} metadata says pixel data not cleared but pixel data is missing for render {}",
                     iteration, id
                 ).into());
             }
         }
 
-        // load checkpoint image
+        // prefer the full-precision sidecar, so resuming continues exactly where the tracer stopped
+        if checkpoint_data_file.exists() {
+            let data = fs::read(&checkpoint_data_file).map_err(|e| e.to_string())?;
+            let pixel_data = utils::decode_pixel_data(&data)?;
+
+            return Ok(Some(RenderCheckpoint {
+                render_id: id,
+                iteration,
+                pixel_data: Some(pixel_data),
+                started_at: meta.started_at,
+                ended_at: meta.ended_at,
+                pixel_data_cleared: false,
+            }));
+        }
+
+        // fall back to the 8-bit preview image for checkpoints written before sidecars existed
         let image = image::open(checkpoint_image_file)
             .map_err(|e| e.to_string())?
             .to_rgba8();
@@ -496,7 +510,8 @@ impl RenderStorage for FileStorage {
 
         let checkpoint_dir = dir.join("checkpoints");
         let checkpoint_image_file = checkpoint_dir.join(format!("{}.png", iteration));
-        Ok(checkpoint_image_file.exists())
+        let checkpoint_data_file = checkpoint_dir.join(format!("{}.bin", iteration));
+        Ok(checkpoint_data_file.exists() || checkpoint_image_file.exists())
     }
 
     async fn create_render_checkpoint(
@@ -516,13 +531,18 @@ impl RenderStorage for FileStorage {
 
         let pixel_data_cleared = checkpoint.pixel_data.is_none();
 
-        // save checkpoint data as a png image if pixel data exists
-        if !pixel_data_cleared {
+        // save checkpoint data if pixel data exists: a png preview for viewing,
+        // and the full-precision accumulation alongside it for resuming
+        if let Some(pixel_data) = &checkpoint.pixel_data {
             let image_file = checkpoint_dir.join(format!("{}.png", checkpoint.iteration));
             let image = checkpoint.as_image(&render.config.parameters);
             image
                 .save_with_format(image_file, image::ImageFormat::Png)
                 .map_err(|e| e.to_string())?;
+
+            let data_file = checkpoint_dir.join(format!("{}.bin", checkpoint.iteration));
+            fs::write(data_file, utils::encode_pixel_data(pixel_data)?)
+                .map_err(|e| e.to_string())?;
         }
 
         let meta_file = checkpoint_dir.join(format!("{}.json", checkpoint.iteration));
@@ -553,6 +573,7 @@ impl RenderStorage for FileStorage {
 
         let checkpoint_dir = dir.join("checkpoints");
         let checkpoint_image_file = checkpoint_dir.join(format!("{}.png", checkpoint));
+        let checkpoint_data_file = checkpoint_dir.join(format!("{}.bin", checkpoint));
         let checkpoint_meta_file = checkpoint_dir.join(format!("{}.json", checkpoint));
 
         // meta file must exist to be able to mark pixel data as cleared
@@ -564,10 +585,13 @@ impl RenderStorage for FileStorage {
             .into());
         }
 
-        // delete the png image file if it exists (pixel data)
+        // delete the png image and full-precision data files if they exist (pixel data)
         if checkpoint_image_file.exists() {
             fs::remove_file(&checkpoint_image_file).map_err(|e| e.to_string())?;
         }
+        if checkpoint_data_file.exists() {
+            fs::remove_file(&checkpoint_data_file).map_err(|e| e.to_string())?;
+        }
 
         // read the meta file, set pixel_data_cleared, and write it back
         let meta_string = fs::read_to_string(&checkpoint_meta_file).map_err(|e| e.to_string())?;
@@ -670,3 +694,101 @@ impl RenderStorage for FileStorage {
         Ok(render_sizes.sum::<Result<u64, String>>()?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        deserialization::RenderConfig,
+        shading::ColorRgb,
+        tracing::{Pixel, PixelData},
+    };
+
+    use super::*;
+
+    /// A directory under the system's temp dir, removed when dropped, even if
+    /// the test panics first.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("luxide-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn checkpoints_load_back_exactly_or_from_their_preview() {
+        let output_dir = TempDir::new("file-storage");
+        let storage = FileStorage::new(output_dir.0.clone()).unwrap();
+
+        let mut config: RenderConfig =
+            serde_json::from_str(include_str!("../../../configs/template.json")).unwrap();
+        config.parameters.image_dimensions = (2, 1);
+        storage
+            .create_render(Render::new(1, config, 1))
+            .await
+            .unwrap();
+
+        // values an 8-bit image can't hold
+        let mut pixel_data = PixelData::new();
+        for (x, color) in [
+            ColorRgb::new(0.123456789, 0.5, 0.9),
+            ColorRgb::new(0.01, 0.7, 0.3),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            pixel_data.insert(
+                (x as u32, 0),
+                Pixel {
+                    color,
+                    weight_sum: 12.5,
+                    sample_count: 16,
+                    luminance_mean: 0.4,
+                    luminance_m2: 0.02,
+                    ..Default::default()
+                },
+            );
+        }
+        let now = chrono::Utc::now();
+        storage
+            .create_render_checkpoint(RenderCheckpoint {
+                render_id: 1,
+                iteration: 1,
+                pixel_data: Some(pixel_data.clone()),
+                started_at: now,
+                ended_at: now,
+                pixel_data_cleared: false,
+            })
+            .await
+            .unwrap();
+
+        let checkpoint = storage.get_render_checkpoint(1, 1).await.unwrap().unwrap();
+        assert_eq!(checkpoint.pixel_data, Some(pixel_data.clone()));
+
+        // without the sidecar, the colors come from the png preview
+        let render_dir = fs::read_dir(&output_dir.0)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        fs::remove_file(render_dir.join("checkpoints").join("1.bin")).unwrap();
+        let checkpoint = storage.get_render_checkpoint(1, 1).await.unwrap().unwrap();
+        let loaded = checkpoint.pixel_data.unwrap();
+        assert_eq!(loaded.len(), pixel_data.len());
+        for (coords, pixel) in &pixel_data {
+            let loaded = &loaded[coords];
+            let [r, g, b]: [f64; 3] = (loaded.color - pixel.color).into();
+            assert!(r.abs().max(g.abs()).max(b.abs()) < 0.01, "{coords:?}");
+            assert_eq!(loaded.weight_sum, 0.0);
+        }
+    }
+}
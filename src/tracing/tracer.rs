@@ -4,9 +4,18 @@ use rand::seq::SliceRandom;
 use rayon::prelude::*;
 use tokio::sync::mpsc;
 
-use crate::{deserialization::RenderData, shading::ColorRgb};
+use crate::{
+    deserialization::RenderData,
+    shading::ColorRgb,
+    utils::{hash_u64s, seed_thread_rng},
+};
 
-pub type PixelData = HashMap<(u32, u32), ColorRgb>;
+use super::{
+    AdaptiveSamplingConfig, AovSample, Filter, PhotonPass, Pixel, PixelSamples, Region,
+    RenderParameters,
+};
+
+pub type PixelData = HashMap<(u32, u32), Pixel>;
 
 #[derive(Debug, Copy, Clone)]
 pub struct ProgressPacket {
@@ -64,40 +73,100 @@ impl Tracer {
 
         cam.initialize(parameters, scene);
 
-        let tiles = Tiles::new(parameters.image_dimensions, parameters.tile_dimensions);
+        // `checkpoint` counts across all frames of an animation
+        let frame_iteration = parameters.frame_iteration(checkpoint);
+        cam.set_frame_start_time(parameters.frame_start_time(frame_iteration.frame));
+
+        recover_legacy_pixels(
+            &mut pixel_data,
+            (frame_iteration.checkpoint - 1) * parameters.samples_per_checkpoint,
+        );
+
+        let tiles = Tiles::new(parameters.render_region(), parameters.tile_dimensions)
+            .collect::<Vec<Tile>>();
+        let tile_sample_counts = tile_sample_counts(&tiles, &pixel_data, parameters);
+
+        // each pixel reseeds its thread's random stream from this, so results
+        // don't depend on which thread renders which tile, or in what order
+        let checkpoint_seed = match parameters.seed {
+            Some(seed) => hash_u64s(&[seed, checkpoint as u64]),
+            None => rand::random(),
+        };
+
+        // some integrators trace light from the emitters for the whole round
+        // before any camera samples are taken
+        let integrator = self.thread_pool.install(|| {
+            parameters
+                .integrator
+                .create(&cam, &scene.world, parameters, checkpoint_seed)
+        });
+        let light_image = integrator.light_image();
+        let photon_map = integrator.photon_map();
 
         let mut rng = rand::rng();
 
-        let mut tiles = tiles.collect::<Vec<Tile>>();
+        let mut tiles = tiles
+            .into_iter()
+            .zip(tile_sample_counts)
+            .enumerate()
+            .collect::<Vec<_>>();
         tiles.shuffle(&mut rng);
-        let colors = self.thread_pool.install(|| {
+        let mut tile_results = self.thread_pool.install(|| {
             let tiles = tiles.par_iter();
 
-            let colors: PixelData = tiles
-                .flat_map(|tile| {
-                    let tile_colors: PixelData = tile
+            let tile_results: Vec<(usize, TileResult)> = tiles
+                .map(|(tile_index, (tile, tile_sample_count))| {
+                    // unlike the random streams, the sequence scrambling stays the same
+                    // across checkpoints, so each checkpoint continues the last one's sequence
+                    let mut sampler = parameters
+                        .sampler
+                        .create(parameters.seed.unwrap_or_default());
+                    let mut splats = TileSplats::new(tile, parameters);
+
+                    let pixel_samples: Vec<((u32, u32), PixelSamples, PhotonPass)> = tile
                         .map(|(x, y)| {
-                            let grid_size =
-                                (parameters.samples_per_checkpoint as f64).sqrt().floor() as u32;
-                            let stratified_count = grid_size * grid_size;
-
-                            let color = (0..parameters.samples_per_checkpoint).fold(
-                                ColorRgb::BLACK,
-                                |acc, i| {
-                                    let ray = if i < stratified_count {
-                                        cam.get_ray_stratified(
-                                            x,
-                                            y,
-                                            i,
-                                            parameters.samples_per_checkpoint,
-                                        )
-                                    } else {
-                                        cam.get_ray(x, y)
-                                    };
-
-                                    acc + cam.ray_color(ray, &scene.world)
-                                },
-                            );
+                            let previous = pixel_data.get(&(x, y)).cloned().unwrap_or_default();
+
+                            let sample_count = match parameters.adaptive_sampling {
+                                Some(adaptive) if is_converged(&previous, &adaptive) => 0,
+                                _ => *tile_sample_count,
+                            };
+
+                            seed_thread_rng(hash_u64s(&[checkpoint_seed, x as u64, y as u64]));
+                            sampler.start_pixel((x, y), previous.sample_count, sample_count);
+
+                            let mut samples = PixelSamples::default();
+                            let mut photon_pass = PhotonPass {
+                                radius: previous.photons.as_ref().map(|photons| photons.radius),
+                                ..Default::default()
+                            };
+                            for i in 0..sample_count {
+                                sampler.start_sample(i);
+
+                                let (u, v) = sampler.get_pixel_2d();
+                                let film_position = (x as f64 + u, y as f64 + v);
+
+                                let ray = cam.get_ray(film_position, sampler.as_mut());
+                                let sample = match ray {
+                                    Some(ray) => {
+                                        integrator.sample(&cam, ray, &scene.world, sampler.as_mut())
+                                    }
+                                    None => (ColorRgb::BLACK, AovSample::default()).into(),
+                                };
+                                if let Some(photon_map) = photon_map {
+                                    photon_pass.gather(
+                                        photon_map,
+                                        sample.visible_point.as_ref(),
+                                        parameters.photon_mapping.initial_radius,
+                                    );
+                                }
+                                if parameters.store_aovs {
+                                    samples.add_aovs(&sample.aovs);
+                                }
+
+                                samples.add(sample.color);
+                                splats.add(&parameters.filter, film_position, sample.color);
+                            }
                             // done with pixel!
 
                             // send progress
@@ -106,57 +175,259 @@ impl Tracer {
                                 println!("Failed to send progress! {:?}", e);
                             }
 
-                            // average samples together
-                            let scaled_color = color / parameters.samples_per_checkpoint as f64;
-
-                            // scale relative to the current round
-                            let img_color = pixel_data.get(&(x, y)).unwrap_or(&ColorRgb::BLACK);
-                            let weighted_color = (img_color * (checkpoint - 1) as f64
-                                + scaled_color)
-                                / checkpoint as f64;
-
-                            // guard against NaNs
-                            let safe_color = weighted_color.de_nan();
-
-                            // send final pixel color
-                            ((x, y), safe_color)
+                            ((x, y), samples, photon_pass)
                         })
                         .collect();
 
                     // done with tile!
 
-                    tile_colors
+                    (
+                        *tile_index,
+                        TileResult {
+                            pixel_samples,
+                            splats,
+                        },
+                    )
                 })
                 .collect();
 
-            colors
+            tile_results
         });
 
         // no longer needed, no reason to wait for the map insertions to finish
         drop(progress_sender);
 
-        for ((x, y), color) in colors {
-            pixel_data.insert((x, y), color);
+        // splats near tile edges overlap, so add them up in a fixed order to get
+        // the same sums no matter how the tiles were scheduled
+        tile_results.sort_unstable_by_key(|(tile_index, _)| *tile_index);
+
+        let (width, height) = parameters.image_dimensions;
+        let mut splat_sums = vec![(ColorRgb::BLACK, 0.0); (width * height) as usize];
+        for (_, result) in &tile_results {
+            result.splats.add_to(&mut splat_sums, width);
+        }
+
+        // fold this round's samples into the running averages
+        for (_, result) in tile_results {
+            for ((x, y), samples, photon_pass) in result.pixel_samples {
+                let (color_sum, weight_sum) = splat_sums[(y * width + x) as usize];
+                let light_color = light_image
+                    .as_ref()
+                    .map_or(ColorRgb::BLACK, |light_image| light_image.pixel((x, y)));
+                let round = Pixel {
+                    color: splat_average(color_sum, weight_sum) + light_color,
+                    weight_sum,
+                    ..samples.into_pixel()
+                };
+
+                let previous = pixel_data.remove(&(x, y)).unwrap_or_default();
+                let pixel = match photon_map {
+                    Some(photon_map) => previous.merge_photon_pass(
+                        &round,
+                        &photon_pass,
+                        photon_map.emitted(),
+                        parameters.photon_mapping.alpha,
+                    ),
+                    None => previous.merge(&round),
+                };
+                pixel_data.insert((x, y), pixel);
+            }
         }
 
         pixel_data
     }
 }
 
+struct TileResult {
+    pixel_samples: Vec<((u32, u32), PixelSamples, PhotonPass)>,
+    splats: TileSplats,
+}
+
+/// Filter-weighted sums of a tile's samples, covering the tile and the margin
+/// around it that its samples' splats can reach.
+///
+/// Each tile keeps its own, so tiles can be rendered in parallel and their
+/// overlapping margins added together afterwards.
+struct TileSplats {
+    origin: (i64, i64),
+    dimensions: (i64, i64),
+    image_dimensions: (i64, i64),
+    sums: Vec<(ColorRgb, f64)>,
+}
+
+impl TileSplats {
+    fn new(tile: &Tile, parameters: &RenderParameters) -> Self {
+        let reach = parameters.filter.pixel_reach() as i64;
+        let origin = (tile.origin.0 as i64 - reach, tile.origin.1 as i64 - reach);
+        let dimensions = (
+            tile.dimensions.0 as i64 + 2 * reach,
+            tile.dimensions.1 as i64 + 2 * reach,
+        );
+
+        Self {
+            origin,
+            dimensions,
+            image_dimensions: (
+                parameters.image_dimensions.0 as i64,
+                parameters.image_dimensions.1 as i64,
+            ),
+            sums: vec![(ColorRgb::BLACK, 0.0); (dimensions.0 * dimensions.1) as usize],
+        }
+    }
+
+    fn add(&mut self, filter: &Filter, film_position: (f64, f64), color: ColorRgb) {
+        let color = color.de_nan();
+        let (x_min, x_max) = filter.pixel_range(film_position.0);
+        let (y_min, y_max) = filter.pixel_range(film_position.1);
+
+        for y in y_min.max(self.origin.1)..=y_max.min(self.origin.1 + self.dimensions.1 - 1) {
+            for x in x_min.max(self.origin.0)..=x_max.min(self.origin.0 + self.dimensions.0 - 1) {
+                let weight = filter.evaluate((
+                    film_position.0 - (x as f64 + 0.5),
+                    film_position.1 - (y as f64 + 0.5),
+                ));
+                if weight == 0.0 {
+                    continue;
+                }
+
+                let index = (y - self.origin.1) * self.dimensions.0 + (x - self.origin.0);
+                let (color_sum, weight_sum) = &mut self.sums[index as usize];
+                *color_sum += color * weight;
+                *weight_sum += weight;
+            }
+        }
+    }
+
+    /// Adds these sums into sums covering the whole image.
+    fn add_to(&self, image_sums: &mut [(ColorRgb, f64)], image_width: u32) {
+        for y in
+            self.origin.1.max(0)..(self.origin.1 + self.dimensions.1).min(self.image_dimensions.1)
+        {
+            for x in self.origin.0.max(0)
+                ..(self.origin.0 + self.dimensions.0).min(self.image_dimensions.0)
+            {
+                let (color_sum, weight_sum) = self.sums
+                    [((y - self.origin.1) * self.dimensions.0 + (x - self.origin.0)) as usize];
+                let image_sum = &mut image_sums[(y * image_width as i64 + x) as usize];
+                image_sum.0 += color_sum;
+                image_sum.1 += weight_sum;
+            }
+        }
+    }
+}
+
+/// The color of a pixel's splat sums. Negative filter lobes can leave the
+/// weights summing to less than zero, which still averages the colors.
+/// Fills in the sample counts and filter weights of pixels loaded from data
+/// written before they were stored. Such renders sampled every pixel evenly,
+/// `sample_count` times over all the checkpoints stored...
+pub fn recover_legacy_pixels(pixel_data: &mut PixelData, sample_count: u32) {
+    for pixel in pixel_data.values_mut() {
+        if pixel.sample_count == 0 {
+            pixel.sample_count = sample_count;
+        }
+        // ...and their pixels only ever averaged their own samples
+        if pixel.weight_sum == 0.0 {
+            pixel.weight_sum = pixel.sample_count as f64;
+        }
+    }
+}
+
+fn splat_average(color_sum: ColorRgb, weight_sum: f64) -> ColorRgb {
+    if weight_sum != 0.0 {
+        color_sum / weight_sum
+    } else {
+        ColorRgb::BLACK
+    }
+}
+
+fn is_converged(pixel: &Pixel, adaptive: &AdaptiveSamplingConfig) -> bool {
+    pixel.sample_count >= adaptive.min_samples
+        && pixel
+            .relative_error()
+            .is_some_and(|error| error < adaptive.noise_threshold)
+}
+
+/// Decides how many samples each pixel of each tile takes this round.
+///
+/// Without adaptive sampling every tile gets `samples_per_checkpoint`. With it,
+/// tiles get samples in proportion to the mean error of their unconverged pixels,
+/// relative to the other tiles.
+fn tile_sample_counts(
+    tiles: &[Tile],
+    pixel_data: &PixelData,
+    parameters: &RenderParameters,
+) -> Vec<u32> {
+    let samples_per_checkpoint = parameters.samples_per_checkpoint;
+    let adaptive = match parameters.adaptive_sampling {
+        Some(adaptive) => adaptive,
+        None => return vec![samples_per_checkpoint; tiles.len()],
+    };
+
+    // None for tiles with pixels that haven't got enough samples to judge yet
+    let tile_errors = tiles
+        .iter()
+        .map(|tile| {
+            let mut error_sum = 0.0;
+            let mut noisy_pixels = 0;
+            for (x, y) in *tile {
+                let pixel = pixel_data.get(&(x, y)).cloned().unwrap_or_default();
+                if pixel.sample_count < adaptive.min_samples {
+                    return None;
+                }
+                if let Some(error) = pixel.relative_error()
+                    && error >= adaptive.noise_threshold
+                {
+                    error_sum += error;
+                    noisy_pixels += 1;
+                }
+            }
+            Some(if noisy_pixels > 0 {
+                error_sum / noisy_pixels as f64
+            } else {
+                0.0
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let noisy_tile_errors = tile_errors
+        .iter()
+        .flatten()
+        .filter(|error| **error > 0.0)
+        .collect::<Vec<_>>();
+    if noisy_tile_errors.is_empty() {
+        return vec![samples_per_checkpoint; tiles.len()];
+    }
+    let mean_error =
+        noisy_tile_errors.iter().copied().sum::<f64>() / noisy_tile_errors.len() as f64;
+
+    let max_samples =
+        ((samples_per_checkpoint as f64 * adaptive.max_sample_multiplier).round() as u32).max(1);
+    tile_errors
+        .into_iter()
+        .map(|error| match error {
+            Some(error) if error > 0.0 => ((samples_per_checkpoint as f64 * error / mean_error)
+                .round() as u32)
+                .clamp(1, max_samples),
+            _ => samples_per_checkpoint,
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, Copy)]
 struct Tiles {
-    image_dimensions: (u32, u32),
+    region: Region,
     tile_dimensions: (u32, u32),
 
     current_origin: (u32, u32),
 }
 
 impl Tiles {
-    pub fn new(image_dimensions: (u32, u32), tile_dimensions: (u32, u32)) -> Self {
+    pub fn new(region: Region, tile_dimensions: (u32, u32)) -> Self {
         Self {
-            image_dimensions,
+            region,
             tile_dimensions,
-            current_origin: (0, 0),
+            current_origin: region.origin,
         }
     }
 }
@@ -165,12 +436,17 @@ impl Iterator for Tiles {
     type Item = Tile;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.current_origin.0 >= self.image_dimensions.0 {
-            self.current_origin.0 = 0;
+        let end = (
+            self.region.origin.0 + self.region.dimensions.0,
+            self.region.origin.1 + self.region.dimensions.1,
+        );
+
+        if self.current_origin.0 >= end.0 {
+            self.current_origin.0 = self.region.origin.0;
             self.current_origin.1 += self.tile_dimensions.1;
         }
 
-        if self.current_origin.1 >= self.image_dimensions.1 {
+        if self.current_origin.1 >= end.1 {
             return None;
         }
 
@@ -182,8 +458,8 @@ impl Iterator for Tiles {
         Some(Tile::new(
             this_origin,
             (
-                width.min(self.image_dimensions.0 - this_origin.0),
-                height.min(self.image_dimensions.1 - this_origin.1),
+                width.min(end.0 - this_origin.0),
+                height.min(end.1 - this_origin.1),
             ),
         ))
     }
@@ -252,3 +528,168 @@ impl Threads {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ops::RangeInclusive;
+
+    use crate::deserialization::{RenderConfig, RenderConfigBuilder};
+
+    use super::*;
+
+    /// The template config, seeded, as a single 8x8 tile.
+    fn template_config() -> RenderConfig {
+        let mut config: RenderConfig =
+            serde_json::from_str(include_str!("../../configs/template.json")).unwrap();
+        config.parameters.image_dimensions = (8, 8);
+        config.parameters.tile_dimensions = (8, 8);
+        config.parameters.seed = Some(7);
+        config
+    }
+
+    fn compile(config: RenderConfig) -> RenderData {
+        RenderConfigBuilder::from(config)
+            .with_builtins()
+            .build()
+            .compile(None)
+            .unwrap()
+    }
+
+    /// Renders each of `iterations` in turn on top of `pixel_data`.
+    fn render_iterations(
+        render_data: &RenderData,
+        pixel_data: PixelData,
+        iterations: RangeInclusive<u32>,
+        threads: usize,
+    ) -> PixelData {
+        let tracer = Tracer::from_threads(Threads::Count(NonZero::new(threads).unwrap()));
+        let (progress_sender, _progress_receiver) = mpsc::unbounded_channel();
+        iterations.fold(pixel_data, |pixel_data, iteration| {
+            tracer.render_to_checkpoint_iteration(
+                iteration,
+                pixel_data,
+                render_data,
+                progress_sender.clone(),
+            )
+        })
+    }
+
+    #[test]
+    fn seeded_renders_match_across_thread_counts() {
+        let mut config = template_config();
+        config.parameters.image_dimensions = (12, 12);
+        config.parameters.tile_dimensions = (5, 5);
+        config.parameters.samples_per_checkpoint = 4;
+        config.parameters.seed = Some(42);
+        let render_data = compile(config);
+
+        let render =
+            |threads: usize| render_iterations(&render_data, PixelData::new(), 1..=2, threads);
+
+        assert_eq!(render(1), render(4));
+    }
+
+    #[test]
+    fn region_renders_merge_into_only_their_pixels() {
+        let mut config = template_config();
+        config.parameters.image_dimensions = (12, 12);
+        config.parameters.tile_dimensions = (5, 5);
+        config.parameters.samples_per_checkpoint = 2;
+        let mut render_data = compile(config);
+
+        let full = render_iterations(&render_data, PixelData::new(), 1..=1, 2);
+
+        let region = Region {
+            origin: (3, 4),
+            dimensions: (6, 3),
+        };
+        render_data.parameters.region = Some(region);
+        let refined = render_iterations(&render_data, full.clone(), 2..=2, 2);
+
+        assert_eq!(refined.len(), full.len());
+        for (coords, pixel) in &refined {
+            let inside = (3..9).contains(&coords.0) && (4..7).contains(&coords.1);
+            if inside {
+                assert_eq!(pixel.sample_count, 4);
+                assert!(pixel.weight_sum > full[coords].weight_sum);
+            } else {
+                assert_eq!(pixel, &full[coords]);
+            }
+        }
+    }
+
+    #[test]
+    fn refining_pixels_saved_as_colors_only_counts_the_stored_samples() {
+        let mut config = template_config();
+        config.parameters.samples_per_checkpoint = 3;
+        config.parameters.total_checkpoints = 2;
+        let mut render_data = compile(config);
+
+        // a finished render of two checkpoints, whose pixel data is only the colors
+        let full = render_iterations(&render_data, PixelData::new(), 1..=2, 1);
+        let mut legacy = full
+            .iter()
+            .map(|(&coords, pixel)| (coords, Pixel::from_color(pixel.color)))
+            .collect::<PixelData>();
+
+        // refined as a third checkpoint, at another sample count
+        recover_legacy_pixels(&mut legacy, 2 * 3);
+        render_data.parameters.total_checkpoints = 3;
+        render_data.parameters.samples_per_checkpoint = 5;
+        render_data.parameters.region = Some(Region {
+            origin: (0, 0),
+            dimensions: (4, 8),
+        });
+        let refined = render_iterations(&render_data, legacy, 3..=3, 1);
+
+        for (coords, pixel) in &refined {
+            if coords.0 < 4 {
+                assert_eq!(pixel.sample_count, 6 + 5);
+            } else {
+                assert_eq!(pixel.sample_count, 6);
+                assert_eq!(pixel.weight_sum, 6.0);
+                assert_eq!(pixel.color, full[coords].color);
+            }
+        }
+    }
+
+    #[test]
+    fn negative_filter_weight_sums_still_average_their_samples() {
+        let mut parameters = template_config().parameters;
+        parameters.image_dimensions = (4, 1);
+        parameters.filter = Filter::Mitchell {
+            radius: 2.0,
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        };
+        let tile = Tile::new((0, 0), (4, 1));
+
+        // pixel 0's center is 1.5 pixels from both samples, in the filter's negative lobe
+        let color = ColorRgb::new(0.2, 0.4, 0.8);
+        let mut splats = TileSplats::new(&tile, &parameters);
+        splats.add(&parameters.filter, (2.0, 0.5), color);
+        splats.add(&parameters.filter, (2.0, 0.5), color);
+        let mut sums = vec![(ColorRgb::BLACK, 0.0); 4];
+        splats.add_to(&mut sums, 4);
+
+        let (color_sum, weight_sum) = sums[0];
+        assert!(weight_sum < 0.0, "{weight_sum}");
+        let round = Pixel {
+            color: splat_average(color_sum, weight_sum),
+            weight_sum,
+            ..Default::default()
+        };
+        assert!((round.color - color).luminance().abs() < 1e-12);
+
+        // and merging into an empty pixel or one with the same color keeps it
+        let merged = Pixel::default().merge(&round);
+        assert!((merged.color - color).luminance().abs() < 1e-12);
+        let previous = Pixel {
+            color,
+            weight_sum: 3.0,
+            ..Default::default()
+        };
+        let merged = previous.merge(&round);
+        assert!((merged.color - color).luminance().abs() < 1e-12);
+    }
+}
@@ -7,9 +7,15 @@ pub use around::*;
 mod binary;
 pub use binary::*;
 
+mod distribution;
+pub use distribution::*;
+
 mod interval;
 pub use interval::Interval;
 
+mod keyframes;
+pub use keyframes::*;
+
 mod progress;
 pub use progress::*;
 
@@ -22,5 +28,8 @@ pub use time::*;
 mod units;
 pub use units::*;
 
+mod random;
+pub use random::*;
+
 mod quadratic;
-pub use quadratic::{solve_quadratic, QuadraticRoots, QuadraticRootsIter};
+pub use quadratic::{QuadraticRoots, QuadraticRootsIter, solve_quadratic};
@@ -1,13 +1,47 @@
+use std::collections::HashMap;
+
 use bincode::config;
 
-use crate::tracing::PixelData;
+use crate::{
+    shading::ColorRgb,
+    tracing::{Pixel, PixelData},
+};
+
+/// Marks pixel data written with per-pixel sample statistics, filter
+/// weights, AOVs and photon mapping state.
+///
+/// Legacy data starts with bincode's varint map length, which can never begin
+/// with `0xFF`, so the two formats can't be confused.
+const PIXEL_DATA_MAGIC: u8 = 0xFF;
+const PIXEL_DATA_VERSION: u8 = 1;
 
 pub fn encode_pixel_data(data: &PixelData) -> Result<Vec<u8>, String> {
-    bincode::encode_to_vec(data, config::standard()).map_err(|e| e.to_string())
+    let mut encoded = vec![PIXEL_DATA_MAGIC, PIXEL_DATA_VERSION];
+    bincode::encode_into_std_write(data, &mut encoded, config::standard())
+        .map_err(|e| e.to_string())?;
+    Ok(encoded)
 }
 
 pub fn decode_pixel_data(data: &[u8]) -> Result<PixelData, String> {
-    bincode::decode_from_slice(data, config::standard())
-        .map(|(pixel_data, _)| pixel_data)
-        .map_err(|e| e.to_string())
+    match data {
+        [PIXEL_DATA_MAGIC, PIXEL_DATA_VERSION, rest @ ..] => {
+            bincode::decode_from_slice(rest, config::standard())
+                .map(|(pixel_data, _)| pixel_data)
+                .map_err(|e| e.to_string())
+        }
+        [PIXEL_DATA_MAGIC, version, ..] => {
+            Err(format!("Unsupported pixel data version: {version}"))
+        }
+        // legacy data only holds the colors, so the sample counts are unknown
+        _ => {
+            bincode::decode_from_slice::<HashMap<(u32, u32), ColorRgb>, _>(data, config::standard())
+                .map(|(colors, _)| {
+                    colors
+                        .into_iter()
+                        .map(|(coords, color)| (coords, Pixel::from_color(color)))
+                        .collect()
+                })
+                .map_err(|e| e.to_string())
+        }
+    }
 }
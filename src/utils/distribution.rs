@@ -0,0 +1,151 @@
+use crate::tracing::ONE_MINUS_EPSILON;
+
+/// A piecewise-constant density over [0, 1), proportional to a function
+/// tabulated in equal steps, sampled by inverting its CDF.
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    function: Vec<f64>,
+    /// `cdf[i]` is the probability of landing before step `i`
+    cdf: Vec<f64>,
+    /// the function's integral over [0, 1)
+    integral: f64,
+}
+
+impl Distribution1D {
+    /// Negative and non-finite values count as 0. A function that's 0
+    /// everywhere is sampled uniformly.
+    pub fn new(function: Vec<f64>) -> Self {
+        let function = function
+            .into_iter()
+            .map(|f| if f.is_finite() { f.max(0.0) } else { 0.0 })
+            .collect::<Vec<_>>();
+        let count = function.len() as f64;
+
+        let mut cdf = Vec::with_capacity(function.len() + 1);
+        cdf.push(0.0);
+        for f in &function {
+            cdf.push(cdf[cdf.len() - 1] + f / count);
+        }
+        let integral = cdf[cdf.len() - 1];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f64 / count
+            };
+        }
+
+        Self {
+            function,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    /// The point `u` maps to, and its step.
+    pub fn sample(&self, u: f64) -> (f64, usize) {
+        // the last step whose CDF is at most `u`
+        let step = self
+            .cdf
+            .partition_point(|&c| c <= u)
+            .clamp(1, self.function.len())
+            - 1;
+        let width = self.cdf[step + 1] - self.cdf[step];
+        let offset = if width > 0.0 {
+            (u - self.cdf[step]) / width
+        } else {
+            0.0
+        };
+        let x = ((step as f64 + offset) / self.function.len() as f64).min(ONE_MINUS_EPSILON);
+        (x, step)
+    }
+
+    /// The density of the step `x` is in.
+    pub fn density(&self, x: f64) -> f64 {
+        let count = self.function.len();
+        let step = ((x * count as f64) as usize).min(count - 1);
+        if self.integral > 0.0 {
+            self.function[step] / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+/// A piecewise-constant density over [0, 1)², proportional to a function
+/// tabulated on a grid, sampled row first and then along the row.
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// `function` holds `height` rows of `width` values, top row first. The
+    /// first coordinate runs along the rows and the second down them.
+    pub fn new(function: &[f64], width: usize, height: usize) -> Self {
+        let rows = function
+            .chunks_exact(width)
+            .take(height)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect::<Vec<_>>();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral()).collect());
+        Self { rows, marginal }
+    }
+
+    /// The function's integral over [0, 1)².
+    pub fn integral(&self) -> f64 {
+        self.marginal.integral()
+    }
+
+    /// The point `(u0, u1)` maps to, and its density.
+    pub fn sample(&self, (u0, u1): (f64, f64)) -> ((f64, f64), f64) {
+        let (y, row) = self.marginal.sample(u1);
+        let (x, _) = self.rows[row].sample(u0);
+        ((x, y), self.density((x, y)))
+    }
+
+    pub fn density(&self, (x, y): (f64, f64)) -> f64 {
+        let count = self.rows.len();
+        let row = ((y * count as f64) as usize).min(count - 1);
+        self.marginal.density(y) * self.rows[row].density(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_land_in_steps_as_often_as_their_density_says() {
+        let function = [0.0, 1.0, 3.0, 0.0, 4.0, 0.0, 2.0, 1.0, 0.0, 5.0, 0.0, 0.0];
+        let distribution = Distribution2D::new(&function, 4, 3);
+
+        let mut counts = [0.0; 12];
+        let (n, m) = (240, 240);
+        for i in 0..n {
+            for j in 0..m {
+                let u = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / m as f64);
+                let ((x, y), density) = distribution.sample(u);
+                let step = (y * 3.0) as usize * 4 + (x * 4.0) as usize;
+                assert!(function[step] > 0.0);
+                assert!((density - distribution.density((x, y))).abs() < 1e-12);
+                counts[step] += 1.0 / (n * m) as f64;
+            }
+        }
+
+        // each step covers a twelfth of the square
+        for (step, count) in counts.iter().enumerate() {
+            let center = (
+                (step % 4) as f64 / 4.0 + 0.125,
+                (step / 4) as f64 / 3.0 + 1.0 / 6.0,
+            );
+            let expected = distribution.density(center) / 12.0;
+            assert!((count - expected).abs() < 1e-3, "step {step}");
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{Point, Quaternion, Vector3};
+
+/// A value that can be blended between two keyframes.
+pub trait Interpolate: Copy {
+    /// The value `t` of the way from `a` to `b`, for `t` in [0, 1].
+    fn interpolate(a: Self, b: Self, t: f64) -> Self;
+}
+
+impl Interpolate for f64 {
+    fn interpolate(a: Self, b: Self, t: f64) -> Self {
+        a + (b - a) * t
+    }
+}
+
+impl Interpolate for Vector3 {
+    fn interpolate(a: Self, b: Self, t: f64) -> Self {
+        a + (b - a) * t
+    }
+}
+
+impl Interpolate for Point {
+    fn interpolate(a: Self, b: Self, t: f64) -> Self {
+        a + a.to(b) * t
+    }
+}
+
+impl Interpolate for Quaternion {
+    fn interpolate(a: Self, b: Self, t: f64) -> Self {
+        a.slerp(b, t)
+    }
+}
+
+/// A value at a point in time, as written in scene configs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Keyframe<T> {
+    pub time: f64,
+    pub value: T,
+}
+
+impl<T> Keyframe<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Keyframe<U> {
+        Keyframe {
+            time: self.time,
+            value: f(self.value),
+        }
+    }
+}
+
+/// A value animated over time, interpolated between keyframes. Before the
+/// first and after the last keyframe, the value holds still.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframes<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Interpolate + Default> Default for Keyframes<T> {
+    fn default() -> Self {
+        Self::constant(T::default())
+    }
+}
+
+impl<T: Interpolate> Keyframes<T> {
+    pub fn new(keyframes: impl IntoIterator<Item = Keyframe<T>>) -> Result<Self, String> {
+        let keyframes: Vec<Keyframe<T>> = keyframes.into_iter().collect();
+        if keyframes.is_empty() {
+            return Err("At least one keyframe is required".to_string());
+        }
+        if keyframes.iter().any(|k| !k.time.is_finite()) {
+            return Err("Keyframe times must be finite".to_string());
+        }
+        if keyframes.windows(2).any(|w| w[0].time >= w[1].time) {
+            return Err("Keyframe times must be strictly increasing".to_string());
+        }
+
+        Ok(Self { keyframes })
+    }
+
+    /// A value that doesn't move.
+    pub fn constant(value: T) -> Self {
+        Self {
+            keyframes: vec![Keyframe { time: 0.0, value }],
+        }
+    }
+
+    pub fn is_constant(&self) -> bool {
+        self.keyframes.len() == 1
+    }
+
+    /// The value at the first keyframe.
+    pub fn first(&self) -> T {
+        self.keyframes[0].value
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = T> + '_ {
+        self.keyframes.iter().map(|k| k.value)
+    }
+
+    /// Pairs of consecutive keyframe values, i.e. the stretches the value
+    /// moves along.
+    pub fn segments(&self) -> impl Iterator<Item = (T, T)> + '_ {
+        self.keyframes.windows(2).map(|w| (w[0].value, w[1].value))
+    }
+
+    pub fn at(&self, time: f64) -> T {
+        // the first keyframe at or after `time`
+        let next = self.keyframes.partition_point(|k| k.time < time);
+        if next == 0 {
+            return self.keyframes[0].value;
+        }
+        if next == self.keyframes.len() {
+            return self.keyframes[next - 1].value;
+        }
+
+        let (a, b) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        T::interpolate(a.value, b.value, (time - a.time) / (b.time - a.time))
+    }
+}
+
+impl Keyframes<f64> {
+    /// The largest value, which interpolating between keyframes never passes.
+    pub fn max(&self) -> f64 {
+        self.values().fold(f64::NEG_INFINITY, f64::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyframes_interpolate_and_hold_at_the_ends() {
+        let keyframes = Keyframes::new([
+            Keyframe {
+                time: 0.0,
+                value: 1.0,
+            },
+            Keyframe {
+                time: 0.5,
+                value: 3.0,
+            },
+            Keyframe {
+                time: 1.0,
+                value: 2.0,
+            },
+        ])
+        .unwrap();
+
+        assert_eq!(keyframes.at(-1.0), 1.0);
+        assert_eq!(keyframes.at(0.25), 2.0);
+        assert_eq!(keyframes.at(0.5), 3.0);
+        assert_eq!(keyframes.at(0.75), 2.5);
+        assert_eq!(keyframes.at(2.0), 2.0);
+
+        assert!(Keyframes::<f64>::new([]).is_err());
+        assert!(
+            Keyframes::new([
+                Keyframe {
+                    time: 1.0,
+                    value: 1.0
+                },
+                Keyframe {
+                    time: 1.0,
+                    value: 2.0
+                },
+            ])
+            .is_err()
+        );
+    }
+}
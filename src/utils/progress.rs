@@ -11,6 +11,9 @@ pub struct ProgressInfo {
     pub elapsed: Duration,
     pub estimated_remaining: Duration,
     pub estimated_total: Duration,
+    /// For animations, the frame the progress is within.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame: Option<FrameProgress>,
 }
 
 impl ProgressInfo {
@@ -20,16 +23,29 @@ impl ProgressInfo {
             elapsed: Duration::from_secs(0),
             estimated_remaining: Duration::from_secs(0),
             estimated_total: Duration::from_secs(0),
+            frame: None,
         }
     }
 }
 
+/// How far along one frame of an animation is.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameProgress {
+    /// Index of the frame, starting at 0.
+    pub frame: u32,
+    pub frame_count: u32,
+    /// Progress through all of the frame's checkpoints, from 0.0 to 1.0.
+    pub progress: f64,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct FormattedProgressInfo {
     progress: String,
     elapsed: String,
     estimated_remaining: String,
     estimated_total: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frame: Option<String>,
 }
 
 /// Formats a progress value (0.0 to 1.0) as a percentage string with dynamic precision.
@@ -66,6 +82,14 @@ impl From<ProgressInfo> for FormattedProgressInfo {
             elapsed: format_duration(info.elapsed),
             estimated_remaining: format_duration(info.estimated_remaining),
             estimated_total: format_duration(info.estimated_total),
+            frame: info.frame.map(|frame| {
+                format!(
+                    "{}/{} ({})",
+                    frame.frame + 1,
+                    frame.frame_count,
+                    format_percentage(frame.progress)
+                )
+            }),
         }
     }
 }
@@ -148,6 +172,7 @@ where
             elapsed,
             estimated_remaining,
             estimated_total,
+            frame: None,
         }
     }
 }
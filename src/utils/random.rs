@@ -0,0 +1,55 @@
+use std::cell::Cell;
+
+thread_local! {
+    // unseeded threads start somewhere random, as `rand::rng()` would
+    static THREAD_RNG_STATE: Cell<u64> = Cell::new(rand::random());
+}
+
+/// Restarts the current thread's random stream from `seed`.
+///
+/// The renderer reseeds before each pixel, so the numbers a pixel draws only
+/// depend on the seed, and not on which thread renders it or what that thread
+/// rendered before.
+pub fn seed_thread_rng(seed: u64) {
+    THREAD_RNG_STATE.with(|state| state.set(seed));
+}
+
+/// A uniform random number in [0, 1) from the current thread's stream.
+pub fn random_f64() -> f64 {
+    // the top 53 bits fill the mantissa exactly
+    (next_u64() >> 11) as f64 * 2f64.powi(-53)
+}
+
+/// A uniform random number in [min, max) from the current thread's stream.
+pub fn random_range(min: f64, max: f64) -> f64 {
+    min + (max - min) * random_f64()
+}
+
+/// A Weyl sequence passed through `mix_bits`, as in SplitMix64. Its output is
+/// fixed by its definition, unlike `rand`'s general-purpose generators, so
+/// seeded renders stay reproducible across dependency upgrades.
+fn next_u64() -> u64 {
+    THREAD_RNG_STATE.with(|state| {
+        let next = state.get().wrapping_add(0x9e3779b97f4a7c15);
+        state.set(next);
+        mix_bits(next)
+    })
+}
+
+/// Scrambles the bits of `value`, so similar inputs give unrelated outputs.
+pub fn mix_bits(mut value: u64) -> u64 {
+    value ^= value >> 31;
+    value = value.wrapping_mul(0x7fb5d329728ea185);
+    value ^= value >> 27;
+    value = value.wrapping_mul(0x81dadef4bc2dd44d);
+    value ^= value >> 33;
+    value
+}
+
+/// Combines `values` into a single hash, e.g. to derive a seed from a render
+/// seed and pixel coordinates.
+pub fn hash_u64s(values: &[u64]) -> u64 {
+    values.iter().fold(0x9e3779b97f4a7c15, |hash, value| {
+        mix_bits(hash ^ mix_bits(*value))
+    })
+}
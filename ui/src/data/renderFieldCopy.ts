@@ -147,6 +147,12 @@ export const RENDER_FIELD_COPY = {
         `An advanced setting that controls some fancy math under the hood. You should probably leave this on.`,
       ],
     },
+    useNextEventEstimation: {
+      label: 'Use Next-Event Estimation',
+      description: [
+        `Aims a shadow ray at a light from every surface the path hits. Scenes lit by small lamps get much less noisy, at the cost of an extra ray per bounce. When on, the emissive weight is ignored.`,
+      ],
+    },
   },
   cameras: {
     verticalFOV: {
@@ -429,6 +435,12 @@ export const RENDER_FIELD_COPY = {
         Lower values (20-40) produce stronger rainbow-like color fringing; higher values (50-80) produce less dispersion.
         Typical glass ranges from 30-60.`,
       },
+      roughness: {
+        label: 'Roughness',
+        description: `How frosted the surface is.
+        0 = smooth, clear glass, 1 = heavily frosted.
+        Rough glass blurs both what it reflects and what is seen through it, like sand-blasted panels or rough ice.`,
+      },
       hasInteriorMedium: {
         label: 'Has Interior Medium?',
         description: [
@@ -7,19 +7,25 @@ import { useAdminUserOverride } from '@/providers/AdminUserOverride';
 export type UseLatestCheckpointImageOptions = {
   renderID: number;
   enabled?: boolean;
+  denoise?: boolean;
 };
 
 export function useLatestCheckpointImageQuery(options: UseLatestCheckpointImageOptions) {
-  const { renderID, enabled } = options;
+  const { renderID, enabled, denoise } = options;
 
   const { authenticatedFetch } = useAuth();
 
   const { targetUserID } = useAdminUserOverride();
 
   const { data: checkpointData, ...queryRest } = useQuery({
-    queryKey: checkpointImageQueryKey(renderID, targetUserID),
+    queryKey: [...checkpointImageQueryKey(renderID, targetUserID), denoise ?? false],
     queryFn: async () => {
-      const blob = await getLatestCheckpointImage(authenticatedFetch, renderID, targetUserID);
+      const blob = await getLatestCheckpointImage(
+        authenticatedFetch,
+        renderID,
+        targetUserID,
+        denoise,
+      );
       if (blob === null) {
         return null;
       }
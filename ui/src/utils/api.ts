@@ -1,4 +1,5 @@
 import type { RawRenderConfig, NormalizedRenderConfig } from './render/config';
+import type { Aov, Region } from './render/parameters';
 
 export function getAPIURL(): string {
   // in production, the UI and API are served from the same origin (Rust embeds both)
@@ -213,6 +214,13 @@ export type ProgressInfoPrecise = {
   elapsed: Duration;
   estimated_remaining: Duration;
   estimated_total: Duration;
+  frame?: FrameProgress;
+};
+
+export type FrameProgress = {
+  frame: number;
+  frame_count: number;
+  progress: number;
 };
 
 export type Duration = {
@@ -325,6 +333,28 @@ export async function updateRenderTotalCheckpoints(
   }
 }
 
+export async function refineRender(
+  fetcher: typeof fetch,
+  renderID: number,
+  region: Region,
+  samplesPerPixel?: number,
+  targetUserID?: number,
+): Promise<void> {
+  const response = await fetcher(
+    appendUserID(`${getAPIURL()}/renders/${renderID}/refine`, targetUserID),
+    {
+      headers: { 'Content-Type': 'application/json' },
+      method: 'POST',
+      body: JSON.stringify({ region, samples_per_pixel: samplesPerPixel }),
+    },
+  );
+
+  if (!response.ok) {
+    const body = await response.text();
+    throw new Error(`failed to refine render: (${response.status}: ${body})`);
+  }
+}
+
 export async function updateRenderName(
   fetcher: typeof fetch,
   renderID: number,
@@ -386,9 +416,11 @@ export async function getLatestCheckpointImage(
   fetcher: typeof fetch,
   renderID: number,
   targetUserID?: number,
+  denoise?: boolean,
 ): Promise<Blob | null> {
+  const query = denoise ? '?denoise=true' : '';
   const response = await fetcher(
-    appendUserID(`${getAPIURL()}/renders/${renderID}/checkpoint/latest`, targetUserID),
+    appendUserID(`${getAPIURL()}/renders/${renderID}/checkpoint/latest${query}`, targetUserID),
   );
 
   if (response.status === 404) {
@@ -403,6 +435,54 @@ export async function getLatestCheckpointImage(
   return await response.blob();
 }
 
+export async function getFrameImage(
+  fetcher: typeof fetch,
+  renderID: number,
+  frame: number,
+  targetUserID?: number,
+): Promise<Blob | null> {
+  const response = await fetcher(
+    appendUserID(`${getAPIURL()}/renders/${renderID}/frame/${frame}`, targetUserID),
+  );
+
+  if (response.status === 404) {
+    return null;
+  }
+
+  if (!response.ok) {
+    const body = await response.text();
+    throw new Error(`failed to get frame image: (${response.status}: ${body})`);
+  }
+
+  return await response.blob();
+}
+
+export async function getAovImage(
+  fetcher: typeof fetch,
+  renderID: number,
+  checkpointIteration: number | 'latest',
+  aov: Aov,
+  targetUserID?: number,
+): Promise<Blob | null> {
+  const response = await fetcher(
+    appendUserID(
+      `${getAPIURL()}/renders/${renderID}/checkpoint/${checkpointIteration}/aov/${aov}`,
+      targetUserID,
+    ),
+  );
+
+  if (response.status === 404) {
+    return null;
+  }
+
+  if (!response.ok) {
+    const body = await response.text();
+    throw new Error(`failed to get AOV image: (${response.status}: ${body})`);
+  }
+
+  return await response.blob();
+}
+
 export async function getAllUsers(fetcher: typeof fetch): Promise<User[]> {
   const response = await fetcher(`${getAPIURL()}/users`);
 
@@ -1,6 +1,22 @@
 import { z } from 'zod';
 import type { NormalizedRenderConfig } from './config';
-import { isNonNullObject } from './utils';
+import { isNonNullObject, keyframesSchema, type Keyframe } from './utils';
+
+export const ProjectionSchema = z.discriminatedUnion('type', [
+  z.object({ type: z.literal('perspective') }),
+  z.object({
+    type: z.literal('orthographic'),
+    view_height: z.number().positive().optional(),
+  }),
+  z.object({ type: z.literal('equirectangular') }),
+  z.object({
+    type: z.literal('fisheye'),
+    mapping: z.enum(['equidistant', 'equisolid']).optional(),
+    field_of_view_degrees: z.number().positive().max(360).optional(),
+  }),
+]);
+
+export type Projection = z.infer<typeof ProjectionSchema>;
 
 export const CameraDataSchema = z.object({
   vertical_field_of_view_degrees: z.number().min(0).max(180),
@@ -9,6 +25,17 @@ export const CameraDataSchema = z.object({
   view_up: z.tuple([z.number(), z.number(), z.number()]),
   defocus_angle_degrees: z.number().min(0).max(180),
   focus_distance: z.union([z.literal('eye_to_target'), z.number().min(0)]),
+  projection: ProjectionSchema.optional(),
+  shutter: z
+    .object({ open: z.number(), close: z.number() })
+    .refine((shutter) => shutter.open <= shutter.close, {
+      message: 'Shutter must open before it closes',
+    })
+    .optional(),
+  eye_location_keyframes: keyframesSchema(z.tuple([z.number(), z.number(), z.number()])).optional(),
+  target_location_keyframes: keyframesSchema(
+    z.tuple([z.number(), z.number(), z.number()]),
+  ).optional(),
 });
 
 export type CameraData = NormalizedCameraData;
@@ -22,6 +49,10 @@ export type RawCameraData = {
   view_up: [number, number, number];
   defocus_angle_degrees: number;
   focus_distance: 'eye_to_target' | number;
+  projection?: Projection;
+  shutter?: { open: number; close: number };
+  eye_location_keyframes?: Keyframe<[number, number, number]>[];
+  target_location_keyframes?: Keyframe<[number, number, number]>[];
 };
 
 export function isCameraData(data: unknown): data is CameraData {
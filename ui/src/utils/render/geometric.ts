@@ -8,10 +8,12 @@ import {
   isAroundCenter,
   isAroundOrigin,
   isTypedObject,
+  keyframesSchema,
   nonZeroNumber,
   toRadians,
   type Angle,
   type Around,
+  type Keyframe,
 } from './utils';
 import { z } from 'zod';
 
@@ -790,6 +792,9 @@ export const GeometricInstanceRotateQuaternionSchema = z.object({
   geometric: z.string().nonempty(),
   quaternion: z.tuple([z.number(), z.number(), z.number(), z.number()]),
   around: AroundSchema,
+  quaternion_keyframes: keyframesSchema(
+    z.tuple([z.number(), z.number(), z.number(), z.number()]),
+  ).optional(),
 });
 
 export type GeometricInstanceRotateQuaternion = NormalizedGeometricInstanceRotateQuaternion;
@@ -799,6 +804,7 @@ export type NormalizedGeometricInstanceRotateQuaternion = {
   geometric: string;
   quaternion: [number, number, number, number];
   around: Around;
+  quaternion_keyframes?: Keyframe<[number, number, number, number]>[];
 };
 
 export type RawGeometricInstanceRotateQuaternion = {
@@ -806,6 +812,7 @@ export type RawGeometricInstanceRotateQuaternion = {
   geometric: string | RawGeometricData;
   quaternion: [number, number, number, number];
   around: Around;
+  quaternion_keyframes?: Keyframe<[number, number, number, number]>[];
 };
 
 export function normalizeGeometricInstanceRotateQuaternion(
@@ -839,6 +846,7 @@ export const GeometricInstanceTranslateSchema = z.object({
   type: z.literal('translate'),
   geometric: z.string().nonempty(),
   translation: z.tuple([z.number(), z.number(), z.number()]),
+  translation_keyframes: keyframesSchema(z.tuple([z.number(), z.number(), z.number()])).optional(),
 });
 
 export type GeometricInstanceTranslate = NormalizedGeometricInstanceTranslate;
@@ -881,6 +889,7 @@ export type RawGeometricInstanceTranslate = {
   type: 'translate';
   geometric: string | RawGeometricData;
   translation: [number, number, number];
+  translation_keyframes?: Keyframe<[number, number, number]>[];
 };
 
 export const GeometricInstanceScaleSchema = z.object({
@@ -888,6 +897,9 @@ export const GeometricInstanceScaleSchema = z.object({
   geometric: z.string().nonempty(),
   scale: z.tuple([nonZeroNumber, nonZeroNumber, nonZeroNumber]),
   around: AroundSchema,
+  scale_keyframes: keyframesSchema(
+    z.tuple([nonZeroNumber, nonZeroNumber, nonZeroNumber]),
+  ).optional(),
 });
 
 export type GeometricInstanceScale = NormalizedGeometricInstanceScale;
@@ -928,6 +940,7 @@ export type RawGeometricInstanceScale = {
   geometric: string | RawGeometricData;
   scale: [number, number, number];
   around: Around;
+  scale_keyframes?: Keyframe<[number, number, number]>[];
 };
 
 export const GeometricParallelogramSchema = z.object({
@@ -1,6 +1,6 @@
 import type { NormalizedRenderConfig, RenderConfig } from './config';
 import { normalizeTextureData, type RawTextureData } from './texture';
-import { getNextUniqueName, isTypedObject } from './utils';
+import { getNextUniqueName, isTypedObject, keyframesSchema, type Keyframe } from './utils';
 import { z } from 'zod';
 
 export type MediumData =
@@ -144,6 +144,8 @@ export const MaterialDielectricSchema = z.object({
   index_of_refraction: z.number().min(0),
   abbe_number: z.number().min(1).optional(),
   medium_data: MediumDataSchema.optional(),
+  roughness: z.number().min(0).max(1).optional(),
+  emittance_scale_keyframes: keyframesSchema(z.number()).optional(),
 });
 
 export type MaterialDielectric = NormalizedMaterialDielectric;
@@ -213,12 +215,15 @@ export type RawMaterialDielectric = {
   index_of_refraction: number;
   abbe_number?: number;
   medium_data?: MediumData;
+  roughness?: number;
+  emittance_scale_keyframes?: Keyframe<number>[];
 };
 
 export const MaterialLambertianSchema = z.object({
   type: z.literal('lambertian'),
   reflectance_texture: z.string().nonempty(),
   emittance_texture: z.string().nonempty(),
+  emittance_scale_keyframes: keyframesSchema(z.number()).optional(),
 });
 
 export type MaterialLambertian = NormalizedMaterialLambertian;
@@ -279,6 +284,7 @@ export type RawMaterialLambertian = {
   type: 'lambertian';
   reflectance_texture: string | RawTextureData;
   emittance_texture: string | RawTextureData;
+  emittance_scale_keyframes?: Keyframe<number>[];
 };
 
 export const MaterialSpecularSchema = z.object({
@@ -286,6 +292,7 @@ export const MaterialSpecularSchema = z.object({
   reflectance_texture: z.string().nonempty(),
   emittance_texture: z.string().nonempty(),
   roughness: z.number().min(0).max(1),
+  emittance_scale_keyframes: keyframesSchema(z.number()).optional(),
 });
 
 export type MaterialSpecular = NormalizedMaterialSpecular;
@@ -347,6 +354,7 @@ export type RawMaterialSpecular = {
   reflectance_texture: string | RawTextureData;
   emittance_texture: string | RawTextureData;
   roughness: number;
+  emittance_scale_keyframes?: Keyframe<number>[];
 };
 export const MaterialDataSchema = z.discriminatedUnion('type', [
   MaterialDielectricSchema,
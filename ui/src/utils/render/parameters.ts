@@ -6,7 +6,9 @@ export const ImportanceSamplingConfigSchema = z.object({
   transmissive_weight: z.number().min(0),
   specular_weight: z.number().min(0),
   virtual_weight: z.number().min(0),
+  environment_weight: z.number().min(0).optional(),
   use_multiple_importance_sampling: z.boolean(),
+  use_next_event_estimation: z.boolean(),
 });
 
 export type ImportanceSamplingConfig = z.infer<typeof ImportanceSamplingConfigSchema>;
@@ -18,6 +20,91 @@ export const BouncesConfigSchema = z.object({
 
 export type BouncesConfig = z.infer<typeof BouncesConfigSchema>;
 
+export const ToneMappingSchema = z.object({
+  operator: z
+    .enum(['clamp', 'scaling_truncation', 'reinhard', 'extended_reinhard', 'aces_filmic', 'agx'])
+    .optional(),
+  exposure: z.number().optional(),
+  white_point: z.number().positive().optional(),
+});
+
+export type ToneMapping = z.infer<typeof ToneMappingSchema>;
+
+export const AdaptiveSamplingConfigSchema = z.object({
+  noise_threshold: z.number().positive(),
+  min_samples: z.number().int().min(0).optional(),
+  max_sample_multiplier: z.number().min(1).optional(),
+});
+
+export type AdaptiveSamplingConfig = z.infer<typeof AdaptiveSamplingConfigSchema>;
+
+export const FilterSchema = z.discriminatedUnion('type', [
+  z.object({
+    type: z.literal('box'),
+    radius: z.number().positive().max(8).optional(),
+  }),
+  z.object({
+    type: z.literal('gaussian'),
+    radius: z.number().positive().max(8).optional(),
+    sigma: z.number().positive().optional(),
+  }),
+  z.object({
+    type: z.literal('mitchell'),
+    radius: z.number().positive().max(8).optional(),
+    b: z.number().optional(),
+    c: z.number().optional(),
+  }),
+  z.object({
+    type: z.literal('blackman_harris'),
+    radius: z.number().positive().max(8).optional(),
+  }),
+]);
+
+export type Filter = z.infer<typeof FilterSchema>;
+
+export const AnimationConfigSchema = z.object({
+  frame_count: z.number().int().min(1),
+  frame_duration: z.number().positive().optional(),
+  start_time: z.number().optional(),
+});
+
+export type AnimationConfig = z.infer<typeof AnimationConfigSchema>;
+
+export const RegionSchema = z.object({
+  origin: z.tuple([z.number().int().min(0), z.number().int().min(0)]),
+  dimensions: z.tuple([z.number().int().min(1), z.number().int().min(1)]),
+});
+
+export type Region = z.infer<typeof RegionSchema>;
+
+export const AovSchema = z.enum([
+  'albedo',
+  'normal',
+  'depth',
+  'position',
+  'material_id',
+  'emission',
+  'direct',
+  'indirect',
+]);
+
+export type Aov = z.infer<typeof AovSchema>;
+
+export const PhotonMappingConfigSchema = z.object({
+  photons_per_checkpoint: z.number().int().min(1).optional(),
+  initial_radius: z.number().positive().optional(),
+  alpha: z.number().gt(0).lt(1).optional(),
+});
+
+export type PhotonMappingConfig = z.infer<typeof PhotonMappingConfigSchema>;
+
+export const DebugConfigSchema = z.object({
+  ambient_occlusion_distance: z.number().positive().optional(),
+  max_bvh_cost: z.number().int().min(1).optional(),
+});
+
+export type DebugConfig = z.infer<typeof DebugConfigSchema>;
+
 export const RenderParametersSchema = z
   .object({
     image_dimensions: z.tuple([z.number().int().min(1), z.number().int().min(1)]),
@@ -28,6 +115,29 @@ export const RenderParametersSchema = z
     bounces: BouncesConfigSchema,
     use_scaling_truncation: z.boolean(),
     importance_sampling: ImportanceSamplingConfigSchema.optional(),
+    tone_mapping: ToneMappingSchema.optional(),
+    adaptive_sampling: AdaptiveSamplingConfigSchema.optional(),
+    sampler: z.enum(['independent', 'stratified', 'halton', 'sobol']).optional(),
+    seed: z.number().int().min(0).optional(),
+    filter: FilterSchema.optional(),
+    animation: AnimationConfigSchema.optional(),
+    region: RegionSchema.optional(),
+    store_aovs: z.boolean().optional(),
+    integrator: z
+      .enum([
+        'path_tracing',
+        'bidirectional',
+        'progressive_photon_mapping',
+        'normals',
+        'uv',
+        'albedo',
+        'ambient_occlusion',
+        'bvh_cost',
+        'path_length',
+      ])
+      .optional(),
+    photon_mapping: PhotonMappingConfigSchema.optional(),
+    debug: DebugConfigSchema.optional(),
   })
   .refine((params) => params.tile_dimensions[0] <= params.image_dimensions[0], {
     message: 'Cannot be larger than image dimensions',
@@ -36,6 +146,16 @@ export const RenderParametersSchema = z
   .refine((params) => params.tile_dimensions[1] <= params.image_dimensions[1], {
     message: 'Cannot be larger than image dimensions',
     path: ['tile_dimensions', 1],
-  });
+  })
+  .refine(
+    ({ region, image_dimensions }) =>
+      !region ||
+      (region.origin[0] + region.dimensions[0] <= image_dimensions[0] &&
+        region.origin[1] + region.dimensions[1] <= image_dimensions[1]),
+    {
+      message: 'Region must fit within the image',
+      path: ['region'],
+    },
+  );
 
 export type RenderParameters = z.infer<typeof RenderParametersSchema>;
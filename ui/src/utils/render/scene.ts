@@ -4,11 +4,84 @@ import type { RenderConfig, NormalizedRenderConfig } from './config';
 import { normalizeGeometricData, type RawGeometricData } from './geometric';
 import { getNextUniqueName, isNonNullObject } from './utils';
 
+const ColorSchema = z.tuple([z.number().min(0), z.number().min(0), z.number().min(0)]);
+
+export const EnvironmentDataSchema = z.discriminatedUnion('type', [
+  z.object({
+    type: z.literal('image'),
+    resource_id: z.number().int(),
+    rotation_degrees: z.number().optional(),
+    intensity: z.number().min(0).optional(),
+  }),
+  z.object({
+    type: z.literal('gradient'),
+    zenith_color: ColorSchema,
+    horizon_color: ColorSchema,
+    ground_color: ColorSchema,
+  }),
+  z.object({
+    type: z.literal('sky'),
+    sun_elevation_degrees: z.number().min(0).max(90),
+    sun_azimuth_degrees: z.number().optional(),
+    turbidity: z.number().min(1.7).max(10).optional(),
+    intensity: z.number().min(0).optional(),
+  }),
+]);
+
+export type EnvironmentData = z.infer<typeof EnvironmentDataSchema>;
+
+const Vector3Schema = z.tuple([z.number(), z.number(), z.number()]);
+
+export const LightDataSchema = z.discriminatedUnion('type', [
+  z.object({
+    type: z.literal('point'),
+    position: Vector3Schema,
+    power: z.number().min(0),
+    color: ColorSchema.optional(),
+    ies_filename: z.string().optional(),
+  }),
+  z.object({
+    type: z.literal('spot'),
+    position: Vector3Schema,
+    direction: Vector3Schema,
+    cone_angle_degrees: z.number().min(0).max(180),
+    cone_delta_degrees: z.number().min(0).optional(),
+    power: z.number().min(0),
+    color: ColorSchema.optional(),
+    ies_filename: z.string().optional(),
+  }),
+  z.object({
+    type: z.literal('directional'),
+    direction: Vector3Schema,
+    irradiance: z.number().min(0),
+    color: ColorSchema.optional(),
+  }),
+  z.object({
+    type: z.literal('sphere'),
+    center: Vector3Schema,
+    radius: z.number().positive(),
+    power: z.number().min(0),
+    color: ColorSchema.optional(),
+  }),
+  z.object({
+    type: z.literal('rectangle'),
+    lower_left: Vector3Schema,
+    u: Vector3Schema,
+    v: Vector3Schema,
+    power: z.number().min(0),
+    color: ColorSchema.optional(),
+  }),
+]);
+
+export type LightData = z.infer<typeof LightDataSchema>;
+
 export const SceneDataSchema = z.object({
   geometrics: z.array(z.string().nonempty()),
   use_bvh: z.boolean(),
   camera: z.string().nonempty(),
-  background_color: z.tuple([z.number().min(0), z.number().min(0), z.number().min(0)]),
+  background_color: ColorSchema,
+  environment: EnvironmentDataSchema.optional(),
+  lights: z.array(LightDataSchema).optional(),
 });
 
 export type SceneData = NormalizedSceneData;
@@ -58,6 +131,8 @@ export type RawSceneData = {
   use_bvh: boolean;
   camera: string | RawCameraData;
   background_color: [number, number, number];
+  environment?: EnvironmentData;
+  lights?: LightData[];
 };
 
 export function isSceneData(x: unknown): x is SceneData {
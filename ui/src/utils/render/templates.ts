@@ -74,6 +74,7 @@ export function getEmptyRenderConfig(): NormalizedRenderConfig {
         specular_weight: 0.0,
         virtual_weight: 0.0,
         use_multiple_importance_sampling: true,
+        use_next_event_estimation: false,
       },
     },
     active_scene: 'Main',
@@ -250,6 +251,7 @@ export function getCornellBoxBaseRenderConfig(): NormalizedRenderConfig {
         specular_weight: 0.0,
         virtual_weight: 0.0,
         use_multiple_importance_sampling: true,
+        use_next_event_estimation: false,
       },
     },
     active_scene: 'Cornell Box',
@@ -122,6 +122,17 @@ export const nonZeroNumber = z.number().refine((val) => val !== 0, {
   message: 'Number must be non-zero',
 });
 
+export type Keyframe<T> = { time: number; value: T };
+
+export function keyframesSchema<T extends z.ZodTypeAny>(value: T) {
+  return z
+    .array(z.object({ time: z.number(), value }))
+    .nonempty()
+    .refine((keyframes) => keyframes.every((k, i) => i === 0 || keyframes[i - 1].time < k.time), {
+      message: 'Keyframe times must be strictly increasing',
+    });
+}
+
 /**
  * fixes dangling references after a geometric, material, or texture is deleted.
  * replaces broken references with default values (__white, __black, __lambertian_white, __unit_box).
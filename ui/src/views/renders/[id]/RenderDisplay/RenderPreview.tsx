@@ -1,7 +1,8 @@
 import { useLatestCheckpointImageQuery } from '@/hooks/useLatestCheckpointImage';
 import { useRenderQuery } from '@/hooks/useRender';
 import { isRenderStateCreated, isRenderStateRunning, isRenderStatePausing } from '@/utils/api';
-import { Spinner } from 'flowbite-react';
+import { Spinner, ToggleSwitch } from 'flowbite-react';
+import { useState } from 'react';
 
 export type RenderPreviewProps = {
   renderID: number;
@@ -13,6 +14,8 @@ export function RenderPreview(props: RenderPreviewProps) {
   const { data: render } = useRenderQuery({ renderID });
   const renderState = render?.state;
 
+  const [denoise, setDenoise] = useState(false);
+
   const checkpointEnabled = renderState ? !isRenderStateCreated(renderState) : true;
 
   const isRunningOrPausing =
@@ -26,6 +29,7 @@ export function RenderPreview(props: RenderPreviewProps) {
   } = useLatestCheckpointImageQuery({
     renderID,
     enabled: checkpointEnabled,
+    denoise,
   });
 
   return (
@@ -54,11 +58,14 @@ export function RenderPreview(props: RenderPreviewProps) {
         </p>
       )}
       {checkpointImage && (
-        <img
-          alt="Render checkpoint"
-          src={checkpointImage}
-          className="max-h-full max-w-full rounded border border-zinc-700 object-contain"
-        />
+        <div className="flex max-h-full min-h-0 flex-col items-center gap-2">
+          <img
+            alt="Render checkpoint"
+            src={checkpointImage}
+            className="min-h-0 max-w-full rounded border border-zinc-700 object-contain"
+          />
+          <ToggleSwitch checked={denoise} label="Denoise" onChange={setDenoise} />
+        </div>
       )}
     </div>
   );
@@ -124,6 +124,17 @@ export function MaterialRow(props: MaterialRowProps) {
                 />
               )}
             </form.AppField>
+            <form.AppField name={`materials.${name}.roughness`}>
+              {(field) => (
+                <field.RangeControl
+                  label="Roughness"
+                  min={0.0}
+                  max={1.0}
+                  step={0.01}
+                  tooltip={RENDER_FIELD_COPY.materials.dielectric.roughness.description}
+                />
+              )}
+            </form.AppField>
             <TextureSelects name={name} />
           </>
         );
@@ -17,6 +17,7 @@ const DEFAULT_IMPORTANCE_SAMPLING = {
   specular_weight: 0.0,
   virtual_weight: 0.0,
   use_multiple_importance_sampling: true,
+  use_next_event_estimation: false,
 };
 
 export type ImportanceSamplingControlsProps = {
@@ -128,6 +129,15 @@ export function ImportanceSamplingControls(props: ImportanceSamplingControlsProp
               />
             )}
           </form.AppField>
+          <form.AppField name="parameters.importance_sampling.use_next_event_estimation">
+            {(field) => (
+              <field.ToggleControl
+                label="Use Next-Event Estimation"
+                labelSuffix={<WarningIconAdvancedProperty />}
+                tooltip={RENDER_FIELD_COPY.parameters.useNextEventEstimation.description}
+              />
+            )}
+          </form.AppField>
         </div>
         <Separator />
       </ExpandableSection>